@@ -22,7 +22,9 @@ use crate::{
     settings::ConnectionsSettingsModel,
     settings_compiled::SettingsCompiled,
     ssl::CertificatesCache,
-    tcp_gateway::{client::TcpGatewayClient, server::TcpGatewayServer, TcpGatewayConnection},
+    tcp_gateway::{
+        client::TcpGatewayClient, server::TcpGatewayServer, GatewayFileRoots, TcpGatewayConnection,
+    },
     upstream_h1_pool::H1PoolRegistry,
     upstream_h2_pool::H2PoolRegistry,
 };
@@ -91,6 +93,18 @@ pub struct AppContext {
 
     pub ip_blocklist: IpBlocklist,
 
+    pub rate_limiters: RateLimiters,
+
+    pub connection_counters: ConnectionCounters,
+    pub concurrency_limiters: ConcurrencyLimiters,
+
+    pub upstream_outliers: UpstreamOutliers,
+
+    pub location_splits: LocationSplits,
+
+    pub response_caches: ResponseCaches,
+    pub stale_stores: StaleStores,
+
     pub traffic: TrafficAccumulator,
 
     pub resolved_domain_ips: ResolvedDomainIps,
@@ -112,10 +126,24 @@ impl AppContext {
         let gateway_server =
             if let Some(gateway_server_settings) = settings_model.get_gateway_server() {
                 let authorized_keys = gateway_server_settings.load_authorized_keys().unwrap();
+                let file_roots = GatewayFileRoots::new(gateway_server_settings.get_file_roots())
+                    .unwrap_or_else(|err| {
+                        panic!(
+                            "Gateway server on port {}: {}",
+                            gateway_server_settings.port, err
+                        )
+                    });
+                if file_roots.is_empty() {
+                    println!(
+                        "Gateway server on port {}: no file_roots, every file request is denied",
+                        gateway_server_settings.port
+                    );
+                }
                 Some(TcpGatewayServer::new(
                     format!("0.0.0.0:{}", gateway_server_settings.port,),
                     authorized_keys,
                     false,
+                    file_roots,
                     gateway_server_settings.is_debug(),
                 ))
             } else {
@@ -129,6 +157,14 @@ impl AppContext {
             let signing_key = client_settings
                 .load_signing_key(id.as_str(), ssh_registry)
                 .unwrap();
+            let file_roots = GatewayFileRoots::new(client_settings.get_file_roots())
+                .unwrap_or_else(|err| panic!("Gateway client '{}': {}", id, err));
+            if file_roots.is_empty() {
+                println!(
+                    "Gateway client '{}': no file_roots, every file request is denied",
+                    id
+                );
+            }
             let client = TcpGatewayClient::new(
                 id.to_string(),
                 client_settings.remote_host.to_string(),
@@ -138,6 +174,7 @@ impl AppContext {
                 client_settings.get_connect_timeout(),
                 client_settings.is_debug(),
                 client_settings.get_sync_ssl_certificates(),
+                file_roots,
             );
 
             gateway_clients.insert(id.clone(), client);
@@ -191,6 +228,13 @@ impl AppContext {
             ),
             rps: Arc::new(RpsAccumulator::new()),
             ip_blocklist,
+            rate_limiters: RateLimiters::new(),
+            connection_counters: ConnectionCounters::new(),
+            concurrency_limiters: ConcurrencyLimiters::new(),
+            upstream_outliers: UpstreamOutliers::new(),
+            location_splits: LocationSplits::new(),
+            response_caches: ResponseCaches::new(),
+            stale_stores: StaleStores::new(),
             traffic: TrafficAccumulator::new(),
             resolved_domain_ips: ResolvedDomainIps::new(),
         }
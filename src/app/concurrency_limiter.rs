@@ -0,0 +1,144 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// `max_concurrent_requests` of a location, with its wait queue, compiled. A
+/// reload that leaves the settings as they were keeps the requests in flight
+/// counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcurrencyLimiterSettings {
+    pub max_requests: usize,
+    /// Requests that wait for a slot at once; the ones past them are turned
+    /// away at once.
+    pub max_queued: usize,
+    /// How long a request waits for a slot before it is turned away.
+    pub queue_timeout: Duration,
+}
+
+/// Requests of a location on their way to the upstream at once.
+pub struct ConcurrencyLimiter {
+    /// What the metrics are labelled with — the location's `id_string`.
+    label: String,
+    settings: ConcurrencyLimiterSettings,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(label: String, settings: ConcurrencyLimiterSettings) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(settings.max_requests)),
+            queued: AtomicUsize::new(0),
+            label,
+            settings,
+        }
+    }
+
+    pub fn get_settings(&self) -> &ConcurrencyLimiterSettings {
+        &self.settings
+    }
+
+    pub fn get_in_flight(&self) -> usize {
+        self.settings
+            .max_requests
+            .saturating_sub(self.semaphore.available_permits())
+    }
+
+    pub fn get_queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// A slot for a request, waiting in the queue for one when they are all
+    /// taken. `None` when the queue is full too, or the wait times out — the
+    /// request is answered with 503.
+    pub async fn acquire(&self) -> Option<ConcurrencyPermit> {
+        let result = self.wait_for_slot().await;
+
+        if result.is_none() {
+            crate::app::APP_CTX
+                .prometheus
+                .inc_concurrency_rejected(&self.label);
+        }
+
+        result
+    }
+
+    async fn wait_for_slot(&self) -> Option<ConcurrencyPermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Some(ConcurrencyPermit { _permit: permit });
+        }
+
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+
+        let result = match queued < self.settings.max_queued {
+            true => tokio::time::timeout(
+                self.settings.queue_timeout,
+                self.semaphore.clone().acquire_owned(),
+            )
+            .await
+            .ok()
+            .and_then(|itm| itm.ok()),
+            false => None,
+        };
+
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+
+        result.map(|permit| ConcurrencyPermit { _permit: permit })
+    }
+}
+
+/// A request counted against its location's `max_concurrent_requests`, until
+/// dropped.
+pub struct ConcurrencyPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(max_queued: usize) -> ConcurrencyLimiter {
+        ConcurrencyLimiter::new(
+            "test".to_string(),
+            ConcurrencyLimiterSettings {
+                max_requests: 1,
+                max_queued,
+                queue_timeout: Duration::from_millis(50),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn a_request_past_the_limit_waits_in_the_queue() {
+        let limiter = Arc::new(limiter(1));
+
+        let first = limiter.wait_for_slot().await.unwrap();
+        assert_eq!(limiter.get_in_flight(), 1);
+
+        let waiting = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.wait_for_slot().await.is_some() })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(limiter.get_queued(), 1);
+
+        drop(first);
+        assert!(waiting.await.unwrap());
+        assert_eq!(limiter.get_queued(), 0);
+        assert_eq!(limiter.get_in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn a_request_is_turned_away_when_the_queue_is_full_or_the_wait_times_out() {
+        let limiter = limiter(0);
+        let _first = limiter.wait_for_slot().await.unwrap();
+        assert!(limiter.wait_for_slot().await.is_none());
+
+        let limiter = self::limiter(1);
+        let _first = limiter.wait_for_slot().await.unwrap();
+        assert!(limiter.wait_for_slot().await.is_none());
+        assert_eq!(limiter.get_queued(), 0);
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::{Arc, Weak};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+use super::{ConcurrencyLimiter, ConcurrencyLimiterSettings};
+
+/// Every `max_concurrent_requests` in the configurations applied so far, by
+/// the location's `id_string`. A reload that leaves the settings as they were
+/// keeps counting the requests already in flight.
+pub struct ConcurrencyLimiters {
+    limiters: Mutex<AHashMap<String, Weak<ConcurrencyLimiter>>>,
+}
+
+impl ConcurrencyLimiters {
+    pub fn new() -> Self {
+        Self {
+            limiters: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    /// The limiter to use for `label` with `settings`.
+    pub fn apply(
+        &self,
+        label: &str,
+        settings: ConcurrencyLimiterSettings,
+    ) -> Arc<ConcurrencyLimiter> {
+        let mut limiters = self.limiters.lock();
+        limiters.retain(|_, itm| itm.strong_count() > 0);
+
+        if let Some(existing) = limiters.get(label).and_then(|itm| itm.upgrade()) {
+            if existing.get_settings() == &settings {
+                return existing;
+            }
+        }
+
+        let limiter = Arc::new(ConcurrencyLimiter::new(label.to_string(), settings));
+        limiters.insert(label.to_string(), Arc::downgrade(&limiter));
+        limiter
+    }
+}
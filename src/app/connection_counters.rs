@@ -0,0 +1,222 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+/// `max_connections` and `max_connections_per_ip` of the endpoints on a listen
+/// port. The endpoint of a connection is not known at accept — SNI or the Host
+/// header picks it later — so endpoints sharing a port share its limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionLimits {
+    pub max_connections: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+}
+
+impl ConnectionLimits {
+    /// The lower of the two limits, field by field.
+    pub fn stricter(&self, other: &ConnectionLimits) -> ConnectionLimits {
+        fn min(left: Option<usize>, right: Option<usize>) -> Option<usize> {
+            match (left, right) {
+                (Some(left), Some(right)) => Some(left.min(right)),
+                (left, right) => left.or(right),
+            }
+        }
+
+        ConnectionLimits {
+            max_connections: min(self.max_connections, other.max_connections),
+            max_connections_per_ip: min(self.max_connections_per_ip, other.max_connections_per_ip),
+        }
+    }
+}
+
+/// Which limit a connection is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionLimitExceeded {
+    MaxConnections(usize),
+    MaxConnectionsPerIp(usize),
+}
+
+#[derive(Default)]
+struct PortConnections {
+    total: usize,
+    by_ip: AHashMap<IpAddr, usize>,
+}
+
+/// Connections open on every tcp listen port, in total and by client IP.
+pub struct ConnectionCounters {
+    ports: Arc<Mutex<AHashMap<u16, PortConnections>>>,
+}
+
+impl ConnectionCounters {
+    pub fn new() -> Self {
+        Self {
+            ports: Arc::new(Mutex::new(AHashMap::new())),
+        }
+    }
+
+    /// Counts a connection from `ip` accepted on `port`, unless it would take
+    /// the port or the IP over `limits`. The connection is counted until the
+    /// permit is dropped.
+    pub fn try_acquire(
+        &self,
+        port: u16,
+        ip: IpAddr,
+        limits: &ConnectionLimits,
+    ) -> Result<ConnectionPermit, ConnectionLimitExceeded> {
+        let mut ports = self.ports.lock();
+        let connections = ports.entry(port).or_default();
+
+        if let Some(max_connections) = limits.max_connections {
+            if connections.total >= max_connections {
+                return Err(ConnectionLimitExceeded::MaxConnections(max_connections));
+            }
+        }
+
+        let by_ip = connections.by_ip.get(&ip).copied().unwrap_or_default();
+
+        if let Some(max_connections_per_ip) = limits.max_connections_per_ip {
+            if by_ip >= max_connections_per_ip {
+                return Err(ConnectionLimitExceeded::MaxConnectionsPerIp(
+                    max_connections_per_ip,
+                ));
+            }
+        }
+
+        connections.total += 1;
+        connections.by_ip.insert(ip, by_ip + 1);
+
+        Ok(ConnectionPermit {
+            ports: self.ports.clone(),
+            port,
+            ip,
+        })
+    }
+
+    /// Connections open on every port that has any, by port.
+    pub fn get_snapshot(&self) -> Vec<PortConnectionsSnapshot> {
+        let ports = self.ports.lock();
+
+        let mut result: Vec<PortConnectionsSnapshot> = ports
+            .iter()
+            .map(|(port, connections)| {
+                let busiest_ip = connections.by_ip.iter().max_by_key(|(_, count)| **count);
+
+                PortConnectionsSnapshot {
+                    port: *port,
+                    connections: connections.total,
+                    clients: connections.by_ip.len(),
+                    busiest_ip: busiest_ip.map(|(ip, count)| (*ip, *count)),
+                }
+            })
+            .collect();
+
+        result.sort_by_key(|itm| itm.port);
+        result
+    }
+}
+
+pub struct PortConnectionsSnapshot {
+    pub port: u16,
+    pub connections: usize,
+    /// Distinct client IPs.
+    pub clients: usize,
+    /// The IP with the most connections, with how many it has.
+    pub busiest_ip: Option<(IpAddr, usize)>,
+}
+
+/// A connection counted against the limits of its listen port, for as long as
+/// it is open.
+pub struct ConnectionPermit {
+    ports: Arc<Mutex<AHashMap<u16, PortConnections>>>,
+    port: u16,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut ports = self.ports.lock();
+
+        let Some(connections) = ports.get_mut(&self.port) else {
+            return;
+        };
+
+        connections.total = connections.total.saturating_sub(1);
+
+        if let Some(by_ip) = connections.by_ip.get_mut(&self.ip) {
+            *by_ip -= 1;
+            if *by_ip == 0 {
+                connections.by_ip.remove(&self.ip);
+            }
+        }
+
+        if connections.total == 0 {
+            ports.remove(&self.port);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    #[test]
+    fn connections_are_limited_per_port_and_per_ip() {
+        let counters = ConnectionCounters::new();
+        let limits = ConnectionLimits {
+            max_connections: Some(3),
+            max_connections_per_ip: Some(2),
+        };
+
+        let first = counters.try_acquire(443, ip(1), &limits).unwrap();
+        let _second = counters.try_acquire(443, ip(1), &limits).unwrap();
+        assert_eq!(
+            counters.try_acquire(443, ip(1), &limits).err(),
+            Some(ConnectionLimitExceeded::MaxConnectionsPerIp(2))
+        );
+
+        let _third = counters.try_acquire(443, ip(2), &limits).unwrap();
+        assert_eq!(
+            counters.try_acquire(443, ip(3), &limits).err(),
+            Some(ConnectionLimitExceeded::MaxConnections(3))
+        );
+
+        // Another port counts on its own.
+        let _other_port = counters.try_acquire(80, ip(1), &limits).unwrap();
+
+        drop(first);
+        assert!(counters.try_acquire(443, ip(3), &limits).is_ok());
+
+        let snapshot = counters.get_snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[1].port, 443);
+        assert_eq!(snapshot[1].connections, 2);
+        assert_eq!(snapshot[1].clients, 2);
+        assert_eq!(snapshot[0].busiest_ip, Some((ip(1), 1)));
+    }
+
+    #[test]
+    fn the_stricter_limits_win() {
+        let endpoint = ConnectionLimits {
+            max_connections: Some(100),
+            max_connections_per_ip: None,
+        };
+        let other = ConnectionLimits {
+            max_connections: Some(50),
+            max_connections_per_ip: Some(10),
+        };
+
+        assert_eq!(
+            endpoint.stricter(&other),
+            ConnectionLimits {
+                max_connections: Some(50),
+                max_connections_per_ip: Some(10),
+            }
+        );
+        assert_eq!(ConnectionLimits::default().stricter(&endpoint), endpoint);
+    }
+}
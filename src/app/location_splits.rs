@@ -0,0 +1,51 @@
+use std::sync::{Arc, Weak};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+use crate::configurations::{LocationSplit, SplitBranch};
+
+/// The `split:` of every location in the configurations applied so far, by
+/// the location's `id_string`. A reload that changes nothing but the weights
+/// of a split updates the split already in use instead of replacing it — a
+/// kept-alive client connection goes on with the configuration it started
+/// with, and this is what makes it follow the new weights without being
+/// dropped.
+pub struct LocationSplits {
+    splits: Mutex<AHashMap<String, Weak<LocationSplit>>>,
+}
+
+impl LocationSplits {
+    pub fn new() -> Self {
+        Self {
+            splits: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    /// The split to use for the location `id_string` with `branches`.
+    pub fn apply(&self, id_string: &str, branches: Vec<SplitBranch>) -> Arc<LocationSplit> {
+        let split = {
+            let mut splits = self.splits.lock();
+            splits.retain(|_, itm| itm.strong_count() > 0);
+
+            match splits.get(id_string).and_then(|itm| itm.upgrade()) {
+                Some(existing) if existing.update_weights(&branches) => existing,
+                _ => {
+                    let split = Arc::new(LocationSplit::new(id_string.to_string(), branches));
+                    splits.insert(id_string.to_string(), Arc::downgrade(&split));
+                    split
+                }
+            }
+        };
+
+        for branch in split.get_branches() {
+            crate::app::APP_CTX.prometheus.set_split_weight(
+                id_string,
+                &branch.name,
+                branch.get_weight() as i64,
+            );
+        }
+
+        split
+    }
+}
@@ -24,6 +24,27 @@ pub use rps_accumulator::*;
 
 mod ip_blocklist;
 pub use ip_blocklist::*;
+mod rate_limiter;
+pub use rate_limiter::*;
+mod rate_limiters;
+pub use rate_limiters::*;
+mod connection_counters;
+pub use connection_counters::*;
+mod concurrency_limiter;
+pub use concurrency_limiter::*;
+mod concurrency_limiters;
+pub use concurrency_limiters::*;
+
+mod upstream_outliers;
+pub use upstream_outliers::*;
+
+mod location_splits;
+pub use location_splits::*;
+
+mod response_caches;
+pub use response_caches::*;
+mod stale_stores;
+pub use stale_stores::*;
 
 mod traffic_accumulator;
 pub use traffic_accumulator::*;
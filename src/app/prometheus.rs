@@ -1,5 +1,7 @@
 use prometheus::{Encoder, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
 
+use super::OutlierProtocol;
+
 pub struct Prometheus {
     pub http1_client_tcp_connects: IntGaugeVec,
     pub http1_client_tcp_read_threads: IntGaugeVec,
@@ -15,11 +17,33 @@ pub struct Prometheus {
 
     pub h2_pool_size: IntGaugeVec,
     pub h2_pool_alive: IntGaugeVec,
+    pub h2_upstream_ejected: IntGaugeVec,
+    pub h2_upstream_consecutive_failures: IntGaugeVec,
     pub h2_ws_active: IntGaugeVec,
 
     pub h1_pool_size: IntGaugeVec,
     pub h1_pool_alive: IntGaugeVec,
+    pub h1_upstream_ejected: IntGaugeVec,
+    pub h1_upstream_consecutive_failures: IntGaugeVec,
+    pub mirror_requests: IntGaugeVec,
+    pub mirror_failures: IntGaugeVec,
+    pub mirror_skipped: IntGaugeVec,
+    pub split_requests: IntGaugeVec,
+    pub split_errors: IntGaugeVec,
+    pub split_weight: IntGaugeVec,
+    pub retries: IntGaugeVec,
+    pub retries_refused: IntGaugeVec,
+    pub cache_hits: IntGaugeVec,
+    pub cache_misses: IntGaugeVec,
+    pub cache_revalidated: IntGaugeVec,
+    pub stale_on_error: IntGaugeVec,
+    pub stale_while_revalidate: IntGaugeVec,
+    pub rate_limited: IntGaugeVec,
+    pub rate_limit_clients: IntGaugeVec,
+    pub connections_rejected: IntGaugeVec,
+    pub concurrency_rejected: IntGaugeVec,
     pub tokio_tasks_spawned: IntGaugeVec,
+    pub gateway_file_requests_denied: IntGaugeVec,
 
     pub domain_rps: IntGaugeVec,
     pub ip_blocklist_size: IntGauge,
@@ -103,6 +127,18 @@ impl Prometheus {
             "Currently connected slots in the per-endpoint h2 upstream pool",
         );
 
+        let h2_upstream_ejected = create_endpoint_gauge_vec(
+            &registry,
+            "h2_upstream_ejected",
+            "1 while an h2 upstream is ejected by outlier detection, 0 otherwise",
+        );
+
+        let h2_upstream_consecutive_failures = create_endpoint_gauge_vec(
+            &registry,
+            "h2_upstream_consecutive_failures",
+            "Failed requests in a row (5xx, connect failure, timeout) to an h2 upstream",
+        );
+
         let h2_ws_active = create_endpoint_gauge_vec(
             &registry,
             "h2_ws_active",
@@ -121,12 +157,122 @@ impl Prometheus {
             "Currently connected reusable slots in the per-endpoint h1 upstream pool",
         );
 
+        let h1_upstream_ejected = create_endpoint_gauge_vec(
+            &registry,
+            "h1_upstream_ejected",
+            "1 while an h1 upstream is ejected by outlier detection, 0 otherwise",
+        );
+
+        let h1_upstream_consecutive_failures = create_endpoint_gauge_vec(
+            &registry,
+            "h1_upstream_consecutive_failures",
+            "Failed requests in a row (5xx, connect failure, timeout) to an h1 upstream",
+        );
+
+        let mirror_requests = create_endpoint_gauge_vec(
+            &registry,
+            "mirror_requests",
+            "Requests copied to a location's mirror_to upstream",
+        );
+        let mirror_failures = create_endpoint_gauge_vec(
+            &registry,
+            "mirror_failures",
+            "Mirror requests that got no response or a 5xx from the mirror_to upstream",
+        );
+        let mirror_skipped = create_endpoint_gauge_vec(
+            &registry,
+            "mirror_skipped",
+            "Sampled requests not mirrored: body over max_body_size or too many mirror requests in flight",
+        );
+
+        let split_requests = create_split_gauge_vec(
+            &registry,
+            "split_requests",
+            "Requests a location's split sent to a branch",
+        );
+        let split_errors = create_split_gauge_vec(
+            &registry,
+            "split_errors",
+            "Requests of a split branch that got no response or a 5xx from its upstream",
+        );
+        let split_weight = create_split_gauge_vec(
+            &registry,
+            "split_weight",
+            "Current weight of a split branch",
+        );
+
+        let retries = create_endpoint_gauge_vec(
+            &registry,
+            "retries",
+            "Requests a location's retry policy sent to the upstream again",
+        );
+        let retries_refused = create_endpoint_gauge_vec(
+            &registry,
+            "retries_refused",
+            "Retries not made because the location's retry budget was spent",
+        );
+
+        let cache_hits = create_endpoint_gauge_vec(
+            &registry,
+            "cache_hits",
+            "Requests a location's cache answered without the upstream",
+        );
+        let cache_misses = create_endpoint_gauge_vec(
+            &registry,
+            "cache_misses",
+            "Requests a location's cache sent on to the upstream",
+        );
+        let cache_revalidated = create_endpoint_gauge_vec(
+            &registry,
+            "cache_revalidated",
+            "Stale responses of a location's cache the upstream confirmed with a 304",
+        );
+
+        let stale_on_error = create_endpoint_gauge_vec(
+            &registry,
+            "stale_on_error",
+            "Upstream failures a location answered with its last good response",
+        );
+        let stale_while_revalidate = create_endpoint_gauge_vec(
+            &registry,
+            "stale_while_revalidate",
+            "Requests a location answered with its last good response while refreshing it",
+        );
+
+        let rate_limited = create_endpoint_gauge_vec(
+            &registry,
+            "rate_limited",
+            "Requests a rate limit answered with 429",
+        );
+        let rate_limit_clients = create_endpoint_gauge_vec(
+            &registry,
+            "rate_limit_clients",
+            "Clients a rate limit tracks",
+        );
+
+        let connections_rejected = create_endpoint_gauge_vec(
+            &registry,
+            "connections_rejected",
+            "Connections a listen port closed at accept for max_connections or max_connections_per_ip",
+        );
+        let concurrency_rejected = create_endpoint_gauge_vec(
+            &registry,
+            "concurrency_rejected",
+            "Requests a location answered with 503 for max_concurrent_requests",
+        );
+
         let tokio_tasks_spawned = create_spawn_gauge_vec(
             &registry,
             "tokio_tasks_spawned",
             "Active tokio tasks grouped by spawn site name",
         );
 
+        let gateway_file_requests_denied = create_gateway_gauge_vec(
+            &registry,
+            "gateway_file_requests_denied",
+            "File requests of a gateway peer denied for being outside of file_roots",
+        );
+
         let domain_rps = create_domain_gauge_vec(
             &registry,
             "domain_rps",
@@ -195,10 +341,32 @@ impl Prometheus {
             http2_server_connections,
             h2_pool_size,
             h2_pool_alive,
+            h2_upstream_ejected,
+            h2_upstream_consecutive_failures,
             h2_ws_active,
             h1_pool_size,
             h1_pool_alive,
+            h1_upstream_ejected,
+            h1_upstream_consecutive_failures,
+            mirror_requests,
+            mirror_failures,
+            mirror_skipped,
+            split_requests,
+            split_errors,
+            split_weight,
+            retries,
+            retries_refused,
+            cache_hits,
+            cache_misses,
+            cache_revalidated,
+            stale_on_error,
+            stale_while_revalidate,
+            rate_limited,
+            rate_limit_clients,
+            connections_rejected,
+            concurrency_rejected,
             tokio_tasks_spawned,
+            gateway_file_requests_denied,
             domain_rps,
             ip_blocklist_size,
             client_to_server_events,
@@ -269,6 +437,114 @@ impl Prometheus {
         self.h1_pool_alive.with_label_values(&[endpoint]).set(0);
     }
 
+    pub fn set_upstream_outlier(
+        &self,
+        protocol: OutlierProtocol,
+        endpoint: &str,
+        ejected: bool,
+        consecutive_failures: i64,
+    ) {
+        let (ejected_gauge, failures_gauge) = match protocol {
+            OutlierProtocol::H1 => (
+                &self.h1_upstream_ejected,
+                &self.h1_upstream_consecutive_failures,
+            ),
+            OutlierProtocol::H2 => (
+                &self.h2_upstream_ejected,
+                &self.h2_upstream_consecutive_failures,
+            ),
+        };
+
+        ejected_gauge
+            .with_label_values(&[endpoint])
+            .set(if ejected { 1 } else { 0 });
+        failures_gauge
+            .with_label_values(&[endpoint])
+            .set(consecutive_failures);
+    }
+
+    pub fn inc_mirror_requests(&self, endpoint: &str) {
+        self.mirror_requests.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_mirror_failures(&self, endpoint: &str) {
+        self.mirror_failures.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_mirror_skipped(&self, endpoint: &str) {
+        self.mirror_skipped.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_split_requests(&self, location: &str, branch: &str) {
+        self.split_requests
+            .with_label_values(&[location, branch])
+            .inc();
+    }
+
+    pub fn inc_split_errors(&self, location: &str, branch: &str) {
+        self.split_errors
+            .with_label_values(&[location, branch])
+            .inc();
+    }
+
+    pub fn set_split_weight(&self, location: &str, branch: &str, weight: i64) {
+        self.split_weight
+            .with_label_values(&[location, branch])
+            .set(weight);
+    }
+
+    pub fn inc_retries(&self, endpoint: &str) {
+        self.retries.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_retries_refused(&self, endpoint: &str) {
+        self.retries_refused.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_cache_hits(&self, endpoint: &str) {
+        self.cache_hits.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_cache_misses(&self, endpoint: &str) {
+        self.cache_misses.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_cache_revalidated(&self, endpoint: &str) {
+        self.cache_revalidated.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_stale_on_error(&self, endpoint: &str) {
+        self.stale_on_error.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn inc_stale_while_revalidate(&self, endpoint: &str) {
+        self.stale_while_revalidate
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
+    pub fn inc_rate_limited(&self, endpoint: &str) {
+        self.rate_limited.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn set_rate_limit_clients(&self, endpoint: &str, value: i64) {
+        self.rate_limit_clients
+            .with_label_values(&[endpoint])
+            .set(value);
+    }
+
+    pub fn inc_connections_rejected(&self, endpoint: &str) {
+        self.connections_rejected
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
+    pub fn inc_concurrency_rejected(&self, endpoint: &str) {
+        self.concurrency_rejected
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
     pub fn inc_http1_server_connections(&self, endpoint: &str) {
         self.http1_server_connections
             .with_label_values(&[endpoint])
@@ -305,6 +581,12 @@ impl Prometheus {
             .dec();
     }
 
+    pub fn inc_gateway_file_requests_denied(&self, gateway: &str) {
+        self.gateway_file_requests_denied
+            .with_label_values(&[gateway])
+            .inc();
+    }
+
     pub fn set_domain_rps(&self, domain: &str, n: i64) {
         self.domain_rps.with_label_values(&[domain]).set(n);
     }
@@ -391,6 +673,16 @@ fn create_endpoint_gauge_vec(registry: &Registry, name: &str, description: &str)
     result
 }
 
+fn create_split_gauge_vec(registry: &Registry, name: &str, description: &str) -> IntGaugeVec {
+    let gauge_opts = Opts::new(name, description);
+    let labels = &["location", "branch"];
+    let result = IntGaugeVec::new(gauge_opts, labels).unwrap();
+
+    registry.register(Box::new(result.clone())).unwrap();
+
+    result
+}
+
 fn create_spawn_gauge_vec(registry: &Registry, name: &str, description: &str) -> IntGaugeVec {
     let gauge_opts = Opts::new(name, description);
     let labels = &["spawn_name"];
@@ -401,6 +693,16 @@ fn create_spawn_gauge_vec(registry: &Registry, name: &str, description: &str) ->
     result
 }
 
+fn create_gateway_gauge_vec(registry: &Registry, name: &str, description: &str) -> IntGaugeVec {
+    let gauge_opts = Opts::new(name, description);
+    let labels = &["gateway"];
+    let result = IntGaugeVec::new(gauge_opts, labels).unwrap();
+
+    registry.register(Box::new(result.clone())).unwrap();
+
+    result
+}
+
 fn create_domain_gauge_vec(registry: &Registry, name: &str, description: &str) -> IntGaugeVec {
     let gauge_opts = Opts::new(name, description);
     let labels = &["domain"];
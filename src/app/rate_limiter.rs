@@ -0,0 +1,320 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+/// What a client of a rate limit is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitKey {
+    Ip,
+    /// Who the request is authenticated as — the client certificate's CN or
+    /// the Google user.
+    Identity,
+    /// The value of a request header, by its name.
+    Header(String),
+    /// The value of a query parameter, by its name.
+    Query(String),
+}
+
+/// `rate_limit:` of global settings, an endpoint or a location, compiled. A
+/// reload that leaves the settings as they were keeps the buckets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimiterSettings {
+    /// `requests` every `per` on average.
+    pub requests: u64,
+    pub per: Duration,
+    /// Requests a client may make at once after being idle.
+    pub burst: u64,
+    pub key: RateLimitKey,
+    /// Every request turned away is a soft failure of the client's IP in the IP
+    /// block-list.
+    pub block_offenders: bool,
+    /// Clients tracked at once.
+    pub max_clients: usize,
+}
+
+impl RateLimiterSettings {
+    /// Tokens a bucket gets back every second.
+    fn get_refill_rate(&self) -> f64 {
+        self.requests as f64 / self.per.as_secs_f64()
+    }
+}
+
+/// What a request is known by, as far as the rate limits are concerned.
+pub struct RateLimitClient<'s> {
+    pub ip: Option<IpAddr>,
+    pub identity: Option<&'s str>,
+    pub get_header: &'s dyn Fn(&str) -> Option<&'s str>,
+    pub query: Option<&'s str>,
+}
+
+impl<'s> RateLimitClient<'s> {
+    fn get_query_value(&self, name: &str) -> Option<&'s str> {
+        self.query?
+            .split('&')
+            .find_map(|itm| match itm.split_once('=') {
+                Some((key, value)) if key == name => Some(value),
+                _ => None,
+            })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(settings: &RateLimiterSettings, now: Instant) -> Self {
+        Self {
+            tokens: settings.burst as f64,
+            updated: now,
+        }
+    }
+
+    fn get_tokens(&self, settings: &RateLimiterSettings, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * settings.get_refill_rate()).min(settings.burst as f64)
+    }
+
+    /// Takes a token, or returns how long until there is one.
+    fn take(&mut self, settings: &RateLimiterSettings, now: Instant) -> Result<(), Duration> {
+        self.tokens = self.get_tokens(settings, now);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64(
+            (1.0 - self.tokens) / settings.get_refill_rate(),
+        ))
+    }
+}
+
+struct RateLimiterBuckets {
+    /// By the hash of the client's key, so a long header value takes no more
+    /// memory than an IP.
+    clients: AHashMap<u64, Bucket>,
+    /// Shared by the clients that come while `max_clients` are tracked.
+    overflow: Bucket,
+}
+
+/// A token bucket per client.
+pub struct RateLimiter {
+    /// What the rate limit metrics are labelled with — `global`, the endpoint
+    /// or the location's `id_string`.
+    label: String,
+    settings: RateLimiterSettings,
+    hasher: ahash::RandomState,
+    buckets: Mutex<RateLimiterBuckets>,
+}
+
+impl RateLimiter {
+    pub fn new(label: String, settings: RateLimiterSettings) -> Self {
+        let now = Instant::now();
+        Self {
+            buckets: Mutex::new(RateLimiterBuckets {
+                clients: AHashMap::new(),
+                overflow: Bucket::new(&settings, now),
+            }),
+            hasher: ahash::RandomState::new(),
+            label,
+            settings,
+        }
+    }
+
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn get_settings(&self) -> &RateLimiterSettings {
+        &self.settings
+    }
+
+    /// Takes a token from the client's bucket, or returns how long until it
+    /// has one. A client with no key and no IP — over a unix socket — is not
+    /// limited.
+    fn check(&self, client: &RateLimitClient, now: Instant) -> Result<(), Duration> {
+        let Some(key) = self.get_client_hash(client) else {
+            return Ok(());
+        };
+
+        let mut buckets = self.buckets.lock();
+        let buckets = &mut *buckets;
+
+        let tracked =
+            buckets.clients.len() < self.settings.max_clients || buckets.clients.contains_key(&key);
+
+        let bucket = match tracked {
+            true => buckets
+                .clients
+                .entry(key)
+                .or_insert_with(|| Bucket::new(&self.settings, now)),
+            false => &mut buckets.overflow,
+        };
+
+        bucket.take(&self.settings, now)
+    }
+
+    fn get_client_hash(&self, client: &RateLimitClient) -> Option<u64> {
+        let value = match &self.settings.key {
+            RateLimitKey::Ip => None,
+            RateLimitKey::Identity => client.identity,
+            RateLimitKey::Header(name) => (client.get_header)(name),
+            RateLimitKey::Query(name) => client.get_query_value(name),
+        };
+
+        match value {
+            Some(value) => Some(self.hasher.hash_one(("key", value))),
+            None => Some(self.hasher.hash_one(("ip", client.ip?))),
+        }
+    }
+
+    /// Drops the clients whose buckets are full again — they are the same as
+    /// not tracked. Returns how many are left.
+    pub fn gc(&self) -> usize {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        let burst = self.settings.burst as f64;
+
+        buckets
+            .clients
+            .retain(|_, itm| itm.get_tokens(&self.settings, now) < burst);
+        buckets.clients.len()
+    }
+}
+
+/// Takes a token from the client's bucket of every limit in turn. On the first
+/// one without, returns how long the client is to wait — the `Retry-After` of
+/// the 429.
+pub fn check_rate_limits<'s>(
+    limiters: impl Iterator<Item = &'s Arc<RateLimiter>>,
+    client: &RateLimitClient,
+) -> Result<(), Duration> {
+    let now = Instant::now();
+
+    for limiter in limiters {
+        if let Err(retry_after) = limiter.check(client, now) {
+            crate::app::APP_CTX
+                .prometheus
+                .inc_rate_limited(limiter.get_label());
+
+            if limiter.settings.block_offenders {
+                if let Some(ip) = client.ip {
+                    crate::app::APP_CTX
+                        .ip_blocklist
+                        .register_failure(ip, crate::app::FailureSeverity::Soft);
+                }
+            }
+
+            return Err(retry_after);
+        }
+    }
+
+    Ok(())
+}
+
+/// The `Retry-After` value for a wait: whole seconds, at least one.
+pub fn get_retry_after_seconds(retry_after: Duration) -> u64 {
+    retry_after.as_secs_f64().ceil().max(1.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(key: RateLimitKey, max_clients: usize) -> RateLimiter {
+        RateLimiter::new(
+            "test".to_string(),
+            RateLimiterSettings {
+                requests: 2,
+                per: Duration::from_secs(1),
+                burst: 3,
+                key,
+                block_offenders: false,
+                max_clients,
+            },
+        )
+    }
+
+    fn no_headers(_: &str) -> Option<&'static str> {
+        None
+    }
+
+    fn client(ip: [u8; 4]) -> RateLimitClient<'static> {
+        RateLimitClient {
+            ip: Some(IpAddr::from(ip)),
+            identity: None,
+            get_header: &no_headers,
+            query: None,
+        }
+    }
+
+    #[test]
+    fn a_client_gets_the_burst_then_the_rate() {
+        let limiter = limiter(RateLimitKey::Ip, 100);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check(&client([10, 0, 0, 1]), now).is_ok());
+        }
+
+        let retry_after = limiter.check(&client([10, 0, 0, 1]), now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_millis(500));
+        assert_eq!(get_retry_after_seconds(retry_after), 1);
+
+        // Another client has a bucket of its own.
+        assert!(limiter.check(&client([10, 0, 0, 2]), now).is_ok());
+
+        // Two tokens a second come back.
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.check(&client([10, 0, 0, 1]), later).is_ok());
+        assert!(limiter.check(&client([10, 0, 0, 1]), later).is_err());
+    }
+
+    #[test]
+    fn a_request_without_the_key_is_counted_against_its_ip() {
+        let limiter = limiter(RateLimitKey::Query("api_key".to_string()), 100);
+        let now = Instant::now();
+
+        let with_key = |key: &'static str| RateLimitClient {
+            query: Some(key),
+            ..client([10, 0, 0, 1])
+        };
+
+        for _ in 0..3 {
+            assert!(limiter.check(&with_key("a=1&api_key=first"), now).is_ok());
+        }
+        assert!(limiter.check(&with_key("api_key=first"), now).is_err());
+
+        assert!(limiter.check(&with_key("api_key=second"), now).is_ok());
+        assert!(limiter.check(&with_key("a=1"), now).is_ok());
+    }
+
+    #[test]
+    fn clients_past_max_clients_share_a_bucket_until_gc() {
+        let limiter = limiter(RateLimitKey::Ip, 1);
+        let now = Instant::now();
+
+        assert!(limiter.check(&client([10, 0, 0, 1]), now).is_ok());
+
+        for ip in 2..5 {
+            assert!(limiter.check(&client([10, 0, 0, ip]), now).is_ok());
+        }
+        assert!(limiter.check(&client([10, 0, 0, 5]), now).is_err());
+
+        // The tracked client's bucket is not full, so it stays.
+        assert_eq!(limiter.gc(), 1);
+
+        let unix_socket = RateLimitClient {
+            ip: None,
+            ..client([0, 0, 0, 0])
+        };
+        assert!(limiter.check(&unix_socket, now).is_ok());
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::{Arc, Weak};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+use super::{RateLimiter, RateLimiterSettings};
+
+/// Every `rate_limit:` in the configurations applied so far, by what it
+/// limits — `global`, the endpoint or the location's `id_string`. A reload
+/// that leaves the settings as they were keeps the clients' buckets, so a
+/// reload does not hand every client a fresh burst.
+pub struct RateLimiters {
+    limiters: Mutex<AHashMap<String, Weak<RateLimiter>>>,
+}
+
+impl RateLimiters {
+    pub fn new() -> Self {
+        Self {
+            limiters: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    /// The limiter to use for `label` with `settings`.
+    pub fn apply(&self, label: &str, settings: RateLimiterSettings) -> Arc<RateLimiter> {
+        let mut limiters = self.limiters.lock();
+        limiters.retain(|_, itm| itm.strong_count() > 0);
+
+        if let Some(existing) = limiters.get(label).and_then(|itm| itm.upgrade()) {
+            if existing.get_settings() == &settings {
+                return existing;
+            }
+        }
+
+        let limiter = Arc::new(RateLimiter::new(label.to_string(), settings));
+        limiters.insert(label.to_string(), Arc::downgrade(&limiter));
+        limiter
+    }
+
+    /// Drops the idle clients of every limiter in use; returns the limiters
+    /// with the clients they still track.
+    pub fn gc(&self) -> Vec<(Arc<RateLimiter>, usize)> {
+        let limiters: Vec<Arc<RateLimiter>> = {
+            let mut limiters = self.limiters.lock();
+            limiters.retain(|_, itm| itm.strong_count() > 0);
+            limiters.values().filter_map(|itm| itm.upgrade()).collect()
+        };
+
+        limiters
+            .into_iter()
+            .map(|itm| {
+                let clients = itm.gc();
+                (itm, clients)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::{Arc, Weak};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+use crate::response_cache::{LocationCache, LocationCacheSettings};
+
+/// The `cache:` of every location in the configurations applied so far, by
+/// the location's `id_string`. A reload that leaves a location's cache
+/// settings as they were keeps what it has cached, and a purge reaches the
+/// caches of every configuration still serving requests.
+pub struct ResponseCaches {
+    caches: Mutex<AHashMap<String, Weak<LocationCache>>>,
+}
+
+impl ResponseCaches {
+    pub fn new() -> Self {
+        Self {
+            caches: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    /// The cache to use for the location `id_string` with `settings`.
+    pub fn apply(
+        &self,
+        id_string: &str,
+        settings: LocationCacheSettings,
+    ) -> Result<Arc<LocationCache>, String> {
+        let mut caches = self.caches.lock();
+        caches.retain(|_, itm| itm.strong_count() > 0);
+
+        if let Some(existing) = caches.get(id_string).and_then(|itm| itm.upgrade()) {
+            if existing.get_settings() == &settings {
+                return Ok(existing);
+            }
+        }
+
+        let cache = Arc::new(LocationCache::new(id_string.to_string(), settings)?);
+        caches.insert(id_string.to_string(), Arc::downgrade(&cache));
+        Ok(cache)
+    }
+
+    /// Purges the caches of the locations whose `id_string` starts with
+    /// `location` (all without one) of the responses whose host and path
+    /// start with `prefix` (all without one). Returns how many responses went.
+    pub async fn purge(&self, location: Option<&str>, prefix: Option<&str>) -> usize {
+        let caches: Vec<Arc<LocationCache>> = self
+            .caches
+            .lock()
+            .iter()
+            .filter(|(id_string, _)| location.is_none_or(|itm| id_string.starts_with(itm)))
+            .filter_map(|(_, itm)| itm.upgrade())
+            .collect();
+
+        let mut result = 0;
+        for cache in caches {
+            result += cache.purge(prefix).await;
+        }
+
+        result
+    }
+}
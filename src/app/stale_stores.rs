@@ -0,0 +1,38 @@
+use std::sync::{Arc, Weak};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+use crate::response_cache::{StaleStore, StaleStoreSettings};
+
+/// The `stale:` store of every location in the configurations applied so
+/// far, by the location's `id_string`. A reload that leaves a location's
+/// stale settings as they were keeps its last good responses — an upstream
+/// that is down across a reload is still covered.
+pub struct StaleStores {
+    stores: Mutex<AHashMap<String, Weak<StaleStore>>>,
+}
+
+impl StaleStores {
+    pub fn new() -> Self {
+        Self {
+            stores: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    /// The store to use for the location `id_string` with `settings`.
+    pub fn apply(&self, id_string: &str, settings: StaleStoreSettings) -> Arc<StaleStore> {
+        let mut stores = self.stores.lock();
+        stores.retain(|_, itm| itm.strong_count() > 0);
+
+        if let Some(existing) = stores.get(id_string).and_then(|itm| itm.upgrade()) {
+            if existing.get_settings() == &settings {
+                return existing;
+            }
+        }
+
+        let store = Arc::new(StaleStore::new(id_string.to_string(), settings));
+        stores.insert(id_string.to_string(), Arc::downgrade(&store));
+        store
+    }
+}
@@ -0,0 +1,382 @@
+use ahash::{AHashMap, AHashSet};
+use parking_lot::Mutex;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+/// Consecutive failed requests after which an upstream is ejected.
+const FAILURES_TO_EJECT: u32 = 5;
+/// Ejection time after the first ejection; doubles with every ejection that
+/// follows without a successful request in between.
+const BASE_EJECTION_SECS: i64 = 10;
+const MAX_EJECTION_SECS: i64 = 5 * 60;
+/// A half-open probe that never reported back (the client went away, the
+/// response was a websocket, ...) gives its slot up after this long, so the
+/// upstream is not stuck waiting for it.
+const PROBE_TIMEOUT_SECS: i64 = 30;
+
+/// Which pipeline reported the outcome — selects the gauge family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutlierProtocol {
+    H1,
+    H2,
+}
+
+/// What counts as a failed request for outlier detection. A 4xx or any other
+/// response the upstream produced on purpose is a success.
+#[derive(Clone, Copy, Debug)]
+pub enum UpstreamFailure {
+    /// Could not dial, or the connection broke before a response head arrived.
+    Connect,
+    Timeout,
+    /// Bytes came back, but not a valid HTTP response.
+    InvalidResponse,
+    /// The upstream answered with this 5xx status.
+    Status(u16),
+}
+
+impl std::fmt::Display for UpstreamFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect => write!(f, "connect failure"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::InvalidResponse => write!(f, "invalid response"),
+            Self::Status(status) => write!(f, "status {}", status),
+        }
+    }
+}
+
+/// Passive health of one upstream, driven only by the outcome of real
+/// requests:
+/// - healthy: every request is admitted; [`FAILURES_TO_EJECT`] failures in a
+///   row eject it;
+/// - ejected: balancing skips it until the back-off runs out;
+/// - half-open: the back-off ran out — exactly one request is admitted as a
+///   probe. Success brings the upstream back, failure ejects it again for
+///   twice as long.
+#[derive(Default)]
+struct OutlierState {
+    consecutive_failures: u32,
+    /// Ejections since the last success; drives the back-off.
+    ejections: u32,
+    ejected_until: Option<DateTimeAsMicroseconds>,
+    probe_started: Option<DateTimeAsMicroseconds>,
+}
+
+/// What a reported outcome did to the upstream — logged and mirrored into the
+/// gauges by the caller.
+#[derive(Debug, PartialEq, Eq)]
+enum OutlierTransition {
+    None,
+    Ejected { secs: i64 },
+    Recovered,
+}
+
+impl OutlierState {
+    fn is_ejected(&self) -> bool {
+        self.ejected_until.is_some()
+    }
+
+    fn admit(&mut self, now: DateTimeAsMicroseconds) -> bool {
+        let Some(ejected_until) = self.ejected_until else {
+            return true;
+        };
+
+        if now.unix_microseconds < ejected_until.unix_microseconds {
+            return false;
+        }
+
+        if let Some(probe_started) = self.probe_started {
+            if now.duration_since(probe_started).get_full_seconds() < PROBE_TIMEOUT_SECS {
+                return false;
+            }
+        }
+
+        self.probe_started = Some(now);
+        true
+    }
+
+    fn on_success(&mut self) -> OutlierTransition {
+        let was_ejected = self.is_ejected();
+        *self = Self::default();
+
+        if was_ejected {
+            OutlierTransition::Recovered
+        } else {
+            OutlierTransition::None
+        }
+    }
+
+    fn on_failure(&mut self, now: DateTimeAsMicroseconds) -> OutlierTransition {
+        if let Some(ejected_until) = self.ejected_until {
+            if now.unix_microseconds < ejected_until.unix_microseconds {
+                // A request that was already under way when the upstream got
+                // ejected — it says nothing new.
+                return OutlierTransition::None;
+            }
+
+            // The half-open probe (or, with nothing to balance to, any request
+            // after the back-off) failed.
+            return self.eject(now);
+        }
+
+        self.consecutive_failures += 1;
+        if self.consecutive_failures < FAILURES_TO_EJECT {
+            return OutlierTransition::None;
+        }
+
+        self.eject(now)
+    }
+
+    fn eject(&mut self, now: DateTimeAsMicroseconds) -> OutlierTransition {
+        self.ejections = self.ejections.saturating_add(1);
+        let secs = ejection_secs(self.ejections);
+
+        let mut ejected_until = now;
+        ejected_until.add_seconds(secs);
+
+        self.consecutive_failures = 0;
+        self.ejected_until = Some(ejected_until);
+        self.probe_started = None;
+
+        OutlierTransition::Ejected { secs }
+    }
+}
+
+fn ejection_secs(ejections: u32) -> i64 {
+    let shift = ejections.saturating_sub(1).min(16);
+    (BASE_EJECTION_SECS << shift).min(MAX_EJECTION_SECS)
+}
+
+struct OutlierEntry {
+    protocol: OutlierProtocol,
+    /// Gauge label — the name of the upstream the outcomes were reported for.
+    label: String,
+    state: OutlierState,
+}
+
+/// Passive outlier detection for upstreams, keyed by pool id (the upstream id
+/// of a balanced location, or of a backup). The h1 pipeline worker and the
+/// pooled h2 dispatch report the outcome of every request to them; balanced
+/// locations consult [`Self::admit`] so ejected upstreams stop receiving
+/// traffic. The single upstream of a location is not tracked — nothing would
+/// be sent elsewhere were it ejected.
+pub struct UpstreamOutliers {
+    map: Mutex<AHashMap<i64, OutlierEntry>>,
+}
+
+impl UpstreamOutliers {
+    pub fn new() -> Self {
+        Self {
+            map: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    /// Whether a request may be sent to the upstream. Claims the half-open
+    /// probe when the back-off has run out, so call it only for the upstream
+    /// the request will actually go to.
+    pub fn admit(&self, id: i64) -> bool {
+        let mut map = self.map.lock();
+        match map.get_mut(&id) {
+            Some(entry) => entry.state.admit(DateTimeAsMicroseconds::now()),
+            None => true,
+        }
+    }
+
+    pub fn is_ejected(&self, id: i64) -> bool {
+        let map = self.map.lock();
+        match map.get(&id) {
+            Some(entry) => entry.state.is_ejected(),
+            None => false,
+        }
+    }
+
+    pub fn register_success(&self, id: i64) {
+        let (entry, transition) = {
+            let mut map = self.map.lock();
+            // Only upstreams that have failed have an entry — the common case
+            // is a lookup miss.
+            let Some(mut entry) = map.remove(&id) else {
+                return;
+            };
+
+            let transition = entry.state.on_success();
+            (entry, transition)
+        };
+
+        crate::app::APP_CTX
+            .prometheus
+            .set_upstream_outlier(entry.protocol, &entry.label, false, 0);
+
+        if transition == OutlierTransition::Recovered {
+            crate::app::APP_CTX.proxy_logs.write_port(
+                "upstream-outliers",
+                None,
+                format!("Upstream {} is back in rotation", entry.label),
+            );
+        }
+    }
+
+    pub fn register_failure(
+        &self,
+        id: i64,
+        protocol: OutlierProtocol,
+        label: &str,
+        failure: UpstreamFailure,
+    ) {
+        let now = DateTimeAsMicroseconds::now();
+
+        let (transition, ejected, consecutive_failures) = {
+            let mut map = self.map.lock();
+            let entry = map.entry(id).or_insert_with(|| OutlierEntry {
+                protocol,
+                label: label.to_string(),
+                state: OutlierState::default(),
+            });
+            entry.protocol = protocol;
+
+            let transition = entry.state.on_failure(now);
+            (
+                transition,
+                entry.state.is_ejected(),
+                entry.state.consecutive_failures,
+            )
+        };
+
+        crate::app::APP_CTX.prometheus.set_upstream_outlier(
+            protocol,
+            label,
+            ejected,
+            consecutive_failures as i64,
+        );
+
+        // One line per ejection, not per failure.
+        if let OutlierTransition::Ejected { secs } = transition {
+            crate::app::APP_CTX.proxy_logs.write_port(
+                "upstream-outliers",
+                None,
+                format!("Ejected upstream {label} for {secs}s (last failure: {failure})"),
+            );
+        }
+    }
+
+    /// Forgets upstreams no longer referenced by the configuration. Called
+    /// by `GcPoolsTimer` alongside the pool registries.
+    pub fn drain_unused(&self, active: &AHashSet<i64>) {
+        let removed: Vec<OutlierEntry> = {
+            let mut map = self.map.lock();
+            let ids: Vec<i64> = map
+                .keys()
+                .filter(|id| !active.contains(id))
+                .copied()
+                .collect();
+            ids.into_iter().filter_map(|id| map.remove(&id)).collect()
+        };
+
+        for entry in removed {
+            crate::app::APP_CTX.prometheus.set_upstream_outlier(
+                entry.protocol,
+                &entry.label,
+                false,
+                0,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTimeAsMicroseconds {
+        DateTimeAsMicroseconds::new(1_700_000_000_000_000 + secs * 1_000_000)
+    }
+
+    fn fail_times(state: &mut OutlierState, now: DateTimeAsMicroseconds, times: u32) {
+        for _ in 0..times {
+            state.on_failure(now);
+        }
+    }
+
+    #[test]
+    fn consecutive_failures_eject() {
+        let mut state = OutlierState::default();
+
+        fail_times(&mut state, at(0), FAILURES_TO_EJECT - 1);
+        assert!(state.admit(at(0)));
+
+        assert_eq!(
+            state.on_failure(at(0)),
+            OutlierTransition::Ejected {
+                secs: BASE_EJECTION_SECS
+            }
+        );
+        assert!(!state.admit(at(1)));
+    }
+
+    #[test]
+    fn a_success_resets_the_count() {
+        let mut state = OutlierState::default();
+
+        fail_times(&mut state, at(0), FAILURES_TO_EJECT - 1);
+        assert_eq!(state.on_success(), OutlierTransition::None);
+        fail_times(&mut state, at(0), FAILURES_TO_EJECT - 1);
+
+        assert!(!state.is_ejected());
+    }
+
+    #[test]
+    fn half_open_admits_a_single_probe() {
+        let mut state = OutlierState::default();
+        fail_times(&mut state, at(0), FAILURES_TO_EJECT);
+
+        let after_backoff = at(BASE_EJECTION_SECS);
+        assert!(state.admit(after_backoff));
+        assert!(!state.admit(after_backoff));
+
+        // A probe that never reports back frees the slot eventually.
+        assert!(state.admit(at(BASE_EJECTION_SECS + PROBE_TIMEOUT_SECS)));
+    }
+
+    #[test]
+    fn successful_probe_recovers() {
+        let mut state = OutlierState::default();
+        fail_times(&mut state, at(0), FAILURES_TO_EJECT);
+
+        assert!(state.admit(at(BASE_EJECTION_SECS)));
+        assert_eq!(state.on_success(), OutlierTransition::Recovered);
+        assert!(state.admit(at(BASE_EJECTION_SECS)));
+        assert!(state.admit(at(BASE_EJECTION_SECS)));
+    }
+
+    #[test]
+    fn failed_probe_doubles_the_backoff() {
+        let mut state = OutlierState::default();
+        fail_times(&mut state, at(0), FAILURES_TO_EJECT);
+
+        let probe_at = at(BASE_EJECTION_SECS);
+        assert!(state.admit(probe_at));
+        assert_eq!(
+            state.on_failure(probe_at),
+            OutlierTransition::Ejected {
+                secs: BASE_EJECTION_SECS * 2
+            }
+        );
+        assert!(!state.admit(at(BASE_EJECTION_SECS * 2)));
+        assert!(state.admit(at(BASE_EJECTION_SECS * 3)));
+    }
+
+    #[test]
+    fn failures_of_requests_in_flight_at_ejection_are_ignored() {
+        let mut state = OutlierState::default();
+        fail_times(&mut state, at(0), FAILURES_TO_EJECT);
+
+        assert_eq!(state.on_failure(at(1)), OutlierTransition::None);
+        assert!(state.admit(at(BASE_EJECTION_SECS)));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(ejection_secs(1), BASE_EJECTION_SECS);
+        assert_eq!(ejection_secs(3), BASE_EJECTION_SECS * 4);
+        assert_eq!(ejection_secs(100), MAX_EJECTION_SECS);
+    }
+}
@@ -0,0 +1,296 @@
+use std::io::Write;
+
+/// A `Content-Encoding` the proxy can decode and encode a body in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    Identity,
+    Gzip,
+    Zstd,
+    /// Only ever encoded: a brotli body from the upstream is passed on as it
+    /// is.
+    Brotli,
+}
+
+impl ContentCoding {
+    /// What the proxy encodes a response in for a client sending
+    /// `accept_encoding`: the coding it gives the highest `q`, the proxy's
+    /// preference — zstd, br, gzip — between equal ones. `None` when it
+    /// accepts none of them.
+    pub fn negotiate(accept_encoding: Option<&str>) -> Option<Self> {
+        Self::get_accepted(accept_encoding).into_iter().next()
+    }
+
+    /// The codings of the proxy the client sending `accept_encoding` accepts,
+    /// the one [`Self::negotiate`] picks first.
+    pub fn get_accepted(accept_encoding: Option<&str>) -> Vec<Self> {
+        let Some(accept_encoding) = accept_encoding else {
+            return vec![];
+        };
+
+        let mut result: Vec<(Self, f32)> = Vec::with_capacity(3);
+
+        for coding in [Self::Zstd, Self::Brotli, Self::Gzip] {
+            let mut q = None;
+            for itm in accept_encoding.split(',') {
+                let mut parts = itm.split(';');
+                let name = parts.next().unwrap_or_default().trim();
+
+                let itm_q = parts
+                    .filter_map(|itm| itm.trim().strip_prefix("q="))
+                    .find_map(|itm| itm.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+
+                if name.eq_ignore_ascii_case(coding.as_str()) {
+                    q = Some(itm_q);
+                    break;
+                }
+
+                if name == "*" {
+                    q = Some(itm_q);
+                }
+            }
+
+            if let Some(q) = q.filter(|itm| *itm > 0.0) {
+                result.push((coding, q));
+            }
+        }
+
+        // Stable: equal ones stay in the proxy's preference.
+        result.sort_by(|a, b| b.1.total_cmp(&a.1));
+        result.into_iter().map(|(coding, _)| coding).collect()
+    }
+
+    /// `None` for a coding the proxy has no decoder for — or a body encoded
+    /// twice over.
+    pub fn parse(content_encoding: Option<&str>) -> Option<Self> {
+        let content_encoding = content_encoding.map(|itm| itm.trim()).unwrap_or_default();
+
+        if content_encoding.is_empty() || content_encoding.eq_ignore_ascii_case("identity") {
+            return Some(Self::Identity);
+        }
+
+        if content_encoding.eq_ignore_ascii_case("gzip")
+            || content_encoding.eq_ignore_ascii_case("x-gzip")
+        {
+            return Some(Self::Gzip);
+        }
+
+        if content_encoding.eq_ignore_ascii_case("zstd") {
+            return Some(Self::Zstd);
+        }
+
+        None
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Brotli => "br",
+        }
+    }
+
+    /// The extension of a file precompressed in the coding — `app.js.gz`.
+    pub fn get_file_extension(&self) -> Option<&'static str> {
+        match self {
+            Self::Identity => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+            Self::Brotli => Some("br"),
+        }
+    }
+}
+
+/// Decodes a body piece by piece, as it arrives.
+pub enum BodyDecoder {
+    Identity,
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+}
+
+impl BodyDecoder {
+    pub fn new(coding: ContentCoding) -> std::io::Result<Self> {
+        match coding {
+            ContentCoding::Identity => Ok(Self::Identity),
+            ContentCoding::Gzip => Ok(Self::Gzip(flate2::write::GzDecoder::new(Vec::new()))),
+            ContentCoding::Zstd => Ok(Self::Zstd(zstd::stream::write::Decoder::new(Vec::new())?)),
+            ContentCoding::Brotli => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "brotli bodies are not decoded",
+            )),
+        }
+    }
+
+    /// What `data` decodes to, as far as it can be decoded yet.
+    pub fn decode(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(data.to_vec()),
+            Self::Gzip(decoder) => {
+                decoder.write_all(data)?;
+                decoder.flush()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            Self::Zstd(decoder) => {
+                decoder.write_all(data)?;
+                decoder.flush()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+
+    /// What is left once the body has ended.
+    pub fn finish(&mut self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(Vec::new()),
+            Self::Gzip(decoder) => {
+                decoder.try_finish()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            Self::Zstd(decoder) => {
+                decoder.flush()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+}
+
+/// Encodes a body piece by piece. Every piece is flushed, so what the client
+/// gets for it never waits for the next one.
+pub enum BodyEncoder {
+    Identity,
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+}
+
+impl BodyEncoder {
+    pub fn new(coding: ContentCoding) -> std::io::Result<Self> {
+        match coding {
+            ContentCoding::Identity => Ok(Self::Identity),
+            ContentCoding::Gzip => Ok(Self::Gzip(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            ))),
+            ContentCoding::Zstd => Ok(Self::Zstd(zstd::stream::write::Encoder::new(
+                Vec::new(),
+                zstd::DEFAULT_COMPRESSION_LEVEL,
+            )?)),
+            ContentCoding::Brotli => Ok(Self::Brotli(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                4096,
+                crate::consts::BROTLI_QUALITY,
+                crate::consts::BROTLI_LG_WINDOW_SIZE,
+            )))),
+        }
+    }
+
+    pub fn encode(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        if data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match self {
+            Self::Identity => Ok(data.to_vec()),
+            Self::Gzip(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+            Self::Zstd(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+            Self::Brotli(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+        }
+    }
+
+    /// The end of the encoded body.
+    pub fn finish(&mut self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(Vec::new()),
+            Self::Gzip(encoder) => {
+                encoder.try_finish()?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+            Self::Zstd(encoder) => {
+                encoder.do_finish()?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+            // A brotli stream only ends when its writer is taken apart.
+            Self::Brotli(_) => match std::mem::replace(self, Self::Identity) {
+                Self::Brotli(encoder) => Ok(encoder.into_inner()),
+                _ => Ok(Vec::new()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_body_encoded_piece_by_piece_decodes_back() {
+        for coding in [
+            ContentCoding::Identity,
+            ContentCoding::Gzip,
+            ContentCoding::Zstd,
+        ] {
+            let mut encoder = BodyEncoder::new(coding).unwrap();
+            let mut encoded = encoder.encode(b"Hello, ").unwrap();
+            encoded.extend(encoder.encode(b"World").unwrap());
+            encoded.extend(encoder.finish().unwrap());
+
+            let mut decoder = BodyDecoder::new(coding).unwrap();
+            let mut decoded = Vec::new();
+            for piece in encoded.chunks(3) {
+                decoded.extend(decoder.decode(piece).unwrap());
+            }
+            decoded.extend(decoder.finish().unwrap());
+
+            assert_eq!(decoded, b"Hello, World", "{:?}", coding);
+        }
+
+        assert_eq!(ContentCoding::parse(None), Some(ContentCoding::Identity));
+        assert_eq!(
+            ContentCoding::parse(Some("GZIP")),
+            Some(ContentCoding::Gzip)
+        );
+        assert_eq!(ContentCoding::parse(Some("br")), None);
+        assert_eq!(ContentCoding::parse(Some("gzip, br")), None);
+    }
+
+    #[test]
+    fn accept_encoding_is_negotiated() {
+        assert_eq!(
+            ContentCoding::negotiate(Some("gzip, deflate, br, zstd")),
+            Some(ContentCoding::Zstd)
+        );
+        assert_eq!(
+            ContentCoding::negotiate(Some("gzip;q=1.0, br;q=0.8")),
+            Some(ContentCoding::Gzip)
+        );
+        assert_eq!(
+            ContentCoding::negotiate(Some("*;q=0.5, zstd;q=0")),
+            Some(ContentCoding::Brotli)
+        );
+        assert_eq!(ContentCoding::negotiate(Some("identity, deflate")), None);
+        assert_eq!(ContentCoding::negotiate(Some("gzip;q=0")), None);
+        assert_eq!(ContentCoding::negotiate(None), None);
+
+        assert_eq!(
+            ContentCoding::get_accepted(Some("gzip, br;q=0.9, zstd;q=0.5")),
+            vec![
+                ContentCoding::Gzip,
+                ContentCoding::Brotli,
+                ContentCoding::Zstd
+            ]
+        );
+    }
+}
@@ -0,0 +1,21 @@
+/// Whether the media type of `content_type` is one of `types` — lowercase,
+/// with `text/*` taking a whole family and `*` taking every type.
+pub fn is_media_type_listed(types: &[String], content_type: &str) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    types.iter().any(|itm| {
+        if itm == "*" || *itm == media_type {
+            return true;
+        }
+
+        match itm.strip_suffix('*') {
+            Some(family) => family.ends_with('/') && media_type.starts_with(family),
+            None => false,
+        }
+    })
+}
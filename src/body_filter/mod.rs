@@ -0,0 +1,12 @@
+mod content_coding;
+pub use content_coding::*;
+mod media_type;
+pub use media_type::*;
+mod partial_content;
+pub use partial_content::*;
+mod sub_filter;
+pub use sub_filter::*;
+mod response_compression;
+pub use response_compression::*;
+mod response_body_filter;
+pub use response_body_filter::*;
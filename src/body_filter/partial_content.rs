@@ -0,0 +1,6 @@
+/// Whether a response carries a part of its resource — a `206`, or anything
+/// with a `Content-Range`. Its bytes are addressed by the client as the
+/// upstream counted them, so neither `sub_filter` nor `compress` may touch it.
+pub fn is_partial_content(status: Option<u16>, content_range: Option<&str>) -> bool {
+    status == Some(206) || content_range.is_some()
+}
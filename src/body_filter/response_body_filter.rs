@@ -0,0 +1,83 @@
+use super::{BodyEncoder, SubFilterStream};
+
+/// What a response body goes through on its way to the client: the location's
+/// `sub_filter`, then the encoding negotiated for `compress`. Every piece is
+/// passed on as soon as it is filtered, so a stream of events — SSE — is never
+/// held back.
+pub struct ResponseBodyFilter {
+    sub_filter: Option<SubFilterStream>,
+    encoder: Option<BodyEncoder>,
+}
+
+impl ResponseBodyFilter {
+    /// `None` when the body goes through neither.
+    pub fn new(sub_filter: Option<SubFilterStream>, encoder: Option<BodyEncoder>) -> Option<Self> {
+        if sub_filter.is_none() && encoder.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            sub_filter,
+            encoder,
+        })
+    }
+
+    /// Takes the next piece of the body as the upstream sent it. Returns what
+    /// goes to the client for it.
+    pub fn push(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let filtered = match self.sub_filter.as_mut() {
+            Some(sub_filter) => sub_filter.push(data)?,
+            None => data.to_vec(),
+        };
+
+        match self.encoder.as_mut() {
+            Some(encoder) => encoder.encode(&filtered),
+            None => Ok(filtered),
+        }
+    }
+
+    /// The rest of the body, once the upstream's has ended.
+    pub fn finish(&mut self) -> std::io::Result<Vec<u8>> {
+        let filtered = match self.sub_filter.as_mut() {
+            Some(sub_filter) => sub_filter.finish()?,
+            None => Vec::new(),
+        };
+
+        match self.encoder.as_mut() {
+            Some(encoder) => {
+                let mut result = encoder.encode(&filtered)?;
+                result.extend(encoder.finish()?);
+                Ok(result)
+            }
+            None => Ok(filtered),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body_filter::{BodyDecoder, ContentCoding};
+
+    #[test]
+    fn every_event_reaches_the_client_as_it_comes() {
+        for coding in [ContentCoding::Gzip, ContentCoding::Zstd] {
+            let encoder = BodyEncoder::new(coding).unwrap();
+            let mut filter = ResponseBodyFilter::new(None, Some(encoder)).unwrap();
+            let mut decoder = BodyDecoder::new(coding).unwrap();
+
+            for event in [b"data: one\n\n".as_slice(), b"data: two\n\n"] {
+                let sent = filter.push(event).unwrap();
+                assert_eq!(decoder.decode(&sent).unwrap(), event, "{:?}", coding);
+            }
+
+            assert!(decoder
+                .decode(&filter.finish().unwrap())
+                .unwrap()
+                .is_empty());
+            assert!(decoder.finish().unwrap().is_empty());
+        }
+
+        assert!(ResponseBodyFilter::new(None, None).is_none());
+    }
+}
@@ -0,0 +1,139 @@
+use super::{BodyEncoder, ContentCoding};
+
+/// `compress`, `compress_types` and `compress_min_size` of a location,
+/// compiled: the upstream's responses are encoded in what the client accepts.
+#[derive(Debug)]
+pub struct ResponseCompression {
+    /// Lowercase media types; `text/*` and `*` take a whole family.
+    types: Vec<String>,
+    /// A response with a smaller `Content-Length` goes on as it is.
+    min_size: usize,
+}
+
+impl ResponseCompression {
+    pub fn new(types: Vec<String>, min_size: usize) -> Self {
+        Self {
+            types: types.into_iter().map(|itm| itm.to_lowercase()).collect(),
+            min_size,
+        }
+    }
+
+    /// Whether a response with this status and these headers is one the
+    /// location encodes — and so one that varies by `Accept-Encoding`. Not one
+    /// the upstream has encoded already, nor one it asks not to be changed
+    /// (`no-transform`), nor a part of a resource.
+    pub fn is_compressible(
+        &self,
+        status: Option<u16>,
+        content_type: Option<&str>,
+        content_encoding: Option<&str>,
+        content_range: Option<&str>,
+        cache_control: Option<&str>,
+    ) -> bool {
+        let Some(content_type) = content_type else {
+            return false;
+        };
+
+        if super::is_partial_content(status, content_range) {
+            return false;
+        }
+
+        if ContentCoding::parse(content_encoding) != Some(ContentCoding::Identity) {
+            return false;
+        }
+
+        let no_transform = cache_control
+            .map(|itm| {
+                itm.split(',')
+                    .any(|itm| itm.trim().eq_ignore_ascii_case("no-transform"))
+            })
+            .unwrap_or(false);
+
+        !no_transform && super::is_media_type_listed(&self.types, content_type)
+    }
+
+    /// Starts encoding a compressible response body in `coding`. `None` for
+    /// one of a known size below `compress_min_size`; a body of unknown size
+    /// is always encoded.
+    pub fn start(
+        &self,
+        coding: ContentCoding,
+        content_length: Option<usize>,
+    ) -> Option<BodyEncoder> {
+        if content_length
+            .map(|itm| itm < self.min_size)
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        if coding == ContentCoding::Identity {
+            return None;
+        }
+
+        BodyEncoder::new(coding).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_listed_unencoded_responses_are_compressed() {
+        let compression = ResponseCompression::new(
+            vec!["text/html".to_string(), "application/*".to_string()],
+            1024,
+        );
+
+        let compressible = |content_type, content_encoding, cache_control| {
+            compression.is_compressible(
+                Some(200),
+                content_type,
+                content_encoding,
+                None,
+                cache_control,
+            )
+        };
+
+        assert!(compressible(Some("text/html; charset=utf-8"), None, None));
+        assert!(compressible(
+            Some("application/json"),
+            Some("identity"),
+            None
+        ));
+        assert!(!compressible(Some("image/png"), None, None));
+        assert!(!compressible(None, None, None));
+        assert!(!compressible(Some("text/html"), Some("gzip"), None));
+        assert!(!compressible(
+            Some("text/html"),
+            None,
+            Some("public, No-Transform")
+        ));
+
+        assert!(compression.start(ContentCoding::Gzip, Some(100)).is_none());
+        assert!(compression.start(ContentCoding::Gzip, Some(4096)).is_some());
+        assert!(compression.start(ContentCoding::Zstd, None).is_some());
+    }
+
+    #[test]
+    fn a_part_of_a_resource_is_not_compressed() {
+        let compression = ResponseCompression::new(vec!["text/html".to_string()], 0);
+
+        assert!(!compression.is_compressible(
+            Some(206),
+            Some("text/html"),
+            None,
+            Some("bytes 0-99/1000"),
+            None
+        ));
+        assert!(!compression.is_compressible(Some(206), Some("text/html"), None, None, None));
+        assert!(!compression.is_compressible(
+            Some(200),
+            Some("text/html"),
+            None,
+            Some("bytes */1000"),
+            None
+        ));
+    }
+}
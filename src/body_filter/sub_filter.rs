@@ -0,0 +1,329 @@
+use std::sync::Arc;
+
+use regex::bytes::{Regex, RegexBuilder};
+
+use super::{BodyDecoder, BodyEncoder, ContentCoding};
+
+/// One entry of `sub_filter`.
+#[derive(Debug)]
+pub struct SubFilterRule {
+    regex: Regex,
+    to: Vec<u8>,
+    /// Literal rules put `to` in as it is; regex ones expand `${1}` /
+    /// `${name}` in it.
+    expand: bool,
+    /// Length of a literal `from`; a regex match can be of any.
+    literal_len: Option<usize>,
+}
+
+impl SubFilterRule {
+    /// `from` is a literal, or a regex as `~...` (`~*...` for a
+    /// case-insensitive one) — the way `match:` reads them.
+    pub fn parse(from: &str, to: &str) -> Result<Self, String> {
+        let (pattern, case_sensitive) = if let Some(pattern) = from.strip_prefix("~*") {
+            (pattern.trim(), false)
+        } else if let Some(pattern) = from.strip_prefix('~') {
+            (pattern.trim(), true)
+        } else {
+            return Ok(Self {
+                regex: Regex::new(&regex::escape(from))
+                    .map_err(|err| format!("sub_filter '{}' is invalid: {}", from, err))?,
+                to: to.as_bytes().to_vec(),
+                expand: false,
+                literal_len: Some(from.len()),
+            });
+        };
+
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|err| format!("sub_filter regex '{}' is invalid: {}", pattern, err))?;
+
+        Ok(Self {
+            regex,
+            to: to.as_bytes().to_vec(),
+            expand: true,
+            literal_len: None,
+        })
+    }
+
+    fn apply(&self, src: Vec<u8>) -> Vec<u8> {
+        let result = match self.expand {
+            true => self.regex.replace_all(&src, self.to.as_slice()),
+            false => self
+                .regex
+                .replace_all(&src, regex::bytes::NoExpand(self.to.as_slice())),
+        };
+
+        match result {
+            std::borrow::Cow::Borrowed(_) => src,
+            std::borrow::Cow::Owned(result) => result,
+        }
+    }
+}
+
+/// `sub_filter` and `sub_filter_types` of a location, compiled.
+#[derive(Debug)]
+pub struct SubFilter {
+    rules: Vec<SubFilterRule>,
+    /// Lowercase media types; `text/*` and `*` take a whole family.
+    types: Vec<String>,
+    /// Bytes at the end of a piece of the body that may be the start of a
+    /// literal match, so are held back for the next one.
+    hold_back: usize,
+}
+
+impl SubFilter {
+    pub fn new(rules: Vec<SubFilterRule>, types: Vec<String>) -> Self {
+        let hold_back = rules
+            .iter()
+            .filter_map(|itm| itm.literal_len)
+            .max()
+            .unwrap_or_default()
+            .saturating_sub(1);
+
+        Self {
+            rules,
+            types: types.into_iter().map(|itm| itm.to_lowercase()).collect(),
+            hold_back,
+        }
+    }
+
+    pub fn is_type_filtered(&self, content_type: &str) -> bool {
+        super::is_media_type_listed(&self.types, content_type)
+    }
+
+    /// Starts filtering a response body with this status and these headers.
+    /// `None` when it is left alone: its type is not one of
+    /// `sub_filter_types`, it is encoded in a way the proxy can not decode, or
+    /// it is a part of a resource.
+    pub fn start(
+        self: &Arc<Self>,
+        status: Option<u16>,
+        content_type: Option<&str>,
+        content_encoding: Option<&str>,
+        content_range: Option<&str>,
+    ) -> Option<SubFilterStream> {
+        if super::is_partial_content(status, content_range) {
+            return None;
+        }
+
+        if !self.is_type_filtered(content_type?) {
+            return None;
+        }
+
+        let coding = ContentCoding::parse(content_encoding)?;
+
+        Some(SubFilterStream {
+            filter: self.clone(),
+            decoder: BodyDecoder::new(coding).ok()?,
+            encoder: BodyEncoder::new(coding).ok()?,
+            pending: Vec::new(),
+        })
+    }
+
+    fn apply(&self, src: &[u8]) -> Vec<u8> {
+        let mut result = src.to_vec();
+        for rule in self.rules.iter() {
+            result = rule.apply(result);
+        }
+        result
+    }
+}
+
+/// One response body going through a [`SubFilter`]: decoded, filtered line by
+/// line and encoded back the way the upstream encoded it.
+pub struct SubFilterStream {
+    filter: Arc<SubFilter>,
+    decoder: BodyDecoder,
+    encoder: BodyEncoder,
+    /// Decoded bytes waiting for the rest of their line.
+    pending: Vec<u8>,
+}
+
+impl SubFilterStream {
+    /// Takes the next piece of the body as the upstream sent it. Returns what
+    /// goes to the client for it — nothing, while a line is not complete.
+    pub fn push(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let decoded = self.decoder.decode(data)?;
+        self.pending.extend_from_slice(&decoded);
+
+        let cut = self.get_cut();
+        if cut == 0 {
+            return Ok(Vec::new());
+        }
+
+        let filtered = self.filter.apply(&self.pending[..cut]);
+        self.pending.drain(..cut);
+        self.encoder.encode(&filtered)
+    }
+
+    /// The rest of the body, once the upstream's has ended.
+    pub fn finish(&mut self) -> std::io::Result<Vec<u8>> {
+        let decoded = self.decoder.finish()?;
+        self.pending.extend_from_slice(&decoded);
+
+        let filtered = self.filter.apply(&self.pending);
+        self.pending.clear();
+
+        let mut result = self.encoder.encode(&filtered)?;
+        result.extend(self.encoder.finish()?);
+        Ok(result)
+    }
+
+    /// How much of `pending` can be filtered now: up to its last line break,
+    /// or — for a line longer than [`crate::consts::SUB_FILTER_WINDOW`] — all
+    /// but what may be the start of a match. Never in the middle of a match.
+    fn get_cut(&self) -> usize {
+        let window = crate::consts::SUB_FILTER_WINDOW;
+        let len = self.pending.len();
+
+        let mut cut = match self.pending.iter().rposition(|itm| *itm == b'\n') {
+            Some(pos) if len - pos - 1 <= window => pos + 1,
+            _ if len > window => len - self.filter.hold_back.min(len),
+            _ => return 0,
+        };
+
+        // A match no shorter than the window itself is cut through anyway.
+        if len > window * 2 {
+            return cut;
+        }
+
+        loop {
+            let before = cut;
+            for rule in self.filter.rules.iter() {
+                for found in rule.regex.find_iter(&self.pending) {
+                    if found.start() >= cut {
+                        break;
+                    }
+
+                    if found.end() > cut {
+                        cut = found.start();
+                    }
+                }
+            }
+
+            if cut == before {
+                return cut;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(rules: &[(&str, &str)]) -> Arc<SubFilter> {
+        Arc::new(SubFilter::new(
+            rules
+                .iter()
+                .map(|(from, to)| SubFilterRule::parse(from, to).unwrap())
+                .collect(),
+            vec!["text/html".to_string(), "application/*".to_string()],
+        ))
+    }
+
+    fn run(stream: &mut SubFilterStream, pieces: &[&[u8]]) -> Vec<u8> {
+        let mut result = Vec::new();
+        for piece in pieces {
+            result.extend(stream.push(piece).unwrap());
+        }
+        result.extend(stream.finish().unwrap());
+        result
+    }
+
+    #[test]
+    fn matches_split_between_pieces_are_replaced() {
+        let filter = filter(&[
+            ("http://10.0.0.5:5123", "https://example.com"),
+            ("~*src=\"/(\\w+)\\.js\"", "src=\"/static/${1}.js\""),
+        ]);
+
+        let mut stream = filter
+            .start(Some(200), Some("text/html; charset=utf-8"), None, None)
+            .unwrap();
+        let result = run(
+            &mut stream,
+            &[
+                b"<a href=\"http://10.0.",
+                b"0.5:5123/login\">\n<script SRC=\"/app",
+                b".js\"></script>\n$1",
+            ],
+        );
+
+        assert_eq!(
+            String::from_utf8(result).unwrap(),
+            "<a href=\"https://example.com/login\">\n<script src=\"/static/app.js\"></script>\n$1"
+        );
+
+        assert!(filter
+            .start(Some(200), Some("image/png"), None, None)
+            .is_none());
+        assert!(filter
+            .start(Some(200), Some("application/json"), None, None)
+            .is_some());
+        assert!(filter.start(Some(200), None, None, None).is_none());
+        assert!(filter
+            .start(Some(200), Some("text/html"), Some("br"), None)
+            .is_none());
+
+        assert!(filter
+            .start(Some(206), Some("text/html"), None, None)
+            .is_none());
+        assert!(filter
+            .start(Some(200), Some("text/html"), None, Some("bytes 0-99/1000"))
+            .is_none());
+    }
+
+    #[test]
+    fn a_line_longer_than_the_window_is_filtered_in_pieces() {
+        let filter = filter(&[("internal.local", "example.com")]);
+        let mut stream = filter
+            .start(Some(200), Some("text/html"), None, None)
+            .unwrap();
+
+        let mut body = vec![b'x'; crate::consts::SUB_FILTER_WINDOW - 5];
+        body.extend_from_slice(b"internal.local");
+        body.extend(vec![b'y'; 100]);
+
+        let first = stream
+            .push(&body[..crate::consts::SUB_FILTER_WINDOW + 2])
+            .unwrap();
+        assert!(
+            !first.is_empty(),
+            "a line over the window is not held whole"
+        );
+
+        let mut result = first;
+        result.extend(run(
+            &mut stream,
+            &[&body[crate::consts::SUB_FILTER_WINDOW + 2..]],
+        ));
+
+        let mut expected = vec![b'x'; crate::consts::SUB_FILTER_WINDOW - 5];
+        expected.extend_from_slice(b"example.com");
+        expected.extend(vec![b'y'; 100]);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn an_encoded_body_is_encoded_back() {
+        let filter = filter(&[("internal", "public")]);
+
+        let mut encoder = BodyEncoder::new(ContentCoding::Gzip).unwrap();
+        let mut upstream_body = encoder.encode(b"see internal\nand internal").unwrap();
+        upstream_body.extend(encoder.finish().unwrap());
+
+        let mut stream = filter
+            .start(Some(200), Some("text/html"), Some("gzip"), None)
+            .unwrap();
+        let pieces: Vec<&[u8]> = upstream_body.chunks(7).collect();
+        let result = run(&mut stream, &pieces);
+
+        let mut decoder = BodyDecoder::new(ContentCoding::Gzip).unwrap();
+        let mut decoded = decoder.decode(&result).unwrap();
+        decoded.extend(decoder.finish().unwrap());
+        assert_eq!(decoded, b"see public\nand public");
+    }
+}
@@ -20,6 +20,14 @@ impl ListenConfiguration {
             ListenConfiguration::Mcp(_) => None,
         }
     }
+
+    pub fn get_connection_limits(&self) -> crate::app::ConnectionLimits {
+        match self {
+            ListenConfiguration::Http(config) => config.get_connection_limits(),
+            ListenConfiguration::Tcp(config) => config.connection_limits,
+            ListenConfiguration::Mcp(config) => config.get_connection_limits(),
+        }
+    }
 }
 
 pub struct AppConfigurationInner {
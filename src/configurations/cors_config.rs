@@ -0,0 +1,313 @@
+/// One entry of `allowed_origins`.
+#[derive(Debug)]
+pub enum CorsOrigin {
+    /// `*` — any origin.
+    Any,
+    /// `https://app.example.com`.
+    Exact(String),
+    /// `https://*.example.com` — the `*` stands for one or more characters,
+    /// none of them a `/`.
+    Wildcard { prefix: String, suffix: String },
+    /// `~^https://app[0-9]+\.example\.com$`, or `~*` for a case-insensitive
+    /// regex — the way `match:` reads them.
+    Regex(regex::Regex),
+}
+
+impl CorsOrigin {
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let src = src.trim();
+
+        if src == "*" {
+            return Ok(Self::Any);
+        }
+
+        if let Some(pattern) = src.strip_prefix("~*") {
+            return Self::compile_regex(pattern, false);
+        }
+
+        if let Some(pattern) = src.strip_prefix('~') {
+            return Self::compile_regex(pattern, true);
+        }
+
+        match src.split_once('*') {
+            Some((prefix, suffix)) => {
+                if suffix.contains('*') {
+                    return Err(format!(
+                        "Origin '{}' has more than one '*'. Use a regex ('~...') instead",
+                        src
+                    ));
+                }
+
+                Ok(Self::Wildcard {
+                    prefix: prefix.to_lowercase(),
+                    suffix: suffix.to_lowercase(),
+                })
+            }
+            None => Ok(Self::Exact(src.trim_end_matches('/').to_lowercase())),
+        }
+    }
+
+    fn compile_regex(pattern: &str, case_sensitive: bool) -> Result<Self, String> {
+        regex::RegexBuilder::new(pattern.trim())
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map(Self::Regex)
+            .map_err(|err| format!("Origin regex '{}' is invalid: {}", pattern.trim(), err))
+    }
+
+    pub fn is_match(&self, origin: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(exact) => exact.eq_ignore_ascii_case(origin),
+            Self::Wildcard { prefix, suffix } => {
+                let origin = origin.to_lowercase();
+                if origin.len() <= prefix.len() + suffix.len() {
+                    return false;
+                }
+
+                match origin
+                    .strip_prefix(prefix.as_str())
+                    .and_then(|itm| itm.strip_suffix(suffix.as_str()))
+                {
+                    Some(middle) => !middle.contains('/'),
+                    None => false,
+                }
+            }
+            Self::Regex(regex) => regex.is_match(origin),
+        }
+    }
+}
+
+/// `cors:` of an endpoint or a location, compiled. A preflight is answered by
+/// the proxy itself; every other response gets the headers for its origin, in
+/// place of the ones the upstream sent.
+#[derive(Debug)]
+pub struct CorsConfig {
+    origins: Vec<CorsOrigin>,
+    /// `Access-Control-Allow-Methods`, joined.
+    allowed_methods: String,
+    /// `Access-Control-Allow-Headers`, joined. `None` — the headers a
+    /// preflight asks for are allowed.
+    allowed_headers: Option<String>,
+    /// `Access-Control-Expose-Headers`, joined.
+    expose_headers: Option<String>,
+    allow_credentials: bool,
+    /// Seconds a browser keeps a preflight's answer.
+    max_age: Option<u64>,
+}
+
+impl CorsConfig {
+    pub fn new(
+        origins: Vec<CorsOrigin>,
+        allowed_methods: Vec<String>,
+        allowed_headers: Option<Vec<String>>,
+        expose_headers: Option<Vec<String>>,
+        allow_credentials: bool,
+        max_age: Option<u64>,
+    ) -> Self {
+        Self {
+            origins,
+            allowed_methods: allowed_methods.join(", "),
+            allowed_headers: allowed_headers.map(|itm| itm.join(", ")),
+            expose_headers: expose_headers
+                .filter(|itm| !itm.is_empty())
+                .map(|itm| itm.join(", ")),
+            allow_credentials,
+            max_age,
+        }
+    }
+
+    pub fn is_origin_allowed(&self, origin: &str) -> bool {
+        self.origins.iter().any(|itm| itm.is_match(origin))
+    }
+
+    /// `*` answers every origin alike — unless credentials are allowed, which
+    /// browsers never accept together with `*`; the origin is echoed then.
+    fn answers_any_origin(&self) -> bool {
+        !self.allow_credentials
+            && self
+                .origins
+                .iter()
+                .any(|itm| matches!(itm, CorsOrigin::Any))
+    }
+
+    /// `Access-Control-Allow-Origin` and `Vary`, then credentials — what a
+    /// preflight and an ordinary response have in common.
+    fn push_origin_headers(&self, origin: &str, result: &mut Vec<(&'static str, String)>) {
+        match self.answers_any_origin() {
+            true => result.push(("Access-Control-Allow-Origin", "*".to_string())),
+            false => {
+                result.push(("Access-Control-Allow-Origin", origin.to_string()));
+                result.push(("Vary", "Origin".to_string()));
+            }
+        }
+
+        if self.allow_credentials {
+            result.push(("Access-Control-Allow-Credentials", "true".to_string()));
+        }
+    }
+
+    /// The headers of the answer to a preflight from `origin`.
+    /// `request_headers` is its `Access-Control-Request-Headers`. `None` when
+    /// the origin is not allowed — the preflight is answered with 403.
+    pub fn get_preflight_headers(
+        &self,
+        origin: &str,
+        request_headers: Option<&str>,
+    ) -> Option<Vec<(&'static str, String)>> {
+        if !self.is_origin_allowed(origin) {
+            return None;
+        }
+
+        let mut result = Vec::with_capacity(6);
+        self.push_origin_headers(origin, &mut result);
+        result.push(("Access-Control-Allow-Methods", self.allowed_methods.clone()));
+
+        let allowed_headers = match self.allowed_headers.as_deref() {
+            Some(allowed_headers) => Some(allowed_headers),
+            None => request_headers.filter(|itm| !itm.trim().is_empty()),
+        };
+
+        if let Some(allowed_headers) = allowed_headers {
+            result.push(("Access-Control-Allow-Headers", allowed_headers.to_string()));
+        }
+
+        if let Some(max_age) = self.max_age {
+            result.push(("Access-Control-Max-Age", max_age.to_string()));
+        }
+
+        Some(result)
+    }
+
+    /// The headers an ordinary response to a request from `origin` gets. None
+    /// but `Vary` when the request has no origin or one not allowed, so a
+    /// cache in between keeps the answers to different origins apart.
+    pub fn get_response_headers(&self, origin: Option<&str>) -> Vec<(&'static str, String)> {
+        let mut result = Vec::with_capacity(4);
+
+        match origin.filter(|itm| self.is_origin_allowed(itm)) {
+            Some(origin) => {
+                self.push_origin_headers(origin, &mut result);
+
+                if let Some(expose_headers) = self.expose_headers.as_ref() {
+                    result.push(("Access-Control-Expose-Headers", expose_headers.clone()));
+                }
+            }
+            None => {
+                if !self.answers_any_origin() {
+                    result.push(("Vary", "Origin".to_string()));
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Whether `name` is one of the `Access-Control-*` headers the proxy answers
+/// with itself when CORS is configured — the upstream's are dropped.
+pub fn is_cors_response_header(name: &str) -> bool {
+    const PREFIX: &str = "access-control-";
+
+    name.len() > PREFIX.len()
+        && name.as_bytes()[..PREFIX.len()].eq_ignore_ascii_case(PREFIX.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors(origins: &[&str], allow_credentials: bool) -> CorsConfig {
+        CorsConfig::new(
+            origins
+                .iter()
+                .map(|itm| CorsOrigin::parse(itm).unwrap())
+                .collect(),
+            vec!["GET".to_string(), "POST".to_string()],
+            None,
+            Some(vec!["X-Request-Id".to_string()]),
+            allow_credentials,
+            Some(600),
+        )
+    }
+
+    #[test]
+    fn origins_are_matched_exactly_by_wildcard_and_by_regex() {
+        let cors = cors(
+            &[
+                "https://app.example.com",
+                "https://*.example.org",
+                "~^https://tenant[0-9]+\\.example\\.net$",
+            ],
+            false,
+        );
+
+        assert!(cors.is_origin_allowed("https://app.example.com"));
+        assert!(cors.is_origin_allowed("https://APP.example.com"));
+        assert!(!cors.is_origin_allowed("http://app.example.com"));
+
+        assert!(cors.is_origin_allowed("https://shop.example.org"));
+        assert!(!cors.is_origin_allowed("https://example.org"));
+        assert!(!cors.is_origin_allowed("https://evil.com/.example.org"));
+
+        assert!(cors.is_origin_allowed("https://tenant42.example.net"));
+        assert!(!cors.is_origin_allowed("https://tenant.example.net"));
+
+        assert!(CorsOrigin::parse("https://*.*.example.com").is_err());
+        assert!(CorsOrigin::parse("~(").is_err());
+    }
+
+    #[test]
+    fn a_preflight_is_answered_for_an_allowed_origin_only() {
+        let cors = cors(&["https://app.example.com"], true);
+
+        assert_eq!(
+            cors.get_preflight_headers("https://app.example.com", Some("content-type")),
+            Some(vec![
+                (
+                    "Access-Control-Allow-Origin",
+                    "https://app.example.com".to_string()
+                ),
+                ("Vary", "Origin".to_string()),
+                ("Access-Control-Allow-Credentials", "true".to_string()),
+                ("Access-Control-Allow-Methods", "GET, POST".to_string()),
+                ("Access-Control-Allow-Headers", "content-type".to_string()),
+                ("Access-Control-Max-Age", "600".to_string()),
+            ])
+        );
+
+        assert_eq!(
+            cors.get_preflight_headers("https://evil.example.com", None),
+            None
+        );
+    }
+
+    #[test]
+    fn any_origin_is_echoed_when_credentials_are_allowed() {
+        let cors_any = cors(&["*"], false);
+        assert_eq!(
+            cors_any.get_response_headers(Some("https://a.com")),
+            vec![
+                ("Access-Control-Allow-Origin", "*".to_string()),
+                ("Access-Control-Expose-Headers", "X-Request-Id".to_string()),
+            ]
+        );
+        assert_eq!(cors_any.get_response_headers(None), vec![]);
+
+        let cors_credentials = cors(&["*"], true);
+        assert_eq!(
+            cors_credentials.get_response_headers(Some("https://a.com"))[0],
+            ("Access-Control-Allow-Origin", "https://a.com".to_string())
+        );
+
+        let cors_exact = cors(&["https://app.example.com"], false);
+        assert_eq!(
+            cors_exact.get_response_headers(Some("https://other.com")),
+            vec![("Vary", "Origin".to_string())]
+        );
+
+        assert!(is_cors_response_header("Access-Control-Allow-Origin"));
+        assert!(!is_cors_response_header("Access-Control-"));
+        assert!(!is_cors_response_header("Vary"));
+    }
+}
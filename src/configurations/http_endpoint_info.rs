@@ -1,6 +1,11 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::{http_proxy_pass::HttpProxyPassIdentity, settings::HttpEndpointModifyHeadersSettings};
+use crate::{
+    app::{ConnectionLimits, RateLimitClient, RateLimiter},
+    http_proxy_pass::HttpProxyPassIdentity,
+    settings::HttpEndpointModifyHeadersSettings,
+};
 
 use super::*;
 
@@ -32,6 +37,17 @@ pub struct HttpEndpointInfo {
     /// Endpoint-scoped transport read/write idle timeouts (resolved cascade,
     /// global → endpoint). Used by every byte pump of this endpoint.
     pub timeouts: crate::types::HttpTimeouts,
+    /// The global `rate_limit`, then the endpoint's own — a request takes a
+    /// token from each, and from its location's after them.
+    pub rate_limits: Vec<Arc<RateLimiter>>,
+    /// Enforced at accept for the whole listen port; see
+    /// [`HttpListenPortConfiguration::get_connection_limits`].
+    pub connection_limits: ConnectionLimits,
+    /// The endpoint's `max_header_size`, `max_headers`, `max_uri_length` and
+    /// `max_body_size`; its locations override them one by one.
+    pub request_limits: RequestLimits,
+    /// The endpoint's `cors`; a location with its own uses that instead.
+    pub cors: Option<Arc<CorsConfig>>,
 }
 
 /// Everything an endpoint is compiled from.
@@ -58,6 +74,10 @@ pub struct HttpEndpointInfoParams {
     pub hsts: bool,
     pub mcp_settings: McpEndpointSettings,
     pub timeouts: crate::types::HttpTimeouts,
+    pub rate_limits: Vec<Arc<RateLimiter>>,
+    pub connection_limits: ConnectionLimits,
+    pub request_limits: RequestLimits,
+    pub cors: Option<Arc<CorsConfig>>,
 }
 
 impl HttpEndpointInfo {
@@ -80,6 +100,10 @@ impl HttpEndpointInfo {
             hsts,
             mcp_settings,
             timeouts,
+            rate_limits,
+            connection_limits,
+            request_limits,
+            cors,
         } = params;
 
         if debug {
@@ -118,9 +142,44 @@ impl HttpEndpointInfo {
             hsts,
             mcp_settings,
             timeouts,
+            rate_limits,
+            connection_limits,
+            request_limits,
+            cors,
         }
     }
 
+    /// Whether a request to `location` falls under any rate limit.
+    pub fn has_rate_limits(&self, location: &ProxyPassLocationConfig) -> bool {
+        !self.rate_limits.is_empty() || location.rate_limit.is_some()
+    }
+
+    /// Takes a token for the request from every rate limit it falls under —
+    /// the endpoint's, then `location`'s. `Err` holds how long the client is
+    /// to wait.
+    pub fn check_rate_limits(
+        &self,
+        location: &ProxyPassLocationConfig,
+        client: &RateLimitClient,
+    ) -> Result<(), Duration> {
+        crate::app::check_rate_limits(
+            self.rate_limits.iter().chain(location.rate_limit.iter()),
+            client,
+        )
+    }
+
+    /// The limits a request to `location` is held to: the location's own, and
+    /// the endpoint's for what the location leaves unset.
+    pub fn get_request_limits(&self, location: &ProxyPassLocationConfig) -> RequestLimits {
+        location.request_limits.or(&self.request_limits)
+    }
+
+    /// The CORS settings of a request to `location`: the location's own, else
+    /// the endpoint's.
+    pub fn get_cors(&self, location: &ProxyPassLocationConfig) -> Option<&CorsConfig> {
+        location.cors.as_deref().or(self.cors.as_deref())
+    }
+
     /// Returns `Some(domain)` if this request should emit per-domain metrics,
     /// `None` otherwise. `request_host` is the value parsed from the inbound
     /// `Host:` header (or h2 `:authority`), already stripped of port.
@@ -140,20 +199,11 @@ impl HttpEndpointInfo {
         self.host_endpoint.as_str()
     }
 
+    /// `path` may carry a query string — only the path part is matched. See
+    /// [`find_best_location`] for how competing locations are ranked.
     pub fn find_location(&self, path: &str) -> Option<&ProxyPassLocationConfig> {
-        for location in self.locations.iter() {
-            if location.path.len() > path.len() {
-                continue;
-            }
-
-            let path_prefix = &path[..location.path.len()];
-
-            if path_prefix.eq_ignore_ascii_case(&location.path) {
-                return Some(&location);
-            }
-        }
-
-        None
+        let index = find_best_location(self.locations.iter(), path, |itm| &itm.matcher)?;
+        self.locations.get(index).map(|itm| itm.as_ref())
     }
 
     /// Guards against HTTP/2 connection coalescing (and any cross-SNI
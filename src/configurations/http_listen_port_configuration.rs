@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::types::ListenHost;
+use crate::{app::ConnectionLimits, types::ListenHost};
 
 use super::*;
 
@@ -87,6 +87,16 @@ impl HttpListenPortConfiguration {
         None
     }
 
+    /// The limits every connection to the port is held to: the lowest any of
+    /// its endpoints sets, since the endpoint is not known at accept.
+    pub fn get_connection_limits(&self) -> ConnectionLimits {
+        self.endpoints
+            .iter()
+            .fold(ConnectionLimits::default(), |result, itm| {
+                result.stricter(&itm.connection_limits)
+            })
+    }
+
     /*
        pub fn get_listen_endpoint_type(&self) -> ListenHttpEndpointType {
            self.endpoint_info[0].listen_endpoint_type
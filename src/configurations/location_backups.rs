@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use super::ProxyPassUpstream;
+
+/// `backup_proxy_pass_to` of a location: the targets a request fails over to,
+/// in order, when its upstream cannot take it. Each backup has a pool of its
+/// own, keyed by its `id` like a balanced location's upstreams.
+pub struct LocationBackups {
+    upstreams: Vec<Arc<ProxyPassUpstream>>,
+    on_status: Vec<u16>,
+}
+
+impl LocationBackups {
+    pub fn new(upstreams: Vec<ProxyPassUpstream>, on_status: Vec<u16>) -> Self {
+        Self {
+            upstreams: upstreams.into_iter().map(Arc::new).collect(),
+            on_status,
+        }
+    }
+
+    pub fn get_upstreams(&self) -> &[Arc<ProxyPassUpstream>] {
+        &self.upstreams
+    }
+
+    /// The backup to try after the first `tried` of them, with its index: the
+    /// next one `admit` takes (outlier detection). When it refuses every one
+    /// left, the next one anyway — as for a balanced location, a request sent
+    /// to a struggling backup beats one refused outright.
+    pub fn next(
+        &self,
+        tried: usize,
+        mut admit: impl FnMut(&ProxyPassUpstream) -> bool,
+    ) -> Option<(usize, &Arc<ProxyPassUpstream>)> {
+        let rest = self.upstreams.get(tried..).unwrap_or_default();
+        if rest.is_empty() {
+            return None;
+        }
+
+        let offset = rest.iter().position(|itm| admit(itm)).unwrap_or(0);
+        Some((tried + offset, &rest[offset]))
+    }
+
+    pub fn get_on_status(&self) -> &[u16] {
+        &self.on_status
+    }
+
+    /// Whether an upstream response with `status` is sent on to the next backup
+    /// instead of being relayed to the client.
+    pub fn fails_over_on(&self, status: u16) -> bool {
+        self.on_status.contains(&status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::ProxyPassToConfig;
+
+    #[test]
+    fn backups_are_tried_in_order() {
+        let backups = LocationBackups::new(
+            vec![
+                ProxyPassUpstream::new(1, "backup-1".to_string(), ProxyPassToConfig::Drop, 1),
+                ProxyPassUpstream::new(2, "backup-2".to_string(), ProxyPassToConfig::Drop, 1),
+            ],
+            vec![502, 503],
+        );
+
+        let admit_all = |_: &ProxyPassUpstream| true;
+        assert_eq!(backups.next(0, admit_all).unwrap().1.id, 1);
+        assert_eq!(backups.next(1, admit_all).unwrap().1.id, 2);
+        assert!(backups.next(2, admit_all).is_none());
+
+        assert!(backups.fails_over_on(503));
+        assert!(!backups.fails_over_on(500));
+    }
+
+    #[test]
+    fn a_refused_backup_is_passed_over_unless_all_are() {
+        let backups = LocationBackups::new(
+            vec![
+                ProxyPassUpstream::new(1, "backup-1".to_string(), ProxyPassToConfig::Drop, 1),
+                ProxyPassUpstream::new(2, "backup-2".to_string(), ProxyPassToConfig::Drop, 1),
+                ProxyPassUpstream::new(3, "backup-3".to_string(), ProxyPassToConfig::Drop, 1),
+            ],
+            vec![],
+        );
+
+        let (index, backup) = backups.next(0, |itm| itm.id != 1).unwrap();
+        assert_eq!((index, backup.id), (1, 2));
+
+        let (index, backup) = backups.next(1, |_| false).unwrap();
+        assert_eq!((index, backup.id), (1, 2));
+    }
+}
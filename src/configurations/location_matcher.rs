@@ -0,0 +1,341 @@
+/// How a location's `path` is compared against the request path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocationMatchMode {
+    /// The path is a prefix of the request path and ends on a segment
+    /// boundary: `/api` matches `/api` and `/api/users`, never `/apiary`.
+    Prefix,
+    /// A `prefix` that, when it is the longest one matching, wins without the
+    /// regex locations being tried — nginx's `^~`.
+    PriorityPrefix,
+    /// The request path equals the location path.
+    Exact,
+    /// The location path is a regular expression searched in the request path.
+    Regex,
+}
+
+impl LocationMatchMode {
+    /// `None` is the default (`prefix`). The nginx modifiers are accepted as
+    /// aliases: `^~` (priority_prefix), `=` (exact), `~` (regex) and `~*`
+    /// (case-insensitive regex — the caller turns case sensitivity off for it).
+    pub fn parse(src: Option<&str>) -> Result<Self, String> {
+        let Some(src) = src else {
+            return Ok(Self::Prefix);
+        };
+
+        match src.trim() {
+            "" | "prefix" => Ok(Self::Prefix),
+            "priority_prefix" | "^~" => Ok(Self::PriorityPrefix),
+            "exact" | "=" => Ok(Self::Exact),
+            "regex" | "~" | "~*" => Ok(Self::Regex),
+            other => Err(format!(
+                "Unknown location match mode '{}'. Expected prefix, priority_prefix, exact or regex",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Prefix => "prefix",
+            Self::PriorityPrefix => "priority_prefix",
+            Self::Exact => "exact",
+            Self::Regex => "regex",
+        }
+    }
+}
+
+/// A location's path, compiled once when the configuration is applied. Both
+/// the h1 byte pipeline and the hyper path pick their location through
+/// [`find_best_location`], so a request routes identically on either.
+#[derive(Debug, Clone)]
+pub struct LocationMatcher {
+    mode: LocationMatchMode,
+    path: String,
+    case_sensitive: bool,
+    regex: Option<regex::Regex>,
+}
+
+impl LocationMatcher {
+    pub fn new(mode: LocationMatchMode, path: &str, case_sensitive: bool) -> Result<Self, String> {
+        let regex = match mode {
+            LocationMatchMode::Regex => {
+                let regex = regex::RegexBuilder::new(path)
+                    .case_insensitive(!case_sensitive)
+                    .build()
+                    .map_err(|err| format!("Invalid location regex '{}': {}", path, err))?;
+                Some(regex)
+            }
+            LocationMatchMode::Prefix
+            | LocationMatchMode::PriorityPrefix
+            | LocationMatchMode::Exact => None,
+        };
+
+        Ok(Self {
+            mode,
+            path: path.to_string(),
+            case_sensitive,
+            regex,
+        })
+    }
+
+    /// The default `prefix`, case-insensitive matcher — what every location got
+    /// before `match:` existed.
+    pub fn new_prefix(path: &str) -> Self {
+        Self {
+            mode: LocationMatchMode::Prefix,
+            path: path.to_string(),
+            case_sensitive: false,
+            regex: None,
+        }
+    }
+
+    pub fn get_mode(&self) -> LocationMatchMode {
+        self.mode
+    }
+
+    pub fn is_case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    pub fn get_regex(&self) -> Option<&regex::Regex> {
+        self.regex.as_ref()
+    }
+
+    /// `path` is the request path without the query string.
+    pub fn is_match(&self, path: &str) -> bool {
+        match self.mode {
+            LocationMatchMode::Exact => self.eq_path(path, self.path.as_str()),
+            LocationMatchMode::Prefix | LocationMatchMode::PriorityPrefix => {
+                self.prefix_len(path).is_some()
+            }
+            LocationMatchMode::Regex => match self.regex.as_ref() {
+                Some(regex) => regex.is_match(path),
+                None => false,
+            },
+        }
+    }
+
+    /// The length of the matched prefix when this is a `prefix` (or
+    /// `priority_prefix`) matcher whose
+    /// path covers `path` on a segment boundary.
+    fn prefix_len(&self, path: &str) -> Option<usize> {
+        let location_path = self.path.as_str();
+
+        if location_path.is_empty() || location_path == "/" {
+            return Some(location_path.len());
+        }
+
+        let prefix = path.get(..location_path.len())?;
+
+        if !self.eq_path(prefix, location_path) {
+            return None;
+        }
+
+        if location_path.ends_with('/') {
+            return Some(location_path.len());
+        }
+
+        match path.as_bytes().get(location_path.len()) {
+            None | Some(b'/') => Some(location_path.len()),
+            Some(_) => None,
+        }
+    }
+
+    fn eq_path(&self, a: &str, b: &str) -> bool {
+        if self.case_sensitive {
+            a == b
+        } else {
+            a.eq_ignore_ascii_case(b)
+        }
+    }
+}
+
+/// Picks the location serving `path_and_query` (only the path part is
+/// compared). The resolution is independent of the order locations are
+/// declared in, except among regexes:
+///
+/// 1. an `exact` location equal to the path wins outright;
+/// 2. the longest matching prefix wins if it is a `priority_prefix` one;
+/// 3. otherwise the regex locations are tried in declaration order, and the
+///    first one matching wins;
+/// 4. otherwise the longest matching `prefix` location wins.
+///
+/// Returns the index of the winner in `locations`.
+pub fn find_best_location<'s, TItem: 's>(
+    locations: impl Iterator<Item = &'s TItem>,
+    path_and_query: &str,
+    get_matcher: impl Fn(&TItem) -> &LocationMatcher,
+) -> Option<usize> {
+    let path = match path_and_query.find('?') {
+        Some(index) => &path_and_query[..index],
+        None => path_and_query,
+    };
+
+    let mut first_regex: Option<usize> = None;
+    let mut longest_prefix: Option<(usize, usize, bool)> = None;
+
+    for (index, location) in locations.enumerate() {
+        let matcher = get_matcher(location);
+        match matcher.mode {
+            LocationMatchMode::Exact => {
+                if matcher.is_match(path) {
+                    return Some(index);
+                }
+            }
+            LocationMatchMode::Regex => {
+                if first_regex.is_none() && matcher.is_match(path) {
+                    first_regex = Some(index);
+                }
+            }
+            LocationMatchMode::Prefix | LocationMatchMode::PriorityPrefix => {
+                if let Some(len) = matcher.prefix_len(path) {
+                    let is_longer = match longest_prefix {
+                        Some((_, best_len, _)) => len > best_len,
+                        None => true,
+                    };
+                    if is_longer {
+                        let skips_regex = matcher.mode == LocationMatchMode::PriorityPrefix;
+                        longest_prefix = Some((index, len, skips_regex));
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some((index, _, true)) = longest_prefix {
+        return Some(index);
+    }
+
+    if first_regex.is_some() {
+        return first_regex;
+    }
+
+    longest_prefix.map(|(index, _, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix(path: &str) -> LocationMatcher {
+        LocationMatcher::new_prefix(path)
+    }
+
+    fn find(locations: &[LocationMatcher], path: &str) -> Option<usize> {
+        find_best_location(locations.iter(), path, |itm| itm)
+    }
+
+    #[test]
+    fn a_prefix_stops_at_a_segment_boundary() {
+        let matcher = prefix("/api");
+
+        assert!(matcher.is_match("/api"));
+        assert!(matcher.is_match("/api/users"));
+        assert!(matcher.is_match("/API/users"));
+        assert!(!matcher.is_match("/apiary"));
+        assert!(!matcher.is_match("/ap"));
+    }
+
+    #[test]
+    fn a_prefix_with_a_trailing_slash_covers_everything_below_it() {
+        let matcher = prefix("/api/");
+
+        assert!(matcher.is_match("/api/"));
+        assert!(matcher.is_match("/api/users"));
+        assert!(!matcher.is_match("/api"));
+    }
+
+    #[test]
+    fn the_longest_prefix_wins_regardless_of_order() {
+        let locations = vec![prefix("/"), prefix("/api"), prefix("/api/v2")];
+
+        assert_eq!(find(&locations, "/api/v2/users?x=1"), Some(2));
+        assert_eq!(find(&locations, "/api/v1/users"), Some(1));
+        assert_eq!(find(&locations, "/apiary"), Some(0));
+    }
+
+    #[test]
+    fn an_exact_match_beats_a_longer_prefix() {
+        let locations = vec![
+            prefix("/health/details"),
+            LocationMatcher::new(LocationMatchMode::Exact, "/health", false).unwrap(),
+            prefix("/"),
+        ];
+
+        assert_eq!(find(&locations, "/health"), Some(1));
+        assert_eq!(find(&locations, "/health/"), Some(2));
+        assert_eq!(find(&locations, "/health/details"), Some(0));
+    }
+
+    #[test]
+    fn a_regex_beats_a_prefix() {
+        let locations = vec![
+            prefix("/"),
+            LocationMatcher::new(LocationMatchMode::Regex, "^/v[0-9]+/", true).unwrap(),
+        ];
+
+        assert_eq!(find(&locations, "/v2/users"), Some(1));
+        assert_eq!(find(&locations, "/V2/users"), Some(0));
+        assert_eq!(find(&locations, "/users"), Some(0));
+    }
+
+    #[test]
+    fn a_priority_prefix_skips_the_regexes_when_it_is_the_longest() {
+        let locations = vec![
+            LocationMatcher::new(LocationMatchMode::Regex, "\\.png$", false).unwrap(),
+            LocationMatcher::new(LocationMatchMode::PriorityPrefix, "/static", false).unwrap(),
+            prefix("/static/generated"),
+            prefix("/"),
+        ];
+
+        assert_eq!(find(&locations, "/static/logo.png"), Some(1));
+        assert_eq!(find(&locations, "/static/generated/chart.png"), Some(0));
+        assert_eq!(find(&locations, "/logo.png"), Some(0));
+        assert_eq!(find(&locations, "/static/app.js"), Some(1));
+    }
+
+    #[test]
+    fn case_sensitive_prefix_and_exact() {
+        let locations = vec![
+            LocationMatcher::new(LocationMatchMode::Prefix, "/Api", true).unwrap(),
+            LocationMatcher::new(LocationMatchMode::Exact, "/Health", true).unwrap(),
+        ];
+
+        assert_eq!(find(&locations, "/Api/x"), Some(0));
+        assert_eq!(find(&locations, "/api/x"), None);
+        assert_eq!(find(&locations, "/health"), None);
+    }
+
+    #[test]
+    fn the_query_string_is_not_part_of_the_match() {
+        let locations = vec![LocationMatcher::new(LocationMatchMode::Exact, "/a", false).unwrap()];
+
+        assert_eq!(find(&locations, "/a?b=/c"), Some(0));
+    }
+
+    #[test]
+    fn an_invalid_regex_is_a_config_error() {
+        assert!(LocationMatcher::new(LocationMatchMode::Regex, "^/(", true).is_err());
+    }
+
+    #[test]
+    fn the_nginx_modifiers_are_accepted() {
+        assert_eq!(
+            LocationMatchMode::parse(Some("=")).unwrap(),
+            LocationMatchMode::Exact
+        );
+        assert_eq!(
+            LocationMatchMode::parse(Some("~*")).unwrap(),
+            LocationMatchMode::Regex
+        );
+        assert_eq!(
+            LocationMatchMode::parse(Some("^~")).unwrap(),
+            LocationMatchMode::PriorityPrefix
+        );
+        assert_eq!(
+            LocationMatchMode::parse(None).unwrap(),
+            LocationMatchMode::Prefix
+        );
+        assert!(LocationMatchMode::parse(Some("fuzzy")).is_err());
+    }
+}
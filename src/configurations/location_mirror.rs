@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::ProxyPassUpstream;
+
+/// Mirror requests of one location on their way at once. Past it requests are
+/// not copied: a slow shadow upstream must not pile up tasks and buffered
+/// bodies behind the real traffic.
+const MAX_IN_FLIGHT: usize = 256;
+
+/// `mirror_to:` of a location — the shadow upstream sampled requests are
+/// copied to. A copy is fire-and-forget: its response is read and thrown away,
+/// and it never delays or fails the request it was taken from. The mirror has
+/// a pool of its own, keyed by its `id` like a balanced location's upstreams.
+pub struct LocationMirror {
+    upstream: ProxyPassUpstream,
+    /// What the mirror's metrics are labelled with.
+    label: String,
+    percent: u64,
+    max_body_size: usize,
+    requests: AtomicU64,
+    in_flight: AtomicUsize,
+}
+
+impl LocationMirror {
+    pub fn new(upstream: ProxyPassUpstream, percent: u32, max_body_size: usize) -> Self {
+        Self {
+            label: format!("{}#{}", upstream.proxy_pass_to.to_string(), upstream.id),
+            upstream,
+            percent: percent.min(100) as u64,
+            max_body_size,
+            requests: AtomicU64::new(0),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn get_upstream(&self) -> &ProxyPassUpstream {
+        &self.upstream
+    }
+
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    /// Largest request body still copied.
+    pub fn get_max_body_size(&self) -> usize {
+        self.max_body_size
+    }
+
+    pub fn get_percent(&self) -> u64 {
+        self.percent
+    }
+
+    /// Whether the next request is copied. The share is spread evenly over the
+    /// requests rather than rolled per request: at 10 percent exactly every
+    /// tenth one goes to the mirror.
+    pub fn sample(&self) -> bool {
+        let n = self.requests.fetch_add(1, Ordering::Relaxed);
+        n.wrapping_mul(self.percent) / 100 != n.wrapping_add(1).wrapping_mul(self.percent) / 100
+    }
+
+    /// Claims a slot for one mirror request; `None` while [`MAX_IN_FLIGHT`]
+    /// of them are on their way already.
+    pub fn try_start(self: &Arc<Self>) -> Option<MirrorLease> {
+        let claimed = self
+            .in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |itm| {
+                (itm < MAX_IN_FLIGHT).then_some(itm + 1)
+            })
+            .is_ok();
+
+        if !claimed {
+            return None;
+        }
+
+        Some(MirrorLease {
+            mirror: self.clone(),
+        })
+    }
+}
+
+/// One mirror request's slot. Frees it when dropped.
+pub struct MirrorLease {
+    mirror: Arc<LocationMirror>,
+}
+
+impl MirrorLease {
+    pub fn get_mirror(&self) -> &LocationMirror {
+        &self.mirror
+    }
+}
+
+impl Drop for MirrorLease {
+    fn drop(&mut self) {
+        self.mirror.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::ProxyPassToConfig;
+
+    fn mirror(percent: u32) -> Arc<LocationMirror> {
+        let upstream = ProxyPassUpstream::new(1, "mirror".to_string(), ProxyPassToConfig::Drop, 1);
+        Arc::new(LocationMirror::new(upstream, percent, 1024))
+    }
+
+    #[test]
+    fn the_sampled_share_is_exact() {
+        for percent in [1, 10, 33, 50, 100] {
+            let mirror = mirror(percent);
+            let sampled = (0..1000).filter(|_| mirror.sample()).count();
+            assert_eq!(sampled, percent as usize * 10);
+        }
+    }
+
+    #[test]
+    fn requests_past_the_in_flight_limit_are_not_mirrored() {
+        let mirror = mirror(100);
+
+        let leases: Vec<_> = (0..MAX_IN_FLIGHT)
+            .map(|_| mirror.try_start().unwrap())
+            .collect();
+        assert!(mirror.try_start().is_none());
+
+        drop(leases);
+        assert!(mirror.try_start().is_some());
+    }
+}
@@ -0,0 +1,220 @@
+/// How the request path is turned into the path sent upstream, for an ordinary
+/// (non-MCP) network location. Compiled once from the location's
+/// `strip_prefix`, `rewrite` and `append_to_upstream_path` settings and applied
+/// in this order:
+///
+/// 1. `strip_prefix` — the location's own path is cut off the request path
+///    (`/service1/users` under `/service1` becomes `/users`), compared with the
+///    case sensitivity the location matched the request with;
+/// 2. `rewrite` — a regex replacement over the resulting path, `${1}`-style
+///    capture groups allowed. A path the regex does not match passes as is;
+/// 3. `append_to_upstream_path` — the result is appended to the path of
+///    `proxy_pass_to` (`/users` onto `http://host/api` becomes `/api/users`).
+///
+/// The client's query string is carried over untouched.
+#[derive(Debug, Clone)]
+pub struct LocationPathRewrite {
+    strip_prefix: Option<String>,
+    case_sensitive: bool,
+    rewrite: Option<(regex::Regex, String)>,
+    upstream_path: Option<String>,
+}
+
+impl LocationPathRewrite {
+    /// `None` when none of the three steps is configured — there is nothing to
+    /// rewrite and the request path is forwarded verbatim.
+    pub fn new(
+        strip_prefix: Option<&str>,
+        case_sensitive: bool,
+        rewrite: Option<(&str, &str)>,
+        upstream_path: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        if strip_prefix.is_none() && rewrite.is_none() && upstream_path.is_none() {
+            return Ok(None);
+        }
+
+        let rewrite = match rewrite {
+            Some((from, to)) => {
+                let regex = regex::Regex::new(from)
+                    .map_err(|err| format!("Invalid rewrite regex '{}': {}", from, err))?;
+                Some((regex, to.to_string()))
+            }
+            None => None,
+        };
+
+        Ok(Some(Self {
+            strip_prefix: strip_prefix.map(|itm| itm.to_string()),
+            case_sensitive,
+            rewrite,
+            upstream_path: upstream_path.map(|itm| itm.to_string()),
+        }))
+    }
+
+    /// Maps the client's path-and-query onto the upstream one.
+    pub fn apply(&self, path_and_query: &str) -> String {
+        let (path, query) = match path_and_query.find('?') {
+            Some(index) => (&path_and_query[..index], Some(&path_and_query[index + 1..])),
+            None => (path_and_query, None),
+        };
+
+        let mut path = match self.strip_prefix.as_deref() {
+            Some(prefix) => strip_path_prefix(path, prefix, self.case_sensitive).to_string(),
+            None => path.to_string(),
+        };
+
+        if let Some((regex, replacement)) = self.rewrite.as_ref() {
+            if regex.is_match(path.as_str()) {
+                path = regex.replace(path.as_str(), replacement.as_str()).to_string();
+            }
+        }
+
+        if let Some(upstream_path) = self.upstream_path.as_deref() {
+            path = join_paths(upstream_path, path.as_str());
+        }
+
+        if !path.starts_with('/') {
+            path.insert(0, '/');
+        }
+
+        match query {
+            Some(query) => {
+                let separator = if path.contains('?') { '&' } else { '?' };
+                format!("{}{}{}", path, separator, query)
+            }
+            None => path,
+        }
+    }
+}
+
+/// What is left of `path` below `prefix`, always starting with `/`. A path the
+/// prefix does not cover is returned as is.
+fn strip_path_prefix<'s>(path: &'s str, prefix: &str, case_sensitive: bool) -> &'s str {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        return path;
+    }
+
+    let covered = match path.get(..prefix.len()) {
+        Some(head) if case_sensitive => head == prefix,
+        Some(head) => head.eq_ignore_ascii_case(prefix),
+        None => false,
+    };
+
+    if !covered {
+        return path;
+    }
+
+    let rest = &path[prefix.len()..];
+    if rest.is_empty() {
+        "/"
+    } else {
+        rest
+    }
+}
+
+/// `/api` + `/users` → `/api/users`. The root of the location maps onto the
+/// upstream path itself, so `/api` + `/` stays `/api`.
+fn join_paths(upstream_path: &str, path: &str) -> String {
+    if path.is_empty() || path == "/" {
+        return upstream_path.to_string();
+    }
+
+    let upstream_path = upstream_path.trim_end_matches('/');
+    let path = path.trim_start_matches('/');
+
+    format!("{}/{}", upstream_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(
+        strip_prefix: Option<&str>,
+        rewrite: Option<(&str, &str)>,
+        upstream_path: Option<&str>,
+    ) -> LocationPathRewrite {
+        LocationPathRewrite::new(strip_prefix, false, rewrite, upstream_path)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn nothing_configured_means_no_rewrite() {
+        assert!(LocationPathRewrite::new(None, false, None, None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn strip_prefix_keeps_the_rest_and_the_query() {
+        let rewrite = rewrite(Some("/service1"), None, None);
+
+        assert_eq!(rewrite.apply("/service1/users?id=5"), "/users?id=5");
+        assert_eq!(rewrite.apply("/service1"), "/");
+        assert_eq!(rewrite.apply("/SERVICE1/a"), "/a");
+    }
+
+    #[test]
+    fn strip_prefix_of_a_case_sensitive_location_keeps_its_case() {
+        let rewrite = LocationPathRewrite::new(Some("/service1"), true, None, None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(rewrite.apply("/service1/users"), "/users");
+        assert_eq!(rewrite.apply("/SERVICE1/a"), "/SERVICE1/a");
+    }
+
+    #[test]
+    fn strip_prefix_of_a_location_with_a_trailing_slash() {
+        let rewrite = rewrite(Some("/service1/"), None, None);
+
+        assert_eq!(rewrite.apply("/service1/users"), "/users");
+    }
+
+    #[test]
+    fn append_to_upstream_path() {
+        let rewrite = rewrite(Some("/service1"), None, Some("/api/"));
+
+        assert_eq!(rewrite.apply("/service1/users?x=1"), "/api/users?x=1");
+        assert_eq!(rewrite.apply("/service1"), "/api/");
+    }
+
+    #[test]
+    fn regex_rewrite_with_capture_groups() {
+        let rewrite = rewrite(None, Some(("^/v1/(.*)$", "/v2/${1}")), None);
+
+        assert_eq!(rewrite.apply("/v1/users?x=1"), "/v2/users?x=1");
+        assert_eq!(rewrite.apply("/v3/users"), "/v3/users");
+    }
+
+    #[test]
+    fn rewrite_runs_after_strip_and_before_append() {
+        let rewrite = rewrite(
+            Some("/svc"),
+            Some(("^/old/(.*)$", "/new/${1}")),
+            Some("/base"),
+        );
+
+        assert_eq!(rewrite.apply("/svc/old/a"), "/base/new/a");
+    }
+
+    #[test]
+    fn a_rewrite_result_without_a_leading_slash_gets_one() {
+        let rewrite = rewrite(None, Some(("^/(.*)$", "${1}")), None);
+
+        assert_eq!(rewrite.apply("/a/b"), "/a/b");
+    }
+
+    #[test]
+    fn a_rewrite_with_its_own_query_keeps_the_client_query_too() {
+        let rewrite = rewrite(None, Some(("^/a$", "/b?tenant=1")), None);
+
+        assert_eq!(rewrite.apply("/a?x=2"), "/b?tenant=1&x=2");
+    }
+
+    #[test]
+    fn an_invalid_regex_is_a_config_error() {
+        assert!(LocationPathRewrite::new(None, false, Some(("(", "/")), None).is_err());
+    }
+}
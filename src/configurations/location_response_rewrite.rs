@@ -0,0 +1,249 @@
+/// One `from` → `to` rule of `proxy_redirect`, `proxy_cookie_domain` or
+/// `proxy_cookie_path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseRewriteRule {
+    pub from: String,
+    pub to: String,
+}
+
+impl ResponseRewriteRule {
+    pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            from: from.into(),
+            to: to.into(),
+        }
+    }
+
+    /// `value` with `from` at its start replaced by `to`. A `from` ending with
+    /// `/` also takes the value it names without the slash, so
+    /// `http://10.0.0.5:5123` is caught by `http://10.0.0.5:5123/` too.
+    fn replace_prefix(&self, value: &str) -> Option<String> {
+        if let Some(head) = value.get(..self.from.len()) {
+            if head.eq_ignore_ascii_case(&self.from) {
+                return Some(format!("{}{}", self.to, &value[self.from.len()..]));
+            }
+        }
+
+        let from = self.from.strip_suffix('/')?;
+        if from.is_empty() || !value.eq_ignore_ascii_case(from) {
+            return None;
+        }
+
+        match self.to.trim_end_matches('/') {
+            "" => Some("/".to_string()),
+            to => Some(to.to_string()),
+        }
+    }
+}
+
+/// `proxy_redirect`, `proxy_cookie_domain` and `proxy_cookie_path` of a
+/// location, compiled: what the upstream's `Location`, `Refresh` and
+/// `Set-Cookie` headers say about its own host and path is turned into what
+/// the client knows the location by.
+#[derive(Debug)]
+pub struct LocationResponseRewrite {
+    /// Prefixes of `Location` and of the url of `Refresh`; the first that
+    /// matches is replaced.
+    redirects: Vec<ResponseRewriteRule>,
+    /// `Domain` of a cookie, matched whole; an empty `to` drops the attribute,
+    /// leaving the cookie to the host that answered.
+    cookie_domains: Vec<ResponseRewriteRule>,
+    /// Prefixes of `Path` of a cookie.
+    cookie_paths: Vec<ResponseRewriteRule>,
+}
+
+impl LocationResponseRewrite {
+    /// `None` when there are no rules at all.
+    pub fn new(
+        redirects: Vec<ResponseRewriteRule>,
+        cookie_domains: Vec<ResponseRewriteRule>,
+        cookie_paths: Vec<ResponseRewriteRule>,
+    ) -> Option<Self> {
+        if redirects.is_empty() && cookie_domains.is_empty() && cookie_paths.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            redirects,
+            cookie_domains,
+            cookie_paths,
+        })
+    }
+
+    /// The value of the response header `name` as the client gets it. `None`
+    /// when it stays as the upstream sent it.
+    pub fn rewrite_header(&self, name: &str, value: &str) -> Option<String> {
+        if name.eq_ignore_ascii_case("location") {
+            return self.rewrite_url(value.trim());
+        }
+
+        if name.eq_ignore_ascii_case("refresh") {
+            return self.rewrite_refresh(value);
+        }
+
+        if name.eq_ignore_ascii_case("set-cookie") {
+            return self.rewrite_set_cookie(value);
+        }
+
+        None
+    }
+
+    /// Rewrites `headers` — name / value pairs — in place.
+    pub fn rewrite_headers(&self, headers: &mut [(String, String)]) {
+        for (name, value) in headers.iter_mut() {
+            if let Some(rewritten) = self.rewrite_header(name, value) {
+                *value = rewritten;
+            }
+        }
+    }
+
+    fn rewrite_url(&self, url: &str) -> Option<String> {
+        self.redirects
+            .iter()
+            .find_map(|itm| itm.replace_prefix(url))
+    }
+
+    /// `5; url=http://10.0.0.5:5123/next` — only the url is rewritten.
+    fn rewrite_refresh(&self, value: &str) -> Option<String> {
+        let lowercase = value.to_ascii_lowercase();
+        let url_start = lowercase.find("url=")? + "url=".len();
+        let url = &value[url_start..];
+
+        let quote = url.chars().next().filter(|itm| *itm == '\'' || *itm == '"');
+        let unquoted = match quote {
+            Some(quote) => url[1..].trim_end_matches(quote),
+            None => url.trim(),
+        };
+
+        let rewritten = self.rewrite_url(unquoted)?;
+
+        Some(match quote {
+            Some(quote) => format!("{}{}{}{}", &value[..url_start], quote, rewritten, quote),
+            None => format!("{}{}", &value[..url_start], rewritten),
+        })
+    }
+
+    fn rewrite_set_cookie(&self, value: &str) -> Option<String> {
+        let mut parts = value.split(';');
+        let mut result = parts.next()?.to_string();
+        let mut rewritten = false;
+
+        for attribute in parts {
+            let (name, attribute_value) = match attribute.split_once('=') {
+                Some((name, attribute_value)) => (name.trim(), attribute_value.trim()),
+                None => (attribute.trim(), ""),
+            };
+
+            if name.eq_ignore_ascii_case("domain") {
+                let domain = attribute_value.trim_start_matches('.');
+                let rule = self.cookie_domains.iter().find(|itm| {
+                    itm.from
+                        .trim_start_matches('.')
+                        .eq_ignore_ascii_case(domain)
+                });
+
+                if let Some(rule) = rule {
+                    rewritten = true;
+                    if !rule.to.is_empty() {
+                        result.push_str("; Domain=");
+                        result.push_str(&rule.to);
+                    }
+                    continue;
+                }
+            }
+
+            if name.eq_ignore_ascii_case("path") {
+                let path = self
+                    .cookie_paths
+                    .iter()
+                    .find_map(|itm| itm.replace_prefix(attribute_value));
+
+                if let Some(path) = path {
+                    rewritten = true;
+                    result.push_str("; Path=");
+                    result.push_str(&path);
+                    continue;
+                }
+            }
+
+            result.push(';');
+            result.push_str(attribute);
+        }
+
+        match rewritten {
+            true => Some(result),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite() -> LocationResponseRewrite {
+        LocationResponseRewrite::new(
+            vec![ResponseRewriteRule::new(
+                "http://10.0.0.5:5123/api/",
+                "/service1/",
+            )],
+            vec![ResponseRewriteRule::new("10.0.0.5", "")],
+            vec![ResponseRewriteRule::new("/api/", "/service1/")],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn redirects_are_turned_back_onto_the_location() {
+        let rewrite = rewrite();
+
+        assert_eq!(
+            rewrite.rewrite_header("Location", "http://10.0.0.5:5123/api/login?next=1"),
+            Some("/service1/login?next=1".to_string())
+        );
+        assert_eq!(
+            rewrite.rewrite_header("location", "http://10.0.0.5:5123/api"),
+            Some("/service1".to_string())
+        );
+        assert_eq!(
+            rewrite.rewrite_header("Location", "https://example.com/"),
+            None
+        );
+        assert_eq!(
+            rewrite.rewrite_header("Refresh", "5; URL='http://10.0.0.5:5123/api/next'"),
+            Some("5; URL='/service1/next'".to_string())
+        );
+        assert_eq!(rewrite.rewrite_header("Content-Type", "text/html"), None);
+    }
+
+    #[test]
+    fn cookie_domain_and_path_are_rewritten() {
+        let rewrite = rewrite();
+
+        assert_eq!(
+            rewrite.rewrite_header(
+                "Set-Cookie",
+                "sid=a=b; Domain=.10.0.0.5; Path=/api/auth; HttpOnly"
+            ),
+            Some("sid=a=b; Path=/service1/auth; HttpOnly".to_string())
+        );
+        assert_eq!(
+            rewrite.rewrite_header("Set-Cookie", "sid=1; Path=/api"),
+            Some("sid=1; Path=/service1".to_string())
+        );
+        assert_eq!(
+            rewrite.rewrite_header("Set-Cookie", "sid=1; Domain=example.com; Path=/other"),
+            None
+        );
+
+        let mut headers = vec![
+            ("Set-Cookie".to_string(), "a=1; Path=/api/".to_string()),
+            ("ETag".to_string(), "\"v1\"".to_string()),
+        ];
+        rewrite.rewrite_headers(&mut headers);
+        assert_eq!(headers[0].1, "a=1; Path=/service1/");
+        assert_eq!(headers[1].1, "\"v1\"");
+
+        assert!(LocationResponseRewrite::new(vec![], vec![], vec![]).is_none());
+    }
+}
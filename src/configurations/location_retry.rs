@@ -0,0 +1,199 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use rand_core::{OsRng, RngCore};
+
+/// What one retry takes from the budget. A request puts `budget_percent` in,
+/// so at 20 percent every fifth request pays for a retry.
+const RETRY_COST: i64 = 100;
+
+/// Retries a budget starts with — a quiet location can still retry.
+const BUDGET_RESERVE: i64 = 10 * RETRY_COST;
+
+/// Retries a budget saves up at most: after a long calm an outage gets this
+/// many on top of its share, not an unbounded burst.
+const BUDGET_MAX: i64 = 100 * RETRY_COST;
+
+/// A failed attempt, as far as retrying it is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryFailure {
+    /// The upstream could not be reached or dropped the connection before it
+    /// answered.
+    Connect,
+    /// The upstream did not answer within the read timeout.
+    Timeout,
+    /// The upstream answered with this status.
+    Status(u16),
+}
+
+/// `retry:` of a location — which failed requests go to their upstream again,
+/// how long to wait before, and the budget that keeps retries from multiplying
+/// the load on an upstream that is already failing.
+pub struct LocationRetry {
+    /// What the retry metrics are labelled with — the location's `id_string`.
+    label: String,
+    attempts: u32,
+    retry_on: Vec<RetryFailure>,
+    /// Retried besides GET / HEAD / OPTIONS; uppercase.
+    methods: Vec<String>,
+    backoff: Duration,
+    max_backoff: Duration,
+    budget_percent: i64,
+    budget: AtomicI64,
+}
+
+impl LocationRetry {
+    pub fn new(
+        label: String,
+        attempts: u32,
+        retry_on: Vec<RetryFailure>,
+        methods: Vec<String>,
+        backoff: Duration,
+        max_backoff: Duration,
+        budget_percent: u32,
+    ) -> Self {
+        Self {
+            label,
+            attempts,
+            retry_on,
+            methods,
+            backoff,
+            max_backoff,
+            budget_percent: budget_percent as i64,
+            budget: AtomicI64::new(BUDGET_RESERVE),
+        }
+    }
+
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    /// Attempts in total, the first one included.
+    pub fn get_attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Whether requests with `method` are retried at all.
+    pub fn retries_method(&self, method: &str) -> bool {
+        matches!(method, "GET" | "HEAD" | "OPTIONS")
+            || self
+                .methods
+                .iter()
+                .any(|itm| itm.eq_ignore_ascii_case(method))
+    }
+
+    /// Whether an attempt that failed this way is retried.
+    pub fn retries_on(&self, failure: RetryFailure) -> bool {
+        self.retry_on.contains(&failure)
+    }
+
+    /// Counts a request the retries are budgeted against.
+    pub fn register_request(&self) {
+        let _ = self
+            .budget
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |itm| {
+                Some((itm + self.budget_percent).min(BUDGET_MAX))
+            });
+    }
+
+    /// Takes one retry from the budget; `false` when it is spent.
+    pub fn try_spend(&self) -> bool {
+        self.budget
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |itm| {
+                (itm >= RETRY_COST).then_some(itm - RETRY_COST)
+            })
+            .is_ok()
+    }
+
+    /// How long to wait before retry number `retry` (1 for the first one).
+    pub fn next_backoff(&self, retry: u32) -> Duration {
+        self.get_backoff(retry, OsRng.next_u32())
+    }
+
+    /// `backoff` doubled for every retry after the first, capped at
+    /// `max_backoff`; `jitter` picks the point in the upper half of it, so
+    /// requests failing together do not come back together.
+    fn get_backoff(&self, retry: u32, jitter: u32) -> Duration {
+        let doublings = retry.saturating_sub(1).min(16);
+        let full = self
+            .backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff);
+
+        let half = full / 2;
+        let spread = (full - half).as_micros() as u64;
+        half + Duration::from_micros(jitter as u64 % (spread + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retry(budget_percent: u32) -> LocationRetry {
+        LocationRetry::new(
+            "retry".to_string(),
+            3,
+            vec![RetryFailure::Connect, RetryFailure::Status(503)],
+            vec!["PUT".to_string()],
+            Duration::from_millis(100),
+            Duration::from_millis(1000),
+            budget_percent,
+        )
+    }
+
+    #[test]
+    fn only_idempotent_and_listed_methods_are_retried() {
+        let retry = retry(20);
+
+        assert!(retry.retries_method("GET"));
+        assert!(retry.retries_method("PUT"));
+        assert!(!retry.retries_method("POST"));
+
+        assert!(retry.retries_on(RetryFailure::Status(503)));
+        assert!(!retry.retries_on(RetryFailure::Status(502)));
+        assert!(!retry.retries_on(RetryFailure::Timeout));
+    }
+
+    #[test]
+    fn the_budget_caps_retries_at_their_share_of_requests() {
+        let retry = retry(20);
+
+        // The reserve first.
+        let reserve = (0..100).take_while(|_| retry.try_spend()).count();
+        assert_eq!(reserve, 10);
+
+        for _ in 0..200 {
+            retry.register_request();
+        }
+        let earned = (0..1000).take_while(|_| retry.try_spend()).count();
+        assert_eq!(earned, 40);
+
+        // A long calm saves up no more than the cap.
+        for _ in 0..100_000 {
+            retry.register_request();
+        }
+        let saved = (0..1000).take_while(|_| retry.try_spend()).count();
+        assert_eq!(saved, 100);
+    }
+
+    #[test]
+    fn the_backoff_doubles_up_to_its_cap() {
+        let retry = retry(20);
+
+        for jitter in [0, 1, 12_345, u32::MAX] {
+            for (attempt, full) in [
+                (1, 100),
+                (2, 200),
+                (3, 400),
+                (4, 800),
+                (5, 1000),
+                (30, 1000),
+            ] {
+                let backoff = retry.get_backoff(attempt, jitter);
+                assert!(backoff >= Duration::from_millis(full / 2));
+                assert!(backoff <= Duration::from_millis(full));
+            }
+        }
+    }
+}
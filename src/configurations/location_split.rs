@@ -0,0 +1,288 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use parking_lot::Mutex;
+
+use super::{LocationUpstreams, ProxyPassUpstream, UpstreamLease};
+
+/// Where a [`SplitMatch`] looks for its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitMatchSource {
+    Cookie,
+    /// The name is lowercased.
+    Header,
+}
+
+/// A cookie or header that sends a request to its branch whatever the weights.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitMatch {
+    pub source: SplitMatchSource,
+    pub name: String,
+    /// `None` matches any value.
+    pub value: Option<String>,
+}
+
+impl SplitMatch {
+    /// Whether the value the request carries under `name` matches.
+    pub fn accepts(&self, value: Option<&str>) -> bool {
+        match (value, self.value.as_deref()) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(value), Some(expected)) => value.trim() == expected,
+        }
+    }
+}
+
+/// One branch of a split. Its position is the position of its upstream in the
+/// location's [`LocationUpstreams`].
+pub struct SplitBranch {
+    pub name: String,
+    pub matches: Vec<SplitMatch>,
+    weight: AtomicU32,
+}
+
+impl SplitBranch {
+    pub fn new(name: String, weight: u32, matches: Vec<SplitMatch>) -> Self {
+        Self {
+            name,
+            matches,
+            weight: AtomicU32::new(weight),
+        }
+    }
+
+    pub fn get_weight(&self) -> u32 {
+        self.weight.load(Ordering::Relaxed)
+    }
+}
+
+/// `split:` of a location. The branches are the location's upstreams; the
+/// split replaces the balance policy's choice between them.
+///
+/// A split outlives the configuration it was compiled with: a reload that
+/// keeps its branches only updates the weights (see `LocationSplits`), so
+/// connections still holding the previous configuration follow the new
+/// weights at once.
+pub struct LocationSplit {
+    /// What the split's metrics are labelled with — the location's
+    /// `id_string`.
+    label: String,
+    branches: Vec<SplitBranch>,
+    /// Smooth weighted round-robin state, as in [`LocationUpstreams`].
+    current_weights: Mutex<Vec<i64>>,
+}
+
+impl LocationSplit {
+    pub fn new(label: String, branches: Vec<SplitBranch>) -> Self {
+        Self {
+            label,
+            current_weights: Mutex::new(vec![0; branches.len()]),
+            branches,
+        }
+    }
+
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn get_branches(&self) -> &[SplitBranch] {
+        &self.branches
+    }
+
+    /// Takes over the weights of `branches` when they are this split's
+    /// branches with nothing but the weights changed. `false` when they
+    /// differ otherwise — they need a split of their own.
+    pub fn update_weights(&self, branches: &[SplitBranch]) -> bool {
+        let same_branches =
+            self.branches.len() == branches.len()
+                && self.branches.iter().zip(branches).all(|(current, new)| {
+                    current.name == new.name && current.matches == new.matches
+                });
+
+        if !same_branches {
+            return false;
+        }
+
+        for (current, new) in self.branches.iter().zip(branches) {
+            current.weight.store(new.get_weight(), Ordering::Relaxed);
+        }
+
+        // Restart the round so the new shares apply from the next request.
+        self.current_weights.lock().fill(0);
+        true
+    }
+
+    /// The first branch one of whose cookies or headers the request carries.
+    /// `get_value` looks a cookie or header up in the request.
+    pub fn find_branch<'s>(
+        &self,
+        mut get_value: impl FnMut(&SplitMatch) -> Option<&'s str>,
+    ) -> Option<usize> {
+        self.branches
+            .iter()
+            .position(|branch| branch.matches.iter().any(|itm| itm.accepts(get_value(itm))))
+    }
+
+    /// Picks the upstream for a request: the `matched` branch, or one by
+    /// weight. `admit` refuses upstreams the way it does for
+    /// [`LocationUpstreams::pick`]; a request picked by weight does not fall
+    /// back to a branch with weight 0.
+    pub fn pick(
+        &self,
+        upstreams: &LocationUpstreams,
+        matched: Option<usize>,
+        mut admit: impl FnMut(&ProxyPassUpstream) -> bool,
+    ) -> UpstreamLease {
+        if let Some(matched) = matched {
+            return upstreams.pick_preferred(matched, admit);
+        }
+
+        let preferred = self.next_weighted();
+        upstreams.pick_preferred(preferred, |upstream| {
+            let weight = upstreams
+                .find_index(&upstream.id_string)
+                .and_then(|index| self.branches.get(index))
+                .map(|branch| branch.get_weight())
+                .unwrap_or(0);
+            weight > 0 && admit(upstream)
+        })
+    }
+
+    /// Counts a request to the branch at `index` and whether it failed — the
+    /// upstream could not be reached or answered 5xx.
+    pub fn report(&self, index: usize, failed: bool) {
+        let Some(branch) = self.branches.get(index) else {
+            return;
+        };
+
+        let prometheus = &crate::app::APP_CTX.prometheus;
+        prometheus.inc_split_requests(&self.label, &branch.name);
+        if failed {
+            prometheus.inc_split_errors(&self.label, &branch.name);
+        }
+    }
+
+    fn next_weighted(&self) -> usize {
+        let mut current_weights = self.current_weights.lock();
+
+        let mut total = 0;
+        let mut best = None;
+        for (index, branch) in self.branches.iter().enumerate() {
+            let weight = branch.get_weight() as i64;
+            if weight == 0 {
+                continue;
+            }
+
+            current_weights[index] += weight;
+            total += weight;
+            match best {
+                Some(best_index) if current_weights[best_index] >= current_weights[index] => {}
+                _ => best = Some(index),
+            }
+        }
+
+        let Some(best) = best else {
+            return 0;
+        };
+
+        current_weights[best] -= total;
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(weights: &[u32]) -> LocationSplit {
+        let branches = weights
+            .iter()
+            .enumerate()
+            .map(|(index, weight)| SplitBranch::new(format!("v{}", index + 1), *weight, vec![]))
+            .collect();
+        LocationSplit::new("split".to_string(), branches)
+    }
+
+    fn shares(split: &LocationSplit, requests: usize) -> Vec<usize> {
+        let mut result = vec![0; split.get_branches().len()];
+        for _ in 0..requests {
+            result[split.next_weighted()] += 1;
+        }
+        result
+    }
+
+    #[test]
+    fn requests_are_split_by_weight() {
+        let split = split(&[95, 5]);
+        assert_eq!(shares(&split, 1000), vec![950, 50]);
+    }
+
+    #[test]
+    fn a_branch_with_weight_0_gets_no_weighted_requests() {
+        let split = split(&[1, 0]);
+        assert_eq!(shares(&split, 100), vec![100, 0]);
+    }
+
+    #[test]
+    fn new_weights_are_taken_over_in_place() {
+        let split = split(&[100, 0]);
+
+        let updated = vec![
+            SplitBranch::new("v1".to_string(), 50, vec![]),
+            SplitBranch::new("v2".to_string(), 50, vec![]),
+        ];
+        assert!(split.update_weights(&updated));
+        assert_eq!(shares(&split, 100), vec![50, 50]);
+
+        let renamed = vec![
+            SplitBranch::new("v1".to_string(), 50, vec![]),
+            SplitBranch::new("v3".to_string(), 50, vec![]),
+        ];
+        assert!(!split.update_weights(&renamed));
+    }
+
+    #[test]
+    fn a_matching_cookie_or_header_picks_the_branch() {
+        let branches = vec![
+            SplitBranch::new("v1".to_string(), 1, vec![]),
+            SplitBranch::new(
+                "v2".to_string(),
+                0,
+                vec![
+                    SplitMatch {
+                        source: SplitMatchSource::Cookie,
+                        name: "canary".to_string(),
+                        value: Some("1".to_string()),
+                    },
+                    SplitMatch {
+                        source: SplitMatchSource::Header,
+                        name: "x-canary".to_string(),
+                        value: None,
+                    },
+                ],
+            ),
+        ];
+        let split = LocationSplit::new("split".to_string(), branches);
+
+        assert_eq!(split.find_branch(|_| None), None);
+        assert_eq!(
+            split.find_branch(|itm| match itm.source {
+                SplitMatchSource::Cookie => Some("0"),
+                SplitMatchSource::Header => None,
+            }),
+            None
+        );
+        assert_eq!(
+            split.find_branch(|itm| match itm.source {
+                SplitMatchSource::Cookie => Some("1"),
+                SplitMatchSource::Header => None,
+            }),
+            Some(1)
+        );
+        assert_eq!(
+            split.find_branch(|itm| match itm.source {
+                SplitMatchSource::Cookie => None,
+                SplitMatchSource::Header => Some("anything"),
+            }),
+            Some(1)
+        );
+    }
+}
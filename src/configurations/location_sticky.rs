@@ -0,0 +1,315 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use encryption::*;
+use parking_lot::Mutex;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use super::location_upstreams::{build_ring, find_on_ring, stable_hash};
+use super::{LocationUpstreams, ProxyPassUpstream, UpstreamLease};
+
+/// A session learned from a response that has not been seen for this long is
+/// forgotten.
+const SESSION_IDLE_SECS: i64 = 60 * 60;
+/// Upper bound on learned sessions per location. Past it new sessions are
+/// pinned by hash only.
+const MAX_SESSIONS: usize = 100_000;
+
+/// What pins a client to an upstream.
+pub enum StickyMode {
+    /// An affinity cookie issued by the proxy: the upstream's `id_string`,
+    /// encrypted with `token_secret_key` — encrypted, not signed: a value the
+    /// client tampers with decrypts to no upstream, and the request is balanced
+    /// as one without the cookie.
+    Cookie { name: String },
+    /// A request header. A value some upstream handed out in the same response
+    /// header (an MCP `Mcp-Session-Id`) goes back to that upstream; any other
+    /// value is hashed onto the upstreams.
+    Header { name: String },
+}
+
+struct PinnedSession {
+    index: usize,
+    last_seen: DateTimeAsMicroseconds,
+}
+
+/// `sticky:` of a location with several upstreams. A pinned client keeps
+/// going to its upstream for as long as outlier detection admits it; once the
+/// upstream is ejected the client is pinned again, to the upstream the
+/// request went to instead.
+pub struct LocationSticky {
+    mode: StickyMode,
+    ring: Vec<(u64, usize)>,
+    sessions: Mutex<AHashMap<String, PinnedSession>>,
+}
+
+impl LocationSticky {
+    pub fn new(mode: StickyMode, upstreams: &LocationUpstreams) -> Self {
+        let ring = match &mode {
+            StickyMode::Header { .. } => build_ring(upstreams.get_upstreams()),
+            StickyMode::Cookie { .. } => Vec::new(),
+        };
+
+        Self {
+            mode,
+            ring,
+            sessions: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    pub fn get_mode(&self) -> &StickyMode {
+        &self.mode
+    }
+
+    /// Picks the upstream for a request. `affinity` is the value of the
+    /// request's sticky cookie / header; without one the location's balance
+    /// policy decides.
+    pub fn pick(
+        &self,
+        upstreams: &LocationUpstreams,
+        affinity: Option<&str>,
+        client_ip: Option<IpAddr>,
+        admit: impl FnMut(&ProxyPassUpstream) -> bool,
+    ) -> UpstreamLease {
+        let now = DateTimeAsMicroseconds::now();
+
+        let Some(preferred) = affinity.and_then(|itm| self.find_pinned(upstreams, itm, now)) else {
+            return upstreams.pick(client_ip, admit);
+        };
+
+        let lease = upstreams.pick_preferred(preferred, admit);
+
+        // Re-pin a learned session that had to move off an ejected upstream.
+        if lease.get_index() != preferred {
+            if let (StickyMode::Header { .. }, Some(session)) = (&self.mode, affinity) {
+                self.learn(session, lease.get_index(), now);
+            }
+        }
+
+        lease
+    }
+
+    /// To be called with the upstream's response. Returns the `Set-Cookie`
+    /// value to add when the client has to be (re)pinned by cookie.
+    /// `response_session` is the value of the sticky header in the response;
+    /// `is_https` whether the client came over an https endpoint.
+    pub fn on_response(
+        &self,
+        affinity: Option<&str>,
+        response_session: Option<&str>,
+        lease: &UpstreamLease,
+        is_https: bool,
+    ) -> Option<String> {
+        match &self.mode {
+            StickyMode::Cookie { name } => {
+                let upstream = lease.get_upstream();
+                if affinity.and_then(decode_cookie).as_deref() == Some(upstream.id_string.as_str())
+                {
+                    return None;
+                }
+
+                Some(build_set_cookie(
+                    name,
+                    &encode_cookie(&upstream.id_string),
+                    is_https,
+                ))
+            }
+            StickyMode::Header { .. } => {
+                if let Some(session) = response_session {
+                    if affinity != Some(session) {
+                        self.learn(session, lease.get_index(), DateTimeAsMicroseconds::now());
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    fn find_pinned(
+        &self,
+        upstreams: &LocationUpstreams,
+        affinity: &str,
+        now: DateTimeAsMicroseconds,
+    ) -> Option<usize> {
+        match &self.mode {
+            StickyMode::Cookie { .. } => upstreams.find_index(decode_cookie(affinity)?.as_str()),
+            StickyMode::Header { .. } => {
+                if let Some(session) = self.sessions.lock().get_mut(affinity) {
+                    session.last_seen = now;
+                    return Some(session.index);
+                }
+
+                Some(find_on_ring(&self.ring, stable_hash(affinity.as_bytes())))
+            }
+        }
+    }
+
+    fn learn(&self, session: &str, index: usize, now: DateTimeAsMicroseconds) {
+        let mut sessions = self.sessions.lock();
+
+        if sessions.len() >= MAX_SESSIONS && !sessions.contains_key(session) {
+            sessions.retain(|_, itm| {
+                now.duration_since(itm.last_seen).get_full_seconds() < SESSION_IDLE_SECS
+            });
+            if sessions.len() >= MAX_SESSIONS {
+                return;
+            }
+        }
+
+        sessions.insert(
+            session.to_string(),
+            PinnedSession {
+                index,
+                last_seen: now,
+            },
+        );
+    }
+}
+
+/// What a request carries for the sticky location it goes to — travels with
+/// the request to the point its response is read.
+pub struct StickyRequest {
+    pub sticky: Arc<LocationSticky>,
+    pub affinity: Option<String>,
+    /// The affinity cookie of an https endpoint is `Secure`.
+    pub is_https: bool,
+}
+
+impl StickyRequest {
+    /// See [`LocationSticky::on_response`].
+    pub fn on_response(
+        &self,
+        response_session: Option<&str>,
+        lease: &UpstreamLease,
+    ) -> Option<String> {
+        self.sticky.on_response(
+            self.affinity.as_deref(),
+            response_session,
+            lease,
+            self.is_https,
+        )
+    }
+
+    /// The response header to pass to [`Self::on_response`], if any.
+    pub fn get_session_header(&self) -> Option<&str> {
+        match self.sticky.get_mode() {
+            StickyMode::Header { name } => Some(name.as_str()),
+            StickyMode::Cookie { .. } => None,
+        }
+    }
+}
+
+/// The affinity cookie is not for scripts, nor sent along with requests other
+/// sites make — and not over plain http when it was issued over https.
+fn build_set_cookie(name: &str, value: &str, is_https: bool) -> String {
+    match is_https {
+        true => format!("{}={}; Path=/; HttpOnly; Secure; SameSite=Lax", name, value),
+        false => format!("{}={}; Path=/; HttpOnly; SameSite=Lax", name, value),
+    }
+}
+
+fn encode_cookie(id_string: &str) -> String {
+    crate::app::APP_CTX
+        .token_secret_key
+        .encrypt(id_string.as_bytes())
+        .as_base_64()
+}
+
+fn decode_cookie(value: &str) -> Option<String> {
+    let aes = AesEncryptedDataOwned::from_base_64(value).ok()?;
+    let decrypted = crate::app::APP_CTX.token_secret_key.decrypt(&aes).ok()?;
+    String::from_utf8(decrypted.as_slice().to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::{LoadBalancePolicy, ProxyPassToConfig};
+
+    fn upstreams(count: usize) -> LocationUpstreams {
+        let upstreams = (0..count)
+            .map(|index| {
+                ProxyPassUpstream::new(
+                    index as i64,
+                    format!("upstream-{}", index),
+                    ProxyPassToConfig::Drop,
+                    1,
+                )
+            })
+            .collect();
+        LocationUpstreams::new(LoadBalancePolicy::RoundRobin, upstreams)
+    }
+
+    fn header_sticky(upstreams: &LocationUpstreams) -> LocationSticky {
+        LocationSticky::new(
+            StickyMode::Header {
+                name: "mcp-session-id".to_string(),
+            },
+            upstreams,
+        )
+    }
+
+    #[test]
+    fn the_same_header_value_lands_on_the_same_upstream() {
+        let upstreams = upstreams(3);
+        let sticky = header_sticky(&upstreams);
+
+        let first = sticky
+            .pick(&upstreams, Some("client-a"), None, |_| true)
+            .get_index();
+        for _ in 0..10 {
+            let lease = sticky.pick(&upstreams, Some("client-a"), None, |_| true);
+            assert_eq!(lease.get_index(), first);
+        }
+    }
+
+    #[test]
+    fn a_session_handed_out_by_an_upstream_goes_back_to_it() {
+        let upstreams = upstreams(3);
+        let sticky = header_sticky(&upstreams);
+
+        // The initializing request has no session yet and goes round-robin.
+        let lease = sticky.pick(&upstreams, None, None, |_| true);
+        let index = lease.get_index();
+        assert!(sticky
+            .on_response(None, Some("session-1"), &lease, false)
+            .is_none());
+
+        for _ in 0..5 {
+            let lease = sticky.pick(&upstreams, Some("session-1"), None, |_| true);
+            assert_eq!(lease.get_index(), index);
+        }
+    }
+
+    #[test]
+    fn an_ejected_upstream_re_pins_the_session() {
+        let upstreams = upstreams(3);
+        let sticky = header_sticky(&upstreams);
+
+        let lease = sticky.pick(&upstreams, None, None, |_| true);
+        let ejected = lease.get_index() as i64;
+        sticky.on_response(None, Some("session-1"), &lease, false);
+
+        let moved = sticky
+            .pick(&upstreams, Some("session-1"), None, |itm| itm.id != ejected)
+            .get_index();
+        assert_ne!(moved as i64, ejected);
+
+        // Stays on the new upstream after the old one is back.
+        let lease = sticky.pick(&upstreams, Some("session-1"), None, |_| true);
+        assert_eq!(lease.get_index(), moved);
+    }
+
+    #[test]
+    fn the_affinity_cookie_of_an_https_endpoint_is_secure() {
+        assert_eq!(
+            build_set_cookie("upstream", "abc", true),
+            "upstream=abc; Path=/; HttpOnly; Secure; SameSite=Lax"
+        );
+        assert_eq!(
+            build_set_cookie("upstream", "abc", false),
+            "upstream=abc; Path=/; HttpOnly; SameSite=Lax"
+        );
+    }
+}
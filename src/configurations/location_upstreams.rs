@@ -0,0 +1,435 @@
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use super::ProxyPassToConfig;
+
+/// Points each upstream gets on the consistent-hash ring per unit of weight.
+const RING_POINTS_PER_WEIGHT: u32 = 40;
+
+/// How a location with several upstreams in `proxy_pass_to` picks one of them
+/// for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadBalancePolicy {
+    /// Every upstream in turn; weights are ignored.
+    RoundRobin,
+    /// In turn, each upstream `weight` times per cycle, spread evenly through
+    /// the cycle rather than in bursts.
+    Weighted,
+    /// The upstream with the fewest requests in flight through this location;
+    /// ties go round-robin.
+    LeastOutstanding,
+    /// The client IP is hashed onto a ring, so a client keeps landing on the
+    /// same upstream; adding or removing an upstream moves only its own share
+    /// of the clients.
+    ConsistentHash,
+}
+
+impl LoadBalancePolicy {
+    /// `None` is the default (`round_robin`).
+    pub fn parse(src: Option<&str>) -> Result<Self, String> {
+        let Some(src) = src else {
+            return Ok(Self::RoundRobin);
+        };
+
+        match src.trim() {
+            "" | "round_robin" | "round-robin" => Ok(Self::RoundRobin),
+            "weighted" | "weighted_round_robin" => Ok(Self::Weighted),
+            "least_outstanding" | "least_outstanding_requests" | "least_conn" => {
+                Ok(Self::LeastOutstanding)
+            }
+            "consistent_hash" | "ip_hash" => Ok(Self::ConsistentHash),
+            other => Err(format!(
+                "Unknown balance policy '{}'. Expected round_robin, weighted, least_outstanding or consistent_hash",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RoundRobin => "round_robin",
+            Self::Weighted => "weighted",
+            Self::LeastOutstanding => "least_outstanding",
+            Self::ConsistentHash => "consistent_hash",
+        }
+    }
+}
+
+/// One of the upstreams a location balances between. Each has a pool of its
+/// own, keyed by `id` the same way a single-upstream location's pool is keyed
+/// by the location id — the first upstream simply reuses the location's.
+pub struct ProxyPassUpstream {
+    pub id: i64,
+    pub id_string: String,
+    pub proxy_pass_to: ProxyPassToConfig,
+    pub weight: u32,
+    outstanding: AtomicUsize,
+    requests: AtomicU64,
+}
+
+impl ProxyPassUpstream {
+    pub fn new(id: i64, id_string: String, proxy_pass_to: ProxyPassToConfig, weight: u32) -> Self {
+        Self {
+            id,
+            id_string,
+            proxy_pass_to,
+            weight: weight.max(1),
+            outstanding: AtomicUsize::new(0),
+            requests: AtomicU64::new(0),
+        }
+    }
+
+    /// Requests currently holding an [`UpstreamLease`] on this upstream.
+    pub fn get_outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::Relaxed)
+    }
+
+    /// Requests sent to this upstream since the configuration was applied.
+    pub fn get_requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+}
+
+/// The upstreams of a location with more than one entry in `proxy_pass_to`,
+/// plus the state its balance policy needs. Compiled once per configuration;
+/// both the h1 byte pipeline and the hyper path pick through [`Self::pick`].
+pub struct LocationUpstreams {
+    policy: LoadBalancePolicy,
+    upstreams: Vec<Arc<ProxyPassUpstream>>,
+    next: AtomicUsize,
+    /// Smooth weighted round-robin state (the nginx algorithm): one running
+    /// score per upstream.
+    current_weights: Mutex<Vec<i64>>,
+    /// `(point, upstream index)`, sorted by point.
+    ring: Vec<(u64, usize)>,
+}
+
+impl LocationUpstreams {
+    pub fn new(policy: LoadBalancePolicy, upstreams: Vec<ProxyPassUpstream>) -> Self {
+        let upstreams: Vec<Arc<ProxyPassUpstream>> = upstreams.into_iter().map(Arc::new).collect();
+        let ring = match policy {
+            LoadBalancePolicy::ConsistentHash => build_ring(&upstreams),
+            _ => Vec::new(),
+        };
+
+        Self {
+            policy,
+            current_weights: Mutex::new(vec![0; upstreams.len()]),
+            upstreams,
+            next: AtomicUsize::new(0),
+            ring,
+        }
+    }
+
+    pub fn get_policy(&self) -> LoadBalancePolicy {
+        self.policy
+    }
+
+    pub fn get_upstreams(&self) -> &[Arc<ProxyPassUpstream>] {
+        &self.upstreams
+    }
+
+    /// Position of the upstream with this `id_string`.
+    pub fn find_index(&self, id_string: &str) -> Option<usize> {
+        self.upstreams
+            .iter()
+            .position(|itm| itm.id_string == id_string)
+    }
+
+    /// Picks the upstream for one request. The returned lease counts the
+    /// request as outstanding on that upstream until it is dropped.
+    /// `client_ip` is only used by `consistent_hash`; without one (a unix
+    /// socket listener) the request goes round-robin.
+    ///
+    /// `admit` says whether an upstream may take the request (outlier
+    /// detection). When the policy's choice is refused, the request goes to
+    /// the next admitted upstream in declaration order; when every upstream is
+    /// refused, to the policy's choice anyway — a request sent to a struggling
+    /// upstream beats one refused outright.
+    pub fn pick(
+        &self,
+        client_ip: Option<IpAddr>,
+        admit: impl FnMut(&ProxyPassUpstream) -> bool,
+    ) -> UpstreamLease {
+        let preferred = self.pick_by_policy(client_ip);
+        self.pick_preferred(preferred, admit)
+    }
+
+    /// [`Self::pick`] with the upstream at `preferred` in place of the policy's
+    /// choice — for a client pinned to it (`sticky`). Refused the same way.
+    pub fn pick_preferred(
+        &self,
+        preferred: usize,
+        mut admit: impl FnMut(&ProxyPassUpstream) -> bool,
+    ) -> UpstreamLease {
+        let len = self.upstreams.len();
+        let index = (0..len)
+            .map(|step| (preferred + step) % len)
+            .find(|index| admit(&self.upstreams[*index]))
+            .unwrap_or(preferred);
+
+        UpstreamLease::new(index, self.upstreams[index].clone())
+    }
+
+    fn pick_by_policy(&self, client_ip: Option<IpAddr>) -> usize {
+        match self.policy {
+            LoadBalancePolicy::RoundRobin => self.next_round_robin(),
+            LoadBalancePolicy::Weighted => self.next_weighted(),
+            LoadBalancePolicy::LeastOutstanding => self.least_outstanding(),
+            LoadBalancePolicy::ConsistentHash => match client_ip {
+                Some(ip) => self.on_ring(ip),
+                None => self.next_round_robin(),
+            },
+        }
+    }
+
+    fn next_round_robin(&self) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % self.upstreams.len()
+    }
+
+    fn next_weighted(&self) -> usize {
+        let mut current_weights = self.current_weights.lock();
+
+        let mut total = 0;
+        let mut best = 0;
+        for (index, upstream) in self.upstreams.iter().enumerate() {
+            current_weights[index] += upstream.weight as i64;
+            total += upstream.weight as i64;
+            if current_weights[index] > current_weights[best] {
+                best = index;
+            }
+        }
+
+        current_weights[best] -= total;
+        best
+    }
+
+    fn least_outstanding(&self) -> usize {
+        // Start the scan at a rotating offset, so ties do not all land on the
+        // first upstream.
+        let start = self.next_round_robin();
+        let len = self.upstreams.len();
+
+        let mut best = start;
+        for step in 1..len {
+            let index = (start + step) % len;
+            if self.upstreams[index].get_outstanding() < self.upstreams[best].get_outstanding() {
+                best = index;
+            }
+        }
+
+        best
+    }
+
+    fn on_ring(&self, ip: IpAddr) -> usize {
+        let point = match ip {
+            IpAddr::V4(ip) => stable_hash(&ip.octets()),
+            IpAddr::V6(ip) => stable_hash(&ip.octets()),
+        };
+
+        find_on_ring(&self.ring, point)
+    }
+}
+
+/// Built from each upstream's identity rather than its position, so the ring
+/// (and with it every client's upstream) survives a reorder or a reload.
+pub(super) fn build_ring(upstreams: &[Arc<ProxyPassUpstream>]) -> Vec<(u64, usize)> {
+    let mut ring = Vec::new();
+
+    for (index, upstream) in upstreams.iter().enumerate() {
+        for point in 0..upstream.weight * RING_POINTS_PER_WEIGHT {
+            let point_key = format!("{}#{}", upstream.id_string, point);
+            ring.push((stable_hash(point_key.as_bytes()), index));
+        }
+    }
+
+    ring.sort_by_key(|(point, _)| *point);
+    ring
+}
+
+/// The upstream owning `point`: the first one at or after it, wrapping around.
+pub(super) fn find_on_ring(ring: &[(u64, usize)], point: u64) -> usize {
+    let index = ring.partition_point(|(itm, _)| *itm < point);
+    match ring.get(index) {
+        Some((_, upstream)) => *upstream,
+        None => ring[0].1,
+    }
+}
+
+/// FNV-1a, finished with the murmur3 avalanche step: FNV alone barely moves the
+/// high bits for short keys such as an IPv4 address, and the ring is ordered
+/// by all 64 of them. Stable across restarts and toolchains, unlike `DefaultHasher`.
+pub(super) fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+/// A request's claim on the upstream it was balanced to. Counts as outstanding
+/// on that upstream for as long as it lives.
+pub struct UpstreamLease {
+    index: usize,
+    upstream: Arc<ProxyPassUpstream>,
+}
+
+impl UpstreamLease {
+    fn new(index: usize, upstream: Arc<ProxyPassUpstream>) -> Self {
+        upstream.outstanding.fetch_add(1, Ordering::Relaxed);
+        upstream.requests.fetch_add(1, Ordering::Relaxed);
+        Self { index, upstream }
+    }
+
+    /// Position of the upstream in [`LocationUpstreams::get_upstreams`].
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    pub fn get_upstream(&self) -> &ProxyPassUpstream {
+        &self.upstream
+    }
+}
+
+impl Drop for UpstreamLease {
+    fn drop(&mut self) {
+        self.upstream.outstanding.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstreams(policy: LoadBalancePolicy, weights: &[u32]) -> LocationUpstreams {
+        let upstreams = weights
+            .iter()
+            .enumerate()
+            .map(|(index, weight)| {
+                ProxyPassUpstream::new(
+                    index as i64,
+                    format!("upstream-{}", index),
+                    ProxyPassToConfig::Drop,
+                    *weight,
+                )
+            })
+            .collect();
+        LocationUpstreams::new(policy, upstreams)
+    }
+
+    fn picks(upstreams: &LocationUpstreams, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| upstreams.pick(None, |_| true).get_index())
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_ignores_weights() {
+        let upstreams = upstreams(LoadBalancePolicy::RoundRobin, &[5, 1, 1]);
+
+        assert_eq!(picks(&upstreams, 6), vec![0, 1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn weighted_spreads_the_heavy_upstream_through_the_cycle() {
+        let upstreams = upstreams(LoadBalancePolicy::Weighted, &[5, 1, 1]);
+
+        assert_eq!(picks(&upstreams, 7), vec![0, 0, 1, 0, 2, 0, 0]);
+    }
+
+    #[test]
+    fn least_outstanding_avoids_the_busy_upstream() {
+        let upstreams = upstreams(LoadBalancePolicy::LeastOutstanding, &[1, 1]);
+
+        let busy = upstreams.pick(None, |_| true);
+        for _ in 0..4 {
+            assert_ne!(upstreams.pick(None, |_| true).get_index(), busy.get_index());
+        }
+
+        assert_eq!(
+            upstreams.get_upstreams()[busy.get_index()].get_outstanding(),
+            1
+        );
+        drop(busy);
+        assert_eq!(upstreams.get_upstreams()[0].get_outstanding(), 0);
+        assert_eq!(upstreams.get_upstreams()[1].get_outstanding(), 0);
+    }
+
+    #[test]
+    fn consistent_hash_keeps_a_client_on_one_upstream() {
+        let upstreams = upstreams(LoadBalancePolicy::ConsistentHash, &[1, 1, 1]);
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+
+        let first = upstreams.pick(Some(ip), |_| true).get_index();
+        for _ in 0..10 {
+            assert_eq!(upstreams.pick(Some(ip), |_| true).get_index(), first);
+        }
+    }
+
+    #[test]
+    fn consistent_hash_spreads_clients_across_upstreams() {
+        let upstreams = upstreams(LoadBalancePolicy::ConsistentHash, &[1, 1, 1]);
+
+        let mut hits = [0usize; 3];
+        for last in 0..=255u8 {
+            let ip = IpAddr::from([192, 168, 1, last]);
+            hits[upstreams.pick(Some(ip), |_| true).get_index()] += 1;
+        }
+
+        assert!(hits.iter().all(|itm| *itm > 30), "{:?}", hits);
+    }
+
+    #[test]
+    fn requests_are_counted_per_upstream() {
+        let upstreams = upstreams(LoadBalancePolicy::RoundRobin, &[1, 1]);
+
+        picks(&upstreams, 3);
+
+        assert_eq!(upstreams.get_upstreams()[0].get_requests(), 2);
+        assert_eq!(upstreams.get_upstreams()[1].get_requests(), 1);
+    }
+
+    #[test]
+    fn refused_upstreams_are_skipped() {
+        let upstreams = upstreams(LoadBalancePolicy::RoundRobin, &[1, 1, 1]);
+
+        let picks: Vec<usize> = (0..4)
+            .map(|_| upstreams.pick(None, |itm| itm.id != 1).get_index())
+            .collect();
+
+        assert_eq!(picks, vec![0, 2, 2, 0]);
+    }
+
+    #[test]
+    fn everything_refused_falls_back_to_the_policy() {
+        let upstreams = upstreams(LoadBalancePolicy::RoundRobin, &[1, 1]);
+
+        let picks: Vec<usize> = (0..3)
+            .map(|_| upstreams.pick(None, |_| false).get_index())
+            .collect();
+
+        assert_eq!(picks, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn policy_names() {
+        assert_eq!(
+            LoadBalancePolicy::parse(None).unwrap(),
+            LoadBalancePolicy::RoundRobin
+        );
+        assert_eq!(
+            LoadBalancePolicy::parse(Some("ip_hash")).unwrap(),
+            LoadBalancePolicy::ConsistentHash
+        );
+        assert!(LoadBalancePolicy::parse(Some("random")).is_err());
+    }
+}
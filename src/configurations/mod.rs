@@ -15,6 +15,32 @@ mod http_type;
 pub use http_type::*;
 mod proxy_pass_location_config;
 pub use proxy_pass_location_config::*;
+mod location_matcher;
+pub use location_matcher::*;
+mod location_path_rewrite;
+pub use location_path_rewrite::*;
+mod location_response_rewrite;
+pub use location_response_rewrite::*;
+mod location_upstreams;
+pub use location_upstreams::*;
+mod location_backups;
+pub use location_backups::*;
+mod location_sticky;
+pub use location_sticky::*;
+mod location_mirror;
+pub use location_mirror::*;
+mod location_split;
+pub use location_split::*;
+mod location_retry;
+pub use location_retry::*;
+mod request_limits;
+pub use request_limits::*;
+mod cors_config;
+pub use cors_config::*;
+mod redirect_template;
+pub use redirect_template::*;
+mod static_body_template;
+pub use static_body_template::*;
 //mod ssh_content;
 //pub use ssh_content::*;
 mod local_file_path;
@@ -1,16 +1,26 @@
+use std::sync::Arc;
 use std::time::Duration;
 
 use my_ssh::ssh_settings::OverSshConnectionSettings;
 
 use crate::{
-    app::APP_CTX, http_content_source::local_path::LocalPathContentSrc, http_content_source::*,
-    http_proxy_pass::content_source::*, settings::ModifyHttpHeadersSettings,
+    app::{
+        ConcurrencyLimiter, ConcurrencyLimiterSettings, RateLimiter, RateLimiterSettings, APP_CTX,
+    },
+    body_filter::{ResponseCompression, SubFilter},
+    http_content_source::local_path::LocalPathContentSrc,
+    http_content_source::*,
+    http_proxy_pass::content_source::*,
+    response_cache::{LocationCache, LocationCacheSettings, StaleStore, StaleStoreSettings},
+    settings::ModifyHttpHeadersSettings,
 };
 
 use super::*;
 
 pub struct ProxyPassLocationConfig {
     pub path: String,
+    /// `path` compiled with the location's `match:` mode.
+    pub matcher: LocationMatcher,
     pub id: i64,
     /// Logical identity of the (listen → upstream) pair, formatted as
     /// `"{listen_host}|{path}->{scheme}://{host}:{port}"`. On config apply
@@ -25,11 +35,47 @@ pub struct ProxyPassLocationConfig {
     pub proxy_pass_to: ProxyPassToConfig,
     pub compress: bool,
     pub auth_header: Option<String>,
+    /// `strip_prefix` / `rewrite` / `append_to_upstream_path`, when any is set.
+    pub path_rewrite: Option<LocationPathRewrite>,
+    /// `proxy_redirect` / `proxy_cookie_domain` / `proxy_cookie_path`, when
+    /// they have any rule.
+    pub response_rewrite: Option<Arc<LocationResponseRewrite>>,
+    /// Set when `proxy_pass_to` lists several upstreams. `proxy_pass_to`, `id`
+    /// and `id_string` then describe the first of them.
+    pub upstreams: Option<LocationUpstreams>,
+    /// `sticky`, when set; only on a location with `upstreams`.
+    pub sticky: Option<Arc<LocationSticky>>,
+    /// `backup_proxy_pass_to`, when set.
+    pub backups: Option<Arc<LocationBackups>>,
+    /// `mirror_to`, when set.
+    pub mirror: Option<Arc<LocationMirror>>,
+    /// `split`, when set; its branches are the `upstreams`.
+    pub split: Option<Arc<LocationSplit>>,
+    /// `retry`, when set.
+    pub retry: Option<Arc<LocationRetry>>,
+    /// `cache`, when set.
+    pub cache: Option<Arc<LocationCache>>,
+    /// `stale`, when set.
+    pub stale: Option<Arc<StaleStore>>,
+    /// `rate_limit`, when set.
+    pub rate_limit: Option<Arc<RateLimiter>>,
+    /// `max_concurrent_requests`, when set.
+    pub concurrency_limit: Option<Arc<ConcurrencyLimiter>>,
+    /// The location's own `max_header_size`, `max_headers`, `max_uri_length`
+    /// and `max_body_size`; see [`HttpEndpointInfo::get_request_limits`].
+    pub request_limits: RequestLimits,
+    /// `cors`, when set; see [`HttpEndpointInfo::get_cors`].
+    pub cors: Option<Arc<CorsConfig>>,
+    /// `sub_filter`, when set.
+    pub sub_filter: Option<Arc<SubFilter>>,
+    /// `compress` with `compress_types` and `compress_min_size`, when set.
+    pub compression: Option<Arc<ResponseCompression>>,
 }
 
 impl ProxyPassLocationConfig {
     pub fn new(
         path: String,
+        matcher: LocationMatcher,
         modify_headers: Option<ModifyHttpHeadersSettings>,
         ip_white_list_id: Option<String>,
         proxy_pass_to: ProxyPassToConfig,
@@ -46,12 +92,13 @@ impl ProxyPassLocationConfig {
             modify_response_headers.populate_response(&mut modify_headers);
         }
 
-        let id_string = build_location_id_string(listen_host, &path, &proxy_pass_to);
+        let id_string = build_location_id_string(listen_host, &path, &matcher, &proxy_pass_to);
         let id = crate::scripts::find_location_id_by_id_string(&id_string)
             .unwrap_or_else(|| APP_CTX.get_next_id());
 
         Self {
             path,
+            matcher,
             id,
             id_string,
             modify_request_headers,
@@ -61,23 +108,290 @@ impl ProxyPassLocationConfig {
             domain_name,
             compress,
             auth_header,
+            path_rewrite: None,
+            response_rewrite: None,
+            upstreams: None,
+            sticky: None,
+            backups: None,
+            mirror: None,
+            split: None,
+            retry: None,
+            cache: None,
+            stale: None,
+            rate_limit: None,
+            concurrency_limit: None,
+            request_limits: RequestLimits::default(),
+            cors: None,
+            sub_filter: None,
+            compression: None,
         }
     }
+
+    /// Makes the location balance between `upstreams` (the first of them being
+    /// `proxy_pass_to` itself). Each upstream gets a pool id of its own,
+    /// preserved across reloads by its `id_string` exactly like a location's;
+    /// the first one keeps the location's.
+    pub fn set_upstreams(
+        &mut self,
+        policy: LoadBalancePolicy,
+        upstreams: Vec<(ProxyPassToConfig, u32)>,
+        listen_host: &str,
+    ) {
+        let upstreams = upstreams
+            .into_iter()
+            .enumerate()
+            .map(|(index, (proxy_pass_to, weight))| {
+                if index == 0 {
+                    return ProxyPassUpstream::new(
+                        self.id,
+                        self.id_string.clone(),
+                        proxy_pass_to,
+                        weight,
+                    );
+                }
+
+                self.create_upstream(proxy_pass_to, weight, listen_host)
+            })
+            .collect();
+
+        self.upstreams = Some(LocationUpstreams::new(policy, upstreams));
+    }
+
+    /// Pins clients to the upstreams set by [`Self::set_upstreams`].
+    pub fn set_sticky(&mut self, mode: StickyMode) {
+        let Some(upstreams) = self.upstreams.as_ref() else {
+            return;
+        };
+
+        self.sticky = Some(Arc::new(LocationSticky::new(mode, upstreams)));
+    }
+
+    /// Sets `split`. A location that had the same branches before the reload
+    /// keeps its split, with the weights updated.
+    pub fn set_split(&mut self, branches: Vec<SplitBranch>) {
+        if self.upstreams.is_none() {
+            return;
+        }
+
+        self.split = Some(APP_CTX.location_splits.apply(&self.id_string, branches));
+    }
+
+    /// Sets `backup_proxy_pass_to`. Every backup gets a pool id of its own,
+    /// the same way the upstreams of a balanced location do.
+    pub fn set_backups(
+        &mut self,
+        backups: Vec<ProxyPassToConfig>,
+        on_status: Vec<u16>,
+        listen_host: &str,
+    ) {
+        let backups = backups
+            .into_iter()
+            .map(|proxy_pass_to| self.create_upstream(proxy_pass_to, 1, listen_host))
+            .collect();
+
+        self.backups = Some(Arc::new(LocationBackups::new(backups, on_status)));
+    }
+
+    /// Sets `mirror_to`. The mirror gets a pool id of its own, the same way a
+    /// backup does.
+    pub fn set_mirror(
+        &mut self,
+        proxy_pass_to: ProxyPassToConfig,
+        percent: u32,
+        max_body_size: usize,
+        listen_host: &str,
+    ) {
+        let upstream = self.create_upstream(proxy_pass_to, 1, listen_host);
+        self.mirror = Some(Arc::new(LocationMirror::new(
+            upstream,
+            percent,
+            max_body_size,
+        )));
+    }
+
+    /// Sets `cache`. A location that had the same cache settings before the
+    /// reload keeps what it has cached.
+    pub fn set_cache(&mut self, settings: LocationCacheSettings) -> Result<(), String> {
+        let cache = APP_CTX
+            .response_caches
+            .apply(&self.id_string, settings)
+            .map_err(|err| format!("Location {}: {}", self.path, err))?;
+
+        self.cache = Some(cache);
+        Ok(())
+    }
+
+    /// Sets `stale`. A location that had the same stale settings before the
+    /// reload keeps its last good responses.
+    pub fn set_stale(&mut self, settings: StaleStoreSettings) {
+        self.stale = Some(APP_CTX.stale_stores.apply(&self.id_string, settings));
+    }
+
+    /// Sets `rate_limit`. A location that had the same rate limit before the
+    /// reload keeps its clients' buckets.
+    pub fn set_rate_limit(&mut self, settings: RateLimiterSettings) {
+        self.rate_limit = Some(APP_CTX.rate_limiters.apply(&self.id_string, settings));
+    }
+
+    /// Sets `max_concurrent_requests`. A location that had the same limit
+    /// before the reload goes on counting the requests in flight.
+    pub fn set_concurrency_limit(&mut self, settings: ConcurrencyLimiterSettings) {
+        self.concurrency_limit = Some(
+            APP_CTX
+                .concurrency_limiters
+                .apply(&self.id_string, settings),
+        );
+    }
+
+    fn create_upstream(
+        &self,
+        proxy_pass_to: ProxyPassToConfig,
+        weight: u32,
+        listen_host: &str,
+    ) -> ProxyPassUpstream {
+        let id_string =
+            build_location_id_string(listen_host, &self.path, &self.matcher, &proxy_pass_to);
+        let id = crate::scripts::find_location_id_by_id_string(&id_string)
+            .unwrap_or_else(|| APP_CTX.get_next_id());
+        ProxyPassUpstream::new(id, id_string, proxy_pass_to, weight)
+    }
+
+    /// Every upstream with the id its pool is keyed by — just the location's
+    /// own unless `proxy_pass_to` is a list — followed by the backups and the
+    /// mirror.
+    pub fn get_pool_ids(&self) -> Vec<(i64, &ProxyPassToConfig)> {
+        let mut result: Vec<(i64, &ProxyPassToConfig)> = match self.upstreams.as_ref() {
+            Some(upstreams) => upstreams
+                .get_upstreams()
+                .iter()
+                .map(|itm| (itm.id, &itm.proxy_pass_to))
+                .collect(),
+            None => vec![(self.id, &self.proxy_pass_to)],
+        };
+
+        if let Some(backups) = self.backups.as_ref() {
+            result.extend(
+                backups
+                    .get_upstreams()
+                    .iter()
+                    .map(|itm| (itm.id, &itm.proxy_pass_to)),
+            );
+        }
+
+        if let Some(mirror) = self.mirror.as_ref() {
+            let upstream = mirror.get_upstream();
+            result.push((upstream.id, &upstream.proxy_pass_to));
+        }
+
+        result
+    }
+
     pub fn get_proxy_pass_to_as_string(&self) -> String {
         self.proxy_pass_to.to_string()
     }
 
-    pub async fn create_data_source(
+    /// One content source per upstream, in `upstreams` order.
+    pub async fn create_data_sources(
+        &self,
+        debug: bool,
+        timeout: Duration,
+    ) -> Vec<HttpProxyPassContentSource> {
+        let Some(upstreams) = self.upstreams.as_ref() else {
+            let source = self
+                .create_data_source(
+                    &self.proxy_pass_to,
+                    self.id,
+                    &self.id_string,
+                    debug,
+                    timeout,
+                )
+                .await;
+            return vec![source];
+        };
+
+        let mut result = Vec::with_capacity(upstreams.get_upstreams().len());
+        for upstream in upstreams.get_upstreams() {
+            let source = self
+                .create_data_source(
+                    &upstream.proxy_pass_to,
+                    upstream.id,
+                    &upstream.id_string,
+                    debug,
+                    timeout,
+                )
+                .await;
+            result.push(source);
+        }
+        result
+    }
+
+    /// One content source per backup, in `backup_proxy_pass_to` order.
+    pub async fn create_backup_data_sources(
         &self,
         debug: bool,
         timeout: Duration,
+    ) -> Vec<HttpProxyPassContentSource> {
+        let Some(backups) = self.backups.as_ref() else {
+            return Vec::new();
+        };
+
+        let mut result = Vec::with_capacity(backups.get_upstreams().len());
+        for backup in backups.get_upstreams() {
+            let source = self
+                .create_data_source(
+                    &backup.proxy_pass_to,
+                    backup.id,
+                    &backup.id_string,
+                    debug,
+                    timeout,
+                )
+                .await;
+            result.push(source);
+        }
+        result
+    }
+
+    /// The content source of `mirror_to`, if set.
+    pub async fn create_mirror_data_source(
+        &self,
+        debug: bool,
+        timeout: Duration,
+    ) -> Option<HttpProxyPassContentSource> {
+        let upstream = self.mirror.as_ref()?.get_upstream();
+
+        let result = self
+            .create_data_source(
+                &upstream.proxy_pass_to,
+                upstream.id,
+                &upstream.id_string,
+                debug,
+                timeout,
+            )
+            .await;
+        Some(result)
+    }
+
+    async fn create_data_source(
+        &self,
+        proxy_pass_to: &ProxyPassToConfig,
+        id: i64,
+        id_string: &str,
+        debug: bool,
+        timeout: Duration,
     ) -> HttpProxyPassContentSource {
-        let is_mcp = self.proxy_pass_to.is_mcp();
+        let is_mcp = proxy_pass_to.is_mcp();
+        // The single upstream of a location is never balanced away from, so
+        // ejecting it would change nothing: its outcomes are not tracked. The
+        // first of balanced upstreams has the location's id too.
+        let track_outliers = self.upstreams.is_some() || id != self.id;
 
-        let result = match &self.proxy_pass_to {
+        let result = match proxy_pass_to {
             ProxyPassToConfig::Static(config) => HttpProxyPassContentSource::Static(
                 crate::http_content_source::static_content::StaticContentSrc::new(config.clone()),
             ),
+            ProxyPassToConfig::Redirect(config) => HttpProxyPassContentSource::Redirect(
+                crate::http_content_source::static_content::RedirectContentSrc::new(config.clone()),
+            ),
             ProxyPassToConfig::Http1(proxy_pass) | ProxyPassToConfig::McpHttp1(proxy_pass) => {
                 match &proxy_pass.remote_host {
                     MyReverseProxyRemoteEndpoint::Gateway { .. } => {
@@ -121,8 +435,8 @@ impl ProxyPassLocationConfig {
                                     debug,
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                     is_mcp,
                                 );
                                 return HttpProxyPassContentSource::Http1(Http1ContentSource {
@@ -140,8 +454,8 @@ impl ProxyPassLocationConfig {
                                     self.domain_name.clone(),
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                     is_mcp,
                                 );
                                 return HttpProxyPassContentSource::Https1(Https1ContentSource {
@@ -157,8 +471,8 @@ impl ProxyPassLocationConfig {
                                     debug,
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                     is_mcp,
                                 );
                                 return HttpProxyPassContentSource::Http1(Http1ContentSource {
@@ -176,8 +490,8 @@ impl ProxyPassLocationConfig {
                                     self.domain_name.clone(),
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                     is_mcp,
                                 );
                                 return HttpProxyPassContentSource::Https1(Https1ContentSource {
@@ -193,8 +507,8 @@ impl ProxyPassLocationConfig {
                                     debug,
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                     is_mcp,
                                 );
                                 return HttpProxyPassContentSource::UnixHttp1(
@@ -254,14 +568,15 @@ impl ProxyPassLocationConfig {
                                     debug,
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                 );
                                 return HttpProxyPassContentSource::Http2(Http2ContentSource {
                                     pool_desc,
                                     pool_params,
                                     factory,
                                     request_timeout: proxy_pass.request_timeout,
+                                    track_outliers,
                                 });
                             }
                             rust_extensions::remote_endpoint::Scheme::Https => {
@@ -271,14 +586,15 @@ impl ProxyPassLocationConfig {
                                     self.domain_name.clone(),
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                 );
                                 return HttpProxyPassContentSource::Https2(Https2ContentSource {
                                     pool_desc,
                                     pool_params,
                                     factory,
                                     request_timeout: proxy_pass.request_timeout,
+                                    track_outliers,
                                 });
                             }
                             rust_extensions::remote_endpoint::Scheme::Ws => {
@@ -287,8 +603,8 @@ impl ProxyPassLocationConfig {
                                     debug,
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                     is_mcp,
                                 );
                                 return HttpProxyPassContentSource::Http1(Http1ContentSource {
@@ -306,8 +622,8 @@ impl ProxyPassLocationConfig {
                                     self.domain_name.clone(),
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                     is_mcp,
                                 );
                                 return HttpProxyPassContentSource::Https1(Https1ContentSource {
@@ -323,8 +639,8 @@ impl ProxyPassLocationConfig {
                                     debug,
                                     proxy_pass.connect_timeout,
                                     proxy_pass.pool_tuning,
-                                    self.id,
-                                    self.id_string.clone(),
+                                    id,
+                                    id_string.to_string(),
                                 );
                                 return HttpProxyPassContentSource::UnixHttp2(
                                     UnixHttp2ContentSource {
@@ -332,6 +648,7 @@ impl ProxyPassLocationConfig {
                                         pool_params,
                                         factory,
                                         request_timeout: proxy_pass.request_timeout,
+                                        track_outliers,
                                     },
                                 );
                             }
@@ -344,7 +661,7 @@ impl ProxyPassLocationConfig {
                     let model = PathOverGatewayContentSource {
                         gateway_id: id.clone(),
                         path: remote_host.clone(),
-                        default_file: model.default_file.clone(),
+                        lookup: model.lookup.clone(),
                     };
                     HttpProxyPassContentSource::PathOverGateway(model)
                 }
@@ -358,7 +675,7 @@ impl ProxyPassLocationConfig {
                     let src = PathOverSshContentSource::new(
                         ssh_session,
                         remote_host.as_str().to_string(),
-                        model.default_file.clone(),
+                        model.lookup.clone(),
                         timeout,
                     );
 
@@ -368,7 +685,7 @@ impl ProxyPassLocationConfig {
                     let local_file_path = LocalFilePath::new(remote_host.as_str().to_string());
                     HttpProxyPassContentSource::LocalPath(LocalPathContentSrc::new(
                         &local_file_path,
-                        model.default_file.clone(),
+                        model.lookup.clone(),
                     ))
                 }
             },
@@ -397,8 +714,8 @@ impl ProxyPassLocationConfig {
                         debug,
                         proxy_pass.connect_timeout,
                         proxy_pass.pool_tuning,
-                        self.id,
-                        self.id_string.clone(),
+                        id,
+                        id_string.to_string(),
                         is_mcp,
                     );
                     return HttpProxyPassContentSource::UnixHttp1(UnixHttp1ContentSource {
@@ -433,14 +750,15 @@ impl ProxyPassLocationConfig {
                         debug,
                         proxy_pass.connect_timeout,
                         proxy_pass.pool_tuning,
-                        self.id,
-                        self.id_string.clone(),
+                        id,
+                        id_string.to_string(),
                     );
                     return HttpProxyPassContentSource::UnixHttp2(UnixHttp2ContentSource {
                         pool_desc,
                         pool_params,
                         factory,
                         request_timeout: proxy_pass.request_timeout,
+                        track_outliers,
                     });
                 }
             },
@@ -475,8 +793,17 @@ impl ProxyPassLocationConfig {
 fn build_location_id_string(
     listen_host: &str,
     path: &str,
+    matcher: &LocationMatcher,
     proxy_pass_to: &ProxyPassToConfig,
 ) -> String {
+    // A prefix location keeps the historical form, so its pools stay warm
+    // across the upgrade; the other modes are tagged so `= /x` and `/x` on the
+    // same upstream remain two locations.
+    let path = match matcher.get_mode() {
+        LocationMatchMode::Prefix => path.to_string(),
+        mode => format!("{}:{}", mode.as_str(), path),
+    };
+
     format!(
         "{}|{}->{}|{}",
         listen_host,
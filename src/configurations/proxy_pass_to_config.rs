@@ -1,12 +1,13 @@
 use std::{sync::Arc, time::Duration};
 
-use crate::configurations::MyReverseProxyRemoteEndpoint;
+use crate::configurations::{MyReverseProxyRemoteEndpoint, RedirectTemplate, StaticBodyTemplate};
+use crate::http_content_source::FileLookup;
 
 #[derive(Debug, Clone)]
 pub struct StaticContentConfig {
     pub status_code: u16,
     pub content_type: Option<String>,
-    pub body: Vec<u8>,
+    pub body: StaticBodyTemplate,
 }
 
 impl StaticContentConfig {
@@ -15,14 +16,41 @@ impl StaticContentConfig {
             "status_code: {}, content_type: {:?}, body: {}bytes",
             self.status_code,
             self.content_type,
-            self.body.len()
+            self.body.get_len()
         )
     }
 }
+
+const HTTPS_REDIRECT_TO: &str = "https://${HOST}${PATH_AND_QUERY}";
+
+/// A `redirect` location — and the `https_redirect` endpoint shortcut: every
+/// request is answered with `status_code` and a `Location` header rendered
+/// from `redirect_to`.
+#[derive(Debug, Clone)]
+pub struct RedirectConfig {
+    pub status_code: u16,
+    pub redirect_to: RedirectTemplate,
+}
+
+impl RedirectConfig {
+    /// What `https_redirect: true` on an `http` endpoint serves.
+    pub fn new_https_redirect() -> Self {
+        Self {
+            status_code: 301,
+            redirect_to: RedirectTemplate::new(HTTPS_REDIRECT_TO, None).unwrap(),
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("{} {}", self.status_code, self.redirect_to.as_str())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ProxyPassFilesPathModel {
     pub files_path: MyReverseProxyRemoteEndpoint,
-    pub default_file: Option<String>,
+    /// `default_file`, `autoindex` and `try_files`.
+    pub lookup: Arc<FileLookup>,
 }
 
 impl ProxyPassFilesPathModel {
@@ -61,6 +89,7 @@ pub enum ProxyPassToConfig {
     UnixHttp2(ProxyPassToModel),
     FilesPath(ProxyPassFilesPathModel),
     Static(Arc<StaticContentConfig>),
+    Redirect(Arc<RedirectConfig>),
     Drop,
     DynamicProxy(Arc<DynamicProxyConfig>),
 }
@@ -77,11 +106,27 @@ impl ProxyPassToConfig {
 
             ProxyPassToConfig::FilesPath(model) => model.to_string(),
             ProxyPassToConfig::Static(model) => model.to_string(),
+            ProxyPassToConfig::Redirect(model) => model.to_string(),
             ProxyPassToConfig::Drop => "drop".to_string(),
             ProxyPassToConfig::DynamicProxy(_) => crate::consts::location_type::DYNAMIC.to_string(),
         }
     }
 
+    /// The same kind of upstream, pointed at `model` — how every entry of a
+    /// `proxy_pass_to` list inherits the location's type. `None` for the
+    /// location types that have no upstream model.
+    pub fn with_model(&self, model: ProxyPassToModel) -> Option<Self> {
+        match self {
+            ProxyPassToConfig::Http1(_) => Some(ProxyPassToConfig::Http1(model)),
+            ProxyPassToConfig::Http2(_) => Some(ProxyPassToConfig::Http2(model)),
+            ProxyPassToConfig::McpHttp1(_) => Some(ProxyPassToConfig::McpHttp1(model)),
+            ProxyPassToConfig::McpHttp2(_) => Some(ProxyPassToConfig::McpHttp2(model)),
+            ProxyPassToConfig::UnixHttp1(_) => Some(ProxyPassToConfig::UnixHttp1(model)),
+            ProxyPassToConfig::UnixHttp2(_) => Some(ProxyPassToConfig::UnixHttp2(model)),
+            _ => None,
+        }
+    }
+
     pub fn get_type_as_str(&self) -> &'static str {
         match self {
             Self::UnixHttp1(_) => "unix+http1",
@@ -92,6 +137,7 @@ impl ProxyPassToConfig {
             Self::Http2(_) => "http2",
             Self::FilesPath(_) => "files_path",
             Self::Static(_) => crate::consts::location_type::STATIC,
+            Self::Redirect(_) => crate::consts::location_type::REDIRECT,
             Self::Drop => "drop",
             Self::DynamicProxy(_) => crate::consts::location_type::DYNAMIC,
         }
@@ -114,7 +160,7 @@ impl ProxyPassToConfig {
 
     /// Returns the upstream's transport kind (`direct` / `ssh` / `gateway`)
     /// for variants that carry a `MyReverseProxyRemoteEndpoint`. `None` for
-    /// static / drop variants that don't reach a remote.
+    /// static / redirect / drop variants that don't reach a remote.
     pub fn remote_endpoint_kind(&self) -> Option<&'static str> {
         match self {
             Self::Http1(m)
@@ -124,7 +170,7 @@ impl ProxyPassToConfig {
             | Self::UnixHttp1(m)
             | Self::UnixHttp2(m) => Some(m.remote_host.kind_as_str()),
             Self::FilesPath(m) => Some(m.files_path.kind_as_str()),
-            Self::Static(_) | Self::Drop | Self::DynamicProxy(_) => None,
+            Self::Static(_) | Self::Redirect(_) | Self::Drop | Self::DynamicProxy(_) => None,
         }
     }
 }
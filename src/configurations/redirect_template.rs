@@ -0,0 +1,189 @@
+/// A piece of `redirect_to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RedirectToken {
+    Text(String),
+    /// `${HOST}` — the request host without its port.
+    Host,
+    /// `${HOST_PORT}` — the Host header as the client sent it.
+    HostPort,
+    /// `${PATH}` — the request path, no query string.
+    Path,
+    /// `${PATH_AND_QUERY}` — the request target as the client sent it.
+    PathAndQuery,
+    /// `${1}`, `${2}`... — a capture group of the location's regex; `${0}` is
+    /// the whole match.
+    Capture(usize),
+}
+
+/// `redirect_to` of a redirect location, parsed once when the configuration is
+/// applied and filled in per request.
+#[derive(Debug, Clone)]
+pub struct RedirectTemplate {
+    src: String,
+    tokens: Vec<RedirectToken>,
+    /// The location's regex, when `redirect_to` uses its capture groups.
+    regex: Option<regex::Regex>,
+}
+
+impl RedirectTemplate {
+    /// `regex` is the location's own regex, `None` for a prefix or exact
+    /// location — which then can not refer to capture groups.
+    pub fn new(src: &str, regex: Option<&regex::Regex>) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut rest = src;
+
+        while let Some(start) = rest.find("${") {
+            if start > 0 {
+                tokens.push(RedirectToken::Text(rest[..start].to_string()));
+            }
+
+            let Some(end) = rest[start..].find('}') else {
+                return Err(format!("Unclosed placeholder in redirect_to '{}'", src));
+            };
+
+            let name = &rest[start + 2..start + end];
+            let token = match name {
+                "HOST" => RedirectToken::Host,
+                "HOST_PORT" => RedirectToken::HostPort,
+                "PATH" => RedirectToken::Path,
+                "PATH_AND_QUERY" => RedirectToken::PathAndQuery,
+                _ => {
+                    let Ok(group) = name.parse::<usize>() else {
+                        return Err(format!(
+                            "Unknown placeholder ${{{}}} in redirect_to '{}'",
+                            name, src
+                        ));
+                    };
+
+                    let Some(regex) = regex else {
+                        return Err(format!(
+                            "redirect_to '{}' refers to capture group ${{{}}}, which needs a regex location",
+                            src, group
+                        ));
+                    };
+
+                    if group >= regex.captures_len() {
+                        return Err(format!(
+                            "redirect_to '{}' refers to capture group ${{{}}}, but the location regex has {} groups",
+                            src,
+                            group,
+                            regex.captures_len() - 1
+                        ));
+                    }
+
+                    RedirectToken::Capture(group)
+                }
+            };
+
+            tokens.push(token);
+            rest = &rest[start + end + 1..];
+        }
+
+        if !rest.is_empty() {
+            tokens.push(RedirectToken::Text(rest.to_string()));
+        }
+
+        let uses_captures = tokens
+            .iter()
+            .any(|itm| matches!(itm, RedirectToken::Capture(_)));
+
+        Ok(Self {
+            src: src.to_string(),
+            tokens,
+            regex: if uses_captures { regex.cloned() } else { None },
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.src
+    }
+
+    /// The redirect target for a request. `host_port` is its Host header (or
+    /// the authority of an h2 request), `path_and_query` its request target.
+    pub fn render(&self, host_port: Option<&str>, path_and_query: &str) -> String {
+        let host_port = host_port.unwrap_or_default();
+        let path = match path_and_query.find('?') {
+            Some(index) => &path_and_query[..index],
+            None => path_and_query,
+        };
+
+        let captures = self.regex.as_ref().and_then(|itm| itm.captures(path));
+
+        let mut result = String::with_capacity(self.src.len() + path_and_query.len());
+
+        for token in self.tokens.iter() {
+            match token {
+                RedirectToken::Text(text) => result.push_str(text),
+                RedirectToken::Host => result.push_str(strip_port(host_port)),
+                RedirectToken::HostPort => result.push_str(host_port),
+                RedirectToken::Path => result.push_str(path),
+                RedirectToken::PathAndQuery => result.push_str(path_and_query),
+                RedirectToken::Capture(group) => {
+                    if let Some(value) = captures.as_ref().and_then(|itm| itm.get(*group)) {
+                        result.push_str(value.as_str());
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// `example.com:8080` → `example.com`, `[::1]:8080` → `[::1]`.
+fn strip_port(host_port: &str) -> &str {
+    if host_port.starts_with('[') {
+        return match host_port.find(']') {
+            Some(index) => &host_port[..index + 1],
+            None => host_port,
+        };
+    }
+
+    match host_port.find(':') {
+        Some(index) => &host_port[..index],
+        None => host_port,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_the_request_host_and_path() {
+        let template = RedirectTemplate::new("https://${HOST}${PATH_AND_QUERY}", None).unwrap();
+
+        assert_eq!(
+            template.render(Some("example.com:8080"), "/a/b?c=d"),
+            "https://example.com/a/b?c=d"
+        );
+        assert_eq!(template.render(Some("[::1]:8080"), "/"), "https://[::1]/");
+
+        let template = RedirectTemplate::new("http://${HOST_PORT}/new${PATH}", None).unwrap();
+        assert_eq!(
+            template.render(Some("example.com:8080"), "/a?c=d"),
+            "http://example.com:8080/new/a"
+        );
+    }
+
+    #[test]
+    fn capture_groups_come_from_the_location_regex() {
+        let regex = regex::Regex::new("^/old/([a-z]+)/(\\d+)$").unwrap();
+        let template =
+            RedirectTemplate::new("https://${HOST}/new/${2}/${1}", Some(&regex)).unwrap();
+
+        assert_eq!(
+            template.render(Some("example.com"), "/old/users/42?x=1"),
+            "https://example.com/new/42/users"
+        );
+
+        assert!(RedirectTemplate::new("/new/${3}", Some(&regex)).is_err());
+        assert!(RedirectTemplate::new("/new/${1}", None).is_err());
+    }
+
+    #[test]
+    fn unknown_or_unclosed_placeholders_are_refused() {
+        assert!(RedirectTemplate::new("https://${HOSTNAME}/", None).is_err());
+        assert!(RedirectTemplate::new("https://${HOST", None).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+/// `max_header_size`, `max_headers`, `max_uri_length` and `max_body_size` of
+/// an endpoint or a location, compiled. What a location leaves unset is taken
+/// from its endpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// Bytes of the request line and headers together.
+    pub max_header_size: Option<usize>,
+    pub max_headers: Option<usize>,
+    /// Bytes of the path and query.
+    pub max_uri_length: Option<usize>,
+    pub max_body_size: Option<usize>,
+}
+
+impl RequestLimits {
+    /// These limits, with the ones they leave unset taken from `fallback`.
+    pub fn or(&self, fallback: &RequestLimits) -> RequestLimits {
+        RequestLimits {
+            max_header_size: self.max_header_size.or(fallback.max_header_size),
+            max_headers: self.max_headers.or(fallback.max_headers),
+            max_uri_length: self.max_uri_length.or(fallback.max_uri_length),
+            max_body_size: self.max_body_size.or(fallback.max_body_size),
+        }
+    }
+
+    /// Checks a request head: its size in bytes, how many headers it has and
+    /// how long its path and query are.
+    pub fn check_head(
+        &self,
+        header_size: usize,
+        headers: usize,
+        uri_length: usize,
+    ) -> Result<(), RequestLimitExceeded> {
+        if let Some(max_uri_length) = self.max_uri_length {
+            if uri_length > max_uri_length {
+                return Err(RequestLimitExceeded::UriLength(max_uri_length));
+            }
+        }
+
+        if let Some(max_header_size) = self.max_header_size {
+            if header_size > max_header_size {
+                return Err(RequestLimitExceeded::HeaderSize(max_header_size));
+            }
+        }
+
+        if let Some(max_headers) = self.max_headers {
+            if headers > max_headers {
+                return Err(RequestLimitExceeded::Headers(max_headers));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the body size a request announces up front.
+    pub fn check_content_length(&self, content_length: usize) -> Result<(), RequestLimitExceeded> {
+        match self.max_body_size {
+            Some(max_body_size) if content_length > max_body_size => {
+                Err(RequestLimitExceeded::BodySize(max_body_size))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Which limit a request is over, with the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestLimitExceeded {
+    HeaderSize(usize),
+    Headers(usize),
+    UriLength(usize),
+    BodySize(usize),
+}
+
+impl RequestLimitExceeded {
+    pub fn get_status_code(&self) -> u16 {
+        match self {
+            Self::HeaderSize(_) | Self::Headers(_) => 431,
+            Self::UriLength(_) => 414,
+            Self::BodySize(_) => 413,
+        }
+    }
+
+    pub fn get_status_text(&self) -> &'static str {
+        match self {
+            Self::HeaderSize(_) | Self::Headers(_) => "Request header fields too large",
+            Self::UriLength(_) => "URI too long",
+            Self::BodySize(_) => "Payload too large",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_location_limit_overrides_the_endpoint_one() {
+        let endpoint = RequestLimits {
+            max_header_size: Some(8192),
+            max_body_size: Some(1024),
+            ..Default::default()
+        };
+        let location = RequestLimits {
+            max_body_size: Some(10 * 1024 * 1024),
+            max_uri_length: Some(256),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            location.or(&endpoint),
+            RequestLimits {
+                max_header_size: Some(8192),
+                max_headers: None,
+                max_uri_length: Some(256),
+                max_body_size: Some(10 * 1024 * 1024),
+            }
+        );
+    }
+
+    #[test]
+    fn a_request_over_a_limit_gets_its_status_code() {
+        let limits = RequestLimits {
+            max_header_size: Some(100),
+            max_headers: Some(3),
+            max_uri_length: Some(20),
+            max_body_size: Some(10),
+        };
+
+        assert_eq!(limits.check_head(100, 3, 20), Ok(()));
+
+        let exceeded = limits.check_head(101, 3, 20).unwrap_err();
+        assert_eq!(exceeded, RequestLimitExceeded::HeaderSize(100));
+        assert_eq!(exceeded.get_status_code(), 431);
+
+        let exceeded = limits.check_head(50, 4, 20).unwrap_err();
+        assert_eq!(exceeded.get_status_code(), 431);
+
+        let exceeded = limits.check_head(500, 3, 21).unwrap_err();
+        assert_eq!(exceeded, RequestLimitExceeded::UriLength(20));
+        assert_eq!(exceeded.get_status_code(), 414);
+
+        assert_eq!(limits.check_content_length(10), Ok(()));
+        let exceeded = limits.check_content_length(11).unwrap_err();
+        assert_eq!(exceeded.get_status_code(), 413);
+
+        assert_eq!(
+            RequestLimits::default().check_head(usize::MAX, 1000, 1000),
+            Ok(())
+        );
+    }
+}
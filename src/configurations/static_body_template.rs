@@ -0,0 +1,305 @@
+use std::{borrow::Cow, net::IpAddr};
+
+/// A piece of the body of a static location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StaticBodyToken {
+    Bytes(Vec<u8>),
+    /// `${HOST_PORT}` — the Host header as the client sent it.
+    HostPort,
+    /// `${PATH_AND_QUERY}` — the request target as the client sent it.
+    PathAndQuery,
+    /// `${CLIENT_CERT_CN}` — whom the client certificate or the login names.
+    ClientCertCn,
+    /// `${ENDPOINT_IP}` or `${CLIENT_IP}` — the ip the client connected from.
+    ClientIp,
+    /// `${ENDPOINT_SCHEMA}` — `http` or `https`.
+    EndpointSchema,
+    /// `${COUNTRY}` — the ISO-2 country of the client ip.
+    Country,
+}
+
+impl StaticBodyToken {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        let result = match name {
+            b"HOST_PORT" => Self::HostPort,
+            b"PATH_AND_QUERY" => Self::PathAndQuery,
+            b"CLIENT_CERT_CN" => Self::ClientCertCn,
+            b"ENDPOINT_IP" | b"CLIENT_IP" => Self::ClientIp,
+            b"ENDPOINT_SCHEMA" => Self::EndpointSchema,
+            b"COUNTRY" => Self::Country,
+            _ => return None,
+        };
+
+        Some(result)
+    }
+}
+
+/// How a value is written into a body of a `content_type`, so that what the
+/// client sent — its Host, its path — is text there and never markup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StaticBodyEscape {
+    /// `text/html`, any `xml` — or no `content_type`, which a browser sniffs.
+    Html,
+    /// `application/json` and any `+json`.
+    Json,
+    /// Any other type is not interpreted by a browser: written as it is.
+    Raw,
+}
+
+impl StaticBodyEscape {
+    fn from_content_type(content_type: Option<&str>) -> Self {
+        let Some(content_type) = content_type else {
+            return Self::Html;
+        };
+
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        if media_type.contains("html") || media_type.ends_with("xml") {
+            return Self::Html;
+        }
+
+        if media_type == "application/json" || media_type.ends_with("+json") {
+            return Self::Json;
+        }
+
+        Self::Raw
+    }
+
+    fn push(&self, result: &mut Vec<u8>, value: &str) {
+        match self {
+            Self::Html => {
+                for c in value.bytes() {
+                    match c {
+                        b'&' => result.extend_from_slice(b"&amp;"),
+                        b'<' => result.extend_from_slice(b"&lt;"),
+                        b'>' => result.extend_from_slice(b"&gt;"),
+                        b'"' => result.extend_from_slice(b"&quot;"),
+                        b'\'' => result.extend_from_slice(b"&#39;"),
+                        _ => result.push(c),
+                    }
+                }
+            }
+            Self::Json => {
+                for c in value.bytes() {
+                    match c {
+                        b'"' => result.extend_from_slice(b"\\\""),
+                        b'\\' => result.extend_from_slice(b"\\\\"),
+                        c if c < 0x20 => {
+                            result.extend_from_slice(format!("\\u{:04x}", c).as_bytes())
+                        }
+                        _ => result.push(c),
+                    }
+                }
+            }
+            Self::Raw => result.extend_from_slice(value.as_bytes()),
+        }
+    }
+}
+
+/// What of the connection a static body is filled in with.
+#[derive(Debug, Clone, Default)]
+pub struct StaticBodyClient {
+    pub ip: Option<IpAddr>,
+    pub client_cert_cn: Option<String>,
+    pub is_https: bool,
+}
+
+/// The body of a static location, parsed once when the configuration is
+/// applied and filled in per request. A placeholder that is not a system
+/// variable is sent as it is; the values are escaped for the `content_type`
+/// of the location.
+///
+/// A body may be any file — an image, say — not only text, so it is parsed
+/// as bytes rather than with the `&str` placeholder iterator of the
+/// configuration.
+#[derive(Debug, Clone)]
+pub struct StaticBodyTemplate {
+    tokens: Vec<StaticBodyToken>,
+    escape: StaticBodyEscape,
+    len: usize,
+}
+
+impl StaticBodyTemplate {
+    pub fn new(src: Vec<u8>, content_type: Option<&str>) -> Self {
+        let len = src.len();
+        let mut tokens = Vec::new();
+        let mut text_start = 0;
+        let mut pos = 0;
+
+        while let Some(start) = find(&src[pos..], b"${").map(|itm| itm + pos) {
+            let Some(end) = find(&src[start..], b"}").map(|itm| itm + start) else {
+                break;
+            };
+
+            match StaticBodyToken::from_name(&src[start + 2..end]) {
+                Some(token) => {
+                    if start > text_start {
+                        tokens.push(StaticBodyToken::Bytes(src[text_start..start].to_vec()));
+                    }
+                    tokens.push(token);
+                    text_start = end + 1;
+                    pos = end + 1;
+                }
+                None => pos = start + 2,
+            }
+        }
+
+        if text_start == 0 {
+            tokens.push(StaticBodyToken::Bytes(src));
+        } else if text_start < len {
+            tokens.push(StaticBodyToken::Bytes(src[text_start..].to_vec()));
+        }
+
+        Self {
+            tokens,
+            escape: StaticBodyEscape::from_content_type(content_type),
+            len,
+        }
+    }
+
+    /// The size of the body as it is configured.
+    pub fn get_len(&self) -> usize {
+        self.len
+    }
+
+    /// The body for a request. `host_port` is its Host header (or the
+    /// authority of an h2 request), `path_and_query` its request target.
+    pub fn render(
+        &self,
+        host_port: Option<&str>,
+        path_and_query: &str,
+        client: &StaticBodyClient,
+    ) -> Cow<'_, [u8]> {
+        if let [StaticBodyToken::Bytes(bytes)] = self.tokens.as_slice() {
+            return Cow::Borrowed(bytes);
+        }
+
+        let mut result = Vec::with_capacity(self.len + path_and_query.len());
+
+        for token in self.tokens.iter() {
+            match token {
+                StaticBodyToken::Bytes(bytes) => result.extend_from_slice(bytes),
+                StaticBodyToken::HostPort => {
+                    self.escape.push(&mut result, host_port.unwrap_or_default())
+                }
+                StaticBodyToken::PathAndQuery => self.escape.push(&mut result, path_and_query),
+                StaticBodyToken::ClientCertCn => {
+                    if let Some(cn) = client.client_cert_cn.as_ref() {
+                        self.escape.push(&mut result, cn);
+                    }
+                }
+                StaticBodyToken::ClientIp => {
+                    if let Some(ip) = client.ip {
+                        self.escape.push(&mut result, &ip.to_string());
+                    }
+                }
+                StaticBodyToken::EndpointSchema => match client.is_https {
+                    true => result.extend_from_slice(b"https"),
+                    false => result.extend_from_slice(b"http"),
+                },
+                StaticBodyToken::Country => {
+                    if let Some(country) = client.ip.and_then(crate::ip_db::lookup_country) {
+                        result.extend_from_slice(&country);
+                    }
+                }
+            }
+        }
+
+        Cow::Owned(result)
+    }
+}
+
+fn find(src: &[u8], pattern: &[u8]) -> Option<usize> {
+    src.windows(pattern.len()).position(|itm| itm == pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_the_request_and_the_client() {
+        let template = StaticBodyTemplate::new(
+            b"<a href=\"${ENDPOINT_SCHEMA}://${HOST_PORT}${PATH_AND_QUERY}\">${CLIENT_IP}</a>"
+                .to_vec(),
+            Some("text/html"),
+        );
+
+        let client = StaticBodyClient {
+            ip: Some("10.0.0.1".parse().unwrap()),
+            client_cert_cn: None,
+            is_https: true,
+        };
+
+        assert_eq!(
+            template
+                .render(Some("example.com:8443"), "/a?b=c", &client)
+                .as_ref(),
+            b"<a href=\"https://example.com:8443/a?b=c\">10.0.0.1</a>"
+        );
+    }
+
+    #[test]
+    fn other_placeholders_are_sent_as_they_are() {
+        let src = b"const a = `${value}`; ${HOST} ${CLIENT_CERT_CN".to_vec();
+        let template = StaticBodyTemplate::new(src.clone(), Some("text/javascript"));
+
+        let body = template.render(Some("example.com"), "/", &StaticBodyClient::default());
+        assert!(matches!(body, Cow::Borrowed(_)));
+        assert_eq!(body.as_ref(), src.as_slice());
+
+        let template =
+            StaticBodyTemplate::new(b"${value} ${CLIENT_CERT_CN}!".to_vec(), Some("text/plain"));
+        let client = StaticBodyClient {
+            client_cert_cn: Some("user".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            template.render(None, "/", &client).as_ref(),
+            b"${value} user!"
+        );
+    }
+
+    #[test]
+    fn values_are_escaped_for_the_content_type() {
+        let path_and_query = "/<script>alert('x')</script>?a=\"b\"&c=\\";
+        let client = StaticBodyClient::default();
+
+        let template = StaticBodyTemplate::new(
+            b"<p>${HOST_PORT}${PATH_AND_QUERY} is down</p>".to_vec(),
+            Some("text/html; charset=utf-8"),
+        );
+        assert_eq!(
+            template
+                .render(Some("a\"b.com"), path_and_query, &client)
+                .as_ref(),
+            b"<p>a&quot;b.com/&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;?a=&quot;b&quot;&amp;c=\\ is down</p>"
+        );
+
+        let template = StaticBodyTemplate::new(b"${PATH_AND_QUERY}".to_vec(), None);
+        assert_eq!(
+            template.render(None, "/<b>", &client).as_ref(),
+            b"/&lt;b&gt;"
+        );
+
+        let template = StaticBodyTemplate::new(
+            b"{\"path\":\"${PATH_AND_QUERY}\"}".to_vec(),
+            Some("application/json"),
+        );
+        assert_eq!(
+            template.render(None, path_and_query, &client).as_ref(),
+            b"{\"path\":\"/<script>alert('x')</script>?a=\\\"b\\\"&c=\\\\\"}"
+        );
+
+        let template = StaticBodyTemplate::new(b"${PATH_AND_QUERY}".to_vec(), Some("text/plain"));
+        assert_eq!(
+            template.render(None, path_and_query, &client).as_ref(),
+            path_and_query.as_bytes()
+        );
+    }
+}
@@ -11,6 +11,7 @@ pub struct TcpEndpointHostConfig {
     pub ip_white_list_id: Option<String>,
     /// Transport read/write idle timeouts (resolved cascade: global → endpoint).
     pub timeouts: crate::types::HttpTimeouts,
+    pub connection_limits: crate::app::ConnectionLimits,
 }
 
 impl TcpEndpointHostConfig {
@@ -48,12 +49,18 @@ impl TcpEndpointHostConfig {
             write_timeout: resolved.write_timeout,
         };
 
+        let connection_limits = crate::scripts::compile_connection_limits(
+            &host_settings.endpoint,
+            &format!("Endpoint '{}'", host_endpoint.as_str()),
+        )?;
+
         let result = Self {
             host_endpoint,
             remote_host: remote_host.into(),
             debug: host_settings.endpoint.get_debug(),
             ip_white_list_id,
             timeouts,
+            connection_limits,
         };
 
         Ok(result)
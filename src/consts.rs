@@ -58,6 +58,84 @@ pub const DEFAULT_MCP_READ_TIMEOUT: Duration = Duration::from_secs(60 * 60);
 // never accumulated in either direction.
 pub const BODY_RELAY_CHUNK_SIZE: usize = 256 * 1024;
 
+// Largest request body copied to a location's `mirror_to` when it sets no
+// `max_body_size`. A mirrored body is held in memory until the copy is sent.
+pub const DEFAULT_MIRROR_MAX_BODY_SIZE: usize = 64 * 1024;
+
+// A location's `cache:` block, where it sets nothing else: what it keeps in
+// memory and on disk, the biggest response it stores and the longest it serves
+// one without asking the upstream.
+pub const DEFAULT_CACHE_MAX_SIZE: usize = 64 * 1024 * 1024;
+pub const DEFAULT_CACHE_MAX_ENTRY_SIZE: usize = 1024 * 1024;
+pub const DEFAULT_CACHE_DISK_MAX_SIZE: usize = 1024 * 1024 * 1024;
+pub const DEFAULT_CACHE_MAX_TTL: Duration = Duration::from_secs(3600);
+
+// What a location's `stale:` block keeps of last good responses where it sets
+// no `max_size`. Its biggest response is the cache's default.
+pub const DEFAULT_STALE_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+// Clients a `rate_limit:` block tracks at once where it sets no `max_clients`.
+// A tracked client costs a few dozen bytes, whatever its key.
+pub const DEFAULT_RATE_LIMIT_MAX_CLIENTS: usize = 100_000;
+
+// What the global `rate_limit:` is known by to the metrics — endpoints and
+// locations are by their own names.
+pub const GLOBAL_RATE_LIMIT_LABEL: &str = "global";
+
+// How long a request queued for a `max_concurrent_requests` slot waits where
+// the location sets no `queue_timeout`.
+pub const DEFAULT_CONCURRENCY_QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// What a `cors:` block allows where it lists no `allowed_methods`.
+pub const DEFAULT_CORS_METHODS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"];
+
+// What a `sub_filter:` replaces in where the location sets no `sub_filter_types`.
+pub const DEFAULT_SUB_FILTER_TYPES: &[&str] = &["text/html"];
+
+// Most of a body `sub_filter:` holds back waiting for the end of a line, so a
+// match is never split between two pieces of the body. A longer line is
+// filtered in pieces of this size.
+pub const SUB_FILTER_WINDOW: usize = 64 * 1024;
+
+// A Range asking for more pieces of a file than this is answered with the
+// whole file rather than a multipart body.
+pub const FILE_MAX_RANGES: usize = 16;
+
+// How long a files location with `precompressed:` takes a missing `.gz`, `.zst`
+// or `.br` sibling of a file for missing, and how many it keeps track of.
+pub const PRECOMPRESSED_PROBE_TTL: Duration = Duration::from_secs(10);
+pub const PRECOMPRESSED_PROBE_MAX_ENTRIES: usize = 10_000;
+
+// A file served over a gateway is asked for a window at a time, a few windows
+// ahead of the client, and comes in chunks well below the gateway's frame cap:
+// neither peer holds more of it than the windows in flight. A window that
+// sends nothing for the timeout fails the response.
+pub const GATEWAY_FILE_CHUNK_SIZE: usize = 256 * 1024;
+pub const GATEWAY_FILE_WINDOW_SIZE: u64 = 4 * 1024 * 1024;
+pub const GATEWAY_FILE_WINDOWS_IN_FLIGHT: usize = 2;
+pub const GATEWAY_FILE_STREAM_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Media types `compress:` encodes when `compress_types` is not set.
+pub const DEFAULT_COMPRESS_TYPES: &[&str] = &[
+    "text/html",
+    "text/css",
+    "text/plain",
+    "text/xml",
+    "text/javascript",
+    "application/javascript",
+    "application/json",
+    "application/xml",
+    "image/svg+xml",
+];
+
+// A response with a Content-Length below this is not worth encoding.
+pub const DEFAULT_COMPRESS_MIN_SIZE: usize = 1024;
+
+// Brotli is encoded as the body streams, so a quality that keeps up with it
+// rather than the best one.
+pub const BROTLI_QUALITY: u32 = 5;
+pub const BROTLI_LG_WINDOW_SIZE: u32 = 22;
+
 pub const HTTP_CR_LF: &[u8] = b"\r\n";
 
 pub const AUTHORIZED_COOKIE_NAME: &str = "x-authorized";
@@ -67,5 +145,6 @@ pub mod location_type {
     pub const MCP: &'static str = "mcp";
     pub const MCP_H2: &'static str = "mcp-h2";
     pub const STATIC: &'static str = "static";
+    pub const REDIRECT: &'static str = "redirect";
     pub const DYNAMIC: &'static str = "dynamic";
 }
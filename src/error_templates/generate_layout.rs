@@ -22,6 +22,23 @@ pub static LOCATION_IS_NOT_FOUND: LazyLock<Vec<u8>> = LazyLock::new(|| {
     )
 });
 
+pub static LOCATION_IS_BUSY: LazyLock<Vec<u8>> = LazyLock::new(|| {
+    generate_layout(
+        503,
+        "Server Error",
+        Some("Too many requests in flight".into()),
+    )
+});
+
+pub static REQUEST_HEADER_TOO_LARGE: LazyLock<Vec<u8>> =
+    LazyLock::new(|| generate_layout_with_close(431, "Request header fields too large", None));
+
+pub static URI_TOO_LONG: LazyLock<Vec<u8>> =
+    LazyLock::new(|| generate_layout_with_close(414, "URI too long", None));
+
+pub static PAYLOAD_TOO_LARGE: LazyLock<Vec<u8>> =
+    LazyLock::new(|| generate_layout_with_close(413, "Payload too large", None));
+
 pub static ERROR_TIMEOUT: LazyLock<Vec<u8>> =
     LazyLock::new(|| generate_layout(503, "Server Error", Some("Timeout".into())));
 
@@ -79,29 +96,25 @@ fn build_layout_with_explicit_status(
     text: &str,
     second_line: Option<StrOrString>,
 ) -> Vec<u8> {
-    use crate::app::APP_VERSION;
+    let body = build_body(status_code, text, second_line);
 
-    let second_line = if let Some(second_line) = second_line {
-        format!("<h4>{}</h4>", second_line.as_str())
-    } else {
-        "".to_string()
-    };
+    let mut headers = crate::h1_utils::Http1HeadersBuilder::new();
+    headers.push_response_first_line(status_code);
+    headers.push_content_length(body.len());
+    headers.push_cl_cr();
 
-    let body = format!(
-        r#"
-        <div style="text-align: center;">
-        <h2>{text}</h2>
-      {second_line}
-        <p>{status_code}</p>
-        <hr/>
-        <div>MyReverseProxy {APP_VERSION}</div>
-        </div>
-        "#
-    )
-    .into_bytes();
+    let mut result = headers.into_bytes();
+    result.extend_from_slice(body.as_bytes());
+    result
+}
+
+/// A 429 for a client over a rate limit, with when to come back.
+pub fn generate_too_many_requests(retry_after_seconds: u64) -> Vec<u8> {
+    let body = build_body(429, "Too many requests", None);
 
     let mut headers = crate::h1_utils::Http1HeadersBuilder::new();
-    headers.push_response_first_line(status_code);
+    headers.push_response_first_line(429);
+    headers.push_header("Retry-After", retry_after_seconds.to_string().as_str());
     headers.push_content_length(body.len());
     headers.push_cl_cr();
 
@@ -116,6 +129,23 @@ fn build_layout(
     second_line: Option<StrOrString>,
     connection_close: bool,
 ) -> Vec<u8> {
+    let body = build_body(status_code, text, second_line);
+
+    let mut headers = crate::h1_utils::Http1HeadersBuilder::new();
+    headers.push_response_first_line(status_code);
+
+    headers.push_content_length(body.len());
+    if connection_close {
+        headers.push_header("Connection", "close");
+    }
+    headers.push_cl_cr();
+
+    let mut result = headers.into_bytes();
+    result.extend_from_slice(body.as_bytes());
+    result
+}
+
+fn build_body(status_code: u16, text: &str, second_line: Option<StrOrString>) -> Vec<u8> {
     use crate::app::APP_VERSION;
 
     let second_line = if let Some(second_line) = second_line {
@@ -124,7 +154,7 @@ fn build_layout(
         "".to_string()
     };
 
-    let body = format!(
+    format!(
         r#"
         <div style="text-align: center;">
         <h2>{text}</h2>
@@ -135,18 +165,5 @@ fn build_layout(
         </div>
         "#
     )
-    .into_bytes();
-
-    let mut headers = crate::h1_utils::Http1HeadersBuilder::new();
-    headers.push_response_first_line(status_code);
-
-    headers.push_content_length(body.len());
-    if connection_close {
-        headers.push_header("Connection", "close");
-    }
-    headers.push_cl_cr();
-
-    let mut result = headers.into_bytes();
-    result.extend_from_slice(body.as_bytes());
-    result
+    .into_bytes()
 }
@@ -3,8 +3,10 @@ use std::sync::Arc;
 use rust_extensions::slice_of_u8_utils::*;
 
 use crate::{
+    body_filter::ResponseBodyFilter,
     configurations::{
-        HttpEndpointInfo, HttpListenPortConfiguration, ModifyHeadersConfig, ProxyPassLocationConfig,
+        HttpEndpointInfo, HttpListenPortConfiguration, LocationResponseRewrite,
+        ModifyHeadersConfig, ProxyPassLocationConfig, RequestLimitExceeded,
     },
     h1_proxy_server::*,
     h1_utils::{Http1Headers, Http1HeadersBuilder, HttpContentLength},
@@ -15,14 +17,15 @@ use crate::{
 
 pub enum H1HeadersKind<'a> {
     Request(&'a HttpEndpointInfo),
-    Response(&'a HttpEndpointInfo),
+    /// With the location's `proxy_redirect` / `proxy_cookie_*` rules, if any.
+    Response(&'a HttpEndpointInfo, Option<&'a LocationResponseRewrite>),
 }
 
 impl<'a> H1HeadersKind<'a> {
     fn modify_headers(&self) -> &ModifyHeadersConfig {
         match self {
             H1HeadersKind::Request(info) => &info.modify_request_headers,
-            H1HeadersKind::Response(info) => &info.modify_response_headers,
+            H1HeadersKind::Response(info, _) => &info.modify_response_headers,
         }
     }
 }
@@ -34,6 +37,9 @@ pub struct H1Reader<TNetworkReadPart: NetworkStreamReadPart + Send + Sync + 'sta
     pub loop_buffer: LoopBuffer,
     pub h1_headers_builder: Http1HeadersBuilder,
     pub timeouts: HttpTimeouts,
+    /// The endpoint's `max_header_size`: a head still not complete past it is
+    /// not read any further.
+    pub max_header_size: Option<usize>,
 }
 
 impl<TNetworkReadPart: NetworkStreamReadPart + Send + Sync + 'static> H1Reader<TNetworkReadPart> {
@@ -43,6 +49,7 @@ impl<TNetworkReadPart: NetworkStreamReadPart + Send + Sync + 'static> H1Reader<T
             loop_buffer: LoopBuffer::new(),
             h1_headers_builder: Http1HeadersBuilder::new(),
             timeouts,
+            max_header_size: None,
         }
     }
 
@@ -57,6 +64,14 @@ impl<TNetworkReadPart: NetworkStreamReadPart + Send + Sync + 'static> H1Reader<T
                     if let Some(headers) = headers {
                         return Ok(headers);
                     }
+
+                    if let Some(max_header_size) = self.max_header_size {
+                        if buf.len() > max_header_size {
+                            return Err(ProxyServerError::RequestLimitExceeded(
+                                RequestLimitExceeded::HeaderSize(max_header_size),
+                            ));
+                        }
+                    }
                 }
             }
 
@@ -131,21 +146,21 @@ impl<TNetworkReadPart: NetworkStreamReadPart + Send + Sync + 'static> H1Reader<T
         kind: H1HeadersKind<'_>,
         http_connection_info: &HttpConnectionInfo,
         identity: &Option<HttpProxyPassIdentity>,
-        mcp_path: Option<&str>,
+        upstream_path: Option<&str>,
         dynamic_proxy_host_override: Option<&str>,
     ) -> Result<bool, ProxyServerError> {
         let modify_headers = kind.modify_headers();
-        let request_endpoint = match &kind {
-            H1HeadersKind::Request(info) => Some(*info),
-            H1HeadersKind::Response(_) => None,
+        let (request_endpoint, response_rewrite) = match &kind {
+            H1HeadersKind::Request(info) => (Some(*info), None),
+            H1HeadersKind::Response(_, response_rewrite) => (None, *response_rewrite),
         };
         self.h1_headers_builder.clear();
         let data = self.loop_buffer.get_data();
 
-        if let Some(mcp_path) = mcp_path {
+        if let Some(upstream_path) = upstream_path {
             http_headers.push_first_line_with_other_path(
                 data,
-                mcp_path,
+                upstream_path,
                 &mut self.h1_headers_builder,
             );
             self.h1_headers_builder.push_cl_cr();
@@ -209,6 +224,16 @@ impl<TNetworkReadPart: NetworkStreamReadPart + Send + Sync + 'static> H1Reader<T
                 } else if inject_country_enabled && header_name.eq_ignore_ascii_case("cf-ipcountry")
                 {
                     // drop client-supplied value; we'll write our own below
+                } else if let Some(value) = response_rewrite.and_then(|itm| {
+                    itm.rewrite_header(
+                        header_name,
+                        std::str::from_utf8(&header[header_name_end_pos + 1..])
+                            .unwrap_or("")
+                            .trim(),
+                    )
+                }) {
+                    self.h1_headers_builder
+                        .push_header(header_name, value.as_str());
                 } else {
                     self.h1_headers_builder.push_raw_payload(header);
                     self.h1_headers_builder
@@ -262,7 +287,7 @@ impl<TNetworkReadPart: NetworkStreamReadPart + Send + Sync + 'static> H1Reader<T
                 .push_header(add_header.0, value.as_str());
         }
 
-        if let H1HeadersKind::Response(info) = &kind {
+        if let H1HeadersKind::Response(info, _) = &kind {
             if !info.keep_alive {
                 self.h1_headers_builder.push_header("Connection", "close");
             }
@@ -327,6 +352,27 @@ impl<TNetworkReadPart: NetworkStreamReadPart + Send + Sync + 'static> H1Reader<T
         }
     }
 
+    /// Like [`Self::transfer_body`], with the body going through the
+    /// location's `sub_filter` / `compress` and on to the client chunked.
+    pub async fn transfer_filtered_body<WritePart: H1Writer + Send + Sync + 'static>(
+        &mut self,
+        connection_id: u64,
+        write_stream: &mut WritePart,
+        content_length: HttpContentLength,
+        filter: &mut ResponseBodyFilter,
+    ) -> Result<usize, ProxyServerError> {
+        super::transfer_body::transfer_filtered_body(
+            connection_id,
+            &mut self.read_part,
+            write_stream,
+            &mut self.loop_buffer,
+            content_length,
+            filter,
+            self.timeouts,
+        )
+        .await
+    }
+
     pub fn into_read_part(self) -> (TNetworkReadPart, LoopBuffer) {
         (self.read_part, self.loop_buffer)
     }
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
+    app::ConnectionPermit,
     configurations::{HttpEndpointInfo, HttpListenPortConfiguration},
     tcp_listener::https::ClientCertificateData,
     types::*,
@@ -34,6 +35,7 @@ pub fn kick_h1_tcp_reverse_proxy_server_from_http(
     accepted_connection: tokio::net::TcpStream,
     connection_ip: impl Into<ConnectionIp>,
     listen_config: Arc<HttpListenPortConfiguration>,
+    connection_permit: ConnectionPermit,
 ) {
     let http_connection_info = HttpConnectionInfo {
         connection_ip: connection_ip.into(),
@@ -42,10 +44,10 @@ pub fn kick_h1_tcp_reverse_proxy_server_from_http(
         listen_config,
     };
 
-    crate::app::spawn_named(
-        "h1_tcp_server_connection",
-        super::serve_reverse_proxy_pipelined(accepted_connection, http_connection_info),
-    );
+    crate::app::spawn_named("h1_tcp_server_connection", async move {
+        let _connection_permit = connection_permit;
+        super::serve_reverse_proxy_pipelined(accepted_connection, http_connection_info).await
+    });
 }
 
 pub fn kick_h1_unix_reverse_proxy_server_from_http(
@@ -0,0 +1,206 @@
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::mpsc;
+
+use crate::configurations::HttpEndpointInfo;
+use crate::h1_utils::{Http1Headers, Http1HeadersBuilder};
+use crate::network_stream::*;
+use crate::response_cache::CachedResponse;
+
+use super::super::{H1Writer, HttpConnectionInfo, HttpHeadersReader};
+use super::{BodyCollectorSink, ChannelSink, ResponseEvent};
+
+/// The headers of a raw HTTP/1 head — request or response — in order, as the
+/// cache takes them.
+pub fn read_head_headers(head: &[u8]) -> Vec<(String, String)> {
+    head.split(|itm| *itm == b'\n')
+        .skip(1)
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| {
+            let line = String::from_utf8_lossy(line);
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// The payload of a chunked body as relayed — framing included. `None` when
+/// it is not a complete one. Trailers are dropped: the cache does not keep
+/// them.
+pub fn decode_chunked_body(src: &[u8]) -> Option<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let line_end = pos + src[pos..].windows(2).position(|itm| itm == b"\r\n")?;
+        let size = std::str::from_utf8(&src[pos..line_end]).ok()?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        pos = line_end + 2;
+
+        if size == 0 {
+            return Some(result);
+        }
+
+        let end = pos.checked_add(size)?;
+        result.extend_from_slice(src.get(pos..end)?);
+        if src.get(end..end + 2)? != b"\r\n" {
+            return None;
+        }
+        pos = end + 2;
+    }
+}
+
+/// A response from the location's cache, ready for the client: the stored
+/// head with `Age` and the endpoint's response headers applied the way the
+/// worker applies them to an upstream's head, and the body to follow it —
+/// none for a HEAD request.
+pub fn compile_cached_response<'s>(
+    cached: &'s CachedResponse,
+    is_head: bool,
+    end_point_info: &HttpEndpointInfo,
+    http_connection_info: &HttpConnectionInfo,
+) -> (Vec<u8>, &'s [u8]) {
+    let has_body = !matches!(cached.status, 204 | 304);
+
+    // The head as the upstream sent it — what the `${…}` values of the
+    // endpoint's response headers are read from.
+    let mut upstream_head = Http1HeadersBuilder::new();
+    upstream_head.push_response_first_line(cached.status);
+    for (name, value) in cached.headers.iter() {
+        upstream_head.push_header(name, value);
+    }
+    upstream_head.push_cl_cr();
+    let upstream_head = upstream_head.into_bytes();
+
+    let modify_headers = &end_point_info.modify_response_headers;
+
+    let mut result = Http1HeadersBuilder::new();
+    result.push_response_first_line(cached.status);
+    for (name, value) in cached.headers.iter() {
+        if !modify_headers.has_to_be_removed(name) {
+            result.push_header(name, value);
+        }
+    }
+
+    result.push_header(
+        "Age",
+        cached.get_age(SystemTime::now()).to_string().as_str(),
+    );
+    if has_body {
+        result.push_content_length(cached.body.len());
+    }
+
+    if let Some(http_headers) = Http1Headers::parse(&upstream_head) {
+        let headers_reader = HttpHeadersReader {
+            http_headers: &http_headers,
+            payload: &upstream_head,
+        };
+
+        for (name, value) in modify_headers.iter_add() {
+            if value.is_empty() {
+                continue;
+            }
+            let value = crate::scripts::populate_value(
+                headers_reader,
+                http_connection_info,
+                &None,
+                value.as_str(),
+            );
+            if value.as_str().is_empty() {
+                continue;
+            }
+            result.push_header(name, value.as_str());
+        }
+    }
+
+    if !end_point_info.keep_alive {
+        result.push_header("Connection", "close");
+    }
+
+    if end_point_info.hsts && end_point_info.listen_endpoint_type.is_https() {
+        result.push_header(
+            "Strict-Transport-Security",
+            "max-age=31536000; includeSubDomains; preload",
+        );
+    }
+
+    result.push_cl_cr();
+
+    let body = match has_body && !is_head {
+        true => cached.body.as_slice(),
+        false => &[],
+    };
+
+    (result.into_bytes(), body)
+}
+
+/// An [`H1Writer`] that relays a response body to the client like
+/// [`ChannelSink`] and keeps a copy of it, up to a limit, for the cache.
+pub struct CacheCaptureSink {
+    client: ChannelSink,
+    copy: BodyCollectorSink,
+}
+
+impl CacheCaptureSink {
+    pub fn new(tx: mpsc::Sender<ResponseEvent>, limit: usize) -> Self {
+        Self {
+            client: ChannelSink::new(tx),
+            copy: BodyCollectorSink::new(limit),
+        }
+    }
+
+    /// The copy of the body as relayed, or `None` when it outgrew the limit.
+    pub fn into_copy(self) -> Option<Vec<u8>> {
+        self.copy.into_body()
+    }
+}
+
+#[async_trait::async_trait]
+impl H1Writer for CacheCaptureSink {
+    async fn write_http_payload(
+        &mut self,
+        request_id: u64,
+        buffer: &[u8],
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        self.client
+            .write_http_payload(request_id, buffer, timeout)
+            .await?;
+        self.copy
+            .write_http_payload(request_id, buffer, timeout)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heads_are_read_into_name_value_pairs() {
+        let headers = read_head_headers(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nETag:\"v1\"\r\n\r\nbody",
+        );
+
+        assert_eq!(
+            headers,
+            vec![
+                ("Content-Type".to_string(), "text/plain".to_string()),
+                ("ETag".to_string(), "\"v1\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn chunked_bodies_are_decoded() {
+        assert_eq!(
+            decode_chunked_body(b"5;ext=1\r\nHello\r\n6\r\n World\r\n0\r\nX-Trailer: a\r\n\r\n"),
+            Some(b"Hello World".to_vec())
+        );
+
+        assert_eq!(decode_chunked_body(b"5\r\nHel"), None);
+        assert_eq!(decode_chunked_body(b"5\r\nHello\r\n"), None);
+    }
+}
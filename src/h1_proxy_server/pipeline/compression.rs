@@ -0,0 +1,98 @@
+use crate::body_filter::ContentCoding;
+
+/// What `compress` makes of the headers — name / value pairs — of a response
+/// of a type the location encodes. It varies by `Accept-Encoding` whether it is
+/// encoded for this client or not. Encoded in `coding`, it is announced so,
+/// and its `ETag` becomes a weak one: the body is no longer byte for byte the
+/// upstream's.
+pub fn apply_compression_headers(
+    headers: &mut Vec<(String, String)>,
+    coding: Option<ContentCoding>,
+) {
+    let mut has_vary = false;
+
+    for (name, value) in headers.iter_mut() {
+        if name.eq_ignore_ascii_case("vary") {
+            has_vary = true;
+            let listed = value.split(',').any(|itm| {
+                let itm = itm.trim();
+                itm == "*" || itm.eq_ignore_ascii_case("accept-encoding")
+            });
+
+            if !listed {
+                value.push_str(", Accept-Encoding");
+            }
+        }
+
+        if coding.is_some() && name.eq_ignore_ascii_case("etag") && !value.starts_with("W/") {
+            *value = format!("W/{}", value);
+        }
+    }
+
+    if !has_vary {
+        headers.push(("Vary".to_string(), "Accept-Encoding".to_string()));
+    }
+
+    if let Some(coding) = coding {
+        headers.retain(|(name, _)| !name.eq_ignore_ascii_case("content-encoding"));
+        headers.push(("Content-Encoding".to_string(), coding.as_str().to_string()));
+    }
+}
+
+/// [`apply_compression_headers`] on a compiled response head. A head that is
+/// not complete is left as it is.
+pub fn apply_compression_to_head(head: &mut Vec<u8>, coding: Option<ContentCoding>) {
+    let Some(head_end) = head.windows(4).position(|itm| itm == b"\r\n\r\n") else {
+        return;
+    };
+
+    let first_line_end = head
+        .windows(2)
+        .position(|itm| itm == crate::consts::HTTP_CR_LF)
+        .unwrap_or(head_end);
+
+    let mut headers = super::read_head_headers(&head[..head_end + 4]);
+    apply_compression_headers(&mut headers, coding);
+
+    let mut result = Vec::with_capacity(head.len() + 64);
+    result.extend_from_slice(&head[..first_line_end + 2]);
+
+    for (name, value) in headers {
+        result.extend_from_slice(name.as_bytes());
+        result.extend_from_slice(b": ");
+        result.extend_from_slice(value.as_bytes());
+        result.extend_from_slice(crate::consts::HTTP_CR_LF);
+    }
+
+    result.extend_from_slice(&head[head_end + 2..]);
+    *head = result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_encoded_response_is_announced_and_varies() {
+        let mut head =
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nETag: \"v1\"\r\nVary: Cookie\r\n\r\n"
+                .to_vec();
+
+        apply_compression_to_head(&mut head, Some(ContentCoding::Gzip));
+
+        assert_eq!(
+            std::str::from_utf8(&head).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nETag: W/\"v1\"\r\nVary: Cookie, Accept-Encoding\r\nContent-Encoding: gzip\r\n\r\n"
+        );
+
+        let mut headers = vec![("ETag".to_string(), "\"v1\"".to_string())];
+        apply_compression_headers(&mut headers, None);
+        assert_eq!(
+            headers,
+            vec![
+                ("ETag".to_string(), "\"v1\"".to_string()),
+                ("Vary".to_string(), "Accept-Encoding".to_string()),
+            ]
+        );
+    }
+}
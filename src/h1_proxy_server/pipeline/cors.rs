@@ -0,0 +1,105 @@
+use crate::configurations::CorsConfig;
+use crate::h1_utils::{Http1HeadersBuilder, Http1ResponseBuilder};
+
+/// The proxy's own answer to a CORS preflight from `origin`: 204 with the
+/// location's CORS headers, or 403 when the origin is not allowed.
+/// `request_headers` is the preflight's `Access-Control-Request-Headers`.
+pub fn build_cors_preflight_response(
+    cors: &CorsConfig,
+    origin: &str,
+    request_headers: Option<&str>,
+) -> Vec<u8> {
+    let Some(headers) = cors.get_preflight_headers(origin, request_headers) else {
+        return Http1ResponseBuilder::new(403).build_with_body(&[]);
+    };
+
+    // A 204 has no body and carries no Content-Length.
+    let mut result = Http1HeadersBuilder::new();
+    result.push_response_first_line(204);
+    for (name, value) in headers.iter() {
+        result.push_header(name, value);
+    }
+    result.push_cl_cr();
+    result.into_bytes()
+}
+
+/// Puts `headers` on the response at the start of `response` — a head, with or
+/// without its body after it — in place of the `Access-Control-*` headers it
+/// has. `None` — the location has no `cors` — and a response whose head is not
+/// complete leave it as it is.
+pub fn apply_cors_headers(response: &mut Vec<u8>, headers: Option<&[(&'static str, String)]>) {
+    let Some(headers) = headers else {
+        return;
+    };
+
+    let Some(head_end) = response.windows(4).position(|itm| itm == b"\r\n\r\n") else {
+        return;
+    };
+
+    let mut result = Vec::with_capacity(response.len() + 128);
+
+    for (index, line) in response[..head_end].split(|itm| *itm == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        if index > 0 {
+            let name = match line.iter().position(|itm| *itm == b':') {
+                Some(pos) => &line[..pos],
+                None => line,
+            };
+
+            if crate::configurations::is_cors_response_header(
+                std::str::from_utf8(name).unwrap_or_default().trim(),
+            ) {
+                continue;
+            }
+        }
+
+        result.extend_from_slice(line);
+        result.extend_from_slice(crate::consts::HTTP_CR_LF);
+    }
+
+    for (name, value) in headers {
+        result.extend_from_slice(name.as_bytes());
+        result.extend_from_slice(b": ");
+        result.extend_from_slice(value.as_bytes());
+        result.extend_from_slice(crate::consts::HTTP_CR_LF);
+    }
+
+    result.extend_from_slice(&response[head_end + 2..]);
+    *response = result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cors_headers_replace_the_upstreams() {
+        let mut response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\naccess-control-allow-origin: *\r\nAccess-Control-Max-Age: 5\r\n\r\nok".to_vec();
+
+        apply_cors_headers(
+            &mut response,
+            Some(&[
+                ("Access-Control-Allow-Origin", "https://a.com".to_string()),
+                ("Vary", "Origin".to_string()),
+            ]),
+        );
+
+        assert_eq!(
+            std::str::from_utf8(&response).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nAccess-Control-Allow-Origin: https://a.com\r\nVary: Origin\r\n\r\nok"
+        );
+
+        let mut head_only = b"HTTP/1.1 204 No Content\r\n\r\n".to_vec();
+        apply_cors_headers(&mut head_only, Some(&[("Vary", "Origin".to_string())]));
+        assert_eq!(
+            std::str::from_utf8(&head_only).unwrap(),
+            "HTTP/1.1 204 No Content\r\nVary: Origin\r\n\r\n"
+        );
+
+        let mut untouched = b"HTTP/1.1 200 OK\r\nAccess-Control-Allow-Origin: *\r\n\r\n".to_vec();
+        let expected = untouched.clone();
+        apply_cors_headers(&mut untouched, None);
+        assert_eq!(untouched, expected);
+    }
+}
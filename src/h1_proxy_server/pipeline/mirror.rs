@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use crate::configurations::{LocationMirror, MirrorLease};
+use crate::h1_proxy_server::{H1Reader, H1Writer};
+use crate::h1_remote_connection::{H1PoolHolder, OwnedUpstream};
+use crate::h1_utils::HttpContentLength;
+use crate::types::HttpTimeouts;
+
+use super::NullSink;
+
+/// A stale kept-alive connection is dialled again once, the same way a
+/// request's delivery is — see `MAX_DELIVERY_ATTEMPTS` of the worker.
+const MAX_MIRROR_ATTEMPTS: u32 = 2;
+
+/// Hands a copy of a request to the location's `mirror_to` and returns at once.
+/// `body` is `None` when the body outgrew the mirror's `max_body_size` — the
+/// request is not mirrored then.
+pub fn spawn_mirror_request(
+    mirror: &Arc<LocationMirror>,
+    pool: &Arc<H1PoolHolder>,
+    head: &[u8],
+    body: Option<Vec<u8>>,
+    timeouts: HttpTimeouts,
+) {
+    let label = mirror.get_label();
+
+    let (Some(body), Some(lease)) = (body, mirror.try_start()) else {
+        crate::app::APP_CTX.prometheus.inc_mirror_skipped(label);
+        return;
+    };
+
+    crate::app::APP_CTX.prometheus.inc_mirror_requests(label);
+
+    crate::app::spawn_named(
+        "h1_mirror_request",
+        run_mirror_request(lease, pool.clone(), head.to_vec(), body, timeouts),
+    );
+}
+
+/// Sends the copy, reads the response and throws it away. Nothing of it goes
+/// anywhere but the metrics: a mirror that is down or answers 5xx counts as a
+/// failure.
+async fn run_mirror_request(
+    lease: MirrorLease,
+    pool: Arc<H1PoolHolder>,
+    head: Vec<u8>,
+    body: Vec<u8>,
+    timeouts: HttpTimeouts,
+) {
+    let mirror = lease.get_mirror();
+    let proxy_pass_to = &mirror.get_upstream().proxy_pass_to;
+
+    let mut attempt = 0u32;
+    let status = loop {
+        attempt += 1;
+
+        let Ok((owned, reused)) = pool.acquire(proxy_pass_to).await else {
+            break None;
+        };
+
+        match send_mirror_request(owned, &head, &body, timeouts).await {
+            Ok((status, reusable)) => {
+                if let Some(owned) = reusable {
+                    pool.release(proxy_pass_to, owned);
+                }
+                break status;
+            }
+            Err(_) if reused && attempt < MAX_MIRROR_ATTEMPTS => continue,
+            Err(_) => break None,
+        }
+    };
+
+    match status {
+        Some(status) if status < 500 => {}
+        _ => crate::app::APP_CTX
+            .prometheus
+            .inc_mirror_failures(mirror.get_label()),
+    }
+}
+
+/// One attempt. Gives back the response status and, when the response was
+/// self-delimiting and read to the end, the connection to pool again.
+async fn send_mirror_request(
+    mut owned: OwnedUpstream,
+    head: &[u8],
+    body: &[u8],
+    timeouts: HttpTimeouts,
+) -> Result<(Option<u16>, Option<OwnedUpstream>), ()> {
+    if !owned
+        .upstream
+        .send_head_bytes(head, timeouts.write_timeout)
+        .await
+    {
+        return Err(());
+    }
+
+    if !body.is_empty()
+        && owned
+            .upstream
+            .write_http_payload(0, body, timeouts.write_timeout)
+            .await
+            .is_err()
+    {
+        return Err(());
+    }
+
+    let OwnedUpstream {
+        upstream,
+        response_read,
+        disconnect_trigger,
+        ssh_handler,
+    } = owned;
+    let mut resp_reader = H1Reader::new(response_read, timeouts);
+
+    let resp_headers = match resp_reader.read_headers().await {
+        Ok(h) => h,
+        Err(_) => {
+            disconnect_trigger.set_value(true);
+            return Err(());
+        }
+    };
+
+    let status = resp_headers
+        .get_first_line(resp_reader.loop_buffer.get_data())
+        .get_status_code();
+    let content_length = resp_headers.content_length;
+    resp_reader.loop_buffer.commit_read(resp_headers.end);
+
+    if resp_reader
+        .transfer_body(upstream.connection_id, &mut NullSink, content_length)
+        .await
+        .is_err()
+    {
+        disconnect_trigger.set_value(true);
+        return Ok((status, None));
+    }
+
+    // Same keep-alive rule as the worker's.
+    let reusable_framing = matches!(
+        content_length,
+        HttpContentLength::Known(_) | HttpContentLength::Chunked
+    );
+    let (response_read, leftover) = resp_reader.into_read_part();
+    if !reusable_framing || !leftover.get_data().is_empty() || disconnect_trigger.get_value() {
+        return Ok((status, None));
+    }
+
+    let owned = OwnedUpstream {
+        upstream,
+        response_read,
+        disconnect_trigger,
+        ssh_handler,
+    };
+    Ok((status, Some(owned)))
+}
@@ -24,6 +24,16 @@ mod client_writer;
 pub use client_writer::*;
 mod worker;
 pub use worker::*;
+mod mirror;
+pub use mirror::*;
+mod cache;
+pub use cache::*;
+mod cors;
+pub use cors::*;
+mod sub_filter;
+pub use sub_filter::*;
+mod compression;
+pub use compression::*;
 mod reader;
 pub use reader::*;
 mod ws_tunnel;
@@ -4,11 +4,22 @@ use std::time::Duration;
 use parking_lot::Mutex;
 use tokio::sync::mpsc;
 
-use crate::configurations::{MyReverseProxyRemoteEndpoint, ProxyPassToConfig, ProxyPassToModel};
+use crate::app::RateLimitClient;
+use crate::body_filter::ContentCoding;
+use crate::configurations::{
+    MyReverseProxyRemoteEndpoint, ProxyPassToConfig, ProxyPassToModel, ProxyPassUpstream,
+    SplitMatchSource, StaticBodyClient, StickyMode, StickyRequest, UpstreamLease,
+};
 use crate::h1_remote_connection::{mcp_path, H1PoolHolder};
+use crate::h1_utils::HttpContentLength;
+use crate::http_content_source::local_path::LocalPathResponse;
 use crate::network_stream::*;
+use crate::response_cache::{CacheLookup, StaleRevalidation};
+use crate::types::HttpRequestReader;
 
-use super::super::{H1HeadersKind, H1Reader, HttpConnectionInfo, ProxyServerError};
+use super::super::{
+    H1HeadersKind, H1Reader, HttpConnectionInfo, HttpHeadersReader, ProxyServerError,
+};
 use super::{
     run_client_writer, run_oauth_gate, run_upstream_request, run_ws_tunnel, BodyChannelSink,
     OAuthGateOutcome, ResponseEvent, ResponseSlot, UpgradeContext, UpstreamRequest,
@@ -110,6 +121,10 @@ pub async fn serve_reverse_proxy_pipelined<
         .unwrap_or_default();
 
     let mut h1_reader = H1Reader::new(server_read_part, timeouts);
+    h1_reader.max_header_size = http_connection_info
+        .endpoint_info
+        .as_ref()
+        .and_then(|e| e.request_limits.max_header_size);
 
     // Per-connection (local) pool, keyed by upstream identity — mcp locations
     // included. A global pool would be passed in instead — same H1PoolHolder
@@ -169,6 +184,10 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
 
     let request_headers = match h1_reader.read_headers().await {
         Ok(h) => h,
+        // A head past the endpoint's `max_header_size` — answered with 431.
+        Err(err @ ProxyServerError::RequestLimitExceeded(_)) => {
+            return respond_error(queue_tx, http_connection_info, None, err).await
+        }
         // No bytes / transport gone — just close.
         Err(_) => return ReaderStep::Close,
     };
@@ -231,6 +250,7 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
     };
 
     h1_reader.timeouts = end_point_info.timeouts;
+    h1_reader.max_header_size = end_point_info.request_limits.max_header_size;
     let write_timeout = end_point_info.timeouts.write_timeout;
     let keep_alive = end_point_info.keep_alive;
     let endpoint_for_error = end_point_info.host_endpoint.as_str().to_string();
@@ -341,6 +361,69 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
         return ReaderStep::Close;
     }
 
+    // Size limits: the head and an announced body are checked here; a chunked
+    // body is counted by the worker as it is streamed to the upstream.
+    let request_limits = end_point_info.get_request_limits(location);
+    let head_checked = {
+        let first_line = request_headers.get_first_line(h1_reader.loop_buffer.get_data());
+        request_limits.check_head(
+            request_headers.end,
+            request_headers.headers_count,
+            first_line.get_path_and_query().len(),
+        )
+    };
+    let body_checked = match request_headers.content_length {
+        HttpContentLength::Known(size) => request_limits.check_content_length(size),
+        _ => Ok(()),
+    };
+    if let Err(exceeded) = head_checked.and(body_checked) {
+        return respond_error(
+            queue_tx,
+            http_connection_info,
+            Some(&endpoint_for_error),
+            ProxyServerError::RequestLimitExceeded(exceeded),
+        )
+        .await;
+    }
+
+    // CORS: a preflight is answered here, without the upstream and before the
+    // credentials it never carries are asked for. Every other response of the
+    // location gets the headers for the request's origin.
+    let cors_headers = match end_point_info.get_cors(location) {
+        Some(cors) => {
+            let buf = h1_reader.loop_buffer.get_data();
+            let origin = request_headers.find_header_value_str(buf, b"origin");
+            let (method, _) = request_headers.get_first_line(buf).get_verb_and_path();
+            let is_preflight = method == "OPTIONS"
+                && request_headers
+                    .find_header_value_str(buf, b"access-control-request-method")
+                    .is_some();
+
+            match origin {
+                Some(origin) if is_preflight => {
+                    let bytes = super::build_cors_preflight_response(
+                        cors,
+                        origin,
+                        request_headers
+                            .find_header_value_str(buf, b"access-control-request-headers"),
+                    );
+                    h1_reader.loop_buffer.commit_read(request_headers.end);
+                    return answer_locally(
+                        h1_reader,
+                        queue_tx,
+                        write_timeout,
+                        keep_alive,
+                        request_headers.content_length,
+                        bytes,
+                    )
+                    .await;
+                }
+                origin => Some(cors.get_response_headers(origin)),
+            }
+        }
+        None => None,
+    };
+
     let identity = match h1_reader
         .authorize(
             &end_point_info,
@@ -372,6 +455,165 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
         .await;
     }
 
+    // Rate limits are counted once the identity is known, so `key: identity`
+    // has it. Over a limit the request is answered with 429 — its body drained
+    // like a redirect's, so the connection stays byte-synced for reuse.
+    if end_point_info.has_rate_limits(location) {
+        let buf = h1_reader.loop_buffer.get_data();
+        let headers = super::read_head_headers(&buf[..request_headers.end]);
+        let (_, path_and_query) = request_headers.get_first_line(buf).get_verb_and_path();
+        let get_header = |name: &str| crate::response_cache::find_header(&headers, name);
+        let client = RateLimitClient {
+            ip: http_connection_info.connection_ip.get_ip_addr(),
+            identity: identity.as_ref().map(|itm| itm.as_str()),
+            get_header: &get_header,
+            query: path_and_query.split_once('?').map(|(_, query)| query),
+        };
+
+        if let Err(retry_after) = end_point_info.check_rate_limits(location, &client) {
+            let bytes = crate::error_templates::generate_too_many_requests(
+                crate::app::get_retry_after_seconds(retry_after),
+            );
+            h1_reader.loop_buffer.commit_read(request_headers.end);
+            return answer_locally(
+                h1_reader,
+                queue_tx,
+                write_timeout,
+                keep_alive,
+                request_headers.content_length,
+                bytes,
+            )
+            .await;
+        }
+    }
+
+    // Redirect: answer with the rendered `Location` and no upstream. Drain the
+    // request body first so the connection stays byte-synced for reuse.
+    if let ProxyPassToConfig::Redirect(cfg) = &location.proxy_pass_to {
+        let headers_reader = HttpHeadersReader {
+            http_headers: &request_headers,
+            payload: h1_reader.loop_buffer.get_data(),
+        };
+        let location_value = cfg.redirect_to.render(
+            headers_reader.get_host(),
+            headers_reader.get_path_and_query().unwrap_or("/"),
+        );
+        let mut bytes = crate::h1_utils::Http1ResponseBuilder::new(cfg.status_code)
+            .add_header("location", &location_value)
+            .build_with_body(&[]);
+        super::apply_cors_headers(&mut bytes, cors_headers.as_deref());
+        h1_reader.loop_buffer.commit_read(request_headers.end);
+        return answer_locally(
+            h1_reader,
+            queue_tx,
+            write_timeout,
+            keep_alive,
+            request_headers.content_length,
+            bytes,
+        )
+        .await;
+    }
+
+    // A location with a cache answers what it holds a fresh response for
+    // without an upstream; a miss travels with the request, so the worker can
+    // store what the upstream answers. A websocket handshake is never cached.
+    let cache = match location.cache.as_ref() {
+        Some(cache) if request_headers.upgrade_value.is_none() => {
+            let buf = h1_reader.loop_buffer.get_data();
+            let headers_reader = HttpHeadersReader {
+                http_headers: &request_headers,
+                payload: buf,
+            };
+            let (method, path_and_query) = request_headers.get_first_line(buf).get_verb_and_path();
+            let is_head = method == "HEAD";
+
+            let lookup = cache
+                .lookup(
+                    method,
+                    headers_reader.get_host().unwrap_or_default(),
+                    path_and_query,
+                    identity.is_some(),
+                    super::read_head_headers(&buf[..request_headers.end]),
+                )
+                .await;
+
+            match lookup {
+                CacheLookup::Bypass => None,
+                CacheLookup::Miss(cache_request) => Some(cache_request),
+                CacheLookup::Hit(cached) => {
+                    let (mut bytes, body) = super::compile_cached_response(
+                        &cached,
+                        is_head,
+                        &end_point_info,
+                        http_connection_info,
+                    );
+                    bytes.extend_from_slice(body);
+                    super::apply_cors_headers(&mut bytes, cors_headers.as_deref());
+                    h1_reader.loop_buffer.commit_read(request_headers.end);
+                    return answer_locally(
+                        h1_reader,
+                        queue_tx,
+                        write_timeout,
+                        keep_alive,
+                        request_headers.content_length,
+                        bytes,
+                    )
+                    .await;
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // A location with `stale` keeps the last good response to every GET; one
+    // young enough is answered at once while the request goes on to the
+    // upstream in the background to refresh it.
+    let mut stale = match location.stale.as_ref() {
+        Some(stale) if request_headers.upgrade_value.is_none() => {
+            let buf = h1_reader.loop_buffer.get_data();
+            let headers_reader = HttpHeadersReader {
+                http_headers: &request_headers,
+                payload: buf,
+            };
+            let (method, path_and_query) = request_headers.get_first_line(buf).get_verb_and_path();
+
+            stale.begin(
+                method,
+                headers_reader.get_host().unwrap_or_default(),
+                path_and_query,
+                identity.is_some(),
+                super::read_head_headers(&buf[..request_headers.end]),
+            )
+        }
+        _ => None,
+    };
+
+    let revalidating = match stale.as_mut().and_then(|itm| itm.find_to_revalidate()) {
+        Some(StaleRevalidation::Serve(kept)) => {
+            let is_head = stale.as_ref().is_some_and(|itm| itm.is_head());
+            let (mut bytes, body) = super::compile_cached_response(
+                &kept,
+                is_head,
+                &end_point_info,
+                http_connection_info,
+            );
+            bytes.extend_from_slice(body);
+            super::apply_cors_headers(&mut bytes, cors_headers.as_deref());
+            h1_reader.loop_buffer.commit_read(request_headers.end);
+            return answer_locally(
+                h1_reader,
+                queue_tx,
+                write_timeout,
+                keep_alive,
+                request_headers.content_length,
+                bytes,
+            )
+            .await;
+        }
+        Some(StaleRevalidation::Refresh(kept)) => Some(kept),
+        None => None,
+    };
+
     // Resolve dynamic_proxy → a per-request synthetic upstream + Host override.
     let (synthetic_proxy_pass_to, dynamic_host_override): (
         Option<ProxyPassToConfig>,
@@ -444,17 +686,162 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
         _ => (None, None),
     };
 
-    let proxy_pass_to_owned: ProxyPassToConfig =
-        synthetic_proxy_pass_to.unwrap_or_else(|| location.proxy_pass_to.clone());
+    // A balanced location picks its upstream per request; the lease travels
+    // with the request so the upstream counts it as outstanding until done.
+    let is_synthetic = synthetic_proxy_pass_to.is_some();
+    let sticky = location.sticky.as_ref().map(|sticky| {
+        let buf = h1_reader.loop_buffer.get_data();
+        let affinity = match sticky.get_mode() {
+            StickyMode::Cookie { name } => HttpHeadersReader {
+                http_headers: &request_headers,
+                payload: buf,
+            }
+            .get_cookie(name),
+            StickyMode::Header { name } => {
+                request_headers.find_header_value_str(buf, name.as_bytes())
+            }
+        };
+
+        StickyRequest {
+            sticky: sticky.clone(),
+            affinity: affinity.map(|itm| itm.to_string()),
+            is_https: http_connection_info
+                .listen_config
+                .listen_endpoint_type
+                .is_https_or_mcp(),
+        }
+    });
+
+    // A split location sends the request to the branch its cookie or header
+    // asks for, else to one by weight.
+    let split = match is_synthetic {
+        true => None,
+        false => location.split.clone(),
+    };
+    let split_match = split.as_ref().and_then(|split| {
+        let buf = h1_reader.loop_buffer.get_data();
+        let headers_reader = HttpHeadersReader {
+            http_headers: &request_headers,
+            payload: buf,
+        };
+        split.find_branch(|itm| match itm.source {
+            SplitMatchSource::Cookie => headers_reader.get_cookie(&itm.name),
+            SplitMatchSource::Header => {
+                request_headers.find_header_value_str(buf, itm.name.as_bytes())
+            }
+        })
+    });
+
+    let (proxy_pass_to_owned, upstream_lease): (ProxyPassToConfig, Option<UpstreamLease>) =
+        match (synthetic_proxy_pass_to, location.upstreams.as_ref()) {
+            (Some(synthetic), _) => (synthetic, None),
+            (None, Some(upstreams)) => {
+                let client_ip = http_connection_info.connection_ip.get_ip_addr();
+                let admit = |upstream: &ProxyPassUpstream| {
+                    crate::app::APP_CTX.upstream_outliers.admit(upstream.id)
+                };
+                let lease = match (sticky.as_ref(), split.as_ref()) {
+                    (Some(itm), _) => {
+                        itm.sticky
+                            .pick(upstreams, itm.affinity.as_deref(), client_ip, admit)
+                    }
+                    (None, Some(split)) => split.pick(upstreams, split_match, admit),
+                    (None, None) => upstreams.pick(client_ip, admit),
+                };
+                (lease.get_upstream().proxy_pass_to.clone(), Some(lease))
+            }
+            (None, None) => (location.proxy_pass_to.clone(), None),
+        };
+
+    // Outlier detection tracks the picked upstream's pool id. A single
+    // upstream is never balanced away from, so ejecting it would change
+    // nothing; a dynamic_proxy target is synthesized per request. Neither is
+    // tracked.
+    let outlier_id = upstream_lease.as_ref().map(|lease| lease.get_upstream().id);
+
+    // A dynamic_proxy target has no backups: its upstream is the request's own.
+    let backups = match is_synthetic {
+        true => None,
+        false => location.backups.clone(),
+    };
+
+    // The location's retry policy, unless the method is not one it repeats.
+    let retry = location
+        .retry
+        .as_ref()
+        .filter(|itm| {
+            let first_line = request_headers.get_first_line(h1_reader.loop_buffer.get_data());
+            itm.retries_method(first_line.get_verb_and_path().0)
+        })
+        .cloned();
+
+    // The location's sub_filter — a HEAD request has no body to replace in.
+    let sub_filter = location
+        .sub_filter
+        .as_ref()
+        .filter(|_| {
+            let first_line = request_headers.get_first_line(h1_reader.loop_buffer.get_data());
+            first_line.get_verb_and_path().0 != "HEAD"
+        })
+        .cloned();
+
+    // The location's compress, with what the client accepts of it. A HEAD
+    // request is answered with the head of the body as the upstream sent it.
+    let compression = location.compression.clone().map(|compression| {
+        let buf = h1_reader.loop_buffer.get_data();
+        let accepted = match request_headers.get_first_line(buf).get_verb_and_path().0 {
+            "HEAD" => None,
+            _ => ContentCoding::negotiate(
+                request_headers.find_header_value_str(buf, b"accept-encoding"),
+            ),
+        };
+        (compression, accepted)
+    });
 
     let content_length = request_headers.content_length;
 
+    // A static body is filled in with what the request came with, before its
+    // headers are compiled away.
+    let static_body = match &location.proxy_pass_to {
+        ProxyPassToConfig::Static(cfg) => {
+            let headers_reader = HttpHeadersReader {
+                http_headers: &request_headers,
+                payload: h1_reader.loop_buffer.get_data(),
+            };
+            let client = StaticBodyClient {
+                ip: http_connection_info.connection_ip.get_ip_addr(),
+                client_cert_cn: identity.as_ref().map(|itm| itm.as_str().to_string()),
+                is_https: http_connection_info
+                    .listen_config
+                    .listen_endpoint_type
+                    .is_https(),
+            };
+            Some(cfg.body.render(
+                headers_reader.get_host(),
+                headers_reader.get_path_and_query().unwrap_or("/"),
+                &client,
+            ))
+        }
+        _ => None,
+    };
+
+    // An MCP location forwards onto its own fixed path; an ordinary one only
+    // when it configures a path rewrite — otherwise the request line is
+    // forwarded verbatim.
+    let rewritten_path = match mcp_path(&proxy_pass_to_owned) {
+        Some(_) => None,
+        None => location.path_rewrite.as_ref().map(|path_rewrite| {
+            let first_line = request_headers.get_first_line(h1_reader.loop_buffer.get_data());
+            path_rewrite.apply(first_line.get_path_and_query())
+        }),
+    };
+
     let compiled = h1_reader.compile_headers(
         request_headers,
         H1HeadersKind::Request(&end_point_info),
         http_connection_info,
         &identity,
-        mcp_path(&proxy_pass_to_owned),
+        mcp_path(&proxy_pass_to_owned).or(rewritten_path.as_deref()),
         dynamic_host_override.as_deref(),
     );
     let is_websocket = match compiled {
@@ -477,29 +864,35 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
         if let Some(content_type) = cfg.content_type.as_ref() {
             builder = builder.add_content_type(content_type);
         }
-        let bytes = builder.build_with_body(&cfg.body);
-        let mut null = super::NullSink;
-        let _ = h1_reader.transfer_body(0, &mut null, content_length).await;
-        return match emit_single_response(queue_tx, write_timeout, bytes).await {
-            ReaderStep::Continue if !keep_alive => ReaderStep::Close,
-            other => other,
-        };
+        let mut bytes = builder.build_with_body(static_body.as_deref().unwrap_or_default());
+        super::apply_cors_headers(&mut bytes, cors_headers.as_deref());
+        return answer_locally(
+            h1_reader,
+            queue_tx,
+            write_timeout,
+            keep_alive,
+            content_length,
+            bytes,
+        )
+        .await;
     }
     if let ProxyPassToConfig::FilesPath(model) = &location.proxy_pass_to {
         // Serve a file from the configured folder (README "Serving the folder
-        // with files"): the request path picks the file; default_file is served
-        // for "/". get_content returns a full HTTP response (or NOT_FOUND /
-        // non-GET 404). Drain the request body so the connection stays synced.
+        // with files"): the request path picks the file — or a directory's
+        // default_file or listing, or a try_files fallback. get_content returns the response head (or NOT_FOUND /
+        // non-GET/HEAD 404) and the reader of the file's body — whole, a range
+        // or none at all for a 304. Drain the request body so the connection
+        // stays synced.
         let content_source = crate::http_content_source::local_path::LocalPathContent::new(
             model.files_path.to_string().as_str(),
-            model.default_file.clone(),
+            model.lookup.clone(),
         );
         content_source.send_headers(0, &h1_reader.h1_headers_builder);
-        let response = content_source.get_content(0).await;
-        let bytes = response.as_slice().to_vec();
+        let mut response = content_source.get_content(0).await;
+        super::apply_cors_headers(&mut response.head, cors_headers.as_deref());
         let mut null = super::NullSink;
         let _ = h1_reader.transfer_body(0, &mut null, content_length).await;
-        return match emit_single_response(queue_tx, write_timeout, bytes).await {
+        return match emit_file_response(queue_tx, write_timeout, response).await {
             ReaderStep::Continue if !keep_alive => ReaderStep::Close,
             other => other,
         };
@@ -509,6 +902,7 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
 
     // Clone the owned context before the borrows end.
     let location_id = location.id;
+    let response_rewrite = location.response_rewrite.clone();
     let conn_info = http_connection_info.clone();
 
     if is_websocket {
@@ -517,21 +911,103 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
         // (No request body on a websocket handshake.)
         return ReaderStep::Upgrade(UpgradeContext {
             proxy_pass_to: proxy_pass_to_owned,
+            upstream_lease,
             end_point_info,
             http_connection_info: conn_info,
             location_id,
+            response_rewrite,
             head,
             write_timeout,
         });
     }
 
+    // Sampled for the location's mirror. A body announced past the mirror's cap
+    // is not copied at all; a chunked one is dropped from the mirror once it
+    // outgrows the cap.
+    let mirror = match is_synthetic {
+        true => None,
+        false => location
+            .mirror
+            .as_ref()
+            .filter(|itm| itm.sample())
+            .and_then(|itm| match content_length {
+                HttpContentLength::Known(size) if size > itm.get_max_body_size() => {
+                    crate::app::APP_CTX
+                        .prometheus
+                        .inc_mirror_skipped(itm.get_label());
+                    None
+                }
+                _ => Some(itm.clone()),
+            }),
+    };
+
+    // A location's `max_concurrent_requests` counts the request until its
+    // response is relayed. Past it, and past the queue, the request is
+    // answered with 503 — or with the kept response it was to refresh.
+    let concurrency_permit = match location.concurrency_limit.as_ref() {
+        Some(limiter) => match limiter.acquire().await {
+            Some(permit) => Some(permit),
+            None => {
+                let mut bytes = match revalidating {
+                    Some(kept) => {
+                        let is_head = stale.as_ref().is_some_and(|itm| itm.is_head());
+                        let (mut bytes, body) = super::compile_cached_response(
+                            &kept,
+                            is_head,
+                            &end_point_info,
+                            &conn_info,
+                        );
+                        bytes.extend_from_slice(body);
+                        bytes
+                    }
+                    None => crate::error_templates::LOCATION_IS_BUSY.clone(),
+                };
+                super::apply_cors_headers(&mut bytes, cors_headers.as_deref());
+                return answer_locally(
+                    h1_reader,
+                    queue_tx,
+                    write_timeout,
+                    keep_alive,
+                    content_length,
+                    bytes,
+                )
+                .await;
+            }
+        },
+        None => None,
+    };
+
     // Reserve this request's ordered output slot, then spawn its worker.
     let (response_tx, response_rx) = mpsc::channel::<ResponseEvent>(RESPONSE_CHANNEL_CAPACITY);
     let (body_tx, body_rx) = mpsc::channel::<Vec<u8>>(REQUEST_BODY_CHANNEL_CAPACITY);
 
+    // A request refreshing a kept response: the slot gets the kept response,
+    // and what the upstream answers goes nowhere but the location's stores.
+    let events = match revalidating {
+        Some(kept) => {
+            let is_head = stale.as_ref().is_some_and(|itm| itm.is_head());
+            let (mut bytes, body) =
+                super::compile_cached_response(&kept, is_head, &end_point_info, &conn_info);
+            bytes.extend_from_slice(body);
+            super::apply_cors_headers(&mut bytes, cors_headers.as_deref());
+
+            let (tx, rx) = mpsc::channel::<ResponseEvent>(2);
+            let _ = tx.send(ResponseEvent::Chunk(bytes)).await;
+            let _ = tx.send(ResponseEvent::Done).await;
+
+            let mut response_rx = response_rx;
+            crate::app::spawn_named("h1_stale_revalidation", async move {
+                while response_rx.recv().await.is_some() {}
+            });
+
+            rx
+        }
+        None => response_rx,
+    };
+
     if queue_tx
         .send(ResponseSlot {
-            events: response_rx,
+            events,
             write_timeout,
         })
         .await
@@ -546,6 +1022,25 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
         run_upstream_request(UpstreamRequest {
             pool: pool.clone(),
             proxy_pass_to: proxy_pass_to_owned,
+            upstream_lease,
+            outlier_id,
+            backups,
+            sticky,
+            split,
+            mirror,
+            retry,
+            cache,
+            stale,
+            concurrency_permit,
+            // A body of a known size was held to it up front.
+            max_body_size: match content_length {
+                HttpContentLength::Chunked => request_limits.max_body_size,
+                _ => None,
+            },
+            cors_headers,
+            response_rewrite,
+            sub_filter,
+            compression,
             end_point_info,
             http_connection_info: conn_info,
             location_id,
@@ -570,6 +1065,25 @@ async fn read_and_dispatch<ReadPart: NetworkStreamReadPart + Send + Sync + 'stat
     }
 }
 
+/// Answer a request with a response built here, without an upstream: what is
+/// left of its body is drained first so the connection stays byte-synced for
+/// reuse. Its head has to be consumed already.
+async fn answer_locally<ReadPart: NetworkStreamReadPart + Send + Sync + 'static>(
+    h1_reader: &mut H1Reader<ReadPart>,
+    queue_tx: &mpsc::Sender<ResponseSlot>,
+    write_timeout: Duration,
+    keep_alive: bool,
+    content_length: HttpContentLength,
+    bytes: Vec<u8>,
+) -> ReaderStep {
+    let mut null = super::NullSink;
+    let _ = h1_reader.transfer_body(0, &mut null, content_length).await;
+    match emit_single_response(queue_tx, write_timeout, bytes).await {
+        ReaderStep::Continue if !keep_alive => ReaderStep::Close,
+        other => other,
+    }
+}
+
 /// Push a single fully-formed response (already-built bytes) as one ordered
 /// slot and keep the connection open. Returns `Close` only if the writer is gone.
 async fn emit_single_response(
@@ -594,6 +1108,55 @@ async fn emit_single_response(
     ReaderStep::Continue
 }
 
+/// Push a file's response as one ordered slot: its head, then its body as the
+/// file is read, so a large file is never held whole. Returns `Close` only if
+/// the writer is gone.
+async fn emit_file_response(
+    queue_tx: &mpsc::Sender<ResponseSlot>,
+    write_timeout: Duration,
+    response: LocalPathResponse,
+) -> ReaderStep {
+    let Some(mut body) = response.body else {
+        return emit_single_response(queue_tx, write_timeout, response.head).await;
+    };
+
+    let (tx, rx) = mpsc::channel::<ResponseEvent>(RESPONSE_CHANNEL_CAPACITY);
+    if queue_tx
+        .send(ResponseSlot {
+            events: rx,
+            write_timeout,
+        })
+        .await
+        .is_err()
+    {
+        return ReaderStep::Close;
+    }
+
+    let head = response.head;
+    crate::app::spawn_named("h1_file_body", async move {
+        if tx.send(ResponseEvent::Chunk(head)).await.is_err() {
+            return;
+        }
+
+        loop {
+            let event = match body.next_chunk().await {
+                Ok(Some(chunk)) => ResponseEvent::Chunk(chunk),
+                Ok(None) => ResponseEvent::Done,
+                // The head has promised a Content-Length the body can not
+                // keep any more.
+                Err(_) => ResponseEvent::Abort,
+            };
+
+            let last = !matches!(event, ResponseEvent::Chunk(_));
+            if tx.send(event).await.is_err() || last {
+                return;
+            }
+        }
+    });
+
+    ReaderStep::Continue
+}
+
 /// Push an ordered error response (per `ProxyServerError::error_handling`) and
 /// close the connection.
 async fn respond_error(
@@ -111,7 +111,8 @@ pub const REQUEST_BODY_CHANNEL_CAPACITY: usize = 4;
 
 /// An [`H1Writer`] that discards everything. Used to DRAIN the request body off
 /// the client stream for responses synthesized without an upstream (static /
-/// local files), so the connection stays byte-synced for the next request.
+/// local files), so the connection stays byte-synced for the next request — and
+/// a mirror's response, which goes nowhere.
 pub struct NullSink;
 
 #[async_trait::async_trait]
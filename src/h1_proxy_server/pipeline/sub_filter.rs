@@ -0,0 +1,55 @@
+/// Makes a compiled response head announce a chunked body: its
+/// `Content-Length` and `Transfer-Encoding` give way to
+/// `Transfer-Encoding: chunked`. For a body `sub_filter` replaces in — its
+/// length is not known until it has ended.
+pub fn reframe_as_chunked(head: &mut Vec<u8>) {
+    let Some(head_end) = head.windows(4).position(|itm| itm == b"\r\n\r\n") else {
+        return;
+    };
+
+    let mut result = Vec::with_capacity(head.len() + 32);
+
+    for (index, line) in head[..head_end].split(|itm| *itm == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        if index > 0 {
+            let name = match line.iter().position(|itm| *itm == b':') {
+                Some(pos) => &line[..pos],
+                None => line,
+            };
+            let name = std::str::from_utf8(name).unwrap_or_default().trim();
+
+            if name.eq_ignore_ascii_case("content-length")
+                || name.eq_ignore_ascii_case("transfer-encoding")
+            {
+                continue;
+            }
+        }
+
+        result.extend_from_slice(line);
+        result.extend_from_slice(crate::consts::HTTP_CR_LF);
+    }
+
+    result.extend_from_slice(b"Transfer-Encoding: chunked");
+    result.extend_from_slice(crate::consts::HTTP_CR_LF);
+    result.extend_from_slice(&head[head_end + 2..]);
+    *head = result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_head_is_reframed_as_chunked() {
+        let mut head =
+            b"HTTP/1.1 200 OK\r\ncontent-length: 12\r\nContent-Type: text/html\r\n\r\n".to_vec();
+
+        reframe_as_chunked(&mut head);
+
+        assert_eq!(
+            std::str::from_utf8(&head).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nTransfer-Encoding: chunked\r\n\r\n"
+        );
+    }
+}
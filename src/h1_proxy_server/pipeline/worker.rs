@@ -3,12 +3,19 @@ use std::time::Duration;
 
 use tokio::sync::mpsc;
 
-use crate::configurations::{HttpEndpointInfo, ProxyPassToConfig};
+use crate::app::{ConcurrencyPermit, OutlierProtocol, ProxyLogScope, UpstreamFailure};
+use crate::body_filter::{ContentCoding, ResponseBodyFilter, ResponseCompression, SubFilter};
+use crate::configurations::{
+    HttpEndpointInfo, LocationBackups, LocationMirror, LocationResponseRewrite, LocationRetry,
+    LocationSplit, ProxyPassToConfig, RetryFailure, StickyRequest, UpstreamLease,
+};
+use crate::h1_proxy_server::transfer_body::ChunkedPayloadCounter;
 use crate::h1_proxy_server::{
     H1HeadersKind, H1Reader, H1Writer, HttpConnectionInfo, ProxyServerError,
 };
 use crate::h1_remote_connection::{H1PoolHolder, OwnedUpstream};
 use crate::h1_utils::HttpContentLength;
+use crate::response_cache::{CacheRequest, StaleRequest};
 
 use super::{ChannelSink, ResponseEvent};
 
@@ -35,6 +42,46 @@ pub struct UpstreamRequest {
     /// Identity of the upstream (also the pool key). Owned because for
     /// dynamic_proxy it is synthesized per request.
     pub proxy_pass_to: ProxyPassToConfig,
+    /// The balanced location's claim on the upstream `proxy_pass_to` was picked
+    /// from. Held until the response is fully relayed.
+    pub upstream_lease: Option<UpstreamLease>,
+    /// Pool id outcomes are reported under for outlier detection — the picked
+    /// upstream's, or the location's. `None` for dynamic_proxy, whose target
+    /// is synthesized per request.
+    pub outlier_id: Option<i64>,
+    /// The location's `backup_proxy_pass_to`, if any.
+    pub backups: Option<Arc<LocationBackups>>,
+    /// Set when the location pins clients to its upstreams (`sticky`).
+    pub sticky: Option<StickyRequest>,
+    /// The location's `split`; the lease's upstream is the branch picked.
+    pub split: Option<Arc<LocationSplit>>,
+    /// The location's `mirror_to`, when this request was sampled for it.
+    pub mirror: Option<Arc<LocationMirror>>,
+    /// The location's `retry`, when the request's method is retried.
+    pub retry: Option<Arc<LocationRetry>>,
+    /// The location's cache, waiting for the response to a request it had no
+    /// fresh one for.
+    pub cache: Option<CacheRequest>,
+    /// The location's stale store: it keeps the response, and answers with
+    /// the last good one when the upstream fails.
+    pub stale: Option<StaleRequest>,
+    /// Counts the request against the location's `max_concurrent_requests`.
+    /// Held until the response is fully relayed.
+    pub concurrency_permit: Option<ConcurrencyPermit>,
+    /// The location's `max_body_size`, or its endpoint's, for a chunked body:
+    /// it is cut off when its payload outgrows it. A body announced past it
+    /// never gets this far.
+    pub max_body_size: Option<usize>,
+    /// The location's CORS headers for the request's origin, in place of the
+    /// upstream's; `None` when the location has no `cors`.
+    pub cors_headers: Option<Vec<(&'static str, String)>>,
+    /// The location's `proxy_redirect` / `proxy_cookie_*` rules, if any.
+    pub response_rewrite: Option<Arc<LocationResponseRewrite>>,
+    /// The location's `sub_filter`; `None` for a HEAD request.
+    pub sub_filter: Option<Arc<SubFilter>>,
+    /// The location's `compress`, with the coding the client accepts — none
+    /// for a HEAD request.
+    pub compression: Option<(Arc<ResponseCompression>, Option<ContentCoding>)>,
     pub end_point_info: Arc<HttpEndpointInfo>,
     pub http_connection_info: HttpConnectionInfo,
     pub location_id: i64,
@@ -60,6 +107,15 @@ struct RequestBodyPump {
     /// Body bytes taken from the client (traffic accounting). Counted once, so a
     /// replayed request is not counted twice.
     total: u64,
+    /// Copy of the body for the location's mirror with its size cap, or `None`
+    /// when the request is not mirrored or the body outgrew the cap.
+    mirror_copy: Option<(Vec<u8>, usize)>,
+    /// Payload bytes the chunked body may carry, with the counter that reads
+    /// them out of its framing.
+    max_size: Option<(usize, ChunkedPayloadCounter)>,
+    /// The body outgrew `max_size`: the request is answered with 413 and the
+    /// rest of the body is left unread.
+    too_large: bool,
 }
 
 impl RequestBodyPump {
@@ -69,9 +125,38 @@ impl RequestBodyPump {
             replay: Some(Vec::new()),
             completed: false,
             total: 0,
+            mirror_copy: None,
+            max_size: None,
+            too_large: false,
         }
     }
 
+    /// Stop taking a chunked body once its payload outgrows `max_size`. The
+    /// chunk framing does not count, just as for a body that is collected.
+    fn limit_size(&mut self, max_size: usize) {
+        self.max_size = Some((max_size, ChunkedPayloadCounter::new()));
+    }
+
+    /// Whether the body outgrew its `max_size` while it was streamed.
+    fn is_too_large(&self) -> bool {
+        self.too_large
+    }
+
+    /// Keep a copy of the body, up to `max_size`, for a mirror of the request.
+    fn copy_for_mirror(&mut self, max_size: usize) {
+        self.mirror_copy = Some((Vec::new(), max_size));
+    }
+
+    /// The copy taken by [`Self::copy_for_mirror`] once the whole body has
+    /// been taken off the client — `None` when it outgrew its cap.
+    fn take_mirror_copy(&mut self) -> Option<Vec<u8>> {
+        if !self.completed {
+            return None;
+        }
+
+        self.mirror_copy.take().map(|(copy, _)| copy)
+    }
+
     /// Whether everything taken from the client so far can still be re-sent.
     fn is_replayable(&self) -> bool {
         self.replay.is_some()
@@ -108,6 +193,14 @@ impl RequestBodyPump {
         while let Some(chunk) = self.rx.recv().await {
             self.total += chunk.len() as u64;
 
+            // Not a byte past the limit goes on to the upstream.
+            if let Some((max_size, payload)) = self.max_size.as_mut() {
+                if payload.feed(&chunk) > *max_size {
+                    self.too_large = true;
+                    return false;
+                }
+            }
+
             // Remembered BEFORE the write: a chunk that fails on the wire still
             // has to be part of a replay.
             if let Some(replay) = self.replay.as_mut() {
@@ -118,6 +211,14 @@ impl RequestBodyPump {
                 }
             }
 
+            if let Some((copy, max_size)) = self.mirror_copy.as_mut() {
+                if copy.len() + chunk.len() > *max_size {
+                    self.mirror_copy = None;
+                } else {
+                    copy.extend_from_slice(&chunk);
+                }
+            }
+
             if upstream
                 .write_http_payload(0, &chunk, write_timeout)
                 .await
@@ -141,6 +242,10 @@ impl RequestBodyPump {
             return true;
         }
 
+        if self.too_large {
+            return false;
+        }
+
         let mut discarded = 0usize;
 
         while let Some(chunk) = self.rx.recv().await {
@@ -174,16 +279,40 @@ impl RequestBodyPump {
 /// - A failure on a FRESHLY dialled connection is a broken upstream, not
 ///   staleness — replaying it would just hit the same wall, so it is answered.
 /// - A timeout is never replayed: the upstream may be working on the request.
+/// - When a failed connect or write is final, or the upstream answers with one
+///   of the location's `backup_on_status`, the request fails over to the next
+///   backup instead of being answered — under the same replay-window rule, and
+///   with a fresh set of attempts.
+/// - A request the location's `retry` covers goes to the same target again —
+///   on the failures and statuses the policy names, after its back-off and
+///   while its budget lasts, under the same replay-window rule. A read failure
+///   or a timeout is retried too: the policy only covers methods that are safe
+///   to repeat. Failing over comes once the retries are used up.
 /// - Once the client has received bytes nothing can be substituted or replayed —
 ///   the response is aborted and the client connection closed.
 pub async fn run_upstream_request(req: UpstreamRequest) {
     let UpstreamRequest {
         pool,
         proxy_pass_to,
+        upstream_lease,
+        outlier_id,
+        backups,
+        sticky,
+        split,
+        mut mirror,
+        retry,
+        cache,
+        stale,
+        concurrency_permit: _concurrency_permit,
+        max_body_size,
+        cors_headers,
+        response_rewrite,
+        sub_filter,
+        compression,
         end_point_info,
         http_connection_info,
         location_id,
-        head,
+        mut head,
         body_rx,
         response_tx,
     } = req;
@@ -192,6 +321,27 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
     let ip = http_connection_info.connection_ip.get_ip_log();
     let is_mcp = proxy_pass_to.is_mcp();
 
+    let mut target = DeliveryTarget {
+        proxy_pass_to,
+        upstream_lease,
+        outlier_id,
+        backups,
+        backups_tried: 0,
+        split,
+    };
+    let mut retry = RetryState::new(retry, endpoint, location_id, &ip);
+    let failure_answer = FailureAnswer {
+        response_tx: &response_tx,
+        is_mcp,
+        stale: stale.as_ref(),
+        end_point_info: &end_point_info,
+        http_connection_info: &http_connection_info,
+        cors_headers: cors_headers.as_deref(),
+        endpoint,
+        location_id,
+        ip: &ip,
+    };
+
     // An MCP listening stream is SSE that legitimately idles with no keepalive
     // between server-initiated messages, far longer than a normal response body
     // ever would. Reading it on the endpoint's ordinary read timeout tears down
@@ -208,6 +358,20 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
     // never waits for (nor holds) the whole thing; only the first REPLAY_WINDOW
     // bytes are remembered, for a possible replay.
     let mut body = RequestBodyPump::new(body_rx);
+    if let Some(mirror) = mirror.as_ref() {
+        body.copy_for_mirror(mirror.get_max_body_size());
+    }
+    if let Some(max_body_size) = max_body_size {
+        body.limit_size(max_body_size);
+    }
+
+    // A stale response in the location's cache is revalidated: the upstream
+    // is asked to confirm it rather than to send it again.
+    if let Some(cache) = cache.as_ref() {
+        for (name, value) in cache.get_validation_headers() {
+            push_header_to_head(&mut head, name, &value);
+        }
+    }
 
     // Deliver the request, replaying it while the client has seen nothing yet.
     // Breaks with a connection whose response head has been read.
@@ -215,38 +379,45 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
     let (
         upstream,
         mut resp_reader,
+        response_status,
         response_content_length,
         response_is_websocket,
+        mut upstream_headers,
         disconnect_trigger,
         ssh_handler,
+        set_cookie,
     ) = loop {
         attempt += 1;
         // Whether a replay is still possible is re-checked at every failure site:
         // streaming the body can end the replay window mid-attempt.
         let retries_left = attempt < MAX_DELIVERY_ATTEMPTS;
 
-        let (mut owned, reused) = match pool.acquire(&proxy_pass_to).await {
+        let (mut owned, reused) = match pool.acquire(&target.proxy_pass_to).await {
             Ok(c) => c,
             Err(err) => {
-                crate::app::APP_CTX.proxy_logs.write_returned_5xx(
-                    endpoint,
-                    Some(location_id),
-                    ip.clone(),
-                    503,
-                    format!(
+                let failed = FailedAttempt {
+                    retry_on: Some(RetryFailure::Connect),
+                    fails_over: true,
+                    outlier_failure: UpstreamFailure::Connect,
+                    reason: "can not connect",
+                    status: 503,
+                    log_line: format!(
                         "can not connect to upstream {}: {:?}",
-                        proxy_pass_to.to_string(),
+                        target.proxy_pass_to.to_string(),
                         err
                     ),
-                );
-                fail_request(
-                    &response_tx,
-                    is_mcp,
-                    crate::error_templates::REMOTE_RESOURCE_IS_NOT_AVAILABLE.as_slice(),
-                    &mut body,
-                )
-                .await;
-                return;
+                    page: crate::error_templates::REMOTE_RESOURCE_IS_NOT_AVAILABLE.as_slice(),
+                };
+                match failure_answer
+                    .handle_failed_attempt(failed, &mut retry, &mut target, &mut body)
+                    .await
+                {
+                    AfterFailure::Resend => {
+                        attempt = 0;
+                        continue;
+                    }
+                    AfterFailure::Answered => return,
+                }
             }
         };
 
@@ -261,24 +432,28 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
             if retries_left && body.is_replayable() {
                 continue;
             }
-            crate::app::APP_CTX.proxy_logs.write_returned_5xx(
-                endpoint,
-                Some(location_id),
-                ip.clone(),
-                503,
-                format!(
+            let failed = FailedAttempt {
+                retry_on: Some(RetryFailure::Connect),
+                fails_over: true,
+                outlier_failure: UpstreamFailure::Connect,
+                reason: "did not accept request head",
+                status: 503,
+                log_line: format!(
                     "upstream {} did not accept request head",
-                    proxy_pass_to.to_string()
+                    target.proxy_pass_to.to_string()
                 ),
-            );
-            fail_request(
-                &response_tx,
-                is_mcp,
-                crate::error_templates::REMOTE_RESOURCE_IS_NOT_AVAILABLE.as_slice(),
-                &mut body,
-            )
-            .await;
-            return;
+                page: crate::error_templates::REMOTE_RESOURCE_IS_NOT_AVAILABLE.as_slice(),
+            };
+            match failure_answer
+                .handle_failed_attempt(failed, &mut retry, &mut target, &mut body)
+                .await
+            {
+                AfterFailure::Resend => {
+                    attempt = 0;
+                    continue;
+                }
+                AfterFailure::Answered => return,
+            }
         }
 
         // Re-deliver the part of the body already taken from the client (nothing
@@ -294,27 +469,63 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
 
         if !delivered {
             drop(owned);
+
+            // Over `max_body_size`: the client's fault, not the upstream's —
+            // answered with 413 and the connection closed, the rest of the
+            // body unread.
+            if body.is_too_large() {
+                if let Some(ip) = http_connection_info.connection_ip.get_ip_addr() {
+                    crate::app::APP_CTX
+                        .ip_blocklist
+                        .register_failure(ip, crate::app::FailureSeverity::Soft);
+                }
+                fail_request(
+                    &response_tx,
+                    is_mcp,
+                    crate::error_templates::PAYLOAD_TOO_LARGE.as_slice(),
+                    &mut body,
+                )
+                .await;
+                return;
+            }
+
             if retries_left && body.is_replayable() {
                 continue;
             }
-            crate::app::APP_CTX.proxy_logs.write_returned_5xx(
-                endpoint,
-                Some(location_id),
-                ip.clone(),
-                502,
-                format!(
+            let failed = FailedAttempt {
+                retry_on: Some(RetryFailure::Connect),
+                fails_over: true,
+                outlier_failure: UpstreamFailure::Connect,
+                reason: "broke while forwarding request body",
+                status: 502,
+                log_line: format!(
                     "upstream {} broke while forwarding request body",
-                    proxy_pass_to.to_string()
+                    target.proxy_pass_to.to_string()
                 ),
+                page: crate::error_templates::ERROR_GETTING_CONTENT_FROM_REMOTE_RESOURCE.as_slice(),
+            };
+            match failure_answer
+                .handle_failed_attempt(failed, &mut retry, &mut target, &mut body)
+                .await
+            {
+                AfterFailure::Resend => {
+                    attempt = 0;
+                    continue;
+                }
+                AfterFailure::Answered => return,
+            }
+        }
+
+        // The whole body is off the client: the mirror gets its copy now,
+        // whatever the upstream goes on to answer.
+        if let Some(mirror) = mirror.take() {
+            super::spawn_mirror_request(
+                &mirror,
+                &pool,
+                &head,
+                body.take_mirror_copy(),
+                end_point_info.timeouts,
             );
-            fail_request(
-                &response_tx,
-                is_mcp,
-                crate::error_templates::ERROR_GETTING_CONTENT_FROM_REMOTE_RESOURCE.as_slice(),
-                &mut body,
-            )
-            .await;
-            return;
         }
 
         // Read the response head. On a REUSED connection a transport failure here
@@ -336,29 +547,74 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
                 if reused && retries_left && body.is_replayable() && is_stale_connection(&err) {
                     continue;
                 }
+                drop(upstream);
+                drop(resp_reader);
+                // The upstream may have got the request: it is retried only as
+                // the policy allows, and never failed over.
                 let (page, label) = classify_upstream_failure(&err);
-                crate::app::APP_CTX.proxy_logs.write_returned_5xx(
-                    endpoint,
-                    Some(location_id),
-                    ip.clone(),
-                    502,
-                    format!(
+                let reason = format!("did not answer ({})", label);
+                let failed = FailedAttempt {
+                    retry_on: retry_failure(&err),
+                    fails_over: false,
+                    outlier_failure: outlier_failure(&err),
+                    reason: &reason,
+                    status: 502,
+                    log_line: format!(
                         "reading response head from upstream {} ({}): {:?}",
-                        proxy_pass_to.to_string(),
+                        target.proxy_pass_to.to_string(),
                         label,
                         err
                     ),
-                );
-                fail_request(&response_tx, is_mcp, page, &mut body).await;
-                return;
+                    page,
+                };
+                match failure_answer
+                    .handle_failed_attempt(failed, &mut retry, &mut target, &mut body)
+                    .await
+                {
+                    AfterFailure::Resend => {
+                        attempt = 0;
+                        continue;
+                    }
+                    AfterFailure::Answered => return,
+                }
             }
         };
 
         let response_content_length = resp_headers.content_length;
 
+        let response_status = resp_headers
+            .get_first_line(resp_reader.loop_buffer.get_data())
+            .get_status_code();
+
+        let response_session = sticky
+            .as_ref()
+            .and_then(|itm| itm.get_session_header())
+            .and_then(|name| {
+                resp_headers
+                    .find_header_value_str(resp_reader.loop_buffer.get_data(), name.as_bytes())
+            })
+            .map(|itm| itm.to_string());
+
+        // The head as the upstream sent it, before the endpoint's response
+        // headers are applied — what the cache and the stale store keep. Its
+        // redirects and cookies are already turned onto the location, so a
+        // kept response is never rewritten twice.
+        let upstream_headers = match cache.is_some() || stale.is_some() {
+            true => {
+                let mut headers = super::read_head_headers(
+                    &resp_reader.loop_buffer.get_data()[..resp_headers.end],
+                );
+                if let Some(response_rewrite) = response_rewrite.as_ref() {
+                    response_rewrite.rewrite_headers(&mut headers);
+                }
+                Some(headers)
+            }
+            false => None,
+        };
+
         let response_is_websocket = match resp_reader.compile_headers(
             resp_headers,
-            H1HeadersKind::Response(&end_point_info),
+            H1HeadersKind::Response(&end_point_info, response_rewrite.as_deref()),
             &http_connection_info,
             &None,
             None,
@@ -368,41 +624,243 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
             Err(err) => {
                 disconnect_trigger.set_value(true);
                 let (page, label) = classify_upstream_failure(&err);
-                crate::app::APP_CTX.proxy_logs.write_returned_5xx(
-                    endpoint,
-                    Some(location_id),
-                    ip.clone(),
-                    502,
-                    format!(
+                let failed = FailedAttempt {
+                    retry_on: None,
+                    fails_over: false,
+                    outlier_failure: UpstreamFailure::InvalidResponse,
+                    reason: label,
+                    status: 502,
+                    log_line: format!(
                         "compiling response head from upstream {} ({}): {:?}",
-                        proxy_pass_to.to_string(),
+                        target.proxy_pass_to.to_string(),
                         label,
                         err
                     ),
-                );
-                fail_request(&response_tx, is_mcp, page, &mut body).await;
+                    page,
+                };
+                failure_answer
+                    .handle_failed_attempt(failed, &mut retry, &mut target, &mut body)
+                    .await;
+                return;
+            }
+        };
+
+        // A status the location retries on: the response is dropped unread
+        // (with its connection) and the request is sent again. Once the
+        // attempts are used up it is relayed as it is.
+        if let Some(status) = response_status {
+            let reason = format!("answered {}", status);
+            if let Some(backoff) =
+                retry.next_retry(RetryFailure::Status(status), &reason, &target, &body)
+            {
+                disconnect_trigger.set_value(true);
+                drop(upstream);
+                drop(resp_reader);
+                if status >= 500 {
+                    target.report_to_outliers(UpstreamFailure::Status(status));
+                }
+                tokio::time::sleep(backoff).await;
+                attempt = 0;
+                continue;
+            }
+        }
+
+        // The upstream answered: a 5xx counts against it, anything else clears
+        // its failure streak.
+        match response_status {
+            Some(status) if status >= 500 => target.report_failure(UpstreamFailure::Status(status)),
+            _ => target.report_success(),
+        }
+
+        // A status the location fails over on: the response is dropped unread
+        // (with its connection) and the request goes to the next backup. With
+        // no backup left it is relayed as it is.
+        if let Some(status) = response_status {
+            if target.fails_over_on(status) {
+                if let Some(failed) = target.fail_over(&body) {
+                    disconnect_trigger.set_value(true);
+                    write_failover(
+                        endpoint,
+                        location_id,
+                        &ip,
+                        &failed,
+                        &target,
+                        &format!("answered {}", status),
+                    );
+                    attempt = 0;
+                    continue;
+                }
+            }
+        }
+
+        // An error status the location's last good response stands in for:
+        // the response is dropped unread (with its connection).
+        if let (Some(itm), Some(status)) = (stale.as_ref(), response_status) {
+            if itm.serves_on_status(status) && failure_answer.serve_kept(&mut body).await {
+                disconnect_trigger.set_value(true);
                 return;
             }
+        }
+
+        // Only an upstream the request was balanced to pins the client — a
+        // backup is not one of them.
+        let set_cookie = match (sticky.as_ref(), target.upstream_lease.as_ref()) {
+            (Some(sticky), Some(lease)) => sticky.on_response(response_session.as_deref(), lease),
+            _ => None,
         };
 
         break (
             upstream,
             resp_reader,
+            response_status,
             response_content_length,
             response_is_websocket,
+            upstream_headers,
             disconnect_trigger,
             ssh_handler,
+            set_cookie,
         );
     };
 
     crate::app::APP_CTX.traffic.record_c2s(endpoint, body.total);
 
+    let has_body = match response_content_length {
+        HttpContentLength::Known(size) => size > 0,
+        HttpContentLength::Chunked => true,
+        HttpContentLength::None => false,
+    };
+    let has_body = has_body && !matches!(response_status, Some(204 | 304));
+
+    // The location's compress, decided on the upstream's head: a response of a
+    // type it encodes varies by Accept-Encoding, and is encoded when the client
+    // accepts a coding. The headers kept with it say so too.
+    let mut varies_by_encoding = false;
+    let mut encoder = None;
+    if let Some((compression, accepted)) = compression.as_ref() {
+        let headers = super::read_head_headers(resp_reader.h1_headers_builder.as_slice());
+        let find = |name: &str| {
+            headers
+                .iter()
+                .find(|(itm, _)| itm.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+
+        if compression.is_compressible(
+            response_status,
+            find("content-type"),
+            find("content-encoding"),
+            find("content-range"),
+            find("cache-control"),
+        ) {
+            varies_by_encoding = true;
+
+            let content_length = match response_content_length {
+                HttpContentLength::Known(size) => Some(size),
+                _ => None,
+            };
+            encoder = accepted.filter(|_| has_body).and_then(|coding| {
+                compression
+                    .start(coding, content_length)
+                    .map(|encoder| (coding, encoder))
+            });
+
+            if let Some(headers) = upstream_headers.as_mut() {
+                super::apply_compression_headers(headers, encoder.as_ref().map(|itm| itm.0));
+            }
+        }
+    }
+
+    // What the cache and the stale store make of the response: a 304
+    // confirming the response the cache revalidated is answered with that
+    // response; one worth keeping has its body copied on its way to the client.
+    let mut revalidated = None;
+    let mut to_store = None;
+    if let (Some(status), Some(headers)) = (response_status, upstream_headers) {
+        let self_delimited = matches!(
+            response_content_length,
+            HttpContentLength::Known(_) | HttpContentLength::Chunked
+        ) || status == 204;
+
+        let mut to_cache = None;
+        if let Some(cache) = cache.as_ref() {
+            if status == 304 {
+                revalidated = cache.on_not_modified(&headers);
+            } else if self_delimited {
+                to_cache = cache
+                    .get_freshness(status, &headers)
+                    .map(|fresh_for| (cache, fresh_for));
+            }
+        }
+
+        if let (Some(stale), Some(revalidated)) = (stale.as_ref(), revalidated.as_ref()) {
+            stale.store_revalidated(revalidated);
+        }
+
+        let to_stale = stale
+            .as_ref()
+            .filter(|itm| self_delimited && itm.is_storable(status, &headers));
+
+        if to_cache.is_some() || to_stale.is_some() {
+            to_store = Some(ResponseToStore {
+                status,
+                headers,
+                to_cache,
+                to_stale,
+            });
+        }
+    }
+
     // From here on the client begins receiving the response — any failure must
     // Abort (close the connection), never substitute an error page.
+    let (mut response_head, cached_body) = match revalidated.as_ref() {
+        Some(cached) => {
+            let (head, body) = super::compile_cached_response(
+                cached,
+                false,
+                &end_point_info,
+                &http_connection_info,
+            );
+            (head, Some(body))
+        }
+        None => (resp_reader.h1_headers_builder.as_slice().to_vec(), None),
+    };
+    if let Some(set_cookie) = set_cookie {
+        push_header_to_head(&mut response_head, "Set-Cookie", &set_cookie);
+    }
+    super::apply_cors_headers(&mut response_head, cors_headers.as_deref());
+
+    // The location's sub_filter replaces in a body of a type it lists, then
+    // compress encodes it; the body goes on chunked, whatever its length was.
+    let sub_filter = match (sub_filter, revalidated.is_none() && has_body) {
+        (Some(sub_filter), true) => {
+            let headers = super::read_head_headers(&response_head);
+            let find = |name: &str| {
+                headers
+                    .iter()
+                    .find(|(itm, _)| itm.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.as_str())
+            };
+            sub_filter.start(
+                response_status,
+                find("content-type"),
+                find("content-encoding"),
+                find("content-range"),
+            )
+        }
+        _ => None,
+    };
+
+    if varies_by_encoding && revalidated.is_none() {
+        super::apply_compression_to_head(&mut response_head, encoder.as_ref().map(|itm| itm.0));
+    }
+
+    let mut body_filter = ResponseBodyFilter::new(sub_filter, encoder.map(|itm| itm.1));
+    if body_filter.is_some() {
+        super::reframe_as_chunked(&mut response_head);
+    }
+
     if response_tx
-        .send(ResponseEvent::Chunk(
-            resp_reader.h1_headers_builder.as_slice().to_vec(),
-        ))
+        .send(ResponseEvent::Chunk(response_head))
         .await
         .is_err()
     {
@@ -416,11 +874,71 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
         return;
     }
 
-    let mut sink = ChannelSink::new(response_tx.clone());
-    let bytes_to_client = match resp_reader
-        .transfer_body(upstream.connection_id, &mut sink, response_content_length)
-        .await
-    {
+    let transferred = match (cached_body, to_store) {
+        // The client gets the cached body. The upstream's 304 has none, even
+        // when it names a Content-Length — there is nothing to read.
+        (Some(body), _) if body.is_empty() => Ok(0),
+        (Some(body), _) => ChannelSink::new(response_tx.clone())
+            .write_http_payload(0, body, timeouts.write_timeout)
+            .await
+            .map(|_| body.len())
+            .map_err(ProxyServerError::NetworkError),
+        (None, Some(to_store)) => {
+            let mut sink =
+                super::CacheCaptureSink::new(response_tx.clone(), to_store.get_max_body_size());
+            let transferred = match body_filter.as_mut() {
+                Some(body_filter) => {
+                    resp_reader
+                        .transfer_filtered_body(
+                            upstream.connection_id,
+                            &mut sink,
+                            response_content_length,
+                            body_filter,
+                        )
+                        .await
+                }
+                None => {
+                    resp_reader
+                        .transfer_body(upstream.connection_id, &mut sink, response_content_length)
+                        .await
+                }
+            };
+
+            let body = match (response_content_length, body_filter.is_some()) {
+                (HttpContentLength::Chunked, _) | (_, true) => sink
+                    .into_copy()
+                    .and_then(|itm| super::decode_chunked_body(&itm)),
+                _ => sink.into_copy(),
+            };
+            if let (Ok(_), Some(body)) = (transferred.as_ref(), body) {
+                to_store.store(body);
+            }
+
+            transferred
+        }
+        (None, None) => {
+            let mut sink = ChannelSink::new(response_tx.clone());
+            match body_filter.as_mut() {
+                Some(body_filter) => {
+                    resp_reader
+                        .transfer_filtered_body(
+                            upstream.connection_id,
+                            &mut sink,
+                            response_content_length,
+                            body_filter,
+                        )
+                        .await
+                }
+                None => {
+                    resp_reader
+                        .transfer_body(upstream.connection_id, &mut sink, response_content_length)
+                        .await
+                }
+            }
+        }
+    };
+
+    let bytes_to_client = match transferred {
         Ok(bytes) => bytes,
         Err(_) => {
             // Response truncated mid-body — cannot recover, close the connection.
@@ -442,10 +960,12 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
     // Everything else is dropped (closed). An SSE response is not
     // self-delimiting, so a streaming mcp connection is never kept — only the
     // short JSON request/response calls are. ---
-    let reusable_framing = matches!(
-        response_content_length,
-        HttpContentLength::Known(_) | HttpContentLength::Chunked
-    );
+    // A 304 never has a body, whatever its headers say.
+    let reusable_framing = revalidated.is_some()
+        || matches!(
+            response_content_length,
+            HttpContentLength::Known(_) | HttpContentLength::Chunked
+        );
     let (response_read, leftover) = resp_reader.into_read_part();
     if reusable_framing && leftover.get_data().is_empty() && !disconnect_trigger.get_value() {
         let owned = OwnedUpstream {
@@ -454,7 +974,7 @@ pub async fn run_upstream_request(req: UpstreamRequest) {
             disconnect_trigger,
             ssh_handler,
         };
-        pool.release(&proxy_pass_to, owned);
+        pool.release(&target.proxy_pass_to, owned);
     }
 }
 
@@ -487,6 +1007,380 @@ fn classify_upstream_failure(err: &ProxyServerError) -> (&'static [u8], &'static
     }
 }
 
+/// The outlier-detection view of an upstream-response failure — the same split
+/// as [`classify_upstream_failure`].
+fn outlier_failure(err: &ProxyServerError) -> UpstreamFailure {
+    match err {
+        ProxyServerError::NetworkError(e) if e.is_timeout() => UpstreamFailure::Timeout,
+        ProxyServerError::NetworkError(_) => UpstreamFailure::Connect,
+        _ => UpstreamFailure::InvalidResponse,
+    }
+}
+
+/// Where the request is being delivered: the location's upstream first, then
+/// its backups in turn.
+struct DeliveryTarget {
+    proxy_pass_to: ProxyPassToConfig,
+    /// Claim on the balanced upstream the request was picked for; released on
+    /// failing over.
+    upstream_lease: Option<UpstreamLease>,
+    outlier_id: Option<i64>,
+    backups: Option<Arc<LocationBackups>>,
+    backups_tried: usize,
+    split: Option<Arc<LocationSplit>>,
+}
+
+impl DeliveryTarget {
+    /// Count a failed delivery against the current target. Only the final
+    /// outcome of a delivery is reported — an attempt that was replayed on a
+    /// fresh connection is a stale kept-alive connection, not a failing
+    /// upstream.
+    fn report_failure(&self, failure: UpstreamFailure) {
+        self.report_to_split(true);
+        self.report_to_outliers(failure);
+    }
+
+    /// Count a failed attempt against the current target for outlier
+    /// detection only — on its own for an attempt that is retried, since the
+    /// split counts a request once, by how it ends.
+    fn report_to_outliers(&self, failure: UpstreamFailure) {
+        let Some(outlier_id) = self.outlier_id else {
+            return;
+        };
+
+        crate::app::APP_CTX.upstream_outliers.register_failure(
+            outlier_id,
+            OutlierProtocol::H1,
+            &format!("{}#{}", self.proxy_pass_to.to_string(), outlier_id),
+            failure,
+        );
+    }
+
+    fn report_success(&self) {
+        self.report_to_split(false);
+
+        if let Some(outlier_id) = self.outlier_id {
+            crate::app::APP_CTX
+                .upstream_outliers
+                .register_success(outlier_id);
+        }
+    }
+
+    /// Counts the outcome against the split branch the request was picked
+    /// for. A backup answering after a fail over is no branch's outcome.
+    fn report_to_split(&self, failed: bool) {
+        if let (Some(split), Some(lease)) = (self.split.as_ref(), self.upstream_lease.as_ref()) {
+            split.report(lease.get_index(), failed);
+        }
+    }
+
+    fn fails_over_on(&self, status: u16) -> bool {
+        match self.backups.as_ref() {
+            Some(backups) => backups.fails_over_on(status),
+            None => false,
+        }
+    }
+
+    /// Switch to the next backup outlier detection admits. Returns the target
+    /// that failed, or `None` when no backup is left or the request can no
+    /// longer be re-sent in full (its body outgrew [`REPLAY_WINDOW`]).
+    fn fail_over(&mut self, body: &RequestBodyPump) -> Option<ProxyPassToConfig> {
+        if !body.is_replayable() {
+            return None;
+        }
+
+        let (index, backup) = self.backups.as_ref()?.next(self.backups_tried, |itm| {
+            crate::app::APP_CTX.upstream_outliers.admit(itm.id)
+        })?;
+        let backup = backup.clone();
+        self.backups_tried = index + 1;
+        self.outlier_id = Some(backup.id);
+        drop(self.upstream_lease.take());
+
+        Some(std::mem::replace(
+            &mut self.proxy_pass_to,
+            backup.proxy_pass_to.clone(),
+        ))
+    }
+}
+
+/// The location's `retry` applied to one request.
+struct RetryState {
+    policy: Option<(Arc<LocationRetry>, ProxyLogScope)>,
+    /// Retries made on the target `backups_tried` of [`DeliveryTarget`]
+    /// points at — every backup gets the policy's attempts anew.
+    target: usize,
+    retries: u32,
+}
+
+impl RetryState {
+    fn new(
+        retry: Option<Arc<LocationRetry>>,
+        endpoint: &str,
+        location_id: i64,
+        ip: &Option<String>,
+    ) -> Self {
+        let policy = retry.map(|retry| {
+            retry.register_request();
+            let log_scope =
+                ProxyLogScope::new(Arc::new(endpoint.to_string()), location_id, ip.clone());
+            (retry, log_scope)
+        });
+
+        Self {
+            policy,
+            target: 0,
+            retries: 0,
+        }
+    }
+
+    /// The back-off to wait before the request goes to `target` again after
+    /// an attempt failed this way. `None` when it is not retried: the policy
+    /// does not cover the failure, its attempts are used up, the budget is
+    /// spent or the body can no longer be re-sent in full.
+    fn next_retry(
+        &mut self,
+        failure: RetryFailure,
+        reason: &str,
+        target: &DeliveryTarget,
+        body: &RequestBodyPump,
+    ) -> Option<Duration> {
+        let (retry, log_scope) = self.policy.as_ref()?;
+
+        if self.target != target.backups_tried {
+            self.target = target.backups_tried;
+            self.retries = 0;
+        }
+
+        if !retry.retries_on(failure)
+            || !body.is_replayable()
+            || self.retries + 1 >= retry.get_attempts()
+        {
+            return None;
+        }
+
+        if !retry.try_spend() {
+            crate::app::APP_CTX
+                .prometheus
+                .inc_retries_refused(retry.get_label());
+            return None;
+        }
+
+        self.retries += 1;
+        crate::app::APP_CTX
+            .prometheus
+            .inc_retries(retry.get_label());
+
+        let backoff = retry.next_backoff(self.retries);
+        log_scope.write_always(format!(
+            "upstream {} {}; retry {} of {} in {}ms",
+            target.proxy_pass_to.to_string(),
+            reason,
+            self.retries,
+            retry.get_attempts() - 1,
+            backoff.as_millis()
+        ));
+
+        Some(backoff)
+    }
+}
+
+/// The retry view of an upstream-response failure. A response that is not
+/// HTTP is not retried.
+fn retry_failure(err: &ProxyServerError) -> Option<RetryFailure> {
+    match err {
+        ProxyServerError::NetworkError(e) if e.is_timeout() => Some(RetryFailure::Timeout),
+        ProxyServerError::NetworkError(_) => Some(RetryFailure::Connect),
+        _ => None,
+    }
+}
+
+/// Adds a header to a compiled head, which ends with the empty line.
+fn push_header_to_head(head: &mut Vec<u8>, name: &str, value: &str) {
+    head.truncate(head.len() - crate::consts::HTTP_CR_LF.len());
+    head.extend_from_slice(name.as_bytes());
+    head.extend_from_slice(b": ");
+    head.extend_from_slice(value.as_bytes());
+    head.extend_from_slice(crate::consts::HTTP_CR_LF);
+    head.extend_from_slice(crate::consts::HTTP_CR_LF);
+}
+
+fn write_failover(
+    endpoint: &str,
+    location_id: i64,
+    ip: &Option<String>,
+    failed: &ProxyPassToConfig,
+    target: &DeliveryTarget,
+    reason: &str,
+) {
+    crate::app::APP_CTX.proxy_logs.write(
+        endpoint,
+        Some(location_id),
+        ip.clone(),
+        format!(
+            "upstream {} {}; failing over to backup {}",
+            failed.to_string(),
+            reason,
+            target.proxy_pass_to.to_string()
+        ),
+    );
+}
+
+/// A delivery attempt that failed, and what may be done about it.
+struct FailedAttempt<'s> {
+    /// The failure as the location's `retry` sees it; `None` when no retry
+    /// covers it.
+    retry_on: Option<RetryFailure>,
+    /// The request may go on to the next backup.
+    fails_over: bool,
+    outlier_failure: UpstreamFailure,
+    /// What the upstream did, for the retry and fail over log lines.
+    reason: &'s str,
+    /// The status and the line logged when the request is answered.
+    status: u16,
+    log_line: String,
+    /// The error page the request is answered with.
+    page: &'s [u8],
+}
+
+/// How a request goes on after a failed attempt.
+enum AfterFailure {
+    /// It is sent again: after the retry's back-off, or to the next backup.
+    Resend,
+    /// It has been answered.
+    Answered,
+}
+
+/// What a request that failed for good is answered with, and where.
+struct FailureAnswer<'s> {
+    response_tx: &'s mpsc::Sender<ResponseEvent>,
+    is_mcp: bool,
+    stale: Option<&'s StaleRequest>,
+    end_point_info: &'s HttpEndpointInfo,
+    http_connection_info: &'s HttpConnectionInfo,
+    cors_headers: Option<&'s [(&'static str, String)]>,
+    endpoint: &'s str,
+    location_id: i64,
+    ip: &'s Option<String>,
+}
+
+impl FailureAnswer<'_> {
+    /// A failed attempt is retried while the location's `retry` allows, then
+    /// failed over to the next backup, and answered once neither is left —
+    /// with the location's last good response, or the error page.
+    async fn handle_failed_attempt(
+        &self,
+        failed: FailedAttempt<'_>,
+        retry: &mut RetryState,
+        target: &mut DeliveryTarget,
+        body: &mut RequestBodyPump,
+    ) -> AfterFailure {
+        let backoff = failed
+            .retry_on
+            .and_then(|failure| retry.next_retry(failure, failed.reason, target, body));
+        if let Some(backoff) = backoff {
+            target.report_to_outliers(failed.outlier_failure);
+            tokio::time::sleep(backoff).await;
+            return AfterFailure::Resend;
+        }
+
+        target.report_failure(failed.outlier_failure);
+
+        if failed.fails_over {
+            if let Some(previous) = target.fail_over(body) {
+                write_failover(
+                    self.endpoint,
+                    self.location_id,
+                    self.ip,
+                    &previous,
+                    target,
+                    failed.reason,
+                );
+                return AfterFailure::Resend;
+            }
+        }
+
+        crate::app::APP_CTX.proxy_logs.write_returned_5xx(
+            self.endpoint,
+            Some(self.location_id),
+            self.ip.clone(),
+            failed.status,
+            failed.log_line,
+        );
+
+        if !self.serve_kept(body).await {
+            fail_request(self.response_tx, self.is_mcp, failed.page, body).await;
+        }
+
+        AfterFailure::Answered
+    }
+
+    /// Answers a request the upstream failed with the location's last good
+    /// response, when its stale store keeps one young enough. `false` when it
+    /// does not — the request is failed as usual then.
+    async fn serve_kept(&self, body: &mut RequestBodyPump) -> bool {
+        let Some(stale) = self.stale else {
+            return false;
+        };
+
+        let Some(kept) = stale.find_on_error() else {
+            return false;
+        };
+
+        let (mut bytes, payload) = super::compile_cached_response(
+            &kept,
+            stale.is_head(),
+            self.end_point_info,
+            self.http_connection_info,
+        );
+        bytes.extend_from_slice(payload);
+        super::apply_cors_headers(&mut bytes, self.cors_headers);
+
+        let stays_synced = body.discard_rest().await;
+
+        if self
+            .response_tx
+            .send(ResponseEvent::Chunk(bytes))
+            .await
+            .is_ok()
+        {
+            let _ = match stays_synced {
+                true => self.response_tx.send(ResponseEvent::Done).await,
+                false => self.response_tx.send(ResponseEvent::Abort).await,
+            };
+        }
+
+        true
+    }
+}
+
+/// A response on its way to the client that the location's cache, stale
+/// store or both keep once its body is read in full.
+struct ResponseToStore<'s> {
+    status: u16,
+    headers: Vec<(String, String)>,
+    to_cache: Option<(&'s CacheRequest, Duration)>,
+    to_stale: Option<&'s StaleRequest>,
+}
+
+impl ResponseToStore<'_> {
+    fn get_max_body_size(&self) -> usize {
+        let to_cache = self.to_cache.map(|(itm, _)| itm.get_max_body_size());
+        let to_stale = self.to_stale.map(|itm| itm.get_max_body_size());
+        to_cache.max(to_stale).unwrap_or_default()
+    }
+
+    fn store(self, body: Vec<u8>) {
+        if let Some(stale) = self.to_stale {
+            stale.store(self.status, self.headers.clone(), body.clone());
+        }
+
+        if let Some((cache, fresh_for)) = self.to_cache {
+            cache.store(self.status, self.headers, body, fresh_for);
+        }
+    }
+}
+
 /// Finish a request that failed before the client received a single byte.
 ///
 /// For a browser-facing location that means substituting an error page. For mcp
@@ -647,6 +1541,61 @@ mod tests {
         assert_eq!(body.total, 6);
     }
 
+    /// The mirror gets the body only once it is complete, and not at all once
+    /// it outgrew the mirror's cap.
+    #[tokio::test]
+    async fn the_mirror_copy_is_the_whole_body_within_its_cap() {
+        let mut body = body_of(vec![b"aa".to_vec(), b"bb".to_vec()]);
+        body.copy_for_mirror(4);
+        assert!(body.take_mirror_copy().is_none());
+
+        let mut upstream = RecordingUpstream::new();
+        assert!(body.stream(&mut upstream, TIMEOUT).await);
+        assert_eq!(body.take_mirror_copy(), Some(b"aabb".to_vec()));
+
+        let mut over_cap = body_of(vec![b"aa".to_vec(), b"bbb".to_vec()]);
+        over_cap.copy_for_mirror(4);
+        assert!(over_cap.stream(&mut upstream, TIMEOUT).await);
+        assert!(over_cap.take_mirror_copy().is_none());
+    }
+
+    /// A body past its limit is cut off before the chunk that outgrows it, and
+    /// the rest of it is not read.
+    #[tokio::test]
+    async fn a_body_past_its_limit_is_not_forwarded_on() {
+        let mut body = body_of(vec![
+            b"5\r\nfirst\r\n".to_vec(),
+            b"6\r\nsecond\r\n".to_vec(),
+            b"5\r\nthird\r\n0\r\n\r\n".to_vec(),
+        ]);
+        body.limit_size(8);
+        let mut upstream = RecordingUpstream::new();
+
+        assert!(!body.stream(&mut upstream, TIMEOUT).await);
+        assert!(body.is_too_large());
+        assert_eq!(upstream.written(), b"5\r\nfirst\r\n".to_vec());
+        assert!(!body.discard_rest().await);
+    }
+
+    /// The limit is on the payload, as it is for a body that is collected: a
+    /// chunked body exactly at it goes through, framing and all.
+    #[tokio::test]
+    async fn a_chunked_body_at_its_limit_is_forwarded_whole() {
+        let chunks = vec![
+            b"5\r\nfirst\r\n".to_vec(),
+            b"6\r\nsec".to_vec(),
+            b"ond\r\n0\r\n\r\n".to_vec(),
+        ];
+        let mut body = body_of(chunks.clone());
+        body.limit_size(11);
+        let mut upstream = RecordingUpstream::new();
+
+        assert!(body.stream(&mut upstream, TIMEOUT).await);
+        assert!(!body.is_too_large());
+        assert_eq!(upstream.written(), chunks.concat());
+        assert!(body.completed);
+    }
+
     /// The rest of a body has to come off the client socket before the connection
     /// can serve anything else; an oversized remainder is not worth reading.
     #[tokio::test]
@@ -1,6 +1,8 @@
 use std::sync::Arc;
 
-use crate::configurations::{HttpEndpointInfo, ProxyPassToConfig};
+use crate::configurations::{
+    HttpEndpointInfo, LocationResponseRewrite, ProxyPassToConfig, UpstreamLease,
+};
 use crate::h1_remote_connection::{OwnedUpstream, Upstream, UpstreamInner};
 use crate::network_stream::*;
 use crate::tcp_utils::{copy_streams, LoopBuffer, WsDirection, WsTrafficRecorder};
@@ -12,9 +14,16 @@ use super::super::{H1HeadersKind, H1Reader, H1Writer, HttpConnectionInfo};
 /// connection entry, which reunites it with the reclaimed client halves.
 pub struct UpgradeContext {
     pub proxy_pass_to: ProxyPassToConfig,
+    /// Counts the handshake against the picked upstream of a balanced
+    /// location. Released once the tunnel is up — a long-lived tunnel is not
+    /// an outstanding request.
+    pub upstream_lease: Option<UpstreamLease>,
     pub end_point_info: Arc<HttpEndpointInfo>,
     pub http_connection_info: HttpConnectionInfo,
     pub location_id: i64,
+    /// The location's `proxy_redirect` / `proxy_cookie_*` rules — for the
+    /// answer of an upstream that refuses the upgrade.
+    pub response_rewrite: Option<Arc<LocationResponseRewrite>>,
     /// Compiled request head (with the Upgrade headers).
     pub head: Vec<u8>,
     pub write_timeout: std::time::Duration,
@@ -71,7 +80,7 @@ pub async fn run_ws_tunnel<
 
     let response_is_upgrade = match resp_reader.compile_headers(
         resp_headers,
-        H1HeadersKind::Response(&ctx.end_point_info),
+        H1HeadersKind::Response(&ctx.end_point_info, ctx.response_rewrite.as_deref()),
         &ctx.http_connection_info,
         &None,
         None,
@@ -1,7 +1,10 @@
 #![allow(warnings)]
 use my_ssh::ssh2::DisconnectCode::ProtocolError;
 
-use crate::{google_auth::GoogleAuthError, network_stream::NetworkError};
+use crate::{
+    configurations::RequestLimitExceeded, google_auth::GoogleAuthError,
+    network_stream::NetworkError,
+};
 
 #[derive(Debug)]
 pub enum ProxyServerError {
@@ -30,6 +33,9 @@ pub enum ProxyServerError {
     /// request). Answered with 421 so the client re-opens a dedicated
     /// connection and performs mTLS against this endpoint's CA.
     MisdirectedClientCertRequired,
+    /// The request is over one of the endpoint's or the location's
+    /// `max_header_size`, `max_headers`, `max_uri_length` or `max_body_size`.
+    RequestLimitExceeded(RequestLimitExceeded),
 }
 
 /// Everything `serve_reverse_proxy` needs to do with a failed request, derived
@@ -140,6 +146,21 @@ impl ProxyServerError {
                 register_ip_failure: false,
             },
 
+            // Over a size limit — answered, then closed: the rest of the
+            // request is left unread. An oversized request is as likely abuse
+            // as malformed framing, so it counts against the source too.
+            Self::RequestLimitExceeded(exceeded) => ErrorHandling {
+                page: Some(match exceeded {
+                    RequestLimitExceeded::HeaderSize(_) | RequestLimitExceeded::Headers(_) => {
+                        tpl::REQUEST_HEADER_TOO_LARGE.as_slice()
+                    }
+                    RequestLimitExceeded::UriLength(_) => tpl::URI_TOO_LONG.as_slice(),
+                    RequestLimitExceeded::BodySize(_) => tpl::PAYLOAD_TOO_LARGE.as_slice(),
+                }),
+                status_5xx: None,
+                register_ip_failure: true,
+            },
+
             // A fully-formed response the proxy itself produced — pass it through.
             Self::HttpResponse(payload) => ErrorHandling {
                 page: Some(payload.as_slice()),
@@ -275,6 +296,19 @@ mod tests {
         assert!(!h.register_ip_failure);
     }
 
+    #[test]
+    fn a_request_over_a_size_limit_is_answered_and_penalises_source() {
+        let h = ProxyServerError::RequestLimitExceeded(RequestLimitExceeded::BodySize(1024))
+            .error_handling();
+        assert_eq!(h.page, Some(tpl::PAYLOAD_TOO_LARGE.as_slice()));
+        assert_eq!(h.status_5xx, None);
+        assert!(h.register_ip_failure);
+
+        let h = ProxyServerError::RequestLimitExceeded(RequestLimitExceeded::Headers(100))
+            .error_handling();
+        assert_eq!(h.page, Some(tpl::REQUEST_HEADER_TOO_LARGE.as_slice()));
+    }
+
     #[test]
     fn http_response_passes_payload_through() {
         let payload = b"HTTP/1.1 418 I am a teapot\r\n\r\n".to_vec();
@@ -0,0 +1,103 @@
+/// Counts the payload a chunked body carries as its raw bytes go by — chunk
+/// sizes, extensions, line ends and trailers are framing and do not count.
+///
+/// The bytes come in whatever pieces the reader relays them in, so a chunk
+/// header may be split anywhere. Malformed framing is the reader's to reject;
+/// here it is only skipped over.
+pub struct ChunkedPayloadCounter {
+    state: ChunkedState,
+    payload: usize,
+}
+
+#[derive(Clone, Copy)]
+enum ChunkedState {
+    /// Reading a chunk-size line: the size so far.
+    Size(usize),
+    /// Past the `;` of a chunk extension: the size read before it.
+    Extension(usize),
+    /// Inside a chunk's data: the bytes of it still to come.
+    Data(usize),
+    /// Past a chunk's data, before the line end that closes it.
+    DataEnd,
+    /// The last chunk went by; only trailers can follow.
+    Trailers,
+}
+
+impl ChunkedPayloadCounter {
+    pub fn new() -> Self {
+        Self {
+            state: ChunkedState::Size(0),
+            payload: 0,
+        }
+    }
+
+    /// Take the next raw bytes of the body; returns the payload counted so far.
+    pub fn feed(&mut self, mut bytes: &[u8]) -> usize {
+        while let Some(&b) = bytes.first() {
+            let mut consumed = 1;
+
+            self.state = match (self.state, b) {
+                (ChunkedState::Data(remaining), _) => {
+                    consumed = remaining.min(bytes.len());
+                    self.payload += consumed;
+                    if consumed == remaining {
+                        ChunkedState::DataEnd
+                    } else {
+                        ChunkedState::Data(remaining - consumed)
+                    }
+                }
+                (ChunkedState::Trailers, _) => break,
+                (ChunkedState::Size(0) | ChunkedState::Extension(0), b'\n') => {
+                    ChunkedState::Trailers
+                }
+                (ChunkedState::Size(size) | ChunkedState::Extension(size), b'\n') => {
+                    ChunkedState::Data(size)
+                }
+                (ChunkedState::Size(size), b';') => ChunkedState::Extension(size),
+                (ChunkedState::Size(size), _) => match (b as char).to_digit(16) {
+                    Some(digit) => {
+                        ChunkedState::Size(size.saturating_mul(16).saturating_add(digit as usize))
+                    }
+                    None => ChunkedState::Size(size),
+                },
+                (ChunkedState::Extension(size), _) => ChunkedState::Extension(size),
+                (ChunkedState::DataEnd, b'\n') => ChunkedState::Size(0),
+                (ChunkedState::DataEnd, _) => ChunkedState::DataEnd,
+            };
+
+            bytes = &bytes[consumed..];
+        }
+
+        self.payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"5\r\nfirst\r\n6;ext=1\r\nsecond\r\n0\r\nTrailer: x\r\n\r\n";
+
+    #[test]
+    fn only_the_chunk_data_is_counted() {
+        let mut counter = ChunkedPayloadCounter::new();
+        assert_eq!(counter.feed(BODY), 11);
+    }
+
+    /// The reader relays the body in arbitrary pieces: a header split across
+    /// them is still read as one.
+    #[test]
+    fn framing_split_anywhere_is_counted_the_same() {
+        let mut counter = ChunkedPayloadCounter::new();
+        let mut payload = 0;
+        for b in BODY {
+            payload = counter.feed(std::slice::from_ref(b));
+        }
+        assert_eq!(payload, 11);
+
+        let mut counter = ChunkedPayloadCounter::new();
+        assert_eq!(counter.feed(&BODY[..1]), 0);
+        assert_eq!(counter.feed(&BODY[1..6]), 3);
+        assert_eq!(counter.feed(&BODY[6..]), 11);
+    }
+}
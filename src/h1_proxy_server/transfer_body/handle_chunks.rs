@@ -38,7 +38,7 @@ pub async fn transfer_chunked_body<
     }
 }
 
-async fn read_chunk_header<ReadPart: NetworkStreamReadPart + Send + Sync + 'static>(
+pub(super) async fn read_chunk_header<ReadPart: NetworkStreamReadPart + Send + Sync + 'static>(
     read_stream: &mut ReadPart,
     loop_buffer: &mut LoopBuffer,
     timeouts: HttpTimeouts,
@@ -4,6 +4,10 @@ mod handle_chunks;
 pub use handle_chunks::*;
 mod chunk_header;
 pub use chunk_header::*;
+mod transfer_filtered_body;
+pub use transfer_filtered_body::*;
+mod chunked_payload_counter;
+pub use chunked_payload_counter::*;
 
 #[cfg(test)]
 pub mod test_utils;
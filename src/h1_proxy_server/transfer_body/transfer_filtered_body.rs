@@ -0,0 +1,247 @@
+use crate::{
+    body_filter::ResponseBodyFilter, h1_proxy_server::*, h1_utils::HttpContentLength,
+    network_stream::*, tcp_utils::*, types::HttpTimeouts,
+};
+
+use super::*;
+
+/// Relays a response body through the location's `sub_filter` and/or
+/// `compress`. The body is read in the framing the upstream sent it in, and what
+/// the filter makes of it goes on chunked — its length is not known until it
+/// has ended. Returns the bytes written to the client.
+pub async fn transfer_filtered_body<
+    ReadPart: NetworkStreamReadPart + Send + Sync + 'static,
+    WritePart: H1Writer + Send + Sync + 'static,
+>(
+    connection_id: u64,
+    read_stream: &mut ReadPart,
+    write_stream: &mut WritePart,
+    loop_buffer: &mut LoopBuffer,
+    content_length: HttpContentLength,
+    filter: &mut ResponseBodyFilter,
+    timeouts: HttpTimeouts,
+) -> Result<usize, ProxyServerError> {
+    let mut total = 0usize;
+
+    match content_length {
+        HttpContentLength::None => {}
+        HttpContentLength::Known(size) => {
+            total += filter_payload(
+                connection_id,
+                read_stream,
+                write_stream,
+                loop_buffer,
+                size,
+                filter,
+                timeouts,
+            )
+            .await?;
+        }
+        HttpContentLength::Chunked => loop {
+            let chunk_header = read_chunk_header(read_stream, loop_buffer, timeouts).await?;
+            loop_buffer.commit_read(chunk_header.len + crate::consts::HTTP_CR_LF.len());
+
+            if chunk_header.chunk_size > 0 {
+                total += filter_payload(
+                    connection_id,
+                    read_stream,
+                    write_stream,
+                    loop_buffer,
+                    chunk_header.chunk_size,
+                    filter,
+                    timeouts,
+                )
+                .await?;
+            }
+
+            // The CRLF after the chunk's data — or the one ending the body.
+            fill_buffer(
+                read_stream,
+                loop_buffer,
+                crate::consts::HTTP_CR_LF.len(),
+                timeouts,
+            )
+            .await?;
+            loop_buffer.commit_read(crate::consts::HTTP_CR_LF.len());
+
+            if chunk_header.chunk_size == 0 {
+                break;
+            }
+        },
+    }
+
+    let rest = filter
+        .finish()
+        .map_err(|_| ProxyServerError::ParsingPayloadError("Response body can not be filtered"))?;
+    total += write_chunk(connection_id, write_stream, &rest, timeouts).await?;
+
+    write_stream
+        .write_http_payload(connection_id, b"0\r\n\r\n", timeouts.write_timeout)
+        .await
+        .map_err(ProxyServerError::CanNotWriteContentToRemoteConnection)?;
+
+    Ok(total + b"0\r\n\r\n".len())
+}
+
+/// Takes `size` bytes of payload off the upstream and writes what the filter
+/// makes of them as they arrive.
+async fn filter_payload<
+    ReadPart: NetworkStreamReadPart + Send + Sync + 'static,
+    WritePart: H1Writer + Send + Sync + 'static,
+>(
+    connection_id: u64,
+    read_stream: &mut ReadPart,
+    write_stream: &mut WritePart,
+    loop_buffer: &mut LoopBuffer,
+    mut remaining_size: usize,
+    filter: &mut ResponseBodyFilter,
+    timeouts: HttpTimeouts,
+) -> Result<usize, ProxyServerError> {
+    let mut total = 0;
+
+    while remaining_size > 0 {
+        fill_buffer(read_stream, loop_buffer, 1, timeouts).await?;
+
+        let read_buf = loop_buffer.get_data();
+        let to_filter = read_buf
+            .len()
+            .min(remaining_size)
+            .min(crate::consts::BODY_RELAY_CHUNK_SIZE);
+
+        let filtered = filter.push(&read_buf[..to_filter]).map_err(|_| {
+            ProxyServerError::ParsingPayloadError("Response body can not be filtered")
+        })?;
+
+        loop_buffer.commit_read(to_filter);
+        remaining_size -= to_filter;
+
+        total += write_chunk(connection_id, write_stream, &filtered, timeouts).await?;
+    }
+
+    Ok(total)
+}
+
+/// Reads until the buffer holds at least `size` bytes.
+async fn fill_buffer<ReadPart: NetworkStreamReadPart + Send + Sync + 'static>(
+    read_stream: &mut ReadPart,
+    loop_buffer: &mut LoopBuffer,
+    size: usize,
+    timeouts: HttpTimeouts,
+) -> Result<(), ProxyServerError> {
+    while loop_buffer.get_data().len() < size {
+        let Some(buffer) = loop_buffer.get_mut() else {
+            println!("Buffer allocation fail - transfer_filtered_body");
+            return Err(ProxyServerError::BufferAllocationFail);
+        };
+
+        let read_size = read_stream
+            .read_with_timeout(buffer, timeouts.read_timeout)
+            .await?;
+
+        loop_buffer.advance(read_size);
+    }
+
+    Ok(())
+}
+
+/// Writes `payload` as one chunk. Nothing for an empty one — that would end
+/// the body.
+async fn write_chunk<WritePart: H1Writer + Send + Sync + 'static>(
+    connection_id: u64,
+    write_stream: &mut WritePart,
+    payload: &[u8],
+    timeouts: HttpTimeouts,
+) -> Result<usize, ProxyServerError> {
+    if payload.is_empty() {
+        return Ok(0);
+    }
+
+    let mut chunk = format!("{:x}\r\n", payload.len()).into_bytes();
+    chunk.extend_from_slice(payload);
+    chunk.extend_from_slice(crate::consts::HTTP_CR_LF);
+
+    write_stream
+        .write_http_payload(connection_id, &chunk, timeouts.write_timeout)
+        .await
+        .map_err(ProxyServerError::CanNotWriteContentToRemoteConnection)?;
+
+    Ok(chunk.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::test_utils::*;
+    use super::*;
+    use crate::body_filter::{SubFilter, SubFilterRule};
+
+    fn start_filter() -> ResponseBodyFilter {
+        let sub_filter = Arc::new(SubFilter::new(
+            vec![SubFilterRule::parse("http://10.0.0.5", "https://example.com").unwrap()],
+            vec!["text/html".to_string()],
+        ))
+        .start(Some("text/html"), None);
+
+        ResponseBodyFilter::new(sub_filter, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_chunked_body_is_filtered_and_reframed() {
+        let wire = b"6\r\n<a hre\r\n16\r\nf=\"http://10.0.0.5/\">\n\r\n0\r\n\r\nGET /next".to_vec();
+        let mut loop_buffer = crate::tcp_utils::LoopBuffer::new();
+        let mut source = FakeSource::with_max_read(wire, 5);
+        let mut sink = RelaySink::new();
+        let mut filter = start_filter();
+
+        let transferred = transfer_filtered_body(
+            0,
+            &mut source,
+            &mut sink,
+            &mut loop_buffer,
+            HttpContentLength::Chunked,
+            &mut filter,
+            test_timeouts(),
+        )
+        .await
+        .unwrap();
+
+        let written = sink.written();
+        assert_eq!(
+            crate::h1_proxy_server::decode_chunked_body(&written).unwrap(),
+            b"<a href=\"https://example.com/\">\n"
+        );
+        assert_eq!(transferred, written.len());
+        assert!(
+            b"GET /next".starts_with(loop_buffer.get_data()),
+            "nothing past the body is taken"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_known_size_body_is_filtered_and_sent_chunked() {
+        let body = b"go to http://10.0.0.5/a";
+        let mut loop_buffer = preloaded_buffer(body);
+        let mut source = FakeSource::exhausted();
+        let mut sink = RelaySink::new();
+        let mut filter = start_filter();
+
+        transfer_filtered_body(
+            0,
+            &mut source,
+            &mut sink,
+            &mut loop_buffer,
+            HttpContentLength::Known(body.len()),
+            &mut filter,
+            test_timeouts(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(sink.written()).unwrap(),
+            "1b\r\ngo to https://example.com/a\r\n0\r\n\r\n"
+        );
+        assert_eq!(source.reads, 0);
+    }
+}
@@ -180,10 +180,11 @@ impl Upstream {
             }
             ProxyPassToConfig::FilesPath(_)
             | ProxyPassToConfig::Static(_)
+            | ProxyPassToConfig::Redirect(_)
             | ProxyPassToConfig::Drop
             | ProxyPassToConfig::DynamicProxy(_) => {
                 unreachable!(
-                    "connect_owned is only for network upstreams; Static/LocalFiles/Drop/DynamicProxy are handled by the reader before the pool"
+                    "connect_owned is only for network upstreams; Static/Redirect/LocalFiles/Drop/DynamicProxy are handled by the reader before the pool"
                 );
             }
         }
@@ -21,4 +21,18 @@ impl<'s> H1HeadersFirstLine<'s> {
 
         (verb, path)
     }
+
+    /// Status code of a response first line (`HTTP/1.1 502 Bad Gateway`).
+    pub fn get_status_code(&self) -> Option<u16> {
+        let index_start = self.data.find_byte_pos(b' ', 0)? + 1;
+        let index_end = index_start + 3;
+        if self.data.len() < index_end {
+            return None;
+        }
+
+        std::str::from_utf8(&self.data[index_start..index_end])
+            .ok()?
+            .parse()
+            .ok()
+    }
 }
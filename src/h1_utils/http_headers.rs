@@ -15,6 +15,8 @@ pub struct Http1Headers {
     pub host_value: Option<HeaderPosition>,
     pub cookie_value: Option<HeaderPosition>,
     pub upgrade_value: Option<HeaderPosition>,
+    /// How many header lines the head has.
+    pub headers_count: usize,
 }
 
 impl Http1Headers {
@@ -37,6 +39,7 @@ impl Http1Headers {
         let mut cookie_value = None;
         let mut upgrade_value = None;
         let mut content_length = HttpContentLength::None;
+        let mut headers_count = 0;
 
         let mut header_start_pos = first_line_end + crate::consts::HTTP_CR_LF.len();
         loop {
@@ -49,11 +52,13 @@ impl Http1Headers {
                     cookie_value,
                     upgrade_value,
                     content_length,
+                    headers_count,
                     end: end + crate::consts::HTTP_CR_LF.len(),
                 });
             }
 
             let http_header = HttpHeader::new(src, header_start_pos, end)?;
+            headers_count += 1;
 
             if http_header.is_my_header_name(HOST_HEADER) {
                 host_value = Some(http_header.get_value());
@@ -144,6 +149,7 @@ mod tests {
         assert!(headers.is_some());
         let headers = headers.unwrap();
         assert_eq!(headers.content_length, HttpContentLength::Known(1024));
+        assert_eq!(headers.headers_count, 2);
     }
 
     #[test]
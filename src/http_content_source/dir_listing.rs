@@ -0,0 +1,222 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::oauth::{html_escape, percent_encode};
+use crate::response_cache::format_http_date;
+
+use super::FileResponse;
+
+/// An entry of a directory being listed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Seconds since the unix epoch.
+    pub modified: Option<u64>,
+}
+
+impl DirEntry {
+    /// One line of `find -printf '%y\t%s\t%T@\t%f\n'` — the way the entries
+    /// of a directory come over ssh and over a gateway alike.
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(4, '\t');
+        let kind = parts.next()?;
+        let size = parts.next()?.parse().ok()?;
+        let modified = parts.next()?.split('.').next()?.parse().ok();
+        let name = parts.next()?;
+
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            name: name.to_string(),
+            is_dir: kind == "d",
+            size,
+            modified,
+        })
+    }
+
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\n",
+            if self.is_dir { "d" } else { "f" },
+            self.size,
+            self.modified.unwrap_or_default(),
+            self.name
+        )
+    }
+}
+
+pub fn parse_dir_entries(src: &[u8]) -> Vec<DirEntry> {
+    String::from_utf8_lossy(src)
+        .lines()
+        .filter_map(DirEntry::parse_line)
+        .collect()
+}
+
+pub fn serialize_dir_entries(entries: &[DirEntry]) -> Vec<u8> {
+    entries
+        .iter()
+        .map(|itm| itm.to_line())
+        .collect::<String>()
+        .into_bytes()
+}
+
+pub async fn read_local_dir(path: &str) -> std::io::Result<Vec<DirEntry>> {
+    let mut result = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(path).await?;
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+
+        result.push(DirEntry {
+            name: entry.file_name().to_string_lossy().to_string(),
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|itm| itm.duration_since(UNIX_EPOCH).ok())
+                .map(|itm| itm.as_secs()),
+        });
+    }
+
+    Ok(result)
+}
+
+/// What `autoindex` answers a directory with: its entries — hidden ones left
+/// out — directories first, each group by name.
+pub struct DirListing {
+    /// The request path of the directory, `/` at the end.
+    pub path: String,
+    pub entries: Vec<DirEntry>,
+}
+
+impl DirListing {
+    pub fn new(path: &str, mut entries: Vec<DirEntry>) -> Self {
+        entries.retain(|itm| !itm.name.starts_with('.'));
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        let mut path = path.to_string();
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+
+        Self { path, entries }
+    }
+
+    /// JSON when the client accepts it, an HTML page otherwise.
+    pub fn to_file_response(&self, accept: Option<&str>) -> FileResponse {
+        let wants_json = accept.is_some_and(|itm| itm.contains("application/json"));
+
+        match wants_json {
+            true => FileResponse::from_content(
+                200,
+                Some("application/json"),
+                self.to_json().into_bytes(),
+            ),
+            false => FileResponse::from_content(
+                200,
+                Some("text/html; charset=utf-8"),
+                self.to_html().into_bytes(),
+            ),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct JsonListing<'s> {
+            path: &'s str,
+            entries: &'s [DirEntry],
+        }
+
+        serde_json::to_string(&JsonListing {
+            path: &self.path,
+            entries: &self.entries,
+        })
+        .unwrap()
+    }
+
+    pub fn to_html(&self) -> String {
+        let title = format!("Index of {}", html_escape(&self.path));
+
+        let mut result = format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{title}</title></head>\n<body>\n<h1>{title}</h1>\n<table>\n"
+        );
+
+        if self.path != "/" {
+            result.push_str("<tr><td><a href=\"../\">../</a></td><td></td><td></td></tr>\n");
+        }
+
+        for entry in self.entries.iter() {
+            let mut href = format!("{}{}", self.path, percent_encode(&entry.name));
+            let mut name = html_escape(&entry.name);
+            let size = match entry.is_dir {
+                true => {
+                    href.push('/');
+                    name.push('/');
+                    "-".to_string()
+                }
+                false => entry.size.to_string(),
+            };
+
+            let modified = entry
+                .modified
+                .map(|itm| format_http_date(SystemTime::UNIX_EPOCH + Duration::from_secs(itm)))
+                .unwrap_or_default();
+
+            result.push_str(&format!(
+                "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                html_escape(&href),
+                name,
+                modified,
+                size
+            ));
+        }
+
+        result.push_str("</table>\n</body>\n</html>\n");
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_listed() {
+        let entries = parse_dir_entries(
+            b"f\t12\t1700000000.5\tb <x>.txt\nd\t4096\t1700000000.0\tdocs\nf\t1\t0\t.env\nbroken\n",
+        );
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            serialize_dir_entries(&entries[..1]),
+            b"f\t12\t1700000000\tb <x>.txt\n"
+        );
+
+        let listing = DirListing::new("/files", entries);
+        assert_eq!(listing.path, "/files/");
+        assert_eq!(
+            listing
+                .entries
+                .iter()
+                .map(|itm| itm.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["docs", "b <x>.txt"]
+        );
+
+        let html = listing.to_html();
+        assert!(html.contains("<a href=\"/files/docs/\">docs/</a>"));
+        assert!(html.contains("<a href=\"/files/b%20%3Cx%3E.txt\">b &lt;x&gt;.txt</a>"));
+
+        assert!(listing
+            .to_json()
+            .starts_with("{\"path\":\"/files/\",\"entries\":[{\"name\":\"docs\",\"is_dir\":true"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::io::SeekFrom;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::body::{Body, Frame, SizeHint};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::mpsc;
+
+use super::{FileBodyPart, FileResponse};
+
+/// Where the bytes of a file being served come from.
+pub enum FileSource {
+    /// A local file, read as the body is sent.
+    File(tokio::fs::File),
+    /// A file downloaded whole: the ssh transport has no ranged read.
+    Memory(Vec<u8>),
+    /// A file on the other side of a gateway, streamed as the body is sent.
+    Gateway(crate::tcp_gateway::FileStreamReader),
+}
+
+/// Produces the body of a [`FileResponse`] piece by piece — never more than
+/// [`crate::consts::BODY_RELAY_CHUNK_SIZE`] of the file at a time, so a large
+/// file is never held in memory.
+pub struct FileBodyReader {
+    source: FileSource,
+    parts: VecDeque<FileBodyPart>,
+}
+
+impl FileBodyReader {
+    pub fn new(source: FileSource, response: &FileResponse) -> Self {
+        Self {
+            source,
+            parts: response.body.iter().cloned().collect(),
+        }
+    }
+
+    /// The reader of the body of `response` — `None` when it has none, or
+    /// when it is not sent, as for a HEAD request.
+    pub fn for_response(
+        source: FileSource,
+        response: &FileResponse,
+        with_body: bool,
+    ) -> Option<Self> {
+        match with_body && !response.body.is_empty() {
+            true => Some(Self::new(source, response)),
+            false => None,
+        }
+    }
+
+    /// The next piece of the body; `None` once it has all been read.
+    pub async fn next_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        let range = match self.parts.pop_front() {
+            Some(FileBodyPart::Bytes(bytes)) => return Ok(Some(bytes)),
+            Some(FileBodyPart::Range(range)) => range,
+            None => return Ok(None),
+        };
+
+        let size = range
+            .get_len()
+            .min(crate::consts::BODY_RELAY_CHUNK_SIZE as u64);
+
+        let chunk = match &mut self.source {
+            FileSource::File(file) => {
+                file.seek(SeekFrom::Start(range.start)).await?;
+                let mut chunk = vec![0u8; size as usize];
+                // A file cut short while it is sent fails here.
+                file.read_exact(&mut chunk).await?;
+                chunk
+            }
+            FileSource::Memory(content) => content
+                .get(range.start as usize..(range.start + size) as usize)
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?
+                .to_vec(),
+            FileSource::Gateway(reader) => reader.read(range.start, size, range.end).await?,
+        };
+
+        if size < range.get_len() {
+            let mut rest = range;
+            rest.start += size;
+            self.parts.push_front(FileBodyPart::Range(rest));
+        }
+
+        Ok(Some(chunk))
+    }
+
+    /// The body for the hyper path: read by a task of its own, a few pieces
+    /// ahead of the client.
+    pub fn into_hyper_body(mut self, content_length: u64) -> BoxBody<Bytes, String> {
+        let (tx, rx) = mpsc::channel(2);
+
+        crate::app::spawn_named("file_body_reader", async move {
+            loop {
+                let chunk = match self.next_chunk().await {
+                    Ok(Some(chunk)) => Ok(Bytes::from(chunk)),
+                    Ok(None) => break,
+                    Err(err) => Err(format!("Can not read the file: {}", err)),
+                };
+
+                let failed = chunk.is_err();
+                if tx.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        FileBody {
+            rx,
+            remaining: content_length,
+        }
+        .boxed()
+    }
+}
+
+/// A file's body on the hyper path, fed by its [`FileBodyReader`].
+struct FileBody {
+    rx: mpsc::Receiver<Result<Bytes, String>>,
+    remaining: u64,
+}
+
+impl Body for FileBody {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        // FileBody is Unpin (every field is), so get_mut is safe.
+        let this = self.get_mut();
+
+        match this.rx.poll_recv(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                this.remaining = this.remaining.saturating_sub(chunk.len() as u64);
+                Poll::Ready(Some(Ok(Frame::data(chunk))))
+            }
+            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_content_source::{FileRequestHeaders, FileVersion};
+
+    #[tokio::test]
+    async fn a_range_is_read_in_pieces() {
+        let size = crate::consts::BODY_RELAY_CHUNK_SIZE + 10;
+        let content: Vec<u8> = (0..size).map(|itm| itm as u8).collect();
+
+        let version = FileVersion {
+            size: size as u64,
+            modified: None,
+        };
+        let request = FileRequestHeaders {
+            range: Some("bytes=5-".to_string()),
+            ..Default::default()
+        };
+        let response = FileResponse::new(&version, None, &request);
+
+        let mut reader = FileBodyReader::new(FileSource::Memory(content.clone()), &response);
+        let mut pieces = Vec::new();
+        while let Some(chunk) = reader.next_chunk().await.unwrap() {
+            pieces.push(chunk);
+        }
+
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces.concat(), content[5..]);
+    }
+}
@@ -0,0 +1,361 @@
+use std::{sync::Arc, time::Instant};
+
+use crate::body_filter::ContentCoding;
+
+use super::{DirEntry, DirListing, FileRequestHeaders, FileResponse, PrecompressedProbes};
+
+/// One entry of `try_files`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TryFilesItem {
+    /// A path of the folder; `$uri` is the request path.
+    Path(String),
+    /// `=404` — the status answered when no path before it is found.
+    Status(u16),
+}
+
+/// What is in a folder at a path.
+pub enum FolderItem<TFile> {
+    File(TFile),
+    Dir,
+    NotFound,
+}
+
+/// A folder files are served from — local, over ssh or over a gateway.
+/// Paths are the folder's own, `/` first.
+#[async_trait::async_trait]
+pub trait FilesFolder {
+    type File: Send;
+    type Error: Send;
+
+    async fn open(&self, path: &str) -> Result<FolderItem<Self::File>, Self::Error>;
+    async fn list_dir(&self, path: &str) -> Result<Vec<DirEntry>, Self::Error>;
+}
+
+pub enum FileLookupResult<TFile> {
+    File {
+        path: String,
+        file: TFile,
+        /// The file is the `.gz`, `.zst` or `.br` sibling of `path`.
+        coding: Option<ContentCoding>,
+    },
+    /// A directory listing or the status of a `try_files` — ready to send.
+    Response(FileResponse),
+    NotFound,
+}
+
+/// `default_file`, `autoindex` and `try_files` of a files location: what of
+/// the folder answers a request path.
+#[derive(Debug, Clone, Default)]
+pub struct FileLookup {
+    /// Served for a directory that has it.
+    pub default_file: Option<String>,
+    /// Lists a directory that has no `default_file`.
+    pub autoindex: bool,
+    /// The paths tried in turn; `[$uri]` when not set.
+    pub try_files: Option<Vec<TryFilesItem>>,
+    /// Serves the precompressed sibling of a file the client accepts.
+    pub precompressed: bool,
+    pub probes: Arc<PrecompressedProbes>,
+}
+
+impl FileLookup {
+    /// `[$uri, $uri/index.html, /index.html]`, `=404` as the last entry only.
+    pub fn parse_try_files(items: &[String]) -> Result<Vec<TryFilesItem>, String> {
+        if items.is_empty() {
+            return Err("try_files can not be empty".to_string());
+        }
+
+        let mut result = Vec::with_capacity(items.len());
+
+        for (index, item) in items.iter().enumerate() {
+            let item = item.trim();
+
+            if let Some(status) = item.strip_prefix('=') {
+                if index != items.len() - 1 {
+                    return Err(format!("try_files: {} can only be the last entry", item));
+                }
+
+                let status = match status.parse::<u16>() {
+                    Ok(status) if (100..=999).contains(&status) => status,
+                    _ => return Err(format!("try_files: {} is not a status code", item)),
+                };
+
+                result.push(TryFilesItem::Status(status));
+                continue;
+            }
+
+            if item.is_empty() {
+                return Err("try_files can not have an empty entry".to_string());
+            }
+
+            result.push(TryFilesItem::Path(item.to_string()));
+        }
+
+        Ok(result)
+    }
+
+    /// Looks `uri_path` up in `folder`. A path that names a directory is
+    /// answered with its `default_file`, or with its listing when `autoindex`
+    /// is on — otherwise the next path is tried. A listing is JSON for a
+    /// client that accepts it. A path that climbs out of the folder is never
+    /// looked up.
+    pub async fn lookup<TFolder: FilesFolder + Sync>(
+        &self,
+        folder: &TFolder,
+        uri_path: &str,
+        request: &FileRequestHeaders,
+    ) -> Result<FileLookupResult<TFolder::File>, TFolder::Error> {
+        let default_try_files = [TryFilesItem::Path("$uri".to_string())];
+        let try_files = self.try_files.as_deref().unwrap_or(&default_try_files);
+
+        for item in try_files {
+            let path = match item {
+                TryFilesItem::Path(path) => path.replace("$uri", uri_path),
+                TryFilesItem::Status(status) => {
+                    return Ok(FileLookupResult::Response(FileResponse::from_content(
+                        *status,
+                        None,
+                        vec![],
+                    )))
+                }
+            };
+
+            let path = match path.starts_with('/') {
+                true => path,
+                false => format!("/{}", path),
+            };
+
+            if path.split('/').any(|itm| itm == "..") {
+                continue;
+            }
+
+            match self.open(folder, &path, request).await? {
+                (FolderItem::File(file), coding) => {
+                    return Ok(FileLookupResult::File { path, file, coding })
+                }
+                (FolderItem::Dir, _) => {
+                    if let Some(default_file) = self.default_file.as_deref() {
+                        let index_path = format!("{}/{}", path.trim_end_matches('/'), default_file);
+                        if let (FolderItem::File(file), coding) =
+                            self.open(folder, &index_path, request).await?
+                        {
+                            return Ok(FileLookupResult::File {
+                                path: index_path,
+                                file,
+                                coding,
+                            });
+                        }
+                    }
+
+                    if self.autoindex {
+                        let entries = folder.list_dir(&path).await?;
+                        let listing = DirListing::new(&path, entries);
+                        let accept = request.accept.as_deref();
+                        return Ok(FileLookupResult::Response(listing.to_file_response(accept)));
+                    }
+                }
+                (FolderItem::NotFound, _) => {}
+            }
+        }
+
+        Ok(FileLookupResult::NotFound)
+    }
+
+    /// `path` of `folder` — or, with `precompressed` on, its sibling in the
+    /// coding the client prefers of those there are. A sibling found missing
+    /// is not looked for again for a while.
+    async fn open<TFolder: FilesFolder + Sync>(
+        &self,
+        folder: &TFolder,
+        path: &str,
+        request: &FileRequestHeaders,
+    ) -> Result<(FolderItem<TFolder::File>, Option<ContentCoding>), TFolder::Error> {
+        if self.precompressed && !path.ends_with('/') {
+            let now = Instant::now();
+
+            for coding in ContentCoding::get_accepted(request.accept_encoding.as_deref()) {
+                let Some(extension) = coding.get_file_extension() else {
+                    continue;
+                };
+
+                let sibling = format!("{}.{}", path, extension);
+                if self.probes.is_known_missing(&sibling, now) {
+                    continue;
+                }
+
+                match folder.open(&sibling).await? {
+                    FolderItem::File(file) => {
+                        self.probes.set_found(&sibling);
+                        return Ok((FolderItem::File(file), Some(coding)));
+                    }
+                    _ => self.probes.set_missing(&sibling, now),
+                }
+            }
+        }
+
+        Ok((folder.open(path).await?, None))
+    }
+
+    /// The headers of the response for a file found: its `Content-Encoding`,
+    /// and — with `precompressed` on — that it varies by `Accept-Encoding`.
+    pub fn push_coding_headers(&self, response: &mut FileResponse, coding: Option<ContentCoding>) {
+        if !self.precompressed {
+            return;
+        }
+
+        response
+            .headers
+            .push(("Vary".to_string(), "Accept-Encoding".to_string()));
+
+        if let Some(coding) = coding {
+            response
+                .headers
+                .push(("Content-Encoding".to_string(), coding.as_str().to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    struct TestFolder(BTreeMap<&'static str, Option<&'static str>>);
+
+    #[async_trait::async_trait]
+    impl FilesFolder for TestFolder {
+        type File = &'static str;
+        type Error = ();
+
+        async fn open(&self, path: &str) -> Result<FolderItem<&'static str>, ()> {
+            let result = match self.0.get(path.trim_end_matches('/')) {
+                Some(Some(content)) => FolderItem::File(*content),
+                Some(None) => FolderItem::Dir,
+                None => FolderItem::NotFound,
+            };
+
+            Ok(result)
+        }
+
+        async fn list_dir(&self, _path: &str) -> Result<Vec<DirEntry>, ()> {
+            Ok(vec![])
+        }
+    }
+
+    async fn lookup_encoded(lookup: &FileLookup, path: &str, accept_encoding: &str) -> String {
+        let folder = TestFolder(BTreeMap::from([
+            ("", None),
+            ("/index.html", Some("root index")),
+            ("/app.js", Some("app")),
+            ("/docs", None),
+            ("/empty", None),
+            ("/docs/index.html", Some("docs index")),
+            ("/app.js.gz", Some("app gz")),
+            ("/app.js.br", Some("app br")),
+        ]));
+
+        let request = FileRequestHeaders {
+            accept_encoding: Some(accept_encoding.to_string()),
+            ..Default::default()
+        };
+        let result = lookup.lookup(&folder, path, &request).await.unwrap();
+
+        match result {
+            FileLookupResult::File {
+                path,
+                file,
+                coding: None,
+            } => format!("{} {}", path, file),
+            FileLookupResult::File {
+                path,
+                file,
+                coding: Some(coding),
+            } => format!("{} {} {}", path, file, coding.as_str()),
+            FileLookupResult::Response(response) => response.status.to_string(),
+            FileLookupResult::NotFound => "not found".to_string(),
+        }
+    }
+
+    async fn lookup(lookup: &FileLookup, path: &str) -> String {
+        lookup_encoded(lookup, path, "").await
+    }
+
+    #[tokio::test]
+    async fn directories_are_served_with_default_file_or_listing() {
+        let mut settings = FileLookup::default();
+        assert_eq!(lookup(&settings, "/app.js").await, "/app.js app");
+        assert_eq!(lookup(&settings, "/docs/").await, "not found");
+        assert_eq!(lookup(&settings, "/../etc/passwd").await, "not found");
+
+        settings.default_file = Some("index.html".to_string());
+        assert_eq!(lookup(&settings, "/").await, "/index.html root index");
+        assert_eq!(
+            lookup(&settings, "/docs").await,
+            "/docs/index.html docs index"
+        );
+        assert_eq!(lookup(&settings, "/empty/").await, "not found");
+
+        settings.autoindex = true;
+        assert_eq!(lookup(&settings, "/empty/").await, "200");
+    }
+
+    #[tokio::test]
+    async fn try_files_fall_back() {
+        let items = ["$uri", "$uri/index.html", "/index.html"].map(|itm| itm.to_string());
+        let settings = FileLookup {
+            try_files: Some(FileLookup::parse_try_files(&items).unwrap()),
+            ..Default::default()
+        };
+
+        assert_eq!(lookup(&settings, "/app.js").await, "/app.js app");
+        assert_eq!(
+            lookup(&settings, "/docs").await,
+            "/docs/index.html docs index"
+        );
+        assert_eq!(
+            lookup(&settings, "/app/settings").await,
+            "/index.html root index"
+        );
+
+        let items = ["$uri", "=418"].map(|itm| itm.to_string());
+        let settings = FileLookup {
+            try_files: Some(FileLookup::parse_try_files(&items).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(lookup(&settings, "/app/settings").await, "418");
+
+        let items = ["=404", "$uri"].map(|itm| itm.to_string());
+        assert!(FileLookup::parse_try_files(&items).is_err());
+    }
+
+    #[tokio::test]
+    async fn precompressed_siblings_are_served() {
+        let settings = FileLookup {
+            precompressed: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            lookup_encoded(&settings, "/app.js", "gzip, zstd").await,
+            "/app.js app gz gzip"
+        );
+        assert_eq!(
+            lookup_encoded(&settings, "/app.js", "gzip, br").await,
+            "/app.js app br br"
+        );
+        assert_eq!(
+            lookup_encoded(&settings, "/app.js", "deflate").await,
+            "/app.js app"
+        );
+        assert!(settings
+            .probes
+            .is_known_missing("/app.js.zst", Instant::now()));
+
+        let settings = FileLookup::default();
+        assert_eq!(
+            lookup_encoded(&settings, "/app.js", "gzip").await,
+            "/app.js app"
+        );
+    }
+}
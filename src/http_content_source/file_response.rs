@@ -0,0 +1,581 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt};
+
+use crate::h1_utils::Http1HeadersBuilder;
+use crate::response_cache::{format_http_date, parse_http_date};
+
+/// The version of a file being served — what its `ETag` and `Last-Modified`
+/// are made of.
+#[derive(Debug, Clone, Copy)]
+pub struct FileVersion {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileVersion {
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    /// `"<modified>-<size>"` in hex, the way nginx makes it. `None` when the
+    /// time the file was modified is not known.
+    pub fn get_etag(&self) -> Option<String> {
+        let modified = self.modified?.duration_since(UNIX_EPOCH).ok()?;
+        Some(format!("\"{:x}-{:x}\"", modified.as_secs(), self.size))
+    }
+
+    pub fn get_last_modified(&self) -> Option<String> {
+        self.modified.map(format_http_date)
+    }
+}
+
+/// The headers of a request for a file its response depends on.
+#[derive(Debug, Default, Clone)]
+pub struct FileRequestHeaders {
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+    pub range: Option<String>,
+    pub if_range: Option<String>,
+    /// What a directory listing is rendered as.
+    pub accept: Option<String>,
+    /// Which precompressed sibling of a file is served.
+    pub accept_encoding: Option<String>,
+}
+
+impl FileRequestHeaders {
+    /// Picks them out of the request's headers — name / value pairs.
+    pub fn from_headers<'s>(headers: impl Iterator<Item = (&'s str, &'s str)>) -> Self {
+        let mut result = Self::default();
+
+        for (name, value) in headers {
+            let field = if name.eq_ignore_ascii_case("if-none-match") {
+                &mut result.if_none_match
+            } else if name.eq_ignore_ascii_case("if-modified-since") {
+                &mut result.if_modified_since
+            } else if name.eq_ignore_ascii_case("range") {
+                &mut result.range
+            } else if name.eq_ignore_ascii_case("if-range") {
+                &mut result.if_range
+            } else if name.eq_ignore_ascii_case("accept") {
+                &mut result.accept
+            } else if name.eq_ignore_ascii_case("accept-encoding") {
+                &mut result.accept_encoding
+            } else {
+                continue;
+            };
+
+            *field = Some(value.trim().to_string());
+        }
+
+        result
+    }
+
+    pub fn from_header_map(headers: &hyper::HeaderMap) -> Self {
+        Self::from_headers(
+            headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+        )
+    }
+
+    /// How the request for the file of `version` is answered. `If-None-Match`
+    /// takes over from `If-Modified-Since`; a `Range` counts only while
+    /// `If-Range` still names this version.
+    pub fn plan(&self, version: &FileVersion) -> FileResponsePlan {
+        let etag = version.get_etag();
+
+        if let Some(if_none_match) = self.if_none_match.as_deref() {
+            if etag_matches(if_none_match, etag.as_deref(), false) {
+                return FileResponsePlan::NotModified;
+            }
+        } else if let (Some(since), Some(modified)) = (
+            self.if_modified_since.as_deref().and_then(parse_http_date),
+            version.modified,
+        ) {
+            // Last-Modified only goes down to the second.
+            let modified = modified
+                .duration_since(UNIX_EPOCH)
+                .map(|itm| itm.as_secs())
+                .unwrap_or_default();
+            let since = since
+                .duration_since(UNIX_EPOCH)
+                .map(|itm| itm.as_secs())
+                .unwrap_or_default();
+
+            if modified <= since {
+                return FileResponsePlan::NotModified;
+            }
+        }
+
+        let Some(range) = self.range.as_deref() else {
+            return FileResponsePlan::Full;
+        };
+
+        if let Some(if_range) = self.if_range.as_deref() {
+            let still_valid = match if_range.starts_with('"') || if_range.starts_with("W/") {
+                true => etag_matches(if_range, etag.as_deref(), true),
+                false => version.get_last_modified().as_deref() == Some(if_range),
+            };
+
+            if !still_valid {
+                return FileResponsePlan::Full;
+            }
+        }
+
+        parse_range(range, version.size)
+    }
+}
+
+/// A range of a file, both ends included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn get_len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FileResponsePlan {
+    /// 304 — the client has this version already.
+    NotModified,
+    /// 200 with the whole file.
+    Full,
+    /// 206 with these ranges of it.
+    Partial(Vec<ByteRange>),
+    /// 416 — none of the ranges asked for is in the file.
+    NotSatisfiable,
+}
+
+/// Whether `list` — the value of `If-None-Match` or `If-Range` — names
+/// `etag`. `If-Range` compares strong tags only.
+fn etag_matches(list: &str, etag: Option<&str>, strong: bool) -> bool {
+    list.split(',').map(|itm| itm.trim()).any(|itm| {
+        if itm == "*" {
+            return !strong;
+        }
+
+        let Some(etag) = etag else {
+            return false;
+        };
+
+        match itm.strip_prefix("W/") {
+            Some(weak) => !strong && weak == etag,
+            None => itm == etag,
+        }
+    })
+}
+
+/// `bytes=0-99,200-`, `bytes=-500`. A value that is not one of these is
+/// ignored — the whole file is sent — and so are more ranges than
+/// [`crate::consts::FILE_MAX_RANGES`].
+fn parse_range(value: &str, size: u64) -> FileResponsePlan {
+    let Some(ranges) = value.trim().strip_prefix("bytes=") else {
+        return FileResponsePlan::Full;
+    };
+
+    let mut result = Vec::new();
+    let mut count = 0;
+
+    for itm in ranges.split(',').map(|itm| itm.trim()) {
+        if itm.is_empty() {
+            continue;
+        }
+
+        count += 1;
+        if count > crate::consts::FILE_MAX_RANGES {
+            return FileResponsePlan::Full;
+        }
+
+        let Some((start, end)) = itm.split_once('-') else {
+            return FileResponsePlan::Full;
+        };
+
+        let range = match (start.trim(), end.trim()) {
+            ("", "") => return FileResponsePlan::Full,
+            ("", suffix) => {
+                let Ok(suffix) = suffix.parse::<u64>() else {
+                    return FileResponsePlan::Full;
+                };
+
+                match suffix > 0 && size > 0 {
+                    true => Some(ByteRange {
+                        start: size.saturating_sub(suffix),
+                        end: size - 1,
+                    }),
+                    false => None,
+                }
+            }
+            (start, end) => {
+                let Ok(start) = start.parse::<u64>() else {
+                    return FileResponsePlan::Full;
+                };
+
+                let end = match end {
+                    "" => u64::MAX,
+                    end => match end.parse::<u64>() {
+                        Ok(end) if end >= start => end,
+                        _ => return FileResponsePlan::Full,
+                    },
+                };
+
+                match start < size {
+                    true => Some(ByteRange {
+                        start,
+                        end: end.min(size - 1),
+                    }),
+                    false => None,
+                }
+            }
+        };
+
+        if let Some(range) = range {
+            result.push(range);
+        }
+    }
+
+    if count == 0 {
+        return FileResponsePlan::Full;
+    }
+
+    match result.is_empty() {
+        true => FileResponsePlan::NotSatisfiable,
+        false => FileResponsePlan::Partial(result),
+    }
+}
+
+/// One piece of the body of a [`FileResponse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileBodyPart {
+    /// The framing of a multipart body.
+    Bytes(Vec<u8>),
+    /// A range of the file.
+    Range(ByteRange),
+}
+
+/// A response to a request for a file, laid out: its status, its headers and
+/// what its body is made of. The file itself is read as the body is sent.
+#[derive(Debug)]
+pub struct FileResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<FileBodyPart>,
+}
+
+impl FileResponse {
+    pub fn new(
+        version: &FileVersion,
+        content_type: Option<&str>,
+        request: &FileRequestHeaders,
+    ) -> Self {
+        let boundary = uuid::Uuid::new_v4().simple().to_string();
+        Self::compile(version, content_type, request, &boundary)
+    }
+
+    /// A response made up in memory — a directory listing, the status of a
+    /// `try_files`.
+    pub fn from_content(status: u16, content_type: Option<&str>, content: Vec<u8>) -> Self {
+        let mut headers = Vec::new();
+        if let Some(content_type) = content_type {
+            headers.push(("Content-Type".to_string(), content_type.to_string()));
+        }
+
+        Self {
+            status,
+            headers,
+            body: match content.is_empty() {
+                true => vec![],
+                false => vec![FileBodyPart::Bytes(content)],
+            },
+        }
+    }
+
+    fn compile(
+        version: &FileVersion,
+        content_type: Option<&str>,
+        request: &FileRequestHeaders,
+        boundary: &str,
+    ) -> Self {
+        let mut headers = vec![("Accept-Ranges".to_string(), "bytes".to_string())];
+
+        if let Some(etag) = version.get_etag() {
+            headers.push(("ETag".to_string(), etag));
+        }
+
+        if let Some(last_modified) = version.get_last_modified() {
+            headers.push(("Last-Modified".to_string(), last_modified));
+        }
+
+        let push_content_type = |headers: &mut Vec<(String, String)>| {
+            if let Some(content_type) = content_type {
+                headers.push(("Content-Type".to_string(), content_type.to_string()));
+            }
+        };
+
+        let whole_file = ByteRange {
+            start: 0,
+            end: version.size.saturating_sub(1),
+        };
+
+        match request.plan(version) {
+            FileResponsePlan::NotModified => Self {
+                status: 304,
+                headers,
+                body: vec![],
+            },
+            FileResponsePlan::Full => {
+                push_content_type(&mut headers);
+                Self {
+                    status: 200,
+                    headers,
+                    body: match version.size {
+                        0 => vec![],
+                        _ => vec![FileBodyPart::Range(whole_file)],
+                    },
+                }
+            }
+            FileResponsePlan::NotSatisfiable => {
+                headers.push((
+                    "Content-Range".to_string(),
+                    format!("bytes */{}", version.size),
+                ));
+                Self {
+                    status: 416,
+                    headers,
+                    body: vec![],
+                }
+            }
+            FileResponsePlan::Partial(ranges) if ranges.len() == 1 => {
+                push_content_type(&mut headers);
+                headers.push((
+                    "Content-Range".to_string(),
+                    format!(
+                        "bytes {}-{}/{}",
+                        ranges[0].start, ranges[0].end, version.size
+                    ),
+                ));
+                Self {
+                    status: 206,
+                    headers,
+                    body: vec![FileBodyPart::Range(ranges[0])],
+                }
+            }
+            FileResponsePlan::Partial(ranges) => {
+                headers.push((
+                    "Content-Type".to_string(),
+                    format!("multipart/byteranges; boundary={}", boundary),
+                ));
+
+                let mut body = Vec::with_capacity(ranges.len() * 2 + 1);
+                for range in ranges {
+                    let mut part_head = format!("\r\n--{}\r\n", boundary);
+                    if let Some(content_type) = content_type {
+                        part_head.push_str(&format!("Content-Type: {}\r\n", content_type));
+                    }
+                    part_head.push_str(&format!(
+                        "Content-Range: bytes {}-{}/{}\r\n\r\n",
+                        range.start, range.end, version.size
+                    ));
+
+                    body.push(FileBodyPart::Bytes(part_head.into_bytes()));
+                    body.push(FileBodyPart::Range(range));
+                }
+                body.push(FileBodyPart::Bytes(
+                    format!("\r\n--{}--\r\n", boundary).into_bytes(),
+                ));
+
+                Self {
+                    status: 206,
+                    headers,
+                    body,
+                }
+            }
+        }
+    }
+
+    pub fn get_content_length(&self) -> u64 {
+        self.body
+            .iter()
+            .map(|itm| match itm {
+                FileBodyPart::Bytes(bytes) => bytes.len() as u64,
+                FileBodyPart::Range(range) => range.get_len(),
+            })
+            .sum()
+    }
+
+    /// The response head for an http/1.1 client. A 304 carries no
+    /// `Content-Length`: it describes the body the client has already.
+    pub fn to_h1_head(&self) -> Vec<u8> {
+        let mut result = Http1HeadersBuilder::new();
+        result.push_response_first_line(self.status);
+
+        for (name, value) in self.headers.iter() {
+            result.push_header(name, value);
+        }
+
+        if self.status != 304 {
+            result.push_header("Content-Length", &self.get_content_length().to_string());
+        }
+
+        result.push_cl_cr();
+        result.into_bytes()
+    }
+
+    /// The response for the hyper path, its body read by `body` — `None` for a
+    /// HEAD request or a response with no body.
+    pub fn to_hyper_response(
+        &self,
+        body: Option<super::FileBodyReader>,
+    ) -> hyper::Response<BoxBody<Bytes, String>> {
+        let mut builder = hyper::Response::builder().status(self.status);
+
+        for (name, value) in self.headers.iter() {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+
+        let content_length = self.get_content_length();
+        if self.status != 304 {
+            builder = builder.header("Content-Length", content_length);
+        }
+
+        let body = match body {
+            Some(body) => body.into_hyper_body(content_length),
+            None => http_body_util::Full::new(Bytes::new())
+                .map_err(crate::to_hyper_error)
+                .boxed(),
+        };
+
+        builder.body(body).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn version() -> FileVersion {
+        FileVersion {
+            size: 1000,
+            modified: Some(UNIX_EPOCH + Duration::from_secs(784111777)),
+        }
+    }
+
+    fn request(headers: &[(&str, &str)]) -> FileRequestHeaders {
+        FileRequestHeaders::from_headers(headers.iter().copied())
+    }
+
+    #[test]
+    fn validators_answer_with_not_modified() {
+        let version = version();
+        assert_eq!(version.get_etag().unwrap(), "\"2ebc98a1-3e8\"");
+        assert_eq!(
+            version.get_last_modified().unwrap(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+
+        for headers in [
+            vec![("If-None-Match", "\"x\", W/\"2ebc98a1-3e8\"")],
+            vec![("if-none-match", "*")],
+            vec![("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT")],
+        ] {
+            assert_eq!(
+                request(&headers).plan(&version),
+                FileResponsePlan::NotModified
+            );
+        }
+
+        // If-None-Match that does not match wins over If-Modified-Since.
+        let headers = [
+            ("If-None-Match", "\"other\""),
+            ("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT"),
+        ];
+        assert_eq!(request(&headers).plan(&version), FileResponsePlan::Full);
+
+        let headers = [("If-Modified-Since", "Sun, 06 Nov 1994 08:49:36 GMT")];
+        assert_eq!(request(&headers).plan(&version), FileResponsePlan::Full);
+    }
+
+    #[test]
+    fn ranges_are_planned() {
+        let version = version();
+        let plan = |range: &str| request(&[("Range", range)]).plan(&version);
+        let range = |start, end| ByteRange { start, end };
+
+        assert_eq!(
+            plan("bytes=0-99"),
+            FileResponsePlan::Partial(vec![range(0, 99)])
+        );
+        assert_eq!(
+            plan("bytes=900-, -50"),
+            FileResponsePlan::Partial(vec![range(900, 999), range(950, 999)])
+        );
+        assert_eq!(
+            plan("bytes=990-2000"),
+            FileResponsePlan::Partial(vec![range(990, 999)])
+        );
+        assert_eq!(plan("bytes=1000-"), FileResponsePlan::NotSatisfiable);
+        assert_eq!(plan("bytes=5-1"), FileResponsePlan::Full);
+        assert_eq!(plan("items=0-1"), FileResponsePlan::Full);
+        assert_eq!(plan("bytes=abc"), FileResponsePlan::Full);
+
+        let stale = [("Range", "bytes=0-1"), ("If-Range", "\"old\"")];
+        assert_eq!(request(&stale).plan(&version), FileResponsePlan::Full);
+
+        let fresh = [
+            ("Range", "bytes=0-1"),
+            ("If-Range", "Sun, 06 Nov 1994 08:49:37 GMT"),
+        ];
+        assert_eq!(
+            request(&fresh).plan(&version),
+            FileResponsePlan::Partial(vec![range(0, 1)])
+        );
+    }
+
+    #[test]
+    fn a_multipart_response_is_laid_out() {
+        let response = FileResponse::compile(
+            &version(),
+            Some("video/mp4"),
+            &request(&[("Range", "bytes=0-9,20-29")]),
+            "XYZ",
+        );
+
+        assert_eq!(response.status, 206);
+        assert_eq!(
+            response.body[0],
+            FileBodyPart::Bytes(
+                b"\r\n--XYZ\r\nContent-Type: video/mp4\r\nContent-Range: bytes 0-9/1000\r\n\r\n"
+                    .to_vec()
+            )
+        );
+        assert_eq!(
+            response.body[4],
+            FileBodyPart::Bytes(b"\r\n--XYZ--\r\n".to_vec())
+        );
+        let framing: u64 = response
+            .body
+            .iter()
+            .filter_map(|itm| match itm {
+                FileBodyPart::Bytes(bytes) => Some(bytes.len() as u64),
+                FileBodyPart::Range(_) => None,
+            })
+            .sum();
+        assert_eq!(response.get_content_length(), framing + 20);
+
+        let head = FileResponse::compile(&version(), None, &request(&[]), "XYZ").to_h1_head();
+        let head = String::from_utf8(head).unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\nAccept-Ranges: bytes\r\n"));
+        assert!(head.ends_with("Content-Length: 1000\r\n\r\n"));
+    }
+}
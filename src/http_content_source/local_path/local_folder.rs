@@ -0,0 +1,45 @@
+use crate::http_content_source::{read_local_dir, DirEntry, FilesFolder, FolderItem};
+
+/// A folder of the local file system files are served from.
+pub struct LocalFolder {
+    root: String,
+}
+
+impl LocalFolder {
+    pub fn new(root: &str) -> Self {
+        Self {
+            root: root.trim_end_matches(std::path::MAIN_SEPARATOR).to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl FilesFolder for LocalFolder {
+    type File = (tokio::fs::File, std::fs::Metadata);
+    type Error = std::io::Error;
+
+    async fn open(&self, path: &str) -> Result<FolderItem<Self::File>, std::io::Error> {
+        let full_path = format!("{}{}", self.root, path);
+
+        let Ok(metadata) = tokio::fs::metadata(&full_path).await else {
+            return Ok(FolderItem::NotFound);
+        };
+
+        if metadata.is_dir() {
+            return Ok(FolderItem::Dir);
+        }
+
+        if !metadata.is_file() {
+            return Ok(FolderItem::NotFound);
+        }
+
+        match tokio::fs::File::open(&full_path).await {
+            Ok(file) => Ok(FolderItem::File((file, metadata))),
+            Err(_) => Ok(FolderItem::NotFound),
+        }
+    }
+
+    async fn list_dir(&self, path: &str) -> Result<Vec<DirEntry>, std::io::Error> {
+        read_local_dir(&format!("{}{}", self.root, path)).await
+    }
+}
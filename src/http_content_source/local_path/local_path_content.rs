@@ -1,21 +1,25 @@
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 use parking_lot::Mutex;
-use rust_extensions::{file_utils::FilePath, SliceOrVec};
+use rust_extensions::file_utils::FilePath;
 
 use crate::h1_utils::*;
+use crate::http_content_source::{FileLookup, FileRequestHeaders};
+
+use super::{LocalFolder, LocalPathResponse};
 
 pub struct LocalPathContent {
-    files_path: FilePath,
-    default_file: Option<String>,
-    requests: Mutex<BTreeMap<u64, (String, String)>>,
+    folder: LocalFolder,
+    lookup: Arc<FileLookup>,
+    requests: Mutex<BTreeMap<u64, (String, String, FileRequestHeaders)>>,
 }
 
 impl LocalPathContent {
-    pub fn new(files_path: &str, default_file: Option<String>) -> Self {
+    pub fn new(files_path: &str, lookup: Arc<FileLookup>) -> Self {
         Self {
-            files_path: FilePath::from_str(files_path),
-            default_file,
+            folder: LocalFolder::new(FilePath::from_str(files_path).as_str()),
+            lookup,
             requests: Mutex::new(Default::default()),
         }
     }
@@ -23,29 +27,51 @@ impl LocalPathContent {
     pub fn send_headers(&self, request_id: u64, h1_headers: &Http1HeadersBuilder) {
         let first_line = h1_headers.get_first_line();
         let (verb, path) = first_line.get_verb_and_path();
-        self.requests
-            .lock()
-            .insert(request_id, (verb.to_string(), path.to_string()));
+
+        let headers = crate::h1_proxy_server::read_head_headers(h1_headers.as_slice());
+        let file_request = FileRequestHeaders::from_headers(
+            headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+
+        self.requests.lock().insert(
+            request_id,
+            (verb.to_string(), path.to_string(), file_request),
+        );
     }
 
-    pub async fn get_content(&self, request_id: u64) -> SliceOrVec<'static, u8> {
-        let verb_and_path = self.requests.lock().remove(&request_id);
+    pub async fn get_content(&self, request_id: u64) -> LocalPathResponse {
+        let request = self.requests.lock().remove(&request_id);
 
-        let Some(verb_and_path) = verb_and_path else {
-            return crate::error_templates::NOT_FOUND.as_slice().into();
+        let Some((verb, path, file_request)) = request else {
+            return LocalPathResponse::not_found();
         };
 
-        let (verb, path) = verb_and_path;
+        let with_body = match verb.as_str() {
+            "GET" => true,
+            "HEAD" => false,
+            _ => return LocalPathResponse::not_found(),
+        };
 
-        if verb != "GET" {
-            return crate::error_templates::NOT_FOUND.as_slice().into();
-        }
+        // The query does not pick the file.
+        let path = path.split('?').next().unwrap_or_default();
 
-        super::serve_file::serve_file(
-            &self.files_path,
-            path.as_str(),
-            self.default_file.as_deref(),
+        let served = super::serve_file::serve_file(
+            &self.folder,
+            &self.lookup,
+            path,
+            &file_request,
+            with_body,
         )
-        .await
+        .await;
+
+        match served {
+            Some((response, body)) if response.status != 404 => LocalPathResponse {
+                head: response.to_h1_head(),
+                body,
+            },
+            _ => LocalPathResponse::not_found(),
+        }
     }
 }
@@ -1,19 +1,19 @@
 use std::sync::Arc;
 
-use my_http_server::WebContentType;
-
-use crate::http_content_source::{RequestExecutor, RequestExecutorResult};
+use crate::http_content_source::{FileLookup, FileRequestHeaders, RequestExecutorResult};
 use crate::http_proxy_pass::{content_source::HttpResponse, ProxyPassError};
 
 use crate::configurations::*;
 
+use super::LocalFolder;
+
 pub struct LocalPathContentSrc {
     pub file_path: String,
-    default_file: Option<String>,
+    lookup: Arc<FileLookup>,
 }
 
 impl LocalPathContentSrc {
-    pub fn new(file_path: &LocalFilePath, default_file: Option<String>) -> Self {
+    pub fn new(file_path: &LocalFilePath, lookup: Arc<FileLookup>) -> Self {
         let mut file_path = file_path.get_value().to_string();
 
         let last_char = *file_path.as_bytes().last().unwrap() as char;
@@ -21,28 +21,7 @@ impl LocalPathContentSrc {
             file_path.pop();
         }
 
-        Self {
-            file_path,
-            default_file,
-        }
-    }
-
-    pub fn execute_request(
-        &self,
-        path: &str,
-    ) -> Result<Arc<dyn RequestExecutor + Send + Sync + 'static>, ProxyPassError> {
-        let file_path = if path == "/" {
-            if let Some(default_file) = self.default_file.as_ref() {
-                format!("{}/{}", self.file_path, default_file)
-            } else {
-                format!("{}{}", self.file_path, path)
-            }
-        } else {
-            format!("{}{}", self.file_path, path)
-        };
-
-        let result = FileRequestExecutor { file_path };
-        Ok(Arc::new(result))
+        Self { file_path, lookup }
     }
 
     pub async fn execute(
@@ -50,32 +29,20 @@ impl LocalPathContentSrc {
         req: http::Request<http_body_util::Full<bytes::Bytes>>,
     ) -> Result<HttpResponse, ProxyPassError> {
         println!("Executing as local path");
-        let request_executor = self.execute_request(req.uri().path())?;
-        let result = request_executor.execute_request().await?;
-        Ok(HttpResponse::Response(result.into()))
-    }
-}
-
-pub struct FileRequestExecutor {
-    file_path: String,
-}
-
-#[async_trait::async_trait]
-impl RequestExecutor for FileRequestExecutor {
-    async fn execute_request(&self) -> Result<RequestExecutorResult, ProxyPassError> {
-        let result = match tokio::fs::read(&self.file_path).await {
-            Ok(content) => RequestExecutorResult {
-                status_code: 200,
-                content_type: WebContentType::detect_by_extension(&self.file_path),
-                body: content,
-            },
-            Err(_) => RequestExecutorResult {
-                status_code: 404,
-                content_type: None,
-                body: "Not found".as_bytes().to_vec(),
-            },
+        let served = super::serve_file::serve_file(
+            &LocalFolder::new(&self.file_path),
+            &self.lookup,
+            req.uri().path(),
+            &FileRequestHeaders::from_header_map(req.headers()),
+            req.method() != hyper::Method::HEAD,
+        )
+        .await;
+
+        let response = match served {
+            Some((response, body)) => response.to_hyper_response(body),
+            None => RequestExecutorResult::not_found().into(),
         };
 
-        Ok(result)
+        Ok(HttpResponse::Response(response))
     }
 }
@@ -3,3 +3,6 @@ pub use local_path_content::*;
 mod local_path_content_source;
 pub use local_path_content_source::*;
 mod serve_file;
+pub use serve_file::*;
+mod local_folder;
+pub use local_folder::*;
@@ -1,38 +1,61 @@
 use my_http_server::WebContentType;
-use rust_extensions::{file_utils::FilePath, SliceOrVec};
 
-use crate::h1_utils::Http1ResponseBuilder;
+use crate::http_content_source::{
+    FileBodyReader, FileLookup, FileLookupResult, FileRequestHeaders, FileResponse, FileSource,
+    FileVersion,
+};
 
-pub async fn serve_file(
-    files_path: &FilePath,
-    path: &str,
-    default_file: Option<&str>,
-) -> SliceOrVec<'static, u8> {
-    let file_name = if path == "/" {
-        if let Some(default_file) = default_file {
-            default_file
-        } else {
-            return crate::error_templates::NOT_FOUND.as_slice().into();
-        }
-    } else {
-        path
-    };
+use super::LocalFolder;
+
+/// A response for a file of the folder: its head, and — unless it has no body
+/// or was asked for with HEAD — the reader of its body.
+pub struct LocalPathResponse {
+    pub head: Vec<u8>,
+    pub body: Option<FileBodyReader>,
+}
 
-    let mut file_path = files_path.clone();
-    file_path.append_segment(file_name);
+impl LocalPathResponse {
+    pub fn not_found() -> Self {
+        Self {
+            head: crate::error_templates::NOT_FOUND.to_vec(),
+            body: None,
+        }
+    }
+}
 
-    match tokio::fs::read(file_path.as_str()).await {
-        Ok(content) => {
-            let mut result = Http1ResponseBuilder::new_as_ok_result();
+/// The response for `path` of the folder, as `lookup` finds it — `None` when
+/// nothing of the folder answers it.
+pub async fn serve_file(
+    folder: &LocalFolder,
+    lookup: &FileLookup,
+    path: &str,
+    request: &FileRequestHeaders,
+    with_body: bool,
+) -> Option<(FileResponse, Option<FileBodyReader>)> {
+    let lookup_result = lookup.lookup(folder, path, request).await.ok()?;
 
-            if let Some(content_type) = WebContentType::detect_by_extension(file_path.as_str()) {
-                result = result.add_content_type(content_type.as_str());
-            }
+    match lookup_result {
+        FileLookupResult::File {
+            path,
+            file: (file, metadata),
+            coding,
+        } => {
+            let content_type = WebContentType::detect_by_extension(&path);
+            let mut response = FileResponse::new(
+                &FileVersion::from_metadata(&metadata),
+                content_type.as_ref().map(|itm| itm.as_str()),
+                request,
+            );
+            lookup.push_coding_headers(&mut response, coding);
 
-            return result.build_with_body(&content).into();
+            let body = FileBodyReader::for_response(FileSource::File(file), &response, with_body);
+            Some((response, body))
         }
-        Err(_) => {
-            return crate::error_templates::NOT_FOUND.as_slice().into();
+        FileLookupResult::Response(response) => {
+            let body =
+                FileBodyReader::for_response(FileSource::Memory(vec![]), &response, with_body);
+            Some((response, body))
         }
+        FileLookupResult::NotFound => None,
     }
 }
@@ -5,6 +5,16 @@ mod ssh_file_content_src;
 pub use ssh_file_content_src::*;
 mod request_executor;
 pub use request_executor::*;
+mod file_response;
+pub use file_response::*;
+mod file_body;
+pub use file_body::*;
+mod file_lookup;
+pub use file_lookup::*;
+mod precompressed_probes;
+pub use precompressed_probes::*;
+mod dir_listing;
+pub use dir_listing::*;
 //mod _content_type;
 //pub use _content_type::*;
 pub mod local_path;
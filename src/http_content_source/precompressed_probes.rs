@@ -0,0 +1,38 @@
+use std::time::Instant;
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+/// The precompressed siblings of files a location found missing, so a request
+/// for a file that has none does not look for them again every time. A sibling
+/// added later is found once its entry is older than
+/// [`crate::consts::PRECOMPRESSED_PROBE_TTL`].
+#[derive(Debug, Default)]
+pub struct PrecompressedProbes {
+    missing: Mutex<AHashMap<String, Instant>>,
+}
+
+impl PrecompressedProbes {
+    pub fn is_known_missing(&self, path: &str, now: Instant) -> bool {
+        let missing = self.missing.lock();
+        missing.get(path).is_some_and(|checked| {
+            now.duration_since(*checked) < crate::consts::PRECOMPRESSED_PROBE_TTL
+        })
+    }
+
+    /// Past [`crate::consts::PRECOMPRESSED_PROBE_MAX_ENTRIES`] the paths are
+    /// all forgotten — they are looked for again, not served wrong.
+    pub fn set_missing(&self, path: &str, now: Instant) {
+        let mut missing = self.missing.lock();
+
+        if missing.len() >= crate::consts::PRECOMPRESSED_PROBE_MAX_ENTRIES {
+            missing.clear();
+        }
+
+        missing.insert(path.to_string(), now);
+    }
+
+    pub fn set_found(&self, path: &str) {
+        self.missing.lock().remove(path);
+    }
+}
@@ -2,14 +2,22 @@ use bytes::Bytes;
 use http_body_util::{combinators::BoxBody, BodyExt};
 use my_http_server::WebContentType;
 
-use crate::http_proxy_pass::ProxyPassError;
-
 pub struct RequestExecutorResult {
     pub status_code: u16,
     pub content_type: Option<WebContentType>,
     pub body: Vec<u8>,
 }
 
+impl RequestExecutorResult {
+    pub fn not_found() -> Self {
+        Self {
+            status_code: 404,
+            content_type: None,
+            body: "Not found".as_bytes().to_vec(),
+        }
+    }
+}
+
 impl Into<hyper::Response<BoxBody<Bytes, String>>> for RequestExecutorResult {
     fn into(self) -> hyper::Response<BoxBody<Bytes, String>> {
         let mut builder = hyper::Response::builder().status(self.status_code);
@@ -24,7 +32,3 @@ impl Into<hyper::Response<BoxBody<Bytes, String>>> for RequestExecutorResult {
             .unwrap()
     }
 }
-#[async_trait::async_trait]
-pub trait RequestExecutor {
-    async fn execute_request(&self) -> Result<RequestExecutorResult, ProxyPassError>;
-}
@@ -1,18 +1,23 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
 
 use arc_swap::ArcSwapOption;
-use hyper::Uri;
 use my_http_server::WebContentType;
 use my_ssh::SshSession;
 
 use crate::http_proxy_pass::{content_source::HttpResponse, ProxyPassError};
 
-use super::{RequestExecutor, RequestExecutorResult};
+use super::{
+    parse_dir_entries, DirEntry, FileBodyReader, FileLookup, FileLookupResult, FileRequestHeaders,
+    FileResponse, FileSource, FileVersion, FilesFolder, FolderItem, RequestExecutorResult,
+};
 
 pub struct PathOverSshContentSource {
     ssh_session: Arc<SshSession>,
     home_value: Arc<ArcSwapOption<String>>,
-    default_file: Option<String>,
+    lookup: Arc<FileLookup>,
     pub file_path: String,
     execute_timeout: Duration,
 }
@@ -21,61 +26,19 @@ impl PathOverSshContentSource {
     pub fn new(
         ssh_session: Arc<SshSession>,
         file_path: String,
-        default_file: Option<String>,
+        lookup: Arc<FileLookup>,
         execute_timeout: Duration,
     ) -> Self {
         Self {
             ssh_session,
             file_path,
             home_value: Arc::new(ArcSwapOption::empty()),
-            default_file,
+            lookup,
             execute_timeout,
         }
     }
 
-    pub async fn get_request_executor(
-        &self,
-        uri: &Uri,
-    ) -> Result<Arc<dyn RequestExecutor + Send + Sync + 'static>, ProxyPassError> {
-        let file_path = if uri.path() == "/" {
-            if let Some(default_file) = self.default_file.as_ref() {
-                format!("{}/{}", self.file_path, default_file)
-            } else {
-                format!("{}{}", self.file_path, uri.path())
-            }
-        } else {
-            format!("{}{}", self.file_path, uri.path())
-        };
-
-        let result = FileOverSshRequestExecutor {
-            file_path,
-            ssh_session: self.ssh_session.clone(),
-            home_value: self.home_value.clone(),
-            execute_timeout: self.execute_timeout,
-        };
-        Ok(Arc::new(result))
-    }
-
-    pub async fn execute(
-        &self,
-        req: http::Request<http_body_util::Full<bytes::Bytes>>,
-    ) -> Result<HttpResponse, ProxyPassError> {
-        let request_executor = self.get_request_executor(&req.uri()).await?;
-        let result = request_executor.execute_request().await?;
-        Ok(HttpResponse::Response(result.into()))
-    }
-}
-
-pub struct FileOverSshRequestExecutor {
-    ssh_session: Arc<SshSession>,
-    file_path: String,
-    home_value: Arc<ArcSwapOption<String>>,
-    execute_timeout: Duration,
-}
-
-#[async_trait::async_trait]
-impl RequestExecutor for FileOverSshRequestExecutor {
-    async fn execute_request(&self) -> Result<RequestExecutorResult, ProxyPassError> {
+    async fn get_root(&self) -> Result<String, ProxyPassError> {
         let file_path = if self.file_path.contains("~") {
             let home_value = match self.home_value.load_full() {
                 Some(value) => value,
@@ -95,17 +58,88 @@ impl RequestExecutor for FileOverSshRequestExecutor {
             self.file_path.clone()
         };
 
+        Ok(file_path.trim_end_matches('/').to_string())
+    }
+
+    /// The transport has no ranged read: a range is cut out of the file
+    /// downloaded whole — but a request the client already has the version of
+    /// is answered with 304 before any download.
+    pub async fn execute(
+        &self,
+        req: http::Request<http_body_util::Full<bytes::Bytes>>,
+    ) -> Result<HttpResponse, ProxyPassError> {
+        let folder = SshFolder {
+            source: self,
+            root: self.get_root().await?,
+        };
+
+        let request = FileRequestHeaders::from_header_map(req.headers());
+        let with_body = req.method() != hyper::Method::HEAD;
+
+        let lookup_result = self
+            .lookup
+            .lookup(&folder, req.uri().path(), &request)
+            .await?;
+
+        let (path, version, coding) = match lookup_result {
+            FileLookupResult::File { path, file, coding } => (path, file, coding),
+            FileLookupResult::Response(response) => {
+                let body =
+                    FileBodyReader::for_response(FileSource::Memory(vec![]), &response, with_body);
+                return Ok(HttpResponse::Response(response.to_hyper_response(body)));
+            }
+            FileLookupResult::NotFound => {
+                return Ok(HttpResponse::Response(
+                    RequestExecutorResult::not_found().into(),
+                ))
+            }
+        };
+
+        let content_type = WebContentType::detect_by_extension(&path);
+        let content_type = content_type.as_ref().map(|itm| itm.as_str());
+
+        if let Some(version) = version.as_ref() {
+            let mut response = FileResponse::new(version, content_type, &request);
+            self.lookup.push_coding_headers(&mut response, coding);
+            if response.status == 304 {
+                return Ok(HttpResponse::Response(response.to_hyper_response(None)));
+            }
+        }
+
+        let full_path = match coding.and_then(|itm| itm.get_file_extension()) {
+            Some(extension) => format!("{}{}.{}", folder.root, path, extension),
+            None => format!("{}{}", folder.root, path),
+        };
+        let content = match self.download(&full_path).await? {
+            Some(content) => content,
+            None => {
+                return Ok(HttpResponse::Response(
+                    RequestExecutorResult::not_found().into(),
+                ))
+            }
+        };
+
+        let version = FileVersion {
+            size: content.len() as u64,
+            modified: version.and_then(|itm| itm.modified),
+        };
+
+        let mut response = FileResponse::new(&version, content_type, &request);
+        self.lookup.push_coding_headers(&mut response, coding);
+        let body = FileBodyReader::for_response(FileSource::Memory(content), &response, with_body);
+
+        Ok(HttpResponse::Response(response.to_hyper_response(body)))
+    }
+
+    /// The content of the file — `None` when there is none.
+    async fn download(&self, file_path: &str) -> Result<Option<Vec<u8>>, ProxyPassError> {
         let result = self
             .ssh_session
-            .download_remote_file(&file_path, self.execute_timeout)
+            .download_remote_file(file_path, self.execute_timeout)
             .await;
 
         match result {
-            Ok(content) => Ok(RequestExecutorResult {
-                status_code: 200,
-                content_type: WebContentType::detect_by_extension(&file_path),
-                body: content,
-            }),
+            Ok(content) => Ok(Some(content)),
             Err(err) => {
                 println!("{} -> Error: {:?}", file_path, err);
                 match &err {
@@ -113,11 +147,7 @@ impl RequestExecutor for FileOverSshRequestExecutor {
                         if let Some(ssh2_error) = ssh_err.as_ssh2() {
                             if let my_ssh::ssh2::ErrorCode::Session(value) = ssh2_error.code() {
                                 if value == -28 {
-                                    return Ok(RequestExecutorResult {
-                                        status_code: 404,
-                                        content_type: None,
-                                        body: "Not found".as_bytes().to_vec(),
-                                    });
+                                    return Ok(None);
                                 }
                             }
                         }
@@ -130,3 +160,69 @@ impl RequestExecutor for FileOverSshRequestExecutor {
         }
     }
 }
+
+/// The remote folder, looked at with `test`, `stat` and `find` over the
+/// session.
+struct SshFolder<'s> {
+    source: &'s PathOverSshContentSource,
+    root: String,
+}
+
+#[async_trait::async_trait]
+impl<'s> FilesFolder for SshFolder<'s> {
+    /// The size and the time the file was modified — `None` when the remote
+    /// host has no GNU `stat` to tell them. Without them a file is served with
+    /// no validators.
+    type File = Option<FileVersion>;
+    type Error = ProxyPassError;
+
+    async fn open(&self, path: &str) -> Result<FolderItem<Option<FileVersion>>, ProxyPassError> {
+        let command = format!(
+            "p={}; if [ -d \"$p\" ]; then echo d; elif [ -f \"$p\" ]; then stat -c '%s %Y' \"$p\" 2>/dev/null || echo f; else echo n; fi",
+            quote_shell_arg(&format!("{}{}", self.root, path))
+        );
+
+        let (output, _) = self
+            .source
+            .ssh_session
+            .execute_command(&command, self.source.execute_timeout)
+            .await?;
+
+        let result = match output.trim() {
+            "d" => FolderItem::Dir,
+            "n" => FolderItem::NotFound,
+            output => FolderItem::File(parse_stat_output(output)),
+        };
+
+        Ok(result)
+    }
+
+    async fn list_dir(&self, path: &str) -> Result<Vec<DirEntry>, ProxyPassError> {
+        let command = format!(
+            "find {} -mindepth 1 -maxdepth 1 -printf '%y\\t%s\\t%T@\\t%f\\n'",
+            quote_shell_arg(&format!("{}{}", self.root, path))
+        );
+
+        let (output, _) = self
+            .source
+            .ssh_session
+            .execute_command(&command, self.source.execute_timeout)
+            .await?;
+
+        Ok(parse_dir_entries(output.as_bytes()))
+    }
+}
+
+/// `<size> <modified>` as `stat -c '%s %Y'` prints it.
+fn parse_stat_output(output: &str) -> Option<FileVersion> {
+    let (size, modified) = output.split_once(' ')?;
+    Some(FileVersion {
+        size: size.parse().ok()?,
+        modified: Some(UNIX_EPOCH + Duration::from_secs(modified.parse().ok()?)),
+    })
+}
+
+/// `value` single-quoted for the remote shell.
+fn quote_shell_arg(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
@@ -1,2 +1,4 @@
 mod static_content_src;
 pub use static_content_src::*;
+mod redirect_content_src;
+pub use redirect_content_src::*;
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use http_body_util::BodyExt;
+
+use crate::configurations::*;
+
+use crate::http_proxy_pass::{content_source::HttpResponse, ProxyPassError};
+
+pub struct RedirectContentSrc {
+    config: Arc<RedirectConfig>,
+}
+
+impl RedirectContentSrc {
+    pub fn new(config: Arc<RedirectConfig>) -> Self {
+        Self { config }
+    }
+
+    pub async fn execute(
+        &self,
+        req: http::Request<http_body_util::Full<bytes::Bytes>>,
+    ) -> Result<HttpResponse, ProxyPassError> {
+        // An h1 client names the host in the header, an h2 one in the uri.
+        let host_port = match req.headers().get(hyper::header::HOST) {
+            Some(host) => host.to_str().ok(),
+            None => req.uri().authority().map(|itm| itm.as_str()),
+        };
+
+        let path_and_query = req
+            .uri()
+            .path_and_query()
+            .map(|itm| itm.as_str())
+            .unwrap_or("/");
+
+        let location = self.config.redirect_to.render(host_port, path_and_query);
+
+        let response = hyper::Response::builder()
+            .status(self.config.status_code)
+            .header(hyper::header::LOCATION, location)
+            .body(
+                http_body_util::Full::new(bytes::Bytes::new())
+                    .map_err(|e| crate::to_hyper_error(e))
+                    .boxed(),
+            )
+            .unwrap();
+
+        Ok(HttpResponse::Response(response))
+    }
+}
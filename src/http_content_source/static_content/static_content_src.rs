@@ -6,51 +6,55 @@ use crate::configurations::*;
 
 use crate::http_proxy_pass::{content_source::HttpResponse, ProxyPassError};
 
-use super::super::{RequestExecutor, RequestExecutorResult};
+use super::super::RequestExecutorResult;
 
 pub struct StaticContentSrc {
-    pub inner: Arc<StaticContentExecutor>,
+    config: Arc<StaticContentConfig>,
 }
 
 impl StaticContentSrc {
     pub fn new(config: Arc<StaticContentConfig>) -> Self {
-        Self {
-            inner: Arc::new(StaticContentExecutor { config }),
-        }
-    }
-
-    pub fn get_request_executor(
-        &self,
-    ) -> Result<Arc<dyn RequestExecutor + Send + Sync + 'static>, ProxyPassError> {
-        Ok(self.inner.clone())
+        Self { config }
     }
 
     pub async fn execute(
         &self,
-        _req: http::Request<http_body_util::Full<bytes::Bytes>>,
+        req: http::Request<http_body_util::Full<bytes::Bytes>>,
     ) -> Result<HttpResponse, ProxyPassError> {
         println!("Executing static request");
-        let request_executor = self.get_request_executor()?;
-        let result = request_executor.execute_request().await?;
-        Ok(HttpResponse::Response(result.into()))
-    }
-}
-
-pub struct StaticContentExecutor {
-    config: Arc<StaticContentConfig>,
-}
 
-#[async_trait::async_trait]
-impl RequestExecutor for StaticContentExecutor {
-    async fn execute_request(&self) -> Result<RequestExecutorResult, ProxyPassError> {
-        Ok(RequestExecutorResult {
+        // An h1 client names the host in the header, an h2 one in the uri.
+        let host_port = match req.headers().get(hyper::header::HOST) {
+            Some(host) => host.to_str().ok(),
+            None => req.uri().authority().map(|itm| itm.as_str()),
+        };
+
+        let path_and_query = req
+            .uri()
+            .path_and_query()
+            .map(|itm| itm.as_str())
+            .unwrap_or("/");
+
+        let client = req
+            .extensions()
+            .get::<StaticBodyClient>()
+            .cloned()
+            .unwrap_or_default();
+
+        let result = RequestExecutorResult {
             status_code: self.config.status_code,
             content_type: if let Some(content_type) = self.config.content_type.clone() {
                 Some(WebContentType::Raw(content_type))
             } else {
                 None
             },
-            body: self.config.body.clone(),
-        })
+            body: self
+                .config
+                .body
+                .render(host_port, path_and_query, &client)
+                .into_owned(),
+        };
+
+        Ok(HttpResponse::Response(result.into()))
     }
 }
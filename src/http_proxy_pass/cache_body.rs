@@ -0,0 +1,180 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::body::{Body, Frame, SizeHint};
+
+use crate::response_cache::{CacheRequest, CachedResponse, StaleRequest};
+
+/// A response body on its way to the client, copied for the location's cache
+/// and stale store as it goes. The copy is handed to `on_end` once the body has
+/// ended — one that breaks off or outgrows `max_size` is not.
+pub struct CacheBody {
+    inner: BoxBody<Bytes, String>,
+    copy: Option<Vec<u8>>,
+    max_size: usize,
+    on_end: Option<Box<dyn FnOnce(Vec<u8>) + Send + Sync>>,
+}
+
+impl CacheBody {
+    fn store(&mut self) {
+        if let (Some(copy), Some(on_end)) = (self.copy.take(), self.on_end.take()) {
+            on_end(copy);
+        }
+    }
+}
+
+impl Body for CacheBody {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        // CacheBody is Unpin (every field is), so get_mut is safe.
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_frame(cx);
+
+        match &result {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let (Some(data), Some(copy)) = (frame.data_ref(), this.copy.as_mut()) {
+                    if copy.len() + data.len() > this.max_size {
+                        this.copy = None;
+                    } else {
+                        copy.extend_from_slice(data);
+                    }
+                }
+
+                // The client may not poll past the last frame when the body
+                // says it has ended.
+                if this.inner.is_end_stream() {
+                    this.store();
+                }
+            }
+            Poll::Ready(Some(Err(_))) => {
+                this.copy = None;
+            }
+            Poll::Ready(None) => this.store(),
+            Poll::Pending => {}
+        }
+
+        result
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// What the location's cache and stale store make of the upstream's
+/// `response`: a 304 confirming the response the cache revalidated is
+/// answered with that response; one worth keeping has its body copied on its
+/// way to the client and stored once it has ended.
+pub fn keep_response(
+    response: hyper::Response<BoxBody<Bytes, String>>,
+    cache_request: Option<CacheRequest>,
+    stale_request: Option<StaleRequest>,
+) -> hyper::Response<BoxBody<Bytes, String>> {
+    let status = response.status().as_u16();
+    let headers = read_header_map(response.headers());
+
+    let mut to_cache = None;
+    if let Some(cache_request) = cache_request {
+        if status == 304 {
+            if let Some(cached) = cache_request.on_not_modified(&headers) {
+                if let Some(stale_request) = stale_request.as_ref() {
+                    stale_request.store_revalidated(&cached);
+                }
+                return cached_to_response(&cached);
+            }
+        } else if let Some(fresh_for) = cache_request.get_freshness(status, &headers) {
+            to_cache = Some((cache_request, fresh_for));
+        }
+    }
+
+    let to_stale = stale_request.filter(|itm| itm.is_storable(status, &headers));
+
+    if to_cache.is_none() && to_stale.is_none() {
+        return response;
+    }
+
+    let max_size = Option::max(
+        to_cache.as_ref().map(|(itm, _)| itm.get_max_body_size()),
+        to_stale.as_ref().map(|itm| itm.get_max_body_size()),
+    )
+    .unwrap_or_default();
+
+    let on_end = move |body: Vec<u8>| {
+        if let Some(stale_request) = to_stale {
+            stale_request.store(status, headers.clone(), body.clone());
+        }
+        if let Some((cache_request, fresh_for)) = to_cache {
+            cache_request.store(status, headers, body, fresh_for);
+        }
+    };
+
+    let (parts, inner) = response.into_parts();
+
+    let mut body = CacheBody {
+        inner,
+        copy: Some(Vec::new()),
+        max_size,
+        on_end: Some(Box::new(on_end)),
+    };
+
+    // A body that is empty from the start is never polled.
+    if body.inner.is_end_stream() {
+        body.store();
+    }
+
+    http::Response::from_parts(parts, body.boxed())
+}
+
+/// A response from the location's cache, with `Age`.
+pub fn cached_to_response(cached: &CachedResponse) -> hyper::Response<BoxBody<Bytes, String>> {
+    let body = Full::new(Bytes::from(cached.body.clone()))
+        .map_err(|err| match err {})
+        .boxed();
+
+    let mut response = hyper::Response::new(body);
+
+    // The status and headers were the upstream's own, so they read back.
+    if let Ok(status) = hyper::StatusCode::from_u16(cached.status) {
+        *response.status_mut() = status;
+    }
+
+    let headers = response.headers_mut();
+    for (name, value) in cached.headers.iter() {
+        if let (Ok(name), Ok(value)) = (
+            hyper::header::HeaderName::from_bytes(name.as_bytes()),
+            hyper::header::HeaderValue::from_str(value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+
+    headers.insert(
+        hyper::header::AGE,
+        hyper::header::HeaderValue::from(cached.get_age(SystemTime::now())),
+    );
+
+    response
+}
+
+/// The headers of a request or response, as the cache takes them.
+pub fn read_header_map(headers: &hyper::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?;
+            Some((name.as_str().to_string(), value.to_string()))
+        })
+        .collect()
+}
@@ -8,9 +8,10 @@ use my_http_client::{http2::MyHttp2Client, MyHttpClientConnector, MyHttpClientDi
 use my_http_client::{HyperResponse, MyHttpClientError};
 use rust_extensions::date_time::DateTimeAsMicroseconds;
 
+use crate::app::{OutlierProtocol, UpstreamFailure};
 use crate::http2_client_pool::Http2ClientPoolItem;
 use crate::http_proxy_pass::ProxyPassError;
-use crate::upstream_h2_pool::H2Pool;
+use crate::upstream_h2_pool::{H2Pool, PoolDesc};
 
 use super::{HttpResponse, WebSocketUpgradeStream};
 
@@ -271,11 +272,16 @@ pub async fn execute_h2(
 ///   substitute the response (e.g. a replayed DELETE returns 404 after the
 ///   first one succeeded) — same policy as nginx's default for idempotent
 ///   methods. 4xx/5xx are not errors — they return Ok.
+///
+///   With `track_outliers`, the final outcome (after failover) feeds passive
+///   outlier detection (`UpstreamOutliers`): a 5xx, a dead upstream and a
+///   timeout all count against the pool's upstream.
 pub async fn execute_pooled_h2<TStream, TConnector>(
     pool: &Arc<H2Pool<TStream, TConnector>>,
     endpoint_label: &str,
     req: hyper::Request<Full<Bytes>>,
     request_timeout: Duration,
+    track_outliers: bool,
 ) -> Result<HttpResponse, ProxyPassError>
 where
     TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
@@ -294,6 +300,24 @@ where
         return Ok(response);
     }
 
+    let result = execute_with_failover(pool, &req, request_timeout).await;
+    if track_outliers {
+        report_outcome(&pool.desc, &result);
+    }
+    result
+}
+
+/// The non-WebSocket part of [`execute_pooled_h2`].
+async fn execute_with_failover<TStream, TConnector>(
+    pool: &Arc<H2Pool<TStream, TConnector>>,
+    req: &hyper::Request<Full<Bytes>>,
+    request_timeout: Duration,
+) -> Result<HttpResponse, ProxyPassError>
+where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    Arc<MyHttp2Client<TStream, TConnector>>: H2Sender,
+{
     // Non-WS: fail over to another live entry on a transport error, but only
     // for idempotent methods — a POST/PATCH may already have been processed
     // upstream, so re-sending it is not safe. Non-idempotent → single attempt.
@@ -310,7 +334,7 @@ where
         };
 
         let client = entry.client.load_full();
-        match execute_h2(&client, &req, request_timeout).await {
+        match execute_h2(&client, req, request_timeout).await {
             Ok(response) => {
                 entry.last_success.update(DateTimeAsMicroseconds::now());
                 return Ok(response);
@@ -335,6 +359,34 @@ where
     Err(last_err.unwrap_or(ProxyPassError::UpstreamUnavailable))
 }
 
+/// Feed the request's final outcome — after failover — to outlier detection:
+/// a 5xx, a dead upstream or a timeout counts against the pool's upstream,
+/// any other response clears its failure streak.
+fn report_outcome(desc: &PoolDesc, result: &Result<HttpResponse, ProxyPassError>) {
+    let failure = match result {
+        Ok(HttpResponse::Response(response)) if response.status().is_server_error() => {
+            UpstreamFailure::Status(response.status().as_u16())
+        }
+        Ok(_) => {
+            crate::app::APP_CTX
+                .upstream_outliers
+                .register_success(desc.location_id);
+            return;
+        }
+        Err(ProxyPassError::MyHttpClientError(MyHttpClientError::RequestTimeout(_))) => {
+            UpstreamFailure::Timeout
+        }
+        Err(_) => UpstreamFailure::Connect,
+    };
+
+    crate::app::APP_CTX.upstream_outliers.register_failure(
+        desc.location_id,
+        OutlierProtocol::H2,
+        &desc.name,
+        failure,
+    );
+}
+
 /// True for errors that mean the upstream **connection** is unusable (dial
 /// failure, disconnect, transport error) — the trigger for dead-marking and
 /// failover. `RequestTimeout` is excluded on purpose: the server is slow but
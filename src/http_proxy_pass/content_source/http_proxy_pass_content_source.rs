@@ -23,6 +23,7 @@ pub enum HttpProxyPassContentSource {
     PathOverSsh(PathOverSshContentSource),
     PathOverGateway(PathOverGatewayContentSource),
     Static(StaticContentSrc),
+    Redirect(RedirectContentSrc),
     Drop,
     DynamicProxy(DynamicProxyContentSource),
 }
@@ -47,6 +48,7 @@ impl HttpProxyPassContentSource {
             //Self::Http2OverGateway(model) => model.execute(req).await,
             Self::PathOverGateway(model) => model.execute(req).await,
             Self::Static(model) => model.execute(req).await,
+            Self::Redirect(model) => model.execute(req).await,
             Self::Drop => {
                 println!(
                     "DROP location hit: {} {} (Host: {:?}) -> connection dropped",
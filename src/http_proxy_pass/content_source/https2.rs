@@ -11,6 +11,9 @@ pub struct Https2ContentSource {
     pub pool_params: PoolParams,
     pub factory: ConnectorFactory<HttpTlsConnector>,
     pub request_timeout: std::time::Duration,
+    /// Whether outcomes are reported to outlier detection — not for the single
+    /// upstream of a location, which nothing balances away from.
+    pub track_outliers: bool,
 }
 
 impl Https2ContentSource {
@@ -30,6 +33,13 @@ impl Https2ContentSource {
             ),
         };
 
-        execute_pooled_h2(&pool, &self.pool_desc.name, req, self.request_timeout).await
+        execute_pooled_h2(
+            &pool,
+            &self.pool_desc.name,
+            req,
+            self.request_timeout,
+            self.track_outliers,
+        )
+        .await
     }
 }
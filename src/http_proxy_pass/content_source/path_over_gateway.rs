@@ -1,30 +1,151 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
 
+use my_http_server::WebContentType;
 use rust_extensions::remote_endpoint::RemoteEndpointOwned;
 
+use crate::http_content_source::{
+    parse_dir_entries, DirEntry, FileBodyReader, FileLookup, FileLookupResult, FileRequestHeaders,
+    FileResponse, FileSource, FileVersion, FilesFolder, FolderItem,
+};
 use crate::http_proxy_pass::ProxyPassError;
+use crate::tcp_gateway::{
+    FileRequestError, FileStreamEvent, FileStreamReader, GetFileStatus, TcpGatewayConnection,
+};
 
 use super::*;
 
 pub struct PathOverGatewayContentSource {
     pub gateway_id: Arc<String>,
     pub path: Arc<RemoteEndpointOwned>,
-    pub default_file: Option<String>,
+    pub lookup: Arc<FileLookup>,
 }
 
 impl PathOverGatewayContentSource {
+    /// The file is streamed from the gateway as the body is sent — a range of
+    /// it is all that is asked for.
     pub async fn execute(
         &self,
         req: http::Request<http_body_util::Full<bytes::Bytes>>,
     ) -> Result<HttpResponse, ProxyPassError> {
-        let result = crate::http_proxy_pass::executors::get_file_from_gateway(
-            self.gateway_id.as_str(),
-            self.path.as_str(),
-            &self.default_file,
-            &req,
-        )
-        .await?;
-
-        Ok(HttpResponse::Response(result.into()))
+        let Some(gateway) = crate::app::APP_CTX.get_gateway_by_id(self.gateway_id.as_str()) else {
+            return Err(ProxyPassError::GatewayError);
+        };
+
+        let folder = GatewayFolder {
+            gateway,
+            root: self.path.as_str(),
+        };
+
+        let request = FileRequestHeaders::from_header_map(req.headers());
+        let with_body = req.method() != hyper::Method::HEAD;
+
+        let lookup_result = self
+            .lookup
+            .lookup(&folder, req.uri().path(), &request)
+            .await?;
+
+        let (response, source) = match lookup_result {
+            FileLookupResult::File { path, file, coding } => {
+                let content_type = WebContentType::detect_by_extension(&path);
+                let mut response = FileResponse::new(
+                    &file.version,
+                    content_type.as_ref().map(|itm| itm.as_str()),
+                    &request,
+                );
+                self.lookup.push_coding_headers(&mut response, coding);
+
+                let reader = FileStreamReader::new(folder.gateway.clone(), file.full_path);
+                (response, FileSource::Gateway(reader))
+            }
+            FileLookupResult::Response(response) => (response, FileSource::Memory(vec![])),
+            FileLookupResult::NotFound => return Err(ProxyPassError::NoLocationFound),
+        };
+
+        let body = FileBodyReader::for_response(source, &response, with_body);
+        Ok(HttpResponse::Response(response.to_hyper_response(body)))
+    }
+}
+
+/// A file of the folder on the other side of a gateway.
+struct GatewayFile {
+    full_path: String,
+    version: FileVersion,
+}
+
+/// The folder on the other side of a gateway.
+struct GatewayFolder<'s> {
+    gateway: Arc<TcpGatewayConnection>,
+    root: &'s str,
+}
+
+impl<'s> GatewayFolder<'s> {
+    fn get_full_path(&self, path: &str) -> String {
+        crate::http_proxy_pass::executors::merge_path_and_file(self.root, path, &None)
+    }
+}
+
+#[async_trait::async_trait]
+impl<'s> FilesFolder for GatewayFolder<'s> {
+    type File = GatewayFile;
+    type Error = ProxyPassError;
+
+    /// Asks for none of the content: the head of the stream is what the path
+    /// is.
+    async fn open(&self, path: &str) -> Result<FolderItem<GatewayFile>, ProxyPassError> {
+        let full_path = self.get_full_path(path);
+
+        let mut receiver = self
+            .gateway
+            .start_file_stream(full_path.as_str(), 0, 0)
+            .map_err(|_| ProxyPassError::GatewayError)?;
+
+        let result = match receiver.recv().await {
+            Ok(FileStreamEvent::Head {
+                status: GetFileStatus::Ok,
+                size,
+                modified,
+            }) => FolderItem::File(GatewayFile {
+                full_path,
+                version: FileVersion {
+                    size,
+                    modified: modified.map(|itm| UNIX_EPOCH + Duration::from_secs(itm)),
+                },
+            }),
+            Ok(FileStreamEvent::Head {
+                status: GetFileStatus::Directory,
+                ..
+            }) => FolderItem::Dir,
+            Ok(_) => FolderItem::NotFound,
+            Err(_) => return Err(ProxyPassError::GatewayError),
+        };
+
+        Ok(result)
+    }
+
+    async fn list_dir(&self, path: &str) -> Result<Vec<DirEntry>, ProxyPassError> {
+        let full_path = self.get_full_path(path);
+
+        let mut receiver = self
+            .gateway
+            .start_file_stream(full_path.as_str(), 0, u64::MAX)
+            .map_err(|_| ProxyPassError::GatewayError)?;
+
+        match receiver.recv().await {
+            Ok(FileStreamEvent::Head {
+                status: GetFileStatus::Directory,
+                ..
+            }) => {}
+            Ok(_) => return Ok(vec![]),
+            Err(_) => return Err(ProxyPassError::GatewayError),
+        }
+
+        match receiver.read_to_end().await {
+            Ok(entries) => Ok(parse_dir_entries(&entries)),
+            Err(FileRequestError::Error(_)) => Ok(vec![]),
+            Err(_) => Err(ProxyPassError::GatewayError),
+        }
     }
 }
@@ -11,6 +11,9 @@ pub struct UnixHttp2ContentSource {
     pub pool_params: PoolParams,
     pub factory: ConnectorFactory<UnixSocketHttpConnector>,
     pub request_timeout: std::time::Duration,
+    /// Whether outcomes are reported to outlier detection — not for the single
+    /// upstream of a location, which nothing balances away from.
+    pub track_outliers: bool,
 }
 
 impl UnixHttp2ContentSource {
@@ -30,6 +33,13 @@ impl UnixHttp2ContentSource {
             ),
         };
 
-        execute_pooled_h2(&pool, &self.pool_desc.name, req, self.request_timeout).await
+        execute_pooled_h2(
+            &pool,
+            &self.pool_desc.name,
+            req,
+            self.request_timeout,
+            self.track_outliers,
+        )
+        .await
     }
 }
@@ -20,6 +20,14 @@ pub enum ProxyPassError {
     Unauthorized,
     UserIsForbidden,
     IpRestricted(String),
+    /// Over a `rate_limit`; the seconds the client is to wait.
+    TooManyRequests(u64),
+    /// Over a location's `max_concurrent_requests`, with its queue full or
+    /// the wait over.
+    LocationIsBusy,
+    /// Over the endpoint's or the location's `max_header_size`,
+    /// `max_headers`, `max_uri_length` or `max_body_size`.
+    RequestLimitExceeded(crate::configurations::RequestLimitExceeded),
     Disconnected,
     Timeout,
     Disposed,
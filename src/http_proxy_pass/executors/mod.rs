@@ -1,4 +1,2 @@
-mod get_file_from_gateway;
-pub use get_file_from_gateway::*;
 mod merge_path_and_file;
 pub use merge_path_and_file::*;
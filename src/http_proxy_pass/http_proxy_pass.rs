@@ -2,22 +2,24 @@ use std::sync::Arc;
 
 use arc_swap::ArcSwapOption;
 use bytes::Bytes;
-use http_body_util::combinators::BoxBody;
+use http_body_util::{combinators::BoxBody, Full};
 use hyper_util::rt::TokioIo;
 use my_http_client::utils::into_full_body_response;
 use my_http_client::MyHttpClientDisconnect;
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::{
+    app::RateLimitClient,
     configurations::*,
     http_proxy_pass::GoogleAuthResult,
+    response_cache::{CacheLookup, CachedResponse, StaleRevalidation},
     tcp_listener::https::ClientCertificateData,
     types::{ConnectionIp, HttpTimeouts},
 };
 
 use super::{
     HttpListenPortInfo, HttpProxyPassIdentity, HttpProxyPassInner, HttpRequestBuilder,
-    ProxyPassError, ProxyPassLocations, WebSocketUpgrade,
+    LocationIndex, ProxyPassError, ProxyPassLocations, WebSocketUpgrade,
 };
 
 pub struct HttpProxyPass {
@@ -77,11 +79,36 @@ impl HttpProxyPass {
 
         let mut req = HttpRequestBuilder::new(req);
 
-        let (request, content_source, location_index, location_debug) = {
+        let (
+            request,
+            content_source,
+            mut upstream_lease,
+            sticky,
+            split,
+            backups,
+            mirror,
+            retry,
+            cache,
+            stale,
+            revalidating,
+            concurrency_permit,
+            response_rewrite,
+            sub_filter,
+            location_index,
+            location_debug,
+        ) = {
             let Some(inner) = self.inner.load_full() else {
                 return Err(ProxyPassError::Disposed);
             };
 
+            // A CORS preflight never carries credentials, so it is answered
+            // before they are asked for — and never reaches the upstream.
+            if let Some(response) =
+                super::http_response_builder::answer_cors_preflight(self, &inner, &req.parts)
+            {
+                return Ok(Ok(response));
+            }
+
             match self.handle_auth_with_g_auth(&req).await {
                 GoogleAuthResult::Passed(user) => {
                     if let Some(email) = user {
@@ -123,23 +150,18 @@ impl HttpProxyPass {
 
             let proxy_pass_location = inner.locations.find(&location_index);
 
+            req.apply_request_limits(
+                &self
+                    .endpoint_info
+                    .get_request_limits(&proxy_pass_location.config),
+            )?;
+
             let location_debug = crate::app::APP_CTX
                 .debug_flags
                 .is_location_debug(location_index.id);
 
             req.process_headers(self, &inner, proxy_pass_location);
 
-            let request = req.into_request(self, proxy_pass_location).await?;
-
-            if location_debug {
-                crate::app::APP_CTX.proxy_logs.write(
-                    self.endpoint_info.host_endpoint.as_str(),
-                    Some(location_index.id),
-                    ip.clone(),
-                    format!("Request parts: {:?}", request.req_parts),
-                );
-            }
-
             if let Some(ip_addr) = connection_ip.get_ip_addr() {
                 if let Some(white_list_ip) = proxy_pass_location.config.ip_white_list_id.as_ref() {
                     if !crate::app::APP_CTX
@@ -157,29 +179,443 @@ impl HttpProxyPass {
                 }
             }
 
+            // Counted once the identity is known, so `key: identity` has it, and
+            // before the body is read — a limited client's body is never buffered.
+            if self
+                .endpoint_info
+                .has_rate_limits(&proxy_pass_location.config)
+            {
+                let req_parts = &req.parts;
+                let identity = inner.identity.load_full();
+                let get_header = |name: &str| {
+                    req_parts
+                        .headers
+                        .get(name)
+                        .and_then(|itm| itm.to_str().ok())
+                };
+                let client = RateLimitClient {
+                    ip: connection_ip.get_ip_addr(),
+                    identity: identity.as_ref().map(|itm| itm.as_str()),
+                    get_header: &get_header,
+                    query: req_parts.uri.query(),
+                };
+
+                if let Err(retry_after) = self
+                    .endpoint_info
+                    .check_rate_limits(&proxy_pass_location.config, &client)
+                {
+                    return Err(ProxyPassError::TooManyRequests(
+                        crate::app::get_retry_after_seconds(retry_after),
+                    ));
+                }
+            }
+
+            let mut request = req.into_request(self, proxy_pass_location).await?;
+
+            if location_debug {
+                crate::app::APP_CTX.proxy_logs.write(
+                    self.endpoint_info.host_endpoint.as_str(),
+                    Some(location_index.id),
+                    ip.clone(),
+                    format!("Request parts: {:?}", request.req_parts),
+                );
+            }
+
+            // A location with a cache answers what it holds a fresh response
+            // for without an upstream; on a miss the cache waits for the
+            // upstream's answer. A websocket upgrade is never cached.
+            let cache = match proxy_pass_location.config.cache.as_ref() {
+                Some(cache) if request.web_socket_upgrade.is_none() => {
+                    let req_parts = &request.req_parts;
+                    let host = req_parts
+                        .headers
+                        .get(hyper::header::HOST)
+                        .and_then(|itm| itm.to_str().ok())
+                        .or(req_parts.uri.host())
+                        .unwrap_or_default();
+                    let path_and_query = req_parts
+                        .uri
+                        .path_and_query()
+                        .map(|itm| itm.as_str())
+                        .unwrap_or("/");
+
+                    let lookup = cache
+                        .lookup(
+                            req_parts.method.as_str(),
+                            host,
+                            path_and_query,
+                            inner.identity.load_full().is_some(),
+                            super::read_header_map(&req_parts.headers),
+                        )
+                        .await;
+
+                    match lookup {
+                        CacheLookup::Bypass => None,
+                        CacheLookup::Hit(cached) => {
+                            return Ok(Ok(self.respond_with_kept(
+                                &inner,
+                                &cached,
+                                &request.req_parts,
+                                &location_index,
+                            )));
+                        }
+                        CacheLookup::Miss(cache_request) => {
+                            // A stale response is revalidated: the upstream is
+                            // asked to confirm it rather than to send it again.
+                            let headers = request.request.headers_mut();
+                            for (name, value) in cache_request.get_validation_headers() {
+                                if let Ok(value) = hyper::header::HeaderValue::from_str(&value) {
+                                    headers.insert(name, value);
+                                }
+                            }
+                            Some(cache_request)
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            // A location with `stale` keeps the last good response to every
+            // GET; one young enough is served at once while the request goes
+            // on to refresh it.
+            let mut stale = match proxy_pass_location.config.stale.as_ref() {
+                Some(stale) if request.web_socket_upgrade.is_none() => {
+                    let req_parts = &request.req_parts;
+                    let host = req_parts
+                        .headers
+                        .get(hyper::header::HOST)
+                        .and_then(|itm| itm.to_str().ok())
+                        .or(req_parts.uri.host())
+                        .unwrap_or_default();
+                    let path_and_query = req_parts
+                        .uri
+                        .path_and_query()
+                        .map(|itm| itm.as_str())
+                        .unwrap_or("/");
+
+                    stale.begin(
+                        req_parts.method.as_str(),
+                        host,
+                        path_and_query,
+                        inner.identity.load_full().is_some(),
+                        super::read_header_map(&req_parts.headers),
+                    )
+                }
+                _ => None,
+            };
+
+            let revalidating = match stale.as_mut().and_then(|itm| itm.find_to_revalidate()) {
+                Some(StaleRevalidation::Serve(kept)) => {
+                    return Ok(Ok(self.respond_with_kept(
+                        &inner,
+                        &kept,
+                        &request.req_parts,
+                        &location_index,
+                    )));
+                }
+                Some(StaleRevalidation::Refresh(kept)) => Some(kept),
+                None => None,
+            };
+
+            // A location's `max_concurrent_requests` counts the request until
+            // the upstream answers it. Past it, and past the queue, the request
+            // is answered with 503 — or with the kept response it was to
+            // refresh.
+            let concurrency_permit = match proxy_pass_location.config.concurrency_limit.as_ref() {
+                Some(limiter) => match limiter.acquire().await {
+                    Some(permit) => Some(permit),
+                    None => {
+                        if let Some(kept) = revalidating.as_ref() {
+                            return Ok(Ok(self.respond_with_kept(
+                                &inner,
+                                kept,
+                                &request.req_parts,
+                                &location_index,
+                            )));
+                        }
+
+                        return Err(ProxyPassError::LocationIsBusy);
+                    }
+                },
+                None => None,
+            };
+
+            let sticky = proxy_pass_location.get_sticky_request(
+                &request.req_parts.headers,
+                self.listening_port_info.endpoint_type.is_https_or_mcp(),
+            );
+            let split_match = proxy_pass_location.get_split_match(&request.req_parts.headers);
+            let (content_source, upstream_lease) = proxy_pass_location.pick_content_source(
+                &connection_ip,
+                sticky.as_ref(),
+                split_match,
+            );
+            let split = proxy_pass_location.config.split.clone();
+
+            let primary = match upstream_lease.as_ref() {
+                Some(lease) => lease.get_upstream().proxy_pass_to.to_string(),
+                None => proxy_pass_location.config.get_proxy_pass_to_as_string(),
+            };
+
+            // A websocket upgrade is never failed over: the upstream that
+            // answered it owns the tunnel.
+            let backups = match request.web_socket_upgrade {
+                Some(_) => None,
+                None => proxy_pass_location
+                    .get_backups()
+                    .map(|(backups, sources)| (backups, sources, primary.clone())),
+            };
+
+            // Nor is it mirrored: a copy of it could not be tunneled anywhere.
+            let mirror = match request.web_socket_upgrade {
+                Some(_) => None,
+                None => proxy_pass_location.sample_mirror(),
+            };
+
+            // Nor retried, and only for a method the retry policy repeats.
+            let retry = match request.web_socket_upgrade {
+                Some(_) => None,
+                None => proxy_pass_location
+                    .config
+                    .retry
+                    .as_ref()
+                    .filter(|itm| itm.retries_method(request.request.method().as_str()))
+                    .map(|itm| (itm.clone(), primary)),
+            };
+
             (
                 request,
-                proxy_pass_location.content_source.clone(),
+                content_source,
+                upstream_lease,
+                sticky,
+                split,
+                backups,
+                mirror,
+                retry,
+                cache,
+                stale,
+                revalidating,
+                concurrency_permit,
+                proxy_pass_location.config.response_rewrite.clone(),
+                proxy_pass_location
+                    .config
+                    .sub_filter
+                    .clone()
+                    .filter(|_| request.req_parts.method != hyper::Method::HEAD),
                 location_index,
                 location_debug,
             )
         };
 
+        // The body is fully buffered on this path, so a request retried or
+        // failing over is re-sent from a copy taken before the first attempt.
+        let resend_request = match backups.is_some() || retry.is_some() {
+            true => Some(clone_request(&request.request)),
+            false => None,
+        };
+
+        if let Some((mirror, mirror_source)) = mirror {
+            spawn_mirror_request(&mirror, mirror_source, &request.request);
+        }
+
+        // The client is answered with the kept response; the request refreshes
+        // it in the background, with no retry or fail over.
+        if let Some(kept) = revalidating {
+            let request_to_send = request.request;
+            crate::app::spawn_named("h2_stale_revalidation", async move {
+                let result = content_source.send_request(request_to_send).await;
+                drop(upstream_lease);
+                drop(concurrency_permit);
+
+                if let Ok(super::content_source::HttpResponse::Response(mut response)) = result {
+                    if let Some(response_rewrite) = response_rewrite.as_ref() {
+                        super::http_response_builder::rewrite_resp_headers(
+                            response.headers_mut(),
+                            response_rewrite,
+                        );
+                    }
+                    if let Some(sub_filter) = sub_filter.as_ref() {
+                        response = super::apply_sub_filter(response, sub_filter);
+                    }
+                    let response = super::keep_response(response, cache, stale);
+                    let mut body = response.into_body();
+                    while let Some(frame) = http_body_util::BodyExt::frame(&mut body).await {
+                        if frame.is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+
+            let Some(inner) = self.inner.load_full() else {
+                return Err(ProxyPassError::Disposed);
+            };
+
+            return Ok(Ok(self.respond_with_kept(
+                &inner,
+                &kept,
+                &request.req_parts,
+                &location_index,
+            )));
+        }
+
+        let mut result = content_source.send_request(request.request).await;
+
+        if let (Some((retry, primary)), Some(retry_request)) = (retry, resend_request.as_ref()) {
+            let log_scope = crate::app::ProxyLogScope::new(
+                Arc::new(endpoint.to_string()),
+                location_index.id,
+                ip.clone(),
+            );
+            result = send_retries(
+                &retry,
+                &content_source,
+                retry_request,
+                result,
+                &log_scope,
+                &primary,
+            )
+            .await;
+        }
+
+        // The split branch is judged by its own answer, before any fail over.
+        if let (Some(split), Some(lease)) = (split.as_ref(), upstream_lease.as_ref()) {
+            let failed = match &result {
+                Ok(super::content_source::HttpResponse::Response(response)) => {
+                    response.status().as_u16() >= 500
+                }
+                Ok(_) => false,
+                Err(_) => true,
+            };
+            split.report(lease.get_index(), failed);
+        }
+
+        if let (Some((backups, backup_sources, primary)), Some(resend_request)) =
+            (backups, resend_request.as_ref())
+        {
+            let mut failed = primary;
+            let mut tried = 0;
+
+            loop {
+                let reason = match &result {
+                    Ok(super::content_source::HttpResponse::Response(response))
+                        if backups.fails_over_on(response.status().as_u16()) =>
+                    {
+                        format!("answered {}", response.status().as_u16())
+                    }
+                    Err(err) if fails_over_on_error(err, resend_request.method()) => {
+                        format!("failed: {:?}", err)
+                    }
+                    _ => break,
+                };
+
+                let Some((index, backup)) = backups.next(tried, |itm| {
+                    crate::app::APP_CTX.upstream_outliers.admit(itm.id)
+                }) else {
+                    break;
+                };
+                tried = index + 1;
+
+                crate::app::APP_CTX.proxy_logs.write(
+                    endpoint,
+                    Some(location_index.id),
+                    ip.clone(),
+                    format!(
+                        "upstream {} {}; failing over to backup {}",
+                        failed,
+                        reason,
+                        backup.proxy_pass_to.to_string()
+                    ),
+                );
+
+                upstream_lease = None;
+                failed = backup.proxy_pass_to.to_string();
+                result = backup_sources[index]
+                    .send_request(clone_request(resend_request))
+                    .await;
+            }
+        }
+
         // Upstream-unreachable failures are always captured per-location,
         // regardless of debug mode — debug only adds the request/response
         // detail above and below.
-        let result = match content_source.send_request(request.request).await {
+        let result = match result {
             Ok(result) => result,
             Err(err) => {
+                let message = match upstream_lease.as_ref() {
+                    Some(lease) => format!(
+                        "Upstream {} request failed: {:?}",
+                        lease.get_upstream().proxy_pass_to.to_string(),
+                        err
+                    ),
+                    None => format!("Upstream request failed: {:?}", err),
+                };
                 crate::app::APP_CTX.proxy_logs.write_location(
                     location_index.id,
                     ip.clone(),
-                    format!("Upstream request failed: {:?}", err),
+                    message,
                 );
+
+                // The last good response stands in for the failure.
+                if let Some(kept) = stale.as_ref().and_then(|itm| itm.find_on_error()) {
+                    let Some(inner) = self.inner.load_full() else {
+                        return Err(ProxyPassError::Disposed);
+                    };
+
+                    return Ok(Ok(self.respond_with_kept(
+                        &inner,
+                        &kept,
+                        &request.req_parts,
+                        &location_index,
+                    )));
+                }
+
                 return Err(err);
             }
         };
 
+        // So it does for an upstream answering with an error status.
+        if let (Some(stale), super::content_source::HttpResponse::Response(response)) =
+            (stale.as_ref(), &result)
+        {
+            let kept = match stale.serves_on_status(response.status().as_u16()) {
+                true => stale.find_on_error(),
+                false => None,
+            };
+
+            if let Some(kept) = kept {
+                let Some(inner) = self.inner.load_full() else {
+                    return Err(ProxyPassError::Disposed);
+                };
+
+                return Ok(Ok(self.respond_with_kept(
+                    &inner,
+                    &kept,
+                    &request.req_parts,
+                    &location_index,
+                )));
+            }
+        }
+
+        // Pinning by the upstream the request was balanced to — a backup does
+        // not pin.
+        let set_cookie = match (sticky.as_ref(), upstream_lease.as_ref()) {
+            (Some(sticky), Some(lease)) => {
+                let response_session = sticky.get_session_header().and_then(|name| {
+                    get_response_headers(&result)
+                        .get(name)
+                        .and_then(|itm| itm.to_str().ok())
+                        .map(|itm| itm.trim())
+                });
+                sticky.on_response(response_session, lease)
+            }
+            _ => None,
+        };
+
+        // The upstream has answered; the body streams on from here without
+        // counting as outstanding.
+        drop(upstream_lease);
+
         let mut response = match result {
             super::content_source::HttpResponse::Response(response) => {
                 if location_debug {
@@ -298,6 +734,22 @@ impl HttpProxyPass {
             }
         };
 
+        // Before it is kept, so a kept response is never rewritten twice.
+        if let Some(response_rewrite) = response_rewrite.as_ref() {
+            super::http_response_builder::rewrite_resp_headers(
+                response.headers_mut(),
+                response_rewrite,
+            );
+        }
+
+        if let Some(sub_filter) = sub_filter.as_ref() {
+            response = super::apply_sub_filter(response, sub_filter);
+        }
+
+        if cache.is_some() || stale.is_some() {
+            response = super::keep_response(response, cache, stale);
+        }
+
         let Some(inner) = self.inner.load_full() else {
             return Err(ProxyPassError::Disposed);
         };
@@ -310,12 +762,40 @@ impl HttpProxyPass {
             &location_index,
         );
 
+        if let Some(set_cookie) = set_cookie {
+            if let Ok(value) = hyper::header::HeaderValue::from_str(&set_cookie) {
+                response
+                    .headers_mut()
+                    .append(hyper::header::SET_COOKIE, value);
+            }
+        }
+
         return Ok(Ok(response));
     }
 
     pub async fn dispose(&self) {
         self.inner.store(None);
     }
+
+    /// A response from the location's cache or stale store, with the
+    /// endpoint's response headers applied.
+    fn respond_with_kept(
+        &self,
+        inner: &HttpProxyPassInner,
+        kept: &CachedResponse,
+        req_parts: &hyper::http::request::Parts,
+        location_index: &LocationIndex,
+    ) -> hyper::Response<BoxBody<Bytes, String>> {
+        let mut response = super::cached_to_response(kept);
+        super::http_response_builder::modify_resp_headers(
+            self,
+            inner,
+            req_parts,
+            response.headers_mut(),
+            location_index,
+        );
+        response
+    }
 }
 
 fn spawn_websocket_pump<S>(
@@ -558,3 +1038,152 @@ async fn copy_one_direction<R, W>(
 
     let _ = writer.shutdown().await;
 }
+
+fn get_response_headers(result: &super::content_source::HttpResponse) -> &hyper::HeaderMap {
+    match result {
+        super::content_source::HttpResponse::Response(response) => response.headers(),
+        super::content_source::HttpResponse::WebSocketUpgrade { response, .. } => {
+            response.headers()
+        }
+    }
+}
+
+/// A copy of a buffered request, to re-send it to a backup.
+fn clone_request(req: &hyper::Request<Full<Bytes>>) -> hyper::Request<Full<Bytes>> {
+    let mut result = hyper::Request::new(req.body().clone());
+    *result.method_mut() = req.method().clone();
+    *result.uri_mut() = req.uri().clone();
+    *result.version_mut() = req.version();
+    *result.headers_mut() = req.headers().clone();
+    result
+}
+
+/// Hands a copy of a buffered request to the location's `mirror_to` and
+/// returns at once. The response is read to the end and dropped; a mirror that
+/// is down or answers 5xx only shows in the metrics.
+fn spawn_mirror_request(
+    mirror: &Arc<LocationMirror>,
+    mirror_source: Arc<super::content_source::HttpProxyPassContentSource>,
+    req: &hyper::Request<Full<Bytes>>,
+) {
+    let label = mirror.get_label();
+
+    let body_size = hyper::body::Body::size_hint(req.body())
+        .exact()
+        .unwrap_or(u64::MAX);
+    let lease = match body_size <= mirror.get_max_body_size() as u64 {
+        true => mirror.try_start(),
+        false => None,
+    };
+
+    let Some(lease) = lease else {
+        crate::app::APP_CTX.prometheus.inc_mirror_skipped(label);
+        return;
+    };
+
+    crate::app::APP_CTX.prometheus.inc_mirror_requests(label);
+
+    let req = clone_request(req);
+    crate::app::spawn_named("h2_mirror_request", async move {
+        let failed = match mirror_source.send_request(req).await {
+            Ok(super::content_source::HttpResponse::Response(response)) => {
+                let mut failed = response.status().is_server_error();
+                let mut body = response.into_body();
+                while let Some(frame) = http_body_util::BodyExt::frame(&mut body).await {
+                    if frame.is_err() {
+                        failed = true;
+                        break;
+                    }
+                }
+                failed
+            }
+            Ok(super::content_source::HttpResponse::WebSocketUpgrade { .. }) => false,
+            Err(_) => true,
+        };
+
+        if failed {
+            crate::app::APP_CTX
+                .prometheus
+                .inc_mirror_failures(lease.get_mirror().get_label());
+        }
+    });
+}
+
+/// Sends the request to the same upstream again for as long as the location's
+/// `retry` covers how the last attempt went. Gives back the last attempt's
+/// result.
+async fn send_retries(
+    retry: &LocationRetry,
+    content_source: &super::content_source::HttpProxyPassContentSource,
+    request: &hyper::Request<Full<Bytes>>,
+    mut result: Result<super::content_source::HttpResponse, ProxyPassError>,
+    log_scope: &crate::app::ProxyLogScope,
+    upstream: &str,
+) -> Result<super::content_source::HttpResponse, ProxyPassError> {
+    retry.register_request();
+
+    let mut retries = 0;
+    loop {
+        let (failure, reason) = match &result {
+            Ok(super::content_source::HttpResponse::Response(response)) => {
+                let status = response.status().as_u16();
+                (RetryFailure::Status(status), format!("answered {}", status))
+            }
+            Ok(_) => return result,
+            Err(ProxyPassError::Timeout) => (RetryFailure::Timeout, "timed out".to_string()),
+            Err(
+                ProxyPassError::DropConnection
+                | ProxyPassError::Disposed
+                | ProxyPassError::ConnectionIsDisposed,
+            ) => return result,
+            Err(err) => (RetryFailure::Connect, format!("failed: {:?}", err)),
+        };
+
+        if !retry.retries_on(failure) || retries + 1 >= retry.get_attempts() {
+            return result;
+        }
+
+        if !retry.try_spend() {
+            crate::app::APP_CTX
+                .prometheus
+                .inc_retries_refused(retry.get_label());
+            return result;
+        }
+
+        retries += 1;
+        crate::app::APP_CTX
+            .prometheus
+            .inc_retries(retry.get_label());
+
+        let backoff = retry.next_backoff(retries);
+        log_scope.write_always(format!(
+            "upstream {} {}; retry {} of {} in {}ms",
+            upstream,
+            reason,
+            retries,
+            retry.get_attempts() - 1,
+            backoff.as_millis()
+        ));
+
+        // The failed response goes before the wait, with its connection.
+        drop(result);
+        tokio::time::sleep(backoff).await;
+
+        result = content_source.send_request(clone_request(request)).await;
+    }
+}
+
+/// Whether a failed request goes on to the next backup. An upstream that could
+/// not be reached never saw the request; any other transport failure may have
+/// reached it, so only an idempotent request is re-sent. A timeout is never
+/// re-sent — the upstream may still be working on it.
+fn fails_over_on_error(err: &ProxyPassError, method: &hyper::Method) -> bool {
+    match err {
+        ProxyPassError::UpstreamUnavailable => true,
+        ProxyPassError::Timeout
+        | ProxyPassError::DropConnection
+        | ProxyPassError::Disposed
+        | ProxyPassError::ConnectionIsDisposed => false,
+        _ => method.is_idempotent(),
+    }
+}
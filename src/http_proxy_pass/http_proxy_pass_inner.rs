@@ -43,12 +43,22 @@ impl HttpProxyPassInner {
             match token {
                 ContentToken::Text(text) => result.push_str(text),
                 ContentToken::Placeholder(placeholder) => match placeholder {
-                    "ENDPOINT_IP" => {
+                    "ENDPOINT_IP" | "CLIENT_IP" => {
                         if let Some(ip) = self.connection_ip.get_ip_addr() {
                             result.push_str(format!("{}", ip).as_str());
                         }
                     }
 
+                    "COUNTRY" => {
+                        if let Some(country) = self
+                            .connection_ip
+                            .get_ip_addr()
+                            .and_then(crate::ip_db::lookup_country)
+                        {
+                            result.push_str(std::str::from_utf8(&country).unwrap_or_default());
+                        }
+                    }
+
                     "PATH_AND_QUERY" => {
                         if let Some(path_and_query) = req.get_path_and_query() {
                             result.push_str(path_and_query);
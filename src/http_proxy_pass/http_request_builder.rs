@@ -30,6 +30,9 @@ pub struct TransformedRequest {
 pub struct HttpRequestBuilder {
     pub parts: Parts,
     body: Incoming,
+    /// Set by [`HttpRequestBuilder::apply_request_limits`]; the body is read
+    /// no further than this.
+    max_body_size: Option<usize>,
 }
 
 /// Whether the client side of this exchange is HTTP/1.x ON THE WIRE. The
@@ -58,33 +61,46 @@ fn is_h2_websocket_connect(parts: &Parts) -> bool {
 /// the whole protocol lives on that single URL — the client's path only selects
 /// the location, it carries nothing the upstream can use. So every request is
 /// rewritten onto the path configured in `proxy_pass_to`, exactly as the h1 byte
-/// pipeline does via `H1Reader::compile_headers`. A no-op for every other
-/// location type, and idempotent when the two paths are equal.
+/// pipeline does via `H1Reader::compile_headers`. An ordinary location is
+/// rewritten only when it configures `strip_prefix` / `rewrite` /
+/// `append_to_upstream_path` ([`LocationPathRewrite`]); otherwise this is a
+/// no-op, and it is idempotent for an MCP location whose two paths are equal.
 ///
 /// Only the path-and-query is replaced — scheme and authority are carried over
 /// untouched. A request that arrived over h2 has an ABSOLUTE uri, and an h2
 /// upstream request needs `:scheme` / `:authority` to be legal at all (h2 refuses
 /// to send one without them, RFC 9113 §8.3.1), so overwriting the whole uri with
 /// the origin-form upstream path would break every `mcp-h2` request.
-fn rewrite_mcp_path(uri: &mut Uri, location: &ProxyPassLocation) {
-    let Some(mcp_path) = crate::h1_remote_connection::mcp_path(&location.config.proxy_pass_to)
-    else {
+fn rewrite_upstream_path(uri: &mut Uri, location: &ProxyPassLocation) {
+    if let Some(mcp_path) = crate::h1_remote_connection::mcp_path(&location.config.proxy_pass_to)
+    {
+        apply_upstream_path(uri, mcp_path, location.config.id_string.as_str());
+        return;
+    }
+
+    let Some(path_rewrite) = location.config.path_rewrite.as_ref() else {
         return;
     };
 
-    apply_upstream_path(uri, mcp_path, location.config.id_string.as_str());
+    let path_and_query = match uri.path_and_query() {
+        Some(path_and_query) => path_and_query.as_str(),
+        None => "/",
+    };
+
+    let upstream_path = path_rewrite.apply(path_and_query);
+    apply_upstream_path(uri, &upstream_path, location.config.id_string.as_str());
 }
 
 /// Swaps the uri's path-and-query for `upstream_path`, keeping everything else.
 /// On bad input the uri is left untouched and the reason is printed — forwarding
-/// the client's own path would silently 404 on the MCP upstream, so it must not
+/// the client's own path would silently 404 on the upstream, so it must not
 /// happen quietly.
 fn apply_upstream_path(uri: &mut Uri, upstream_path: &str, location_id: &str) {
     let path_and_query = match upstream_path.parse::<hyper::http::uri::PathAndQuery>() {
         Ok(path_and_query) => path_and_query,
         Err(err) => {
             println!(
-                "MCP location [{}]: upstream path '{}' is not a valid path: {:?}",
+                "Location [{}]: upstream path '{}' is not a valid path: {:?}",
                 location_id, upstream_path, err
             );
             return;
@@ -98,7 +114,7 @@ fn apply_upstream_path(uri: &mut Uri, upstream_path: &str, location_id: &str) {
         Ok(rewritten) => *uri = rewritten,
         Err(err) => {
             println!(
-                "MCP location [{}]: can not apply upstream path '{}' to uri '{}': {:?}",
+                "Location [{}]: can not apply upstream path '{}' to uri '{}': {:?}",
                 location_id, upstream_path, uri, err
             );
         }
@@ -109,7 +125,54 @@ impl HttpRequestBuilder {
     pub fn new(src: hyper::Request<hyper::body::Incoming>) -> Self {
         let (parts, body) = src.into_parts();
 
-        Self { parts, body }
+        Self {
+            parts,
+            body,
+            max_body_size: None,
+        }
+    }
+
+    /// Checks the head, and the body size the request announces, against
+    /// `limits`. A body of unknown size is cut off when it outgrows
+    /// `max_body_size` as it is read.
+    pub fn apply_request_limits(&mut self, limits: &RequestLimits) -> Result<(), ProxyPassError> {
+        let uri_length = match self.parts.uri.path_and_query() {
+            Some(path_and_query) => path_and_query.as_str().len(),
+            None => 1,
+        };
+
+        // As it would take on the wire over HTTP/1.1: `{method} {uri}
+        // HTTP/1.1\r\n`, then `{name}: {value}\r\n` for every header.
+        let header_size = self.parts.method.as_str().len()
+            + uri_length
+            + 11
+            + self
+                .parts
+                .headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len() + 4)
+                .sum::<usize>()
+            + 2;
+
+        limits
+            .check_head(header_size, self.parts.headers.len(), uri_length)
+            .map_err(ProxyPassError::RequestLimitExceeded)?;
+
+        let content_length = self
+            .parts
+            .headers
+            .get(CONTENT_LENGTH)
+            .and_then(|itm| itm.to_str().ok())
+            .and_then(|itm| itm.trim().parse::<usize>().ok());
+
+        if let Some(content_length) = content_length {
+            limits
+                .check_content_length(content_length)
+                .map_err(ProxyPassError::RequestLimitExceeded)?;
+        }
+
+        self.max_body_size = limits.max_body_size;
+        Ok(())
     }
 
     pub async fn into_request(
@@ -141,7 +204,21 @@ impl HttpRequestBuilder {
         }
 
         if dest_http1.is_none() {
-            let (parts, body) = self.build_request(location, ip).await?;
+            let (mut parts, body) = self.build_request(location, ip).await?;
+
+            // A static body is filled in with what the connection came with.
+            if let ProxyPassToConfig::Static(_) = &location.config.proxy_pass_to {
+                if let Some(inner) = proxy_pass.inner.load_full() {
+                    parts.extensions.insert(StaticBodyClient {
+                        ip: inner.connection_ip.get_ip_addr(),
+                        client_cert_cn: inner
+                            .identity
+                            .load_full()
+                            .map(|itm| itm.as_str().to_string()),
+                        is_https: inner.http_listen_port_info.endpoint_type.is_https(),
+                    });
+                }
+            }
 
             return Ok(TransformedRequest {
                 req_parts: parts.clone(),
@@ -214,8 +291,11 @@ impl HttpRequestBuilder {
             .remove::<hyper::upgrade::OnUpgrade>()
             .ok_or(ProxyPassError::NoWebSocketUpgrade)?;
 
+        let mut uri = self.parts.uri.clone();
+        rewrite_upstream_path(&mut uri, location);
+
         let mut builder = Request::builder()
-            .uri(self.parts.uri.clone())
+            .uri(uri)
             .method(Method::CONNECT)
             .version(hyper::Version::HTTP_2);
 
@@ -306,7 +386,8 @@ impl HttpRequestBuilder {
             "/"
         };
 
-        let uri: Uri = path_and_query.parse().unwrap();
+        let mut uri: Uri = path_and_query.parse().unwrap();
+        rewrite_upstream_path(&mut uri, location);
 
         let host_header = if let Some(host) = self.parts.uri.host() {
             if let Some(port) = self.parts.uri.port() {
@@ -397,7 +478,7 @@ impl HttpRequestBuilder {
         };
 
         let mut uri: Uri = path_and_query.parse().unwrap();
-        rewrite_mcp_path(&mut uri, location);
+        rewrite_upstream_path(&mut uri, location);
 
         let host_header = if let Some(host) = self.parts.headers.get("host") {
             host.to_str().unwrap().to_string()
@@ -423,8 +504,7 @@ impl HttpRequestBuilder {
             builder = builder.header(header.0, header.1);
         }
 
-        let collected = self.body.collect().await?;
-        let bytes = collected.to_bytes();
+        let bytes = collect_body(self.body, self.max_body_size).await?;
 
         let req_parts = self.parts.clone();
 
@@ -508,10 +588,9 @@ impl HttpRequestBuilder {
 
         let mut parts = self.parts.clone();
 
-        rewrite_mcp_path(&mut parts.uri, location);
+        rewrite_upstream_path(&mut parts.uri, location);
 
-        let collected = self.body.collect().await?;
-        let bytes = collected.to_bytes();
+        let bytes = collect_body(self.body, self.max_body_size).await?;
 
         let before_compress = bytes.len();
 
@@ -623,6 +702,31 @@ fn apply_cf_ip_country(parts: &mut Parts, inner: &HttpProxyPassInner, endpoint:
         .insert(HeaderName::from_static("cf-ipcountry"), value);
 }
 
+/// Reads the whole request body, giving up with 413 as soon as it outgrows
+/// `max_body_size`.
+async fn collect_body(
+    body: Incoming,
+    max_body_size: Option<usize>,
+) -> Result<Bytes, ProxyPassError> {
+    let Some(max_body_size) = max_body_size else {
+        return Ok(body.collect().await?.to_bytes());
+    };
+
+    match http_body_util::Limited::new(body, max_body_size)
+        .collect()
+        .await
+    {
+        Ok(collected) => Ok(collected.to_bytes()),
+        Err(err) if err.is::<http_body_util::LengthLimitError>() => Err(
+            ProxyPassError::RequestLimitExceeded(RequestLimitExceeded::BodySize(max_body_size)),
+        ),
+        Err(err) => match err.downcast::<hyper::Error>() {
+            Ok(err) => Err(ProxyPassError::HyperError(*err)),
+            Err(err) => Err(ProxyPassError::IoError(std::io::Error::other(err))),
+        },
+    }
+}
+
 fn into_full_body(src: Bytes, location_id: i64, ip: Option<String>) -> Full<Bytes> {
     if crate::app::APP_CTX
         .debug_flags
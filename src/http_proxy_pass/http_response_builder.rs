@@ -1,9 +1,14 @@
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
 use hyper::{
     header::{self, HeaderName, HeaderValue},
     HeaderMap,
 };
 
-use crate::{configurations::ModifyHeadersConfig, types::HttpRequestReader};
+use crate::{
+    configurations::{LocationResponseRewrite, ModifyHeadersConfig},
+    types::HttpRequestReader,
+};
 
 use super::{HttpProxyPass, HttpProxyPassInner, LocationIndex};
 
@@ -42,6 +47,139 @@ pub fn modify_resp_headers(
         headers,
         &proxy_pass_location.config.modify_response_headers,
     );
+
+    if let Some(cors) = proxy_pass
+        .endpoint_info
+        .get_cors(&proxy_pass_location.config)
+    {
+        let origin = req_parts
+            .headers
+            .get(header::ORIGIN)
+            .and_then(|itm| itm.to_str().ok());
+        apply_cors_headers(headers, &cors.get_response_headers(origin));
+    }
+}
+
+/// Turns the upstream's `Location`, `Refresh` and `Set-Cookie` onto the
+/// location — every value of a header sent more than once. Done to the
+/// response as the upstream sent it, before it is kept.
+pub fn rewrite_resp_headers(
+    headers: &mut HeaderMap<HeaderValue>,
+    rewrite: &LocationResponseRewrite,
+) {
+    for name in [
+        header::LOCATION,
+        HeaderName::from_static("refresh"),
+        header::SET_COOKIE,
+    ] {
+        let mut rewritten = false;
+        let values: Vec<HeaderValue> = headers
+            .get_all(&name)
+            .iter()
+            .map(|value| {
+                let new_value = value
+                    .to_str()
+                    .ok()
+                    .and_then(|itm| rewrite.rewrite_header(name.as_str(), itm))
+                    .and_then(|itm| HeaderValue::from_str(&itm).ok());
+
+                match new_value {
+                    Some(new_value) => {
+                        rewritten = true;
+                        new_value
+                    }
+                    None => value.clone(),
+                }
+            })
+            .collect();
+
+        if !rewritten {
+            continue;
+        }
+
+        headers.remove(&name);
+        for value in values {
+            headers.append(name.clone(), value);
+        }
+    }
+}
+
+/// The proxy's own answer to a CORS preflight, when the location the request
+/// is for has `cors` — 204 with its CORS headers, or 403 for an origin it does
+/// not allow. `None` for any other request.
+pub fn answer_cors_preflight(
+    proxy_pass: &HttpProxyPass,
+    inner: &HttpProxyPassInner,
+    req_parts: &hyper::http::request::Parts,
+) -> Option<hyper::Response<BoxBody<Bytes, String>>> {
+    if req_parts.method != hyper::Method::OPTIONS
+        || !req_parts
+            .headers
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+    {
+        return None;
+    }
+
+    let origin = req_parts.headers.get(header::ORIGIN)?.to_str().ok()?;
+
+    let location_index = inner
+        .locations
+        .find_location_index(
+            &req_parts.uri,
+            proxy_pass.endpoint_info.host_endpoint.as_str(),
+            None,
+            false,
+        )
+        .ok()?;
+    let proxy_pass_location = inner.locations.find(&location_index);
+
+    let cors = proxy_pass
+        .endpoint_info
+        .get_cors(&proxy_pass_location.config)?;
+
+    let request_headers = req_parts
+        .headers
+        .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        .and_then(|itm| itm.to_str().ok());
+
+    let mut response =
+        hyper::Response::new(Full::new(Bytes::new()).map_err(|err| match err {}).boxed());
+
+    match cors.get_preflight_headers(origin, request_headers) {
+        Some(cors_headers) => {
+            *response.status_mut() = hyper::StatusCode::NO_CONTENT;
+            apply_cors_headers(response.headers_mut(), &cors_headers);
+        }
+        None => *response.status_mut() = hyper::StatusCode::FORBIDDEN,
+    }
+
+    Some(response)
+}
+
+/// Puts `cors_headers` on a response in place of the `Access-Control-*`
+/// headers it has.
+fn apply_cors_headers(
+    headers: &mut HeaderMap<HeaderValue>,
+    cors_headers: &[(&'static str, String)],
+) {
+    let to_remove: Vec<HeaderName> = headers
+        .keys()
+        .filter(|itm| crate::configurations::is_cors_response_header(itm.as_str()))
+        .cloned()
+        .collect();
+
+    for name in to_remove {
+        headers.remove(name);
+    }
+
+    for (name, value) in cors_headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            headers.append(name, value);
+        }
+    }
 }
 
 fn modify_headers(
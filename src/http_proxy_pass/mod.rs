@@ -20,6 +20,10 @@ mod handle_ga;
 pub use handle_ga::*;
 mod handle_oauth;
 pub use handle_oauth::*;
+mod cache_body;
+pub use cache_body::*;
+mod sub_filter_body;
+pub use sub_filter_body::*;
 
 mod http_proxy_pass_identity;
 pub use http_proxy_pass_identity::*;
@@ -1,11 +1,15 @@
 use std::sync::Arc;
 
-use hyper::Uri;
-
-use crate::{configurations::*, http_proxy_pass::content_source::*};
+use crate::{configurations::*, http_proxy_pass::content_source::*, types::ConnectionIp};
 
 pub struct ProxyPassLocation {
-    pub content_source: Arc<HttpProxyPassContentSource>,
+    /// One per upstream, in `config.upstreams` order — a single one unless
+    /// `proxy_pass_to` is a list.
+    pub content_sources: Vec<Arc<HttpProxyPassContentSource>>,
+    /// One per backup, in `backup_proxy_pass_to` order.
+    pub backup_content_sources: Vec<Arc<HttpProxyPassContentSource>>,
+    /// The content source of `mirror_to`, if set.
+    pub mirror_content_source: Option<Arc<HttpProxyPassContentSource>>,
     pub config: Arc<ProxyPassLocationConfig>,
     pub compress: bool,
 }
@@ -15,11 +19,19 @@ impl ProxyPassLocation {
         // `debug` here is the settings-driven connector-level debug passed to the
         // upstream content source; the in-memory request logging is gated at
         // runtime via `APP_CTX.debug_flags` instead.
-        let content_source = config
-            .create_data_source(debug, crate::consts::DEFAULT_HTTP_CONNECT_TIMEOUT)
+        let content_sources = config
+            .create_data_sources(debug, crate::consts::DEFAULT_HTTP_CONNECT_TIMEOUT)
+            .await;
+        let backup_content_sources = config
+            .create_backup_data_sources(debug, crate::consts::DEFAULT_HTTP_CONNECT_TIMEOUT)
+            .await;
+        let mirror_content_source = config
+            .create_mirror_data_source(debug, crate::consts::DEFAULT_HTTP_CONNECT_TIMEOUT)
             .await;
         let result = Self {
-            content_source: Arc::new(content_source),
+            content_sources: content_sources.into_iter().map(Arc::new).collect(),
+            backup_content_sources: backup_content_sources.into_iter().map(Arc::new).collect(),
+            mirror_content_source: mirror_content_source.map(Arc::new),
             config,
             compress,
         };
@@ -27,13 +39,103 @@ impl ProxyPassLocation {
         result
     }
 
-    pub fn is_my_uri(&self, uri: &Uri) -> bool {
-        let result = rust_extensions::str_utils::starts_with_case_insensitive(
-            uri.path(),
-            self.config.path.as_str(),
-        );
+    /// The content source serving this request. For a balanced location the
+    /// lease keeps the request counted against the picked upstream — hold it
+    /// until the upstream has answered. `split_match` is the branch of a
+    /// `split` location the request asks for, see [`Self::get_split_match`].
+    pub fn pick_content_source(
+        &self,
+        connection_ip: &ConnectionIp,
+        sticky: Option<&StickyRequest>,
+        split_match: Option<usize>,
+    ) -> (Arc<HttpProxyPassContentSource>, Option<UpstreamLease>) {
+        let Some(upstreams) = self.config.upstreams.as_ref() else {
+            return (self.content_sources[0].clone(), None);
+        };
 
-        result
+        let client_ip = connection_ip.get_ip_addr();
+        let admit =
+            |upstream: &ProxyPassUpstream| crate::app::APP_CTX.upstream_outliers.admit(upstream.id);
+
+        let lease = match (sticky, self.config.split.as_ref()) {
+            (Some(sticky), _) => {
+                sticky
+                    .sticky
+                    .pick(upstreams, sticky.affinity.as_deref(), client_ip, admit)
+            }
+            (None, Some(split)) => split.pick(upstreams, split_match, admit),
+            (None, None) => upstreams.pick(client_ip, admit),
+        };
+        (self.content_sources[lease.get_index()].clone(), Some(lease))
+    }
+
+    /// What the request carries for a `sticky` location: its affinity cookie
+    /// or header value.
+    pub fn get_sticky_request(
+        &self,
+        headers: &hyper::HeaderMap,
+        is_https: bool,
+    ) -> Option<StickyRequest> {
+        let sticky = self.config.sticky.as_ref()?;
+
+        let affinity = match sticky.get_mode() {
+            // A cookie may come split over several `cookie` headers (h2).
+            StickyMode::Cookie { name } => headers
+                .get_all(hyper::header::COOKIE)
+                .iter()
+                .filter_map(|itm| itm.to_str().ok())
+                .find_map(|itm| crate::utils::get_cookie(itm, name)),
+            StickyMode::Header { name } => headers
+                .get(name.as_str())
+                .and_then(|itm| itm.to_str().ok())
+                .map(|itm| itm.trim()),
+        };
+
+        Some(StickyRequest {
+            sticky: sticky.clone(),
+            affinity: affinity.map(|itm| itm.to_string()),
+            is_https,
+        })
+    }
+
+    /// The branch of a `split` location whose cookie or header the request
+    /// carries.
+    pub fn get_split_match(&self, headers: &hyper::HeaderMap) -> Option<usize> {
+        let split = self.config.split.as_ref()?;
+
+        split.find_branch(|itm| match itm.source {
+            SplitMatchSource::Cookie => headers
+                .get_all(hyper::header::COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .find_map(|value| crate::utils::get_cookie(value, &itm.name)),
+            SplitMatchSource::Header => headers
+                .get(itm.name.as_str())
+                .and_then(|value| value.to_str().ok()),
+        })
+    }
+
+    /// The backups a request fails over to, with their content sources —
+    /// `None` when the location has none.
+    pub fn get_backups(
+        &self,
+    ) -> Option<(Arc<LocationBackups>, Vec<Arc<HttpProxyPassContentSource>>)> {
+        let backups = self.config.backups.as_ref()?;
+        Some((backups.clone(), self.backup_content_sources.clone()))
+    }
+
+    /// The mirror with its content source when this request is sampled for
+    /// it — `None` when the location has no `mirror_to` or the request is not
+    /// picked.
+    pub fn sample_mirror(&self) -> Option<(Arc<LocationMirror>, Arc<HttpProxyPassContentSource>)> {
+        let mirror = self.config.mirror.as_ref()?;
+        let source = self.mirror_content_source.as_ref()?;
+
+        if !mirror.sample() {
+            return None;
+        }
+
+        Some((mirror.clone(), source.clone()))
     }
 
     pub fn is_http1(&self) -> Option<bool> {
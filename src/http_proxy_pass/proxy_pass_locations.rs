@@ -34,29 +34,33 @@ impl ProxyPassLocations {
         ip: Option<String>,
         debug: bool,
     ) -> Result<LocationIndex, ProxyPassError> {
-        for (index, proxy_pass_location) in self.data.iter().enumerate() {
-            if debug {
+        let index = find_best_location(self.data.iter(), uri.path(), |itm| &itm.config.matcher);
+
+        if debug {
+            for (index, proxy_pass_location) in self.data.iter().enumerate() {
                 crate::app::APP_CTX.proxy_logs.write(
                     endpoint,
                     None,
                     ip.clone(),
                     format!(
-                        "{} ProxyPass path: [{}] UriPath: [{}]",
+                        "{} ProxyPass path: [{}] Match: [{}] UriPath: [{}]",
                         index,
                         proxy_pass_location.config.path.as_str(),
+                        proxy_pass_location.config.matcher.get_mode().as_str(),
                         uri.path()
                     ),
                 );
             }
-            if proxy_pass_location.is_my_uri(uri) {
-                return Ok(LocationIndex {
-                    index,
-                    id: proxy_pass_location.config.id,
-                });
-            }
         }
 
-        return Err(ProxyPassError::NoLocationFound);
+        let Some(index) = index else {
+            return Err(ProxyPassError::NoLocationFound);
+        };
+
+        Ok(LocationIndex {
+            index,
+            id: self.data[index].config.id,
+        })
     }
 
     pub fn find(&self, location_index: &LocationIndex) -> &ProxyPassLocation {
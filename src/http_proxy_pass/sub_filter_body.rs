@@ -0,0 +1,123 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::body::{Body, Frame, SizeHint};
+
+use crate::body_filter::{SubFilter, SubFilterStream};
+
+/// A response body on its way to the client through the location's
+/// `sub_filter`. A frame that completes no line yet yields nothing; the rest
+/// comes once the upstream's body has ended.
+pub struct SubFilterBody {
+    inner: BoxBody<Bytes, String>,
+    stream: SubFilterStream,
+    finished: bool,
+}
+
+impl Body for SubFilterBody {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        // SubFilterBody is Unpin (every field is), so get_mut is safe.
+        let this = self.get_mut();
+
+        loop {
+            if this.finished {
+                return Poll::Ready(None);
+            }
+
+            match Pin::new(&mut this.inner).poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => {
+                    let frame = match frame.into_data() {
+                        Ok(data) => data,
+                        // Trailers are passed on as they are.
+                        Err(frame) => return Poll::Ready(Some(Ok(frame))),
+                    };
+
+                    match this.stream.push(&frame) {
+                        Ok(filtered) if filtered.is_empty() => continue,
+                        Ok(filtered) => {
+                            return Poll::Ready(Some(Ok(Frame::data(Bytes::from(filtered)))))
+                        }
+                        Err(err) => {
+                            this.finished = true;
+                            return Poll::Ready(Some(Err(format!(
+                                "Response body can not be decoded for sub_filter: {}",
+                                err
+                            ))));
+                        }
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => {
+                    this.finished = true;
+
+                    return match this.stream.finish() {
+                        Ok(rest) if rest.is_empty() => Poll::Ready(None),
+                        Ok(rest) => Poll::Ready(Some(Ok(Frame::data(Bytes::from(rest))))),
+                        Err(err) => Poll::Ready(Some(Err(format!(
+                            "Response body can not be decoded for sub_filter: {}",
+                            err
+                        )))),
+                    };
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.finished
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::default()
+    }
+}
+
+/// Puts the upstream's `response` through the location's `sub_filter` when
+/// its body is of a type it lists. Its `Content-Length` is dropped — the
+/// length of the body is not known until it has ended.
+pub fn apply_sub_filter(
+    response: hyper::Response<BoxBody<Bytes, String>>,
+    sub_filter: &Arc<SubFilter>,
+) -> hyper::Response<BoxBody<Bytes, String>> {
+    if matches!(response.status().as_u16(), 204 | 304) || response.body().is_end_stream() {
+        return response;
+    }
+
+    let headers = response.headers();
+    let header = |name: hyper::header::HeaderName| {
+        headers
+            .get(name)
+            .and_then(|itm| itm.to_str().ok())
+            .map(|itm| itm.to_string())
+    };
+
+    let Some(stream) = sub_filter.start(
+        Some(response.status().as_u16()),
+        header(hyper::header::CONTENT_TYPE).as_deref(),
+        header(hyper::header::CONTENT_ENCODING).as_deref(),
+        header(hyper::header::CONTENT_RANGE).as_deref(),
+    ) else {
+        return response;
+    };
+
+    let (mut parts, inner) = response.into_parts();
+    parts.headers.remove(hyper::header::CONTENT_LENGTH);
+
+    let body = SubFilterBody {
+        inner,
+        stream,
+        finished: false,
+    };
+
+    http::Response::from_parts(parts, body.boxed())
+}
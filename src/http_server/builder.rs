@@ -57,6 +57,8 @@ pub fn build_controllers() -> ControllersMiddleware {
 
     result.register_post_action(Arc::new(super::controllers::ip_blocklist::UnblockIpAction));
 
+    result.register_post_action(Arc::new(super::controllers::cache::PurgeCacheAction));
+
     result.register_get_action(Arc::new(
         super::controllers::ssl_certificates::GetCurrentSslCertificatesAction,
     ));
@@ -0,0 +1,2 @@
+mod purge_cache_action;
+pub use purge_cache_action::*;
@@ -0,0 +1,53 @@
+use my_http_server::{
+    macros::{http_route, MyHttpInput, MyHttpObjectStructure},
+    HttpContext, HttpFailResult, HttpOkResult, HttpOutput,
+};
+use serde::Serialize;
+
+#[http_route(
+    method: "POST",
+    route: "/api/Cache/Purge",
+    summary: "Purge cached responses",
+    description: "Removes responses from the caches of the locations with a `cache:` block, from memory and disk",
+    input_data: PurgeCacheInput,
+    controller: "Cache",
+    result:[
+        {status_code: 200, description: "Ok response", model: "PurgeCacheResponse"},
+    ]
+)]
+pub struct PurgeCacheAction;
+
+async fn handle_request(
+    _action: &PurgeCacheAction,
+    input_data: PurgeCacheInput,
+    _ctx: &HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let purged = crate::app::APP_CTX
+        .response_caches
+        .purge(input_data.location.as_deref(), input_data.prefix.as_deref())
+        .await;
+
+    HttpOutput::as_json(PurgeCacheResponse { purged })
+        .into_ok_result(true)
+        .into()
+}
+
+#[derive(MyHttpInput)]
+pub struct PurgeCacheInput {
+    #[http_form_data(
+        name = "location",
+        description = "Only the locations whose id_string starts with this. All without it"
+    )]
+    pub location: Option<String>,
+
+    #[http_form_data(
+        name = "prefix",
+        description = "Only the responses whose host and path start with this, e.g. example.com/static/. All without it"
+    )]
+    pub prefix: Option<String>,
+}
+
+#[derive(Serialize, MyHttpObjectStructure)]
+pub struct PurgeCacheResponse {
+    pub purged: usize,
+}
@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod configuration;
 pub mod debug;
 pub mod ip_blocklist;
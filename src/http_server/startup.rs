@@ -44,6 +44,7 @@ pub fn start() {
     mcp.register_tool_call(Arc::new(GetSettingsHandler));
     mcp.register_tool_call(Arc::new(GetAppliedSettingsHandler));
     mcp.register_tool_call(Arc::new(ReloadSettingsHandler));
+    mcp.register_tool_call(Arc::new(PurgeCacheHandler));
     mcp.register_tool_call(Arc::new(GetDynamicSettingsHandler));
     mcp.register_tool_call(Arc::new(SetDynamicSettingsHandler));
     mcp.register_tool_call(Arc::new(GetProxyLogsHandler));
@@ -3,11 +3,12 @@ use std::{sync::Arc, time::Duration};
 use rust_extensions::MyTimer;
 use timers::{
     CrlRefresherTimer, EndpointRpsTimer, GatewaySyncCertsTimer, GcConnectionsTimer, GcPoolsTimer,
-    IpBlocklistGcTimer, MetricsTimer, PoolSupervisorTimer, ResolveDomainsIpTimer,
-    SslCertsRefreshTimer, TrafficTimer,
+    IpBlocklistGcTimer, MetricsTimer, PoolSupervisorTimer, RateLimitGcTimer,
+    ResolveDomainsIpTimer, SslCertsRefreshTimer, TrafficTimer,
 };
 
 mod app;
+mod body_filter;
 mod flows;
 //mod http2_executor;
 
@@ -39,6 +40,7 @@ mod mcp;
 mod metrics;
 mod network_stream;
 mod oauth;
+mod response_cache;
 mod ssl;
 mod tcp_gateway;
 mod timers;
@@ -88,6 +90,7 @@ async fn main() {
     gc_connections_time.register_timer("GcConnections", Arc::new(GcConnectionsTimer));
     gc_connections_time.register_timer("GatewaySyncCerts", Arc::new(GatewaySyncCertsTimer));
     gc_connections_time.register_timer("IpBlocklistGc", Arc::new(IpBlocklistGcTimer));
+    gc_connections_time.register_timer("RateLimitGc", Arc::new(RateLimitGcTimer));
 
     gc_connections_time.start(
         crate::app::APP_CTX.states.clone(),
@@ -1,12 +1,15 @@
 use std::sync::atomic::Ordering;
 
-use ahash::AHashSet;
+use ahash::{AHashMap, AHashSet};
 use mcp_server_middleware::*;
 use rust_extensions::date_time::DateTimeAsMicroseconds;
 use serde::*;
 
-use crate::app::APP_CTX;
-use crate::configurations::{AppConfigurationInner, ListenConfiguration};
+use crate::app::{PortConnectionsSnapshot, APP_CTX};
+use crate::configurations::{
+    AppConfigurationInner, ListenConfiguration, ProxyPassLocationConfig, ProxyPassUpstream,
+    StickyMode,
+};
 use crate::upstream_h1_pool::{DISPOSABLE_COUNTER, MAX_DISPOSABLE};
 use crate::upstream_status::UpstreamStatus;
 
@@ -85,6 +88,48 @@ pub struct PoolSnapshot {
     pub has_matching_location: bool,
 }
 
+#[derive(ApplyJsonSchema, Debug, Serialize, Deserialize)]
+pub struct UpstreamSnapshot {
+    #[property(
+        description = "location_id of the location this upstream belongs to (the pool of its first upstream is keyed by the same id)"
+    )]
+    pub location_id: i64,
+
+    #[property(
+        description = "Id the upstream's own pool is keyed by in its registry — look it up with lookup_pool"
+    )]
+    pub pool_location_id: i64,
+
+    #[property(description = "Logical identity string of the upstream's pool")]
+    pub id_string: String,
+
+    #[property(description = "Stringified upstream target")]
+    pub proxy_pass_to: String,
+
+    #[property(description = "Weight from the proxy_pass_to list (1 when not set)")]
+    pub weight: i64,
+
+    #[property(
+        description = "Requests currently in flight to this upstream through the location — what least_outstanding balances on"
+    )]
+    pub outstanding_requests: i64,
+
+    #[property(
+        description = "Requests balanced to this upstream since the configuration was applied"
+    )]
+    pub total_requests: i64,
+
+    #[property(
+        description = "True while outlier detection has ejected this upstream after consecutive 5xx / connect failures / timeouts; balancing skips it until a half-open probe succeeds"
+    )]
+    pub ejected: bool,
+
+    #[property(
+        description = "True if a pool keyed by pool_location_id is registered in any of the 6 pool registries"
+    )]
+    pub has_pool: bool,
+}
+
 #[derive(ApplyJsonSchema, Debug, Serialize, Deserialize)]
 pub struct LocationSnapshot {
     #[property(description = "Listener label: 'tcp:<port>' or 'unix:<socket_path>'")]
@@ -118,6 +163,84 @@ pub struct LocationSnapshot {
         description = "True if a pool with this location_id is registered in any of the 6 pool registries. False is normal until the first request creates the pool lazily — but is suspicious if the same location had a pool earlier."
     )]
     pub has_pool: bool,
+
+    #[property(
+        description = "Balance policy when proxy_pass_to is a list of upstreams: round_robin / weighted / least_outstanding / consistent_hash. null for a single upstream"
+    )]
+    pub balance: Option<String>,
+
+    #[property(
+        description = "How clients are pinned to one of the upstreams: 'cookie:<name>' or 'header:<name>'. null when the location is not sticky"
+    )]
+    pub sticky: Option<String>,
+
+    #[property(
+        description = "Current split branches as '<name>=<weight>' in declaration order, e.g. 'v1=95, v2=5'; upstreams lists their targets in the same order. null when the location has no split"
+    )]
+    pub split: Option<String>,
+
+    #[property(
+        description = "Per-upstream state when proxy_pass_to is a list of upstreams, in declaration order. Empty for a single upstream"
+    )]
+    pub upstreams: Vec<UpstreamSnapshot>,
+
+    #[property(
+        description = "backup_proxy_pass_to targets a request fails over to, in the order they are tried. Empty when the location has none"
+    )]
+    pub backups: Vec<UpstreamSnapshot>,
+
+    #[property(
+        description = "mirror_to target sampled requests are copied to. null when the location has none"
+    )]
+    pub mirror: Option<UpstreamSnapshot>,
+
+    #[property(description = "Percentage of requests copied to mirror. null without mirror_to")]
+    pub mirror_percent: Option<i64>,
+
+    #[property(
+        description = "max_concurrent_requests of the location. null when its requests are not limited"
+    )]
+    pub max_concurrent_requests: Option<i64>,
+
+    #[property(
+        description = "Requests of the location on their way to the upstream right now, counted against max_concurrent_requests. null without it"
+    )]
+    pub in_flight_requests: Option<i64>,
+
+    #[property(
+        description = "Requests waiting for a max_concurrent_requests slot right now; past max_queued_requests they are answered with 503. null without max_concurrent_requests"
+    )]
+    pub queued_requests: Option<i64>,
+}
+
+#[derive(ApplyJsonSchema, Debug, Serialize, Deserialize)]
+pub struct ListenPortSnapshot {
+    #[property(description = "TCP listen port")]
+    pub port: i64,
+
+    #[property(description = "Client connections open on the port right now")]
+    pub connections: i64,
+
+    #[property(description = "Distinct client IPs with connections open on the port")]
+    pub clients: i64,
+
+    #[property(
+        description = "Client IP holding the most connections open on the port. null when none is open"
+    )]
+    pub busiest_ip: Option<String>,
+
+    #[property(description = "Connections busiest_ip holds open. null when none is open")]
+    pub busiest_ip_connections: Option<i64>,
+
+    #[property(
+        description = "max_connections the port is held to — the lowest any endpoint on it sets. A connection past it is closed at accept. null when not limited"
+    )]
+    pub max_connections: Option<i64>,
+
+    #[property(
+        description = "max_connections_per_ip the port is held to — the lowest any endpoint on it sets. null when not limited"
+    )]
+    pub max_connections_per_ip: Option<i64>,
 }
 
 #[derive(ApplyJsonSchema, Debug, Serialize, Deserialize)]
@@ -150,13 +273,18 @@ pub struct GetProxyStateSnapshotResponse {
         description = "Hard cap on concurrent on-demand (disposable) h1 connections process-wide (MAX_DISPOSABLE). When live_disposables_global reaches this, get_connection stops opening overflow connections and retries every 10ms instead — a sustained value at the cap means upstreams are saturated."
     )]
     pub max_disposables_global: i64,
+
+    #[property(
+        description = "Client connections per TCP listen port against its max_connections / max_connections_per_ip: every port of the current configuration, and any other still holding connections"
+    )]
+    pub listen_ports: Vec<ListenPortSnapshot>,
 }
 
 pub struct GetProxyStateSnapshotHandler;
 
 impl ToolDefinition for GetProxyStateSnapshotHandler {
     const FUNC_NAME: &'static str = "get_proxy_state_snapshot";
-    const DESCRIPTION: &'static str = "Detailed snapshot of all upstream pools across the 6 pool registries (h1/h2 × tcp/tls/uds) and all locations from the current configuration. Per pool: alive/total entry counts, last_status (what the proxy believes about the upstream — 'ok'/'error'/'unknown' from the last connect/revive/health-ping, i.e. whether WE see it is down), live_disposables (on-demand connections in flight for this upstream — h1 only), shutdown flag, id_string, and per-entry state (dead / last_success / idle_secs / rented). Top-level: live_disposables_global / max_disposables_global (process-wide on-demand budget) and orphan/naked correlation. Use to see, per upstream, which connections are pooled vs on-demand and whether the proxy has detected it as down.";
+    const DESCRIPTION: &'static str = "Detailed snapshot of all upstream pools across the 6 pool registries (h1/h2 × tcp/tls/uds) and all locations from the current configuration. Per pool: alive/total entry counts, last_status (what the proxy believes about the upstream — 'ok'/'error'/'unknown' from the last connect/revive/health-ping, i.e. whether WE see it is down), live_disposables (on-demand connections in flight for this upstream — h1 only), shutdown flag, id_string, and per-entry state (dead / last_success / idle_secs / rented). Per location: in_flight_requests / queued_requests against max_concurrent_requests. Top-level: live_disposables_global / max_disposables_global (process-wide on-demand budget), listen_ports (client connections per port and its busiest IP against max_connections / max_connections_per_ip) and orphan/naked correlation. Use to see, per upstream, which connections are pooled vs on-demand and whether the proxy has detected it as down.";
 }
 
 #[async_trait::async_trait]
@@ -186,12 +314,25 @@ pub async fn build_proxy_state_snapshot() -> GetProxyStateSnapshotResponse {
         pools.extend(snapshot_h2_pools(&APP_CTX.h2_tls_pools, "h2_tls", now));
         pools.extend(snapshot_h2_pools(&APP_CTX.h2_uds_pools, "h2_uds", now));
 
-        let mut locations = APP_CTX
+        let (mut locations, listen_ports) = APP_CTX
             .current_configuration
-            .get(|cfg| collect_locations(cfg))
+            .get(|cfg| (collect_locations(cfg), collect_listen_ports(cfg)))
             .await;
 
-        let location_id_set: AHashSet<i64> = locations.iter().map(|l| l.location_id).collect();
+        // Every upstream of a balanced location, every backup and the mirror
+        // key a pool of their own.
+        let location_id_set: AHashSet<i64> = locations
+            .iter()
+            .flat_map(|l| {
+                std::iter::once(l.location_id).chain(
+                    l.upstreams
+                        .iter()
+                        .chain(l.backups.iter())
+                        .chain(l.mirror.iter())
+                        .map(|u| u.pool_location_id),
+                )
+            })
+            .collect();
         let pool_id_set: AHashSet<i64> = pools.iter().map(|p| p.location_id).collect();
 
         for pool in pools.iter_mut() {
@@ -199,6 +340,14 @@ pub async fn build_proxy_state_snapshot() -> GetProxyStateSnapshotResponse {
         }
         for loc in locations.iter_mut() {
             loc.has_pool = pool_id_set.contains(&loc.location_id);
+            for upstream in loc
+                .upstreams
+                .iter_mut()
+                .chain(loc.backups.iter_mut())
+                .chain(loc.mirror.iter_mut())
+            {
+                upstream.has_pool = pool_id_set.contains(&upstream.pool_location_id);
+            }
         }
 
         let orphan_pool_location_ids: Vec<i64> = pools
@@ -220,6 +369,7 @@ pub async fn build_proxy_state_snapshot() -> GetProxyStateSnapshotResponse {
             naked_location_ids,
             live_disposables_global: DISPOSABLE_COUNTER.load(Ordering::Relaxed) as i64,
             max_disposables_global: MAX_DISPOSABLE as i64,
+            listen_ports,
         }
     }
 }
@@ -368,6 +518,51 @@ fn collect_locations(cfg: &AppConfigurationInner) -> Vec<LocationSnapshot> {
     out
 }
 
+/// Connections open on every tcp listen port, against the port's limits.
+fn collect_listen_ports(cfg: &AppConfigurationInner) -> Vec<ListenPortSnapshot> {
+    let mut counted: AHashMap<u16, PortConnectionsSnapshot> = APP_CTX
+        .connection_counters
+        .get_snapshot()
+        .into_iter()
+        .map(|itm| (itm.port, itm))
+        .collect();
+
+    let mut ports: Vec<u16> = cfg
+        .listen_tcp_endpoints
+        .keys()
+        .chain(counted.keys())
+        .copied()
+        .collect();
+    ports.sort();
+    ports.dedup();
+
+    ports
+        .into_iter()
+        .map(|port| {
+            let limits = cfg
+                .listen_tcp_endpoints
+                .get(&port)
+                .map(|itm| itm.get_connection_limits())
+                .unwrap_or_default();
+            let counted = counted.remove(&port);
+            let busiest_ip = counted.as_ref().and_then(|itm| itm.busiest_ip);
+
+            ListenPortSnapshot {
+                port: port as i64,
+                connections: counted
+                    .as_ref()
+                    .map(|itm| itm.connections as i64)
+                    .unwrap_or(0),
+                clients: counted.as_ref().map(|itm| itm.clients as i64).unwrap_or(0),
+                busiest_ip: busiest_ip.map(|(ip, _)| ip.to_string()),
+                busiest_ip_connections: busiest_ip.map(|(_, connections)| connections as i64),
+                max_connections: limits.max_connections.map(|itm| itm as i64),
+                max_connections_per_ip: limits.max_connections_per_ip.map(|itm| itm as i64),
+            }
+        })
+        .collect()
+}
+
 fn absorb_listen(
     listen: &ListenConfiguration,
     listen_label: &str,
@@ -389,7 +584,102 @@ fn absorb_listen(
                 proxy_pass_to_type: location.proxy_pass_to.get_type_as_str().to_string(),
                 proxy_pass_to: location.proxy_pass_to.to_string(),
                 has_pool: false,
+                balance: location
+                    .upstreams
+                    .as_ref()
+                    .map(|itm| itm.get_policy().as_str().to_string()),
+                sticky: location.sticky.as_ref().map(|itm| match itm.get_mode() {
+                    StickyMode::Cookie { name } => format!("cookie:{}", name),
+                    StickyMode::Header { name } => format!("header:{}", name),
+                }),
+                split: location.split.as_ref().map(|itm| {
+                    itm.get_branches()
+                        .iter()
+                        .map(|branch| format!("{}={}", branch.name, branch.get_weight()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }),
+                upstreams: build_upstream_snapshots(location),
+                backups: build_backup_snapshots(location),
+                mirror: location
+                    .mirror
+                    .as_ref()
+                    .map(|itm| build_upstream_snapshot(location, itm.get_upstream())),
+                mirror_percent: location.mirror.as_ref().map(|itm| itm.get_percent() as i64),
+                max_concurrent_requests: location
+                    .concurrency_limit
+                    .as_ref()
+                    .map(|itm| itm.get_settings().max_requests as i64),
+                in_flight_requests: location
+                    .concurrency_limit
+                    .as_ref()
+                    .map(|itm| itm.get_in_flight() as i64),
+                queued_requests: location
+                    .concurrency_limit
+                    .as_ref()
+                    .map(|itm| itm.get_queued() as i64),
             });
         }
     }
 }
+
+/// Per-upstream state of a balanced location; empty for a single upstream.
+/// Shared with `lookup_pool`. `has_pool` is left false for the caller to fill.
+pub(super) fn build_upstream_snapshots(
+    location: &ProxyPassLocationConfig,
+) -> Vec<UpstreamSnapshot> {
+    let Some(upstreams) = location.upstreams.as_ref() else {
+        return Vec::new();
+    };
+
+    upstreams
+        .get_upstreams()
+        .iter()
+        .map(|upstream| build_upstream_snapshot(location, upstream))
+        .collect()
+}
+
+/// The backups of a location, in the order they are tried.
+fn build_backup_snapshots(location: &ProxyPassLocationConfig) -> Vec<UpstreamSnapshot> {
+    let Some(backups) = location.backups.as_ref() else {
+        return Vec::new();
+    };
+
+    backups
+        .get_upstreams()
+        .iter()
+        .map(|backup| build_upstream_snapshot(location, backup))
+        .collect()
+}
+
+fn build_upstream_snapshot(
+    location: &ProxyPassLocationConfig,
+    upstream: &ProxyPassUpstream,
+) -> UpstreamSnapshot {
+    UpstreamSnapshot {
+        location_id: location.id,
+        pool_location_id: upstream.id,
+        id_string: upstream.id_string.clone(),
+        proxy_pass_to: upstream.proxy_pass_to.to_string(),
+        weight: upstream.weight as i64,
+        outstanding_requests: upstream.get_outstanding() as i64,
+        total_requests: upstream.get_requests() as i64,
+        ejected: APP_CTX.upstream_outliers.is_ejected(upstream.id),
+        has_pool: false,
+    }
+}
+
+/// Every upstream of every balanced location, every backup and every mirror in
+/// the current configuration.
+pub(super) fn collect_upstreams(cfg: &AppConfigurationInner) -> Vec<UpstreamSnapshot> {
+    collect_locations(cfg)
+        .into_iter()
+        .flat_map(|location| {
+            location
+                .upstreams
+                .into_iter()
+                .chain(location.backups)
+                .chain(location.mirror)
+        })
+        .collect()
+}
@@ -190,7 +190,11 @@ pub fn build_settings_summary(
                 .iter()
                 .map(|loc| LocationSettingsSummary {
                     path: loc.path.clone().unwrap_or_default(),
-                    proxy_pass_to: loc.proxy_pass_to.clone().unwrap_or_default(),
+                    proxy_pass_to: loc
+                        .proxy_pass_to
+                        .as_ref()
+                        .map(|itm| itm.as_string())
+                        .unwrap_or_default(),
                     location_type: loc.location_type.clone().unwrap_or_default(),
                     whitelisted_ip: loc.whitelisted_ip.clone().unwrap_or_default(),
                 })
@@ -4,7 +4,10 @@ use serde::*;
 
 use crate::app::APP_CTX;
 
-use super::{build_h1_pool_snapshot, build_h2_pool_snapshot, PoolSnapshot};
+use super::{
+    build_h1_pool_snapshot, build_h2_pool_snapshot, collect_upstreams, PoolSnapshot,
+    UpstreamSnapshot,
+};
 
 #[derive(ApplyJsonSchema, Debug, Serialize, Deserialize)]
 pub struct LookupPoolInputData {
@@ -28,13 +31,18 @@ pub struct LookupPoolResponse {
         description = "Pools matching the lookup. Normally 0 or 1 — but if the same id_string somehow exists in multiple registries (regression), all matches are returned."
     )]
     pub matches: Vec<PoolSnapshot>,
+
+    #[property(
+        description = "Upstreams of balanced locations (proxy_pass_to as a list), backup_proxy_pass_to and mirror_to targets matching the lookup — by the upstream's own pool id / id_string, or all upstreams of the location when location_id is the location's. Carries the per-upstream outstanding / total request counters."
+    )]
+    pub upstreams: Vec<UpstreamSnapshot>,
 }
 
 pub struct LookupPoolHandler;
 
 impl ToolDefinition for LookupPoolHandler {
     const FUNC_NAME: &'static str = "lookup_pool";
-    const DESCRIPTION: &'static str = "Look up an upstream pool by location_id and/or id_string across all 6 pool registries. Returns the same per-pool detail as get_proxy_state_snapshot (last_status / live_disposables / per-entry dead / rented / last_success) but only for matching pools — useful for re-checking one upstream right after observing a state change, e.g. to confirm whether the proxy now sees it as 'ok' or still 'error'.";
+    const DESCRIPTION: &'static str = "Look up an upstream pool by location_id and/or id_string across all 6 pool registries. Returns the same per-pool detail as get_proxy_state_snapshot (last_status / live_disposables / per-entry dead / rented / last_success) but only for matching pools — useful for re-checking one upstream right after observing a state change, e.g. to confirm whether the proxy now sees it as 'ok' or still 'error'. For a location balancing between several upstreams, also returns the matching upstreams with their outstanding / total request counters.";
 }
 
 #[async_trait::async_trait]
@@ -71,9 +79,25 @@ impl McpToolCall<LookupPoolInputData, LookupPoolResponse> for LookupPoolHandler
         matches.extend(filter_h2(&APP_CTX.h2_tls_pools, "h2_tls", now, &id_match));
         matches.extend(filter_h2(&APP_CTX.h2_uds_pools, "h2_uds", now, &id_match));
 
+        let mut upstreams: Vec<UpstreamSnapshot> = APP_CTX
+            .current_configuration
+            .get(|cfg| collect_upstreams(cfg))
+            .await
+            .into_iter()
+            .filter(|upstream| {
+                id_match(upstream.pool_location_id, &upstream.id_string)
+                    || model.location_id == Some(upstream.location_id)
+            })
+            .collect();
+
+        for upstream in upstreams.iter_mut() {
+            upstream.has_pool = has_pool(upstream.pool_location_id);
+        }
+
         Ok(LookupPoolResponse {
             captured_at: now.to_rfc3339(),
             matches,
+            upstreams,
         })
     }
 }
@@ -111,3 +135,12 @@ where
         .map(|pool| build_h2_pool_snapshot(pool, registry, now))
         .collect()
 }
+
+fn has_pool(location_id: i64) -> bool {
+    APP_CTX.h1_tcp_pools.get(location_id).is_some()
+        || APP_CTX.h1_tls_pools.get(location_id).is_some()
+        || APP_CTX.h1_uds_pools.get(location_id).is_some()
+        || APP_CTX.h2_tcp_pools.get(location_id).is_some()
+        || APP_CTX.h2_tls_pools.get(location_id).is_some()
+        || APP_CTX.h2_uds_pools.get(location_id).is_some()
+}
@@ -7,6 +7,7 @@ mod get_ssl_certificate_tool_call;
 mod get_ssl_endpoints_status_tool_call;
 mod init_ssl_certificate_tool_call;
 mod lookup_pool_tool_call;
+mod purge_cache_tool_call;
 mod reload_settings_tool_call;
 mod set_dynamic_settings_tool_call;
 
@@ -19,5 +20,6 @@ pub use get_ssl_certificate_tool_call::*;
 pub use get_ssl_endpoints_status_tool_call::*;
 pub use init_ssl_certificate_tool_call::*;
 pub use lookup_pool_tool_call::*;
+pub use purge_cache_tool_call::*;
 pub use reload_settings_tool_call::*;
 pub use set_dynamic_settings_tool_call::*;
@@ -0,0 +1,47 @@
+use mcp_server_middleware::*;
+use serde::*;
+
+use crate::app::APP_CTX;
+
+#[derive(ApplyJsonSchema, Debug, Serialize, Deserialize)]
+pub struct PurgeCacheInputData {
+    #[property(
+        description = "Only purge the caches of the locations whose id_string (see get_proxy_state_snapshot) starts with this. Optional — every cached location without it."
+    )]
+    pub location: Option<String>,
+
+    #[property(
+        description = "Only purge the responses whose host and path start with this, e.g. 'example.com/static/'. The host is lower-case and has no port. Optional — every response without it."
+    )]
+    pub prefix: Option<String>,
+}
+
+#[derive(ApplyJsonSchema, Debug, Serialize, Deserialize)]
+pub struct PurgeCacheResponse {
+    #[property(description = "How many cached responses were removed, from memory and disk.")]
+    pub purged: i64,
+}
+
+pub struct PurgeCacheHandler;
+
+impl ToolDefinition for PurgeCacheHandler {
+    const FUNC_NAME: &'static str = "purge_cache";
+    const DESCRIPTION: &'static str = "Remove responses from the caches of the locations with a `cache:` block, in memory and on disk. The next request for a purged response goes to the upstream. Without arguments every cache is emptied.";
+}
+
+#[async_trait::async_trait]
+impl McpToolCall<PurgeCacheInputData, PurgeCacheResponse> for PurgeCacheHandler {
+    async fn execute_tool_call(
+        &self,
+        model: PurgeCacheInputData,
+    ) -> Result<PurgeCacheResponse, String> {
+        let purged = APP_CTX
+            .response_caches
+            .purge(model.location.as_deref(), model.prefix.as_deref())
+            .await;
+
+        Ok(PurgeCacheResponse {
+            purged: purged as i64,
+        })
+    }
+}
@@ -0,0 +1,216 @@
+use std::time::{Duration, SystemTime};
+
+/// The `Cache-Control` directives the cache acts on; every other one is
+/// ignored.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CacheControl {
+    pub no_store: bool,
+    pub no_cache: bool,
+    pub private: bool,
+    pub max_age: Option<u64>,
+    pub s_maxage: Option<u64>,
+}
+
+impl CacheControl {
+    /// Reads every `Cache-Control` header in `headers`.
+    pub fn from_headers(headers: &[(String, String)]) -> Self {
+        let mut result = Self::default();
+
+        for (_, value) in headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("cache-control"))
+        {
+            for directive in value.split(',') {
+                let (name, argument) = match directive.split_once('=') {
+                    Some((name, argument)) => {
+                        (name.trim(), Some(argument.trim().trim_matches('"')))
+                    }
+                    None => (directive.trim(), None),
+                };
+
+                if name.eq_ignore_ascii_case("no-store") {
+                    result.no_store = true;
+                } else if name.eq_ignore_ascii_case("no-cache") {
+                    result.no_cache = true;
+                } else if name.eq_ignore_ascii_case("private") {
+                    result.private = true;
+                } else if name.eq_ignore_ascii_case("max-age") {
+                    // An invalid max-age makes the response stale, not fresh.
+                    result.max_age = Some(argument.and_then(|itm| itm.parse().ok()).unwrap_or(0));
+                } else if name.eq_ignore_ascii_case("s-maxage") {
+                    result.s_maxage = Some(argument.and_then(|itm| itm.parse().ok()).unwrap_or(0));
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// The first header called `name`, case-insensitively.
+pub fn find_header<'s>(headers: &'s [(String, String)], name: &str) -> Option<&'s str> {
+    headers
+        .iter()
+        .find(|(itm, _)| itm.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Whether a response with `headers` may be kept and handed to other clients:
+/// not so with `no-store`, `private`, a `Set-Cookie` (a shared cache would
+/// hand one client's cookie to the next) or `Vary: *`.
+pub fn is_shareable(headers: &[(String, String)]) -> bool {
+    let cache_control = CacheControl::from_headers(headers);
+
+    if cache_control.no_store || cache_control.private {
+        return false;
+    }
+
+    if find_header(headers, "set-cookie").is_some() {
+        return false;
+    }
+
+    !headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("vary"))
+        .flat_map(|(_, value)| value.split(','))
+        .any(|itm| itm.trim() == "*")
+}
+
+/// How long a response with `headers`, received at `now`, stays fresh — or
+/// `None` when it is not to be stored at all (see [`is_shareable`]).
+///
+/// `s-maxage` wins over `max-age`, which wins over `Expires`. A response with
+/// none of them is stored only when it has a validator, fresh for no time at
+/// all — every request for it then revalidates instead of downloading it
+/// again. `no-cache` keeps the response but makes it revalidate the same way.
+/// Freshness is capped at `max_ttl`.
+pub fn get_freshness(
+    headers: &[(String, String)],
+    now: SystemTime,
+    max_ttl: Duration,
+) -> Option<Duration> {
+    if !is_shareable(headers) {
+        return None;
+    }
+
+    let cache_control = CacheControl::from_headers(headers);
+
+    let has_validator =
+        find_header(headers, "etag").is_some() || find_header(headers, "last-modified").is_some();
+
+    let explicit = match cache_control.s_maxage.or(cache_control.max_age) {
+        Some(seconds) => Some(Duration::from_secs(seconds)),
+        None => find_header(headers, "expires").map(|expires| {
+            let date = find_header(headers, "date")
+                .and_then(super::parse_http_date)
+                .unwrap_or(now);
+            super::parse_http_date(expires)
+                .and_then(|expires| expires.duration_since(date).ok())
+                .unwrap_or_default()
+        }),
+    };
+
+    let fresh_for = match (explicit, has_validator) {
+        (_, _) if cache_control.no_cache => Duration::ZERO,
+        (Some(fresh_for), _) => fresh_for,
+        (None, true) => Duration::ZERO,
+        (None, false) => return None,
+    };
+
+    // Stale on arrival with nothing to revalidate it by: not worth keeping.
+    if fresh_for.is_zero() && !has_validator {
+        return None;
+    }
+
+    Some(fresh_for.min(max_ttl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(src: &[(&str, &str)]) -> Vec<(String, String)> {
+        src.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    const MAX_TTL: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn max_age_and_s_maxage_set_the_freshness() {
+        let now = SystemTime::now();
+
+        let fresh_for = get_freshness(
+            &headers(&[("Cache-Control", "public, max-age=60")]),
+            now,
+            MAX_TTL,
+        );
+        assert_eq!(fresh_for, Some(Duration::from_secs(60)));
+
+        let fresh_for = get_freshness(
+            &headers(&[("cache-control", "max-age=60, s-maxage=120")]),
+            now,
+            MAX_TTL,
+        );
+        assert_eq!(fresh_for, Some(Duration::from_secs(120)));
+
+        let fresh_for = get_freshness(
+            &headers(&[("Cache-Control", "max-age=86400")]),
+            now,
+            MAX_TTL,
+        );
+        assert_eq!(fresh_for, Some(MAX_TTL));
+    }
+
+    #[test]
+    fn expires_counts_from_the_date_header() {
+        let fresh_for = get_freshness(
+            &headers(&[
+                ("Date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+                ("Expires", "Sun, 06 Nov 1994 08:59:37 GMT"),
+            ]),
+            SystemTime::now(),
+            MAX_TTL,
+        );
+        assert_eq!(fresh_for, Some(Duration::from_secs(600)));
+
+        // An invalid or past Expires without a validator is not kept.
+        let fresh_for = get_freshness(&headers(&[("Expires", "0")]), SystemTime::now(), MAX_TTL);
+        assert_eq!(fresh_for, None);
+    }
+
+    #[test]
+    fn validators_alone_store_a_response_that_always_revalidates() {
+        let now = SystemTime::now();
+
+        let fresh_for = get_freshness(&headers(&[("ETag", "\"v1\"")]), now, MAX_TTL);
+        assert_eq!(fresh_for, Some(Duration::ZERO));
+
+        let fresh_for = get_freshness(
+            &headers(&[
+                ("Cache-Control", "no-cache, max-age=60"),
+                ("ETag", "\"v1\""),
+            ]),
+            now,
+            MAX_TTL,
+        );
+        assert_eq!(fresh_for, Some(Duration::ZERO));
+
+        assert_eq!(get_freshness(&headers(&[]), now, MAX_TTL), None);
+    }
+
+    #[test]
+    fn private_and_personal_responses_are_not_stored() {
+        let now = SystemTime::now();
+
+        for src in [
+            headers(&[("Cache-Control", "no-store, max-age=60")]),
+            headers(&[("Cache-Control", "private, max-age=60")]),
+            headers(&[("Cache-Control", "max-age=60"), ("Set-Cookie", "a=b")]),
+            headers(&[("Cache-Control", "max-age=60"), ("Vary", "Accept, *")]),
+        ] {
+            assert_eq!(get_freshness(&src, now, MAX_TTL), None);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::find_header;
+
+/// Headers that describe the connection a response came over, or the framing
+/// of its body, rather than the response itself. They are not stored: the
+/// response is served again over other connections, with a body of known size.
+const NOT_STORED_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authenticate",
+    "transfer-encoding",
+    "te",
+    "trailer",
+    "upgrade",
+    "content-length",
+    "age",
+];
+
+/// Headers a `304 Not Modified` carries over from the response it stands for.
+const NOT_MODIFIED_HEADERS: &[&str] = &[
+    "cache-control",
+    "content-location",
+    "date",
+    "etag",
+    "expires",
+    "last-modified",
+    "vary",
+];
+
+/// Written first in a disk tier file; a file without it is not read.
+const DISK_FORMAT_MARK: &[u8] = b"my-reverse-proxy-cache-1\n";
+
+/// A response as the cache keeps it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedResponse {
+    pub status: u16,
+    /// The upstream's headers, without the hop-by-hop and framing ones.
+    pub headers: Vec<(String, String)>,
+    /// The body, with any chunked framing taken off.
+    pub body: Vec<u8>,
+    /// When the response was received, or last revalidated.
+    pub stored_at: SystemTime,
+    /// How long after `stored_at` the response is served without asking the
+    /// upstream.
+    pub fresh_for: Duration,
+}
+
+impl CachedResponse {
+    pub fn new(
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+        stored_at: SystemTime,
+        fresh_for: Duration,
+    ) -> Self {
+        let headers = headers
+            .into_iter()
+            .filter(|(name, _)| is_stored_header(name))
+            .collect();
+
+        Self {
+            status,
+            headers,
+            body,
+            stored_at,
+            fresh_for,
+        }
+    }
+
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Whole seconds since the response was stored — its `Age`.
+    pub fn get_age(&self, now: SystemTime) -> u64 {
+        now.duration_since(self.stored_at)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    pub fn is_fresh(&self, now: SystemTime) -> bool {
+        now.duration_since(self.stored_at).unwrap_or_default() < self.fresh_for
+    }
+
+    /// Whether the upstream can be asked if the response is still current.
+    pub fn has_validators(&self) -> bool {
+        self.get_header("etag").is_some() || self.get_header("last-modified").is_some()
+    }
+
+    /// What the response takes up in memory, near enough.
+    pub fn get_size(&self) -> usize {
+        self.body.len()
+            + self
+                .headers
+                .iter()
+                .map(|(name, value)| name.len() + value.len() + 4)
+                .sum::<usize>()
+    }
+
+    /// The request headers that ask the upstream whether this response is
+    /// still current.
+    pub fn get_validation_headers(&self) -> Vec<(&'static str, String)> {
+        let mut result = Vec::with_capacity(2);
+
+        if let Some(etag) = self.get_header("etag") {
+            result.push(("If-None-Match", etag.to_string()));
+        }
+
+        if let Some(last_modified) = self.get_header("last-modified") {
+            result.push(("If-Modified-Since", last_modified.to_string()));
+        }
+
+        result
+    }
+
+    /// The response after the upstream answered its revalidation with a 304:
+    /// the headers the 304 carries replace the stored ones of the same name.
+    pub fn refresh(
+        &self,
+        not_modified_headers: &[(String, String)],
+        now: SystemTime,
+        fresh_for: Duration,
+    ) -> Self {
+        let not_modified_headers: Vec<&(String, String)> = not_modified_headers
+            .iter()
+            .filter(|(name, _)| is_stored_header(name))
+            .collect();
+
+        let mut headers: Vec<(String, String)> = self
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                !not_modified_headers
+                    .iter()
+                    .any(|(itm, _)| itm.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+            .collect();
+        headers.extend(not_modified_headers.into_iter().cloned());
+
+        Self {
+            status: self.status,
+            headers,
+            body: self.body.clone(),
+            stored_at: now,
+            fresh_for,
+        }
+    }
+
+    /// Whether a client that sent `request_headers` already has this response
+    /// — its `If-None-Match`, or failing that its `If-Modified-Since`, is
+    /// satisfied — and gets a 304 instead of the body.
+    pub fn is_not_modified_for(&self, request_headers: &[(String, String)]) -> bool {
+        if self.status != 200 {
+            return false;
+        }
+
+        if let Some(if_none_match) = find_header(request_headers, "if-none-match") {
+            let Some(etag) = self.get_header("etag") else {
+                return false;
+            };
+
+            return if_none_match
+                .split(',')
+                .map(|itm| itm.trim())
+                .any(|itm| itm == "*" || weak_etag(itm) == weak_etag(etag));
+        }
+
+        if let Some(if_modified_since) = find_header(request_headers, "if-modified-since") {
+            let last_modified = self
+                .get_header("last-modified")
+                .and_then(super::parse_http_date);
+            let if_modified_since = super::parse_http_date(if_modified_since);
+
+            if let (Some(last_modified), Some(if_modified_since)) =
+                (last_modified, if_modified_since)
+            {
+                return last_modified <= if_modified_since;
+            }
+        }
+
+        false
+    }
+
+    /// The `304 Not Modified` a client gets when it already has the response.
+    pub fn to_not_modified(&self) -> Self {
+        Self {
+            status: 304,
+            headers: self
+                .headers
+                .iter()
+                .filter(|(name, _)| {
+                    NOT_MODIFIED_HEADERS
+                        .iter()
+                        .any(|itm| name.eq_ignore_ascii_case(itm))
+                })
+                .cloned()
+                .collect(),
+            body: Vec::new(),
+            stored_at: self.stored_at,
+            fresh_for: self.fresh_for,
+        }
+    }
+
+    /// The response served past its time, with `warning` — `110 - "Response
+    /// is Stale"` say — as its `Warning`.
+    pub fn to_stale(&self, warning: &str) -> Self {
+        let mut result = self.clone();
+        result
+            .headers
+            .retain(|(name, _)| !name.eq_ignore_ascii_case("warning"));
+        result
+            .headers
+            .push(("Warning".to_string(), warning.to_string()));
+        result
+    }
+
+    /// The response as a disk tier file; `key` is written along so a file
+    /// read back can be checked against the key it was looked up by.
+    pub fn to_disk_bytes(&self, key: &str) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.get_size() + key.len() + 64);
+        result.extend_from_slice(DISK_FORMAT_MARK);
+        push_bytes(&mut result, key.as_bytes());
+        result.extend_from_slice(&self.status.to_be_bytes());
+
+        let stored_at = self
+            .stored_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        result.extend_from_slice(&stored_at.to_be_bytes());
+        result.extend_from_slice(&(self.fresh_for.as_millis() as u64).to_be_bytes());
+
+        result.extend_from_slice(&(self.headers.len() as u32).to_be_bytes());
+        for (name, value) in self.headers.iter() {
+            push_bytes(&mut result, name.as_bytes());
+            push_bytes(&mut result, value.as_bytes());
+        }
+
+        push_bytes(&mut result, &self.body);
+        result
+    }
+
+    /// Reads back what [`Self::to_disk_bytes`] wrote for `key`.
+    pub fn from_disk_bytes(key: &str, src: &[u8]) -> Option<Self> {
+        let mut reader = DiskReader {
+            src: src.strip_prefix(DISK_FORMAT_MARK)?,
+        };
+
+        if reader.read_bytes()? != key.as_bytes() {
+            return None;
+        }
+
+        let status = u16::from_be_bytes(reader.read_array()?);
+        let stored_at =
+            UNIX_EPOCH + Duration::from_millis(u64::from_be_bytes(reader.read_array()?));
+        let fresh_for = Duration::from_millis(u64::from_be_bytes(reader.read_array()?));
+
+        let headers_count = u32::from_be_bytes(reader.read_array()?) as usize;
+        let mut headers = Vec::with_capacity(headers_count.min(256));
+        for _ in 0..headers_count {
+            let name = String::from_utf8(reader.read_bytes()?.to_vec()).ok()?;
+            let value = String::from_utf8(reader.read_bytes()?.to_vec()).ok()?;
+            headers.push((name, value));
+        }
+
+        let body = reader.read_bytes()?.to_vec();
+        if !reader.src.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            status,
+            headers,
+            body,
+            stored_at,
+            fresh_for,
+        })
+    }
+}
+
+fn is_stored_header(name: &str) -> bool {
+    !NOT_STORED_HEADERS
+        .iter()
+        .any(|itm| name.eq_ignore_ascii_case(itm))
+}
+
+/// An entity tag without its weakness mark: `If-None-Match` compares weakly.
+fn weak_etag(src: &str) -> &str {
+    src.strip_prefix("W/").unwrap_or(src)
+}
+
+fn push_bytes(dest: &mut Vec<u8>, src: &[u8]) {
+    dest.extend_from_slice(&(src.len() as u64).to_be_bytes());
+    dest.extend_from_slice(src);
+}
+
+struct DiskReader<'s> {
+    src: &'s [u8],
+}
+
+impl<'s> DiskReader<'s> {
+    fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.src.len() < N {
+            return None;
+        }
+
+        let (result, rest) = self.src.split_at(N);
+        self.src = rest;
+        result.try_into().ok()
+    }
+
+    fn read_bytes(&mut self) -> Option<&'s [u8]> {
+        let len = u64::from_be_bytes(self.read_array()?) as usize;
+        if self.src.len() < len {
+            return None;
+        }
+
+        let (result, rest) = self.src.split_at(len);
+        self.src = rest;
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> CachedResponse {
+        CachedResponse::new(
+            200,
+            vec![
+                ("Content-Type".to_string(), "text/css".to_string()),
+                ("ETag".to_string(), "\"v1\"".to_string()),
+                ("Cache-Control".to_string(), "max-age=60".to_string()),
+                ("Transfer-Encoding".to_string(), "chunked".to_string()),
+                ("Connection".to_string(), "keep-alive".to_string()),
+            ],
+            b"body { }".to_vec(),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            Duration::from_secs(60),
+        )
+    }
+
+    fn headers(src: &[(&str, &str)]) -> Vec<(String, String)> {
+        src.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn hop_by_hop_headers_are_not_stored() {
+        let response = response();
+
+        assert_eq!(response.headers.len(), 3);
+        assert!(response.get_header("transfer-encoding").is_none());
+        assert_eq!(response.get_header("etag"), Some("\"v1\""));
+    }
+
+    #[test]
+    fn freshness_and_age_count_from_when_it_was_stored() {
+        let response = response();
+
+        assert!(response.is_fresh(response.stored_at + Duration::from_secs(59)));
+        assert!(!response.is_fresh(response.stored_at + Duration::from_secs(60)));
+        assert_eq!(
+            response.get_age(response.stored_at + Duration::from_millis(2500)),
+            2
+        );
+    }
+
+    #[test]
+    fn conditional_requests_are_matched_against_the_validators() {
+        let response = response();
+
+        assert!(response.is_not_modified_for(&headers(&[("If-None-Match", "W/\"v0\", \"v1\"")])));
+        assert!(!response.is_not_modified_for(&headers(&[("If-None-Match", "\"v0\"")])));
+        assert!(!response.is_not_modified_for(&headers(&[])));
+
+        let not_modified = response.to_not_modified();
+        assert_eq!(not_modified.status, 304);
+        assert!(not_modified.body.is_empty());
+        assert!(not_modified.get_header("content-type").is_none());
+        assert_eq!(not_modified.get_header("etag"), Some("\"v1\""));
+    }
+
+    #[test]
+    fn a_304_refreshes_the_stored_headers() {
+        let response = response();
+        let now = response.stored_at + Duration::from_secs(100);
+
+        let refreshed = response.refresh(
+            &headers(&[("Cache-Control", "max-age=120"), ("Connection", "close")]),
+            now,
+            Duration::from_secs(120),
+        );
+
+        assert_eq!(refreshed.get_header("cache-control"), Some("max-age=120"));
+        assert!(refreshed.get_header("connection").is_none());
+        assert_eq!(refreshed.body, response.body);
+        assert!(refreshed.is_fresh(now + Duration::from_secs(119)));
+    }
+
+    #[test]
+    fn survives_the_disk_round_trip() {
+        let response = response();
+        let bytes = response.to_disk_bytes("GET example.com/site.css");
+
+        assert_eq!(
+            CachedResponse::from_disk_bytes("GET example.com/site.css", &bytes),
+            Some(response)
+        );
+        assert_eq!(
+            CachedResponse::from_disk_bytes("GET example.com/other.css", &bytes),
+            None
+        );
+        assert_eq!(
+            CachedResponse::from_disk_bytes("GET example.com/site.css", &bytes[..bytes.len() - 1]),
+            None
+        );
+    }
+}
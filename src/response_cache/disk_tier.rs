@@ -0,0 +1,180 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+
+use super::{CachedResponse, Lru};
+
+const FILE_EXTENSION: &str = "cache";
+const TEMP_FILE_EXTENSION: &str = "tmp";
+
+/// The disk tier of a location's cache: the responses pushed out of memory,
+/// one file each, named by the hash of their key. The index of what is on
+/// disk lives in memory and starts empty, so whatever a previous run left in
+/// the folder is deleted when the tier is created.
+pub struct DiskTier {
+    path: PathBuf,
+    index: Mutex<Lru<()>>,
+    next_temp_id: AtomicU64,
+}
+
+impl DiskTier {
+    pub fn new(path: PathBuf, max_size: usize) -> Result<Self, String> {
+        std::fs::create_dir_all(&path).map_err(|err| {
+            format!(
+                "Can not create cache folder {}: {}",
+                path.to_string_lossy(),
+                err
+            )
+        })?;
+
+        let dir = std::fs::read_dir(&path).map_err(|err| {
+            format!(
+                "Can not read cache folder {}: {}",
+                path.to_string_lossy(),
+                err
+            )
+        })?;
+
+        for entry in dir.flatten() {
+            let file_path = entry.path();
+            let is_ours = file_path
+                .extension()
+                .is_some_and(|itm| itm == FILE_EXTENSION || itm == TEMP_FILE_EXTENSION);
+
+            if is_ours {
+                let _ = std::fs::remove_file(file_path);
+            }
+        }
+
+        Ok(Self {
+            path,
+            index: Mutex::new(Lru::new(max_size)),
+            next_temp_id: AtomicU64::new(0),
+        })
+    }
+
+    pub async fn read(&self, key: &str) -> Option<CachedResponse> {
+        self.index.lock().get(key)?;
+
+        let bytes = tokio::fs::read(self.get_file_path(key)).await.ok()?;
+        CachedResponse::from_disk_bytes(key, &bytes)
+    }
+
+    /// Writes the response and indexes it once it is on disk — a response
+    /// still being written is not found.
+    pub async fn write(&self, key: String, response: &CachedResponse) {
+        let bytes = response.to_disk_bytes(&key);
+        let file_path = self.get_file_path(&key);
+
+        // Written aside and renamed into place, so a read never sees half a
+        // file.
+        let temp_id = self.next_temp_id.fetch_add(1, Ordering::Relaxed);
+        let temp_path = file_path.with_extension(format!("{}.{}", temp_id, TEMP_FILE_EXTENSION));
+
+        if tokio::fs::write(&temp_path, &bytes).await.is_err() {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return;
+        }
+
+        if tokio::fs::rename(&temp_path, &file_path).await.is_err() {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return;
+        }
+
+        let evicted = self.index.lock().insert(key, (), bytes.len());
+        for (key, _) in evicted {
+            let _ = tokio::fs::remove_file(self.get_file_path(&key)).await;
+        }
+    }
+
+    /// Removes every response `filter` picks by key; returns their keys.
+    pub async fn remove_where(&self, filter: impl Fn(&str) -> bool) -> Vec<String> {
+        let removed = self.index.lock().remove_where(filter);
+
+        let mut result = Vec::with_capacity(removed.len());
+        for (key, _) in removed {
+            let _ = tokio::fs::remove_file(self.get_file_path(&key)).await;
+            result.push(key);
+        }
+
+        result
+    }
+
+    fn get_file_path(&self, key: &str) -> PathBuf {
+        let hash = Sha256::digest(key.as_bytes());
+
+        let mut file_name = String::with_capacity(hash.len() * 2 + FILE_EXTENSION.len() + 1);
+        for byte in hash.iter() {
+            file_name.push_str(&format!("{:02x}", byte));
+        }
+        file_name.push('.');
+        file_name.push_str(FILE_EXTENSION);
+
+        self.path.join(file_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir()
+            .join("my-reverse-proxy-cache-tests")
+            .join(name);
+        let _ = std::fs::remove_dir_all(&folder);
+        folder
+    }
+
+    fn response(body: &[u8]) -> CachedResponse {
+        CachedResponse::new(
+            200,
+            vec![("Content-Type".to_string(), "text/plain".to_string())],
+            body.to_vec(),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            Duration::from_secs(60),
+        )
+    }
+
+    #[tokio::test]
+    async fn responses_are_read_back_until_pushed_out() {
+        let folder = temp_folder("push-out");
+        let tier = DiskTier::new(folder.clone(), 500).unwrap();
+
+        tier.write("GET a/1".to_string(), &response(&[1; 100]))
+            .await;
+        tier.write("GET a/2".to_string(), &response(&[2; 100]))
+            .await;
+        assert_eq!(tier.read("GET a/1").await, Some(response(&[1; 100])));
+
+        // `a/2` is the least recently used one now.
+        tier.write("GET a/3".to_string(), &response(&[3; 100]))
+            .await;
+        assert_eq!(tier.read("GET a/2").await, None);
+        assert!(tier.read("GET a/1").await.is_some());
+
+        assert_eq!(tier.remove_where(|itm| itm == "GET a/3").await.len(), 1);
+        assert_eq!(tier.read("GET a/3").await, None);
+
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+
+    #[tokio::test]
+    async fn what_an_earlier_run_left_is_deleted() {
+        let folder = temp_folder("earlier-run");
+
+        let tier = DiskTier::new(folder.clone(), 1024).unwrap();
+        tier.write("GET a/1".to_string(), &response(b"1")).await;
+        drop(tier);
+
+        let tier = DiskTier::new(folder.clone(), 1024).unwrap();
+        assert_eq!(tier.read("GET a/1").await, None);
+        assert_eq!(std::fs::read_dir(&folder).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+}
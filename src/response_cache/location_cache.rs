@@ -0,0 +1,321 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use ahash::AHashSet;
+use parking_lot::Mutex;
+
+use super::{find_header, CacheControl, CachedResponse, DiskTier, MemoryTier};
+
+/// Statuses stored. Anything else — a 206 of a range request, a 304 to a
+/// client's own conditional request, an error — goes to the client only.
+const STORED_STATUSES: &[u16] = &[200, 203, 204, 300, 301, 308, 404, 410];
+
+/// Request headers that make the request conditional on what the client
+/// already has. The cache does not add validators of its own to such a
+/// request: the upstream's answer is meant for the client.
+const CONDITIONAL_HEADERS: &[&str] = &[
+    "if-none-match",
+    "if-modified-since",
+    "if-match",
+    "if-unmodified-since",
+    "if-range",
+];
+
+/// `cache:` of a location, compiled. Two locations with equal settings can
+/// share what is cached across a reload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocationCacheSettings {
+    /// Bytes of responses kept in memory.
+    pub max_size: usize,
+    /// A response bigger than this is not stored.
+    pub max_entry_size: usize,
+    /// How long a response is fresh at most, whatever its headers say.
+    pub max_ttl: Duration,
+    /// Folder of the disk tier; none without it.
+    pub disk_path: Option<PathBuf>,
+    /// Bytes of responses kept on disk.
+    pub disk_max_size: usize,
+}
+
+/// The response cache of a location: an LRU of responses in memory, with the
+/// ones pushed out of it moved to disk when there is a disk tier.
+///
+/// Responses are stored by key — `GET {host}{path_and_query}`, followed by the
+/// request's values of the headers the response's `Vary` names. A HEAD request
+/// is answered from the entry of the GET: its response is that of the GET less
+/// the body, so a second entry would only hold the same head twice — and a
+/// HEAD never stores one.
+pub struct LocationCache {
+    /// What the cache metrics are labelled with — the location's `id_string`.
+    label: String,
+    settings: LocationCacheSettings,
+    memory: Mutex<MemoryTier>,
+    disk: Option<Arc<DiskTier>>,
+}
+
+impl LocationCache {
+    pub fn new(label: String, settings: LocationCacheSettings) -> Result<Self, String> {
+        let disk = match settings.disk_path.as_ref() {
+            Some(disk_path) => Some(Arc::new(DiskTier::new(
+                disk_path.clone(),
+                settings.disk_max_size,
+            )?)),
+            None => None,
+        };
+
+        Ok(Self {
+            memory: Mutex::new(MemoryTier::new(settings.max_size)),
+            label,
+            settings,
+            disk,
+        })
+    }
+
+    pub fn get_settings(&self) -> &LocationCacheSettings {
+        &self.settings
+    }
+
+    /// Decides what to do with a request for `path_and_query` at `host`.
+    /// `headers` are the request's; `has_identity` whether the proxy knows
+    /// who sent it — a Google or OAuth login, a client certificate, an
+    /// `auth_header`.
+    pub async fn lookup(
+        self: &Arc<Self>,
+        method: &str,
+        host: &str,
+        path_and_query: &str,
+        has_identity: bool,
+        headers: Vec<(String, String)>,
+    ) -> CacheLookup {
+        let is_head = match method {
+            "GET" => false,
+            "HEAD" => true,
+            _ => return CacheLookup::Bypass,
+        };
+
+        // A shared cache must not hand one user's authorized response to
+        // another — nor one the upstream made for the identity the proxy
+        // passes it.
+        if has_identity || find_header(&headers, "authorization").is_some() {
+            return CacheLookup::Bypass;
+        }
+
+        let cache_control = CacheControl::from_headers(&headers);
+        if cache_control.no_store {
+            return CacheLookup::Bypass;
+        }
+
+        // The client asks for a response the upstream has confirmed.
+        let no_cache = cache_control.no_cache
+            || cache_control.max_age == Some(0)
+            || find_header(&headers, "pragma")
+                .is_some_and(|itm| itm.trim().eq_ignore_ascii_case("no-cache"));
+
+        let key = super::get_request_key(host, path_and_query);
+        let cached = self.find(&key, &headers).await;
+
+        if let Some(cached) = cached.as_ref() {
+            if !no_cache && cached.is_fresh(SystemTime::now()) {
+                crate::app::APP_CTX.prometheus.inc_cache_hits(&self.label);
+
+                let response = match cached.is_not_modified_for(&headers) {
+                    true => Arc::new(cached.to_not_modified()),
+                    false => cached.clone(),
+                };
+
+                return CacheLookup::Hit(response);
+            }
+        }
+
+        crate::app::APP_CTX.prometheus.inc_cache_misses(&self.label);
+
+        let is_conditional = CONDITIONAL_HEADERS
+            .iter()
+            .any(|itm| find_header(&headers, itm).is_some());
+
+        let revalidating = match is_head || is_conditional {
+            true => None,
+            false => cached.filter(|itm| itm.has_validators()),
+        };
+
+        CacheLookup::Miss(CacheRequest {
+            cache: self.clone(),
+            key,
+            headers,
+            revalidating,
+            is_head,
+        })
+    }
+
+    /// Removes every response whose key, less the method, starts with
+    /// `prefix` — `example.com/static/` say — or all of them without one.
+    /// Returns how many went.
+    pub async fn purge(&self, prefix: Option<&str>) -> usize {
+        let matches = |key: &str| {
+            let Some(prefix) = prefix else {
+                return true;
+            };
+
+            key.split_once(' ')
+                .is_some_and(|(_, itm)| itm.starts_with(prefix))
+        };
+
+        let mut removed: AHashSet<String> = self
+            .memory
+            .lock()
+            .remove_where(matches)
+            .into_iter()
+            .collect();
+
+        if let Some(disk) = self.disk.as_ref() {
+            removed.extend(disk.remove_where(matches).await);
+        }
+
+        removed.len()
+    }
+
+    /// The stored response for the request with `headers` to `key`, from
+    /// memory or, failing that, from disk — moving it back to memory.
+    async fn find(&self, key: &str, headers: &[(String, String)]) -> Option<Arc<CachedResponse>> {
+        let full_key = {
+            let mut memory = self.memory.lock();
+            let full_key = memory.get_full_key(key, headers);
+
+            if let Some(response) = memory.get(&full_key) {
+                return Some(response);
+            }
+
+            full_key
+        };
+
+        let response = Arc::new(self.disk.as_ref()?.read(&full_key).await?);
+        self.put_in_memory(full_key, response.clone());
+        Some(response)
+    }
+
+    fn store(&self, key: &str, request_headers: &[(String, String)], response: CachedResponse) {
+        if response.get_size() > self.settings.max_entry_size {
+            return;
+        }
+
+        let full_key = self
+            .memory
+            .lock()
+            .index_vary(key, request_headers, &response);
+
+        self.put_in_memory(full_key, Arc::new(response));
+    }
+
+    /// What does not fit in memory any more goes to the disk tier.
+    fn put_in_memory(&self, full_key: String, response: Arc<CachedResponse>) {
+        let evicted = self.memory.lock().insert(full_key, response);
+
+        let Some(disk) = self.disk.as_ref() else {
+            return;
+        };
+
+        for (key, response) in evicted {
+            let disk = disk.clone();
+            crate::app::spawn_named("response_cache_to_disk", async move {
+                disk.write(key, &response).await;
+            });
+        }
+    }
+}
+
+/// What to do with a request, as far as the cache is concerned.
+pub enum CacheLookup {
+    /// Not for the cache: the request goes to the upstream as it is and the
+    /// response is not stored.
+    Bypass,
+    /// Served from the cache without the upstream — a 304 when the client
+    /// already has the response.
+    Hit(Arc<CachedResponse>),
+    /// The request goes to the upstream and the response may be stored.
+    Miss(CacheRequest),
+}
+
+/// A request the cache had no fresh response for, on its way to the upstream.
+pub struct CacheRequest {
+    cache: Arc<LocationCache>,
+    key: String,
+    headers: Vec<(String, String)>,
+    /// The stale response the upstream is asked to confirm.
+    revalidating: Option<Arc<CachedResponse>>,
+    /// A HEAD request: its response has no body to store.
+    is_head: bool,
+}
+
+impl CacheRequest {
+    /// The headers to add to the upstream request — the validators of the
+    /// stale response, when there is one.
+    pub fn get_validation_headers(&self) -> Vec<(&'static str, String)> {
+        match self.revalidating.as_ref() {
+            Some(itm) => itm.get_validation_headers(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The upstream answered the revalidation with `304 Not Modified` and
+    /// `headers`: the stale response is refreshed and stored again, and is
+    /// what the client gets. `None` when the request was not a revalidation —
+    /// the 304 then answers the client's own condition.
+    pub fn on_not_modified(&self, headers: &[(String, String)]) -> Option<Arc<CachedResponse>> {
+        let revalidating = self.revalidating.as_ref()?;
+
+        crate::app::APP_CTX
+            .prometheus
+            .inc_cache_revalidated(&self.cache.label);
+
+        let now = SystemTime::now();
+        let mut merged = revalidating.headers.clone();
+        merged.retain(|(name, _)| {
+            !headers
+                .iter()
+                .any(|(itm, _)| itm.eq_ignore_ascii_case(name))
+        });
+        merged.extend(headers.iter().cloned());
+
+        let fresh_for =
+            super::get_freshness(&merged, now, self.cache.settings.max_ttl).unwrap_or_default();
+        let refreshed = revalidating.refresh(headers, now, fresh_for);
+
+        self.cache
+            .store(&self.key, &self.headers, refreshed.clone());
+
+        let response = match refreshed.is_not_modified_for(&self.headers) {
+            true => refreshed.to_not_modified(),
+            false => refreshed,
+        };
+
+        Some(Arc::new(response))
+    }
+
+    /// How long the upstream's response with `status` and `headers` stays
+    /// fresh, or `None` when it is not to be stored.
+    pub fn get_freshness(&self, status: u16, headers: &[(String, String)]) -> Option<Duration> {
+        if self.is_head || !STORED_STATUSES.contains(&status) {
+            return None;
+        }
+
+        super::get_freshness(headers, SystemTime::now(), self.cache.settings.max_ttl)
+    }
+
+    /// The biggest body worth collecting for [`Self::store`].
+    pub fn get_max_body_size(&self) -> usize {
+        self.cache.settings.max_entry_size
+    }
+
+    /// Stores the upstream's response, once its body has been read in full.
+    pub fn store(
+        &self,
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+        fresh_for: Duration,
+    ) {
+        let response = CachedResponse::new(status, headers, body, SystemTime::now(), fresh_for);
+        self.cache.store(&self.key, &self.headers, response);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use ahash::AHashMap;
+
+struct LruEntry<V> {
+    value: V,
+    size: usize,
+    tick: u64,
+}
+
+/// Keys in least-recently-used order with the size of each, holding the total
+/// under `max_size` by pushing the oldest out. Both cache tiers are one — the
+/// memory one with the responses as values, the disk one with nothing but the
+/// sizes of its files.
+pub struct Lru<V> {
+    entries: AHashMap<String, LruEntry<V>>,
+    /// Tick of last use → key; the first is the next to go.
+    order: BTreeMap<u64, String>,
+    next_tick: u64,
+    size: usize,
+    max_size: usize,
+}
+
+impl<V> Lru<V> {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: AHashMap::new(),
+            order: BTreeMap::new(),
+            next_tick: 0,
+            size: 0,
+            max_size,
+        }
+    }
+
+    /// The value under `key`, marked as just used.
+    pub fn get(&mut self, key: &str) -> Option<&V> {
+        let tick = self.take_tick();
+        let entry = self.entries.get_mut(key)?;
+
+        self.order.remove(&entry.tick);
+        entry.tick = tick;
+        self.order.insert(tick, key.to_string());
+
+        Some(&entry.value)
+    }
+
+    /// Puts `value` under `key`, replacing what was there, and returns what
+    /// was pushed out to make room — the replaced value not included. A value
+    /// bigger than the whole LRU is handed straight back.
+    pub fn insert(&mut self, key: String, value: V, size: usize) -> Vec<(String, V)> {
+        self.remove(&key);
+
+        if size > self.max_size {
+            return vec![(key, value)];
+        }
+
+        let mut evicted = Vec::new();
+        while self.size + size > self.max_size {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.size -= entry.size;
+                evicted.push((oldest, entry.value));
+            }
+        }
+
+        let tick = self.take_tick();
+        self.order.insert(tick, key.clone());
+        self.entries.insert(key, LruEntry { value, size, tick });
+        self.size += size;
+
+        evicted
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.tick);
+        self.size -= entry.size;
+        Some(entry.value)
+    }
+
+    /// Removes every entry `filter` picks and returns them.
+    pub fn remove_where(&mut self, filter: impl Fn(&str) -> bool) -> Vec<(String, V)> {
+        let keys: Vec<String> = self
+            .entries
+            .keys()
+            .filter(|itm| filter(itm))
+            .cloned()
+            .collect();
+
+        keys.into_iter()
+            .filter_map(|key| {
+                let value = self.remove(&key)?;
+                Some((key, value))
+            })
+            .collect()
+    }
+
+    fn take_tick(&mut self) -> u64 {
+        let result = self.next_tick;
+        self.next_tick += 1;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_least_recently_used_go_first() {
+        let mut lru = Lru::new(30);
+
+        assert!(lru.insert("a".to_string(), 1, 10).is_empty());
+        assert!(lru.insert("b".to_string(), 2, 10).is_empty());
+        assert!(lru.insert("c".to_string(), 3, 10).is_empty());
+
+        // `a` is used, so `b` is now the oldest.
+        assert_eq!(lru.get("a"), Some(&1));
+
+        let evicted = lru.insert("d".to_string(), 4, 15);
+        assert_eq!(evicted, vec![("b".to_string(), 2), ("c".to_string(), 3)]);
+        assert_eq!(lru.get("b"), None);
+        assert_eq!(lru.get("a"), Some(&1));
+        assert_eq!(lru.get("d"), Some(&4));
+    }
+
+    #[test]
+    fn replacing_and_removing_keep_the_size_right() {
+        let mut lru = Lru::new(100);
+
+        lru.insert("a".to_string(), 1, 10);
+        lru.insert("a".to_string(), 2, 20);
+        assert_eq!(lru.get("a"), Some(&2));
+
+        lru.insert("ab".to_string(), 3, 5);
+        lru.insert("b".to_string(), 4, 5);
+        let removed = lru.remove_where(|itm| itm.starts_with('a'));
+        assert_eq!(removed.len(), 2);
+
+        assert_eq!(
+            lru.insert("huge".to_string(), 5, 101),
+            vec![("huge".to_string(), 5)]
+        );
+
+        // Only `b` is left, so 95 more fit without pushing it out.
+        assert!(lru.insert("c".to_string(), 6, 95).is_empty());
+        assert_eq!(lru.get("b"), Some(&4));
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use super::{find_header, CachedResponse, Lru};
+
+/// What the `Vary` index of a memory tier may take up. It outlives the
+/// responses it describes — losing one of its entries only costs a miss.
+const VARY_INDEX_MAX_SIZE: usize = 4 * 1024 * 1024;
+
+/// Responses in memory by key — `GET {host}{path_and_query}`, followed by the
+/// request's values of the headers the response's `Vary` names.
+pub struct MemoryTier {
+    responses: Lru<Arc<CachedResponse>>,
+    /// The `Vary` header names of what is stored under a key, by the key.
+    vary: Lru<Vec<String>>,
+}
+
+impl MemoryTier {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            responses: Lru::new(max_size),
+            vary: Lru::new(VARY_INDEX_MAX_SIZE),
+        }
+    }
+
+    /// The key the response to a request with `headers` for `key` is stored
+    /// under.
+    pub fn get_full_key(&mut self, key: &str, headers: &[(String, String)]) -> String {
+        let vary = self.vary.get(key).cloned();
+        get_full_key(key, vary.as_deref().unwrap_or_default(), headers)
+    }
+
+    pub fn get(&mut self, full_key: &str) -> Option<Arc<CachedResponse>> {
+        self.responses.get(full_key).cloned()
+    }
+
+    /// Notes the `Vary` of `response` to the request with `request_headers`
+    /// for `key`, and returns the key it is to be stored under.
+    pub fn index_vary(
+        &mut self,
+        key: &str,
+        request_headers: &[(String, String)],
+        response: &CachedResponse,
+    ) -> String {
+        let mut vary: Vec<String> = response
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("vary"))
+            .flat_map(|(_, value)| value.split(','))
+            .map(|itm| itm.trim().to_ascii_lowercase())
+            .filter(|itm| !itm.is_empty())
+            .collect();
+        vary.sort();
+        vary.dedup();
+
+        let full_key = get_full_key(key, &vary, request_headers);
+
+        if vary.is_empty() {
+            self.vary.remove(key);
+        } else {
+            let size = key.len() + vary.iter().map(|itm| itm.len()).sum::<usize>();
+            self.vary.insert(key.to_string(), vary, size);
+        }
+
+        full_key
+    }
+
+    /// Returns what was pushed out to make room.
+    pub fn insert(
+        &mut self,
+        full_key: String,
+        response: Arc<CachedResponse>,
+    ) -> Vec<(String, Arc<CachedResponse>)> {
+        let size = response.get_size();
+        self.responses.insert(full_key, response, size)
+    }
+
+    /// Removes every response `filter` picks by key; returns their keys.
+    pub fn remove_where(&mut self, filter: impl Fn(&str) -> bool) -> Vec<String> {
+        self.vary.remove_where(&filter);
+        self.responses
+            .remove_where(filter)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect()
+    }
+}
+
+/// The key of a request for `path_and_query` at `host`. The port is left out,
+/// so one purge prefix covers every listen port.
+pub fn get_request_key(host: &str, path_and_query: &str) -> String {
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.bytes().all(|itm| itm.is_ascii_digit()) => {
+            name
+        }
+        _ => host,
+    };
+
+    format!("GET {}{}", host.to_ascii_lowercase(), path_and_query)
+}
+
+/// `key` followed by the request's value of every header in `vary`.
+fn get_full_key(key: &str, vary: &[String], headers: &[(String, String)]) -> String {
+    let mut result = key.to_string();
+
+    for name in vary {
+        result.push('\n');
+        result.push_str(name);
+        result.push(':');
+        if let Some(value) = find_header(headers, name) {
+            result.push_str(value.trim());
+        }
+    }
+
+    result
+}
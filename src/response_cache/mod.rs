@@ -0,0 +1,16 @@
+mod http_date;
+pub use http_date::*;
+mod cache_control;
+pub use cache_control::*;
+mod cached_response;
+pub use cached_response::*;
+mod lru;
+pub use lru::*;
+mod memory_tier;
+pub use memory_tier::*;
+mod disk_tier;
+pub use disk_tier::*;
+mod location_cache;
+pub use location_cache::*;
+mod stale_store;
+pub use stale_store::*;
@@ -0,0 +1,364 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use ahash::AHashSet;
+use parking_lot::Mutex;
+
+use super::{find_header, CacheControl, CachedResponse, MemoryTier};
+
+/// Statuses kept as the last good response.
+const STORED_STATUSES: &[u16] = &[200, 203, 204, 300, 301, 308];
+
+/// Upstream statuses answered with the last good response instead — the
+/// errors of RFC 5861 `stale-if-error`.
+const ERROR_STATUSES: &[u16] = &[500, 502, 503, 504];
+
+const STALE_WARNING: &str = "110 - \"Response is Stale\"";
+const REVALIDATION_FAILED_WARNING: &str = "111 - \"Revalidation Failed\"";
+
+/// `stale:` of a location, compiled. Two locations with equal settings can
+/// share what is kept across a reload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleStoreSettings {
+    /// Bytes of responses kept.
+    pub max_size: usize,
+    /// A response bigger than this is not kept.
+    pub max_entry_size: usize,
+    /// How long after it was received a response stands in for an upstream
+    /// that fails; none without `stale-if-error`.
+    pub if_error: Option<Duration>,
+    /// How long after it was received a response is served at once while the
+    /// upstream is asked for a new one; none without `stale-while-revalidate`.
+    pub while_revalidate: Option<Duration>,
+}
+
+/// The last good response of the upstream to every GET of a location — an LRU
+/// in memory, keyed the same way as the response cache, whatever the
+/// response's own freshness.
+pub struct StaleStore {
+    /// What the stale metrics are labelled with — the location's `id_string`.
+    label: String,
+    settings: StaleStoreSettings,
+    memory: Mutex<MemoryTier>,
+    /// Full keys of the responses a request is refreshing in the background.
+    refreshing: Mutex<AHashSet<String>>,
+}
+
+impl StaleStore {
+    pub fn new(label: String, settings: StaleStoreSettings) -> Self {
+        Self {
+            memory: Mutex::new(MemoryTier::new(settings.max_size)),
+            refreshing: Mutex::new(AHashSet::new()),
+            label,
+            settings,
+        }
+    }
+
+    pub fn get_settings(&self) -> &StaleStoreSettings {
+        &self.settings
+    }
+
+    /// The store's part in a request for `path_and_query` at `host`, or `None`
+    /// when it takes none. `headers` are the request's; `has_identity` whether
+    /// the proxy knows who sent it.
+    pub fn begin(
+        self: &Arc<Self>,
+        method: &str,
+        host: &str,
+        path_and_query: &str,
+        has_identity: bool,
+        headers: Vec<(String, String)>,
+    ) -> Option<StaleRequest> {
+        let is_head = match method {
+            "GET" => false,
+            "HEAD" => true,
+            _ => return None,
+        };
+
+        // One user's authorized response is not served to another — nor one
+        // made for the identity the proxy passes the upstream.
+        if has_identity || find_header(&headers, "authorization").is_some() {
+            return None;
+        }
+
+        if CacheControl::from_headers(&headers).no_store {
+            return None;
+        }
+
+        let key = super::get_request_key(host, path_and_query);
+        let full_key = self.memory.lock().get_full_key(&key, &headers);
+
+        Some(StaleRequest {
+            store: self.clone(),
+            key,
+            full_key,
+            headers,
+            is_head,
+            refreshing: false,
+        })
+    }
+
+    fn find(&self, full_key: &str, max_age: Duration) -> Option<Arc<CachedResponse>> {
+        let response = self.memory.lock().get(full_key)?;
+
+        let age = SystemTime::now()
+            .duration_since(response.stored_at)
+            .unwrap_or_default();
+
+        match age < max_age {
+            true => Some(response),
+            false => None,
+        }
+    }
+
+    fn store(&self, key: &str, request_headers: &[(String, String)], response: CachedResponse) {
+        if response.get_size() > self.settings.max_entry_size {
+            return;
+        }
+
+        let mut memory = self.memory.lock();
+        let full_key = memory.index_vary(key, request_headers, &response);
+        memory.insert(full_key, Arc::new(response));
+    }
+}
+
+/// What a request may be answered with while the upstream is asked for a new
+/// response.
+pub enum StaleRevalidation {
+    /// Another request is refreshing the response already — this one is
+    /// answered with it and goes no further.
+    Serve(Arc<CachedResponse>),
+    /// The client is answered with the response, and the request goes on to
+    /// the upstream in the background to refresh it.
+    Refresh(Arc<CachedResponse>),
+}
+
+/// A request of a location with `stale:`, on its way to the upstream.
+pub struct StaleRequest {
+    store: Arc<StaleStore>,
+    key: String,
+    full_key: String,
+    headers: Vec<(String, String)>,
+    /// A HEAD request: it is answered from the store, but its response has no
+    /// body to keep.
+    is_head: bool,
+    /// The request is the one refreshing its response in the background.
+    refreshing: bool,
+}
+
+impl StaleRequest {
+    pub fn is_head(&self) -> bool {
+        self.is_head
+    }
+
+    /// The response kept for the request, when it is younger than
+    /// `while_revalidate` and the client did not ask for a confirmed one.
+    pub fn find_to_revalidate(&mut self) -> Option<StaleRevalidation> {
+        let while_revalidate = self.store.settings.while_revalidate?;
+
+        let cache_control = CacheControl::from_headers(&self.headers);
+        let no_cache = cache_control.no_cache
+            || cache_control.max_age == Some(0)
+            || find_header(&self.headers, "pragma")
+                .is_some_and(|itm| itm.trim().eq_ignore_ascii_case("no-cache"));
+        if no_cache {
+            return None;
+        }
+
+        let response = self.store.find(&self.full_key, while_revalidate)?;
+
+        crate::app::APP_CTX
+            .prometheus
+            .inc_stale_while_revalidate(&self.store.label);
+
+        let response = Arc::new(response.to_stale(STALE_WARNING));
+
+        match self.store.refreshing.lock().insert(self.full_key.clone()) {
+            true => {
+                self.refreshing = true;
+                Some(StaleRevalidation::Refresh(response))
+            }
+            false => Some(StaleRevalidation::Serve(response)),
+        }
+    }
+
+    /// Whether an upstream answer with `status` is replaced with the response
+    /// kept for the request, if there is one.
+    pub fn serves_on_status(&self, status: u16) -> bool {
+        self.store.settings.if_error.is_some() && ERROR_STATUSES.contains(&status)
+    }
+
+    /// The response kept for the request, when it is younger than `if_error`
+    /// — what the client gets in place of an upstream failure. A request
+    /// refreshing its response in the background has answered its client
+    /// already.
+    pub fn find_on_error(&self) -> Option<Arc<CachedResponse>> {
+        if self.refreshing {
+            return None;
+        }
+
+        let if_error = self.store.settings.if_error?;
+        let response = self.store.find(&self.full_key, if_error)?;
+
+        crate::app::APP_CTX
+            .prometheus
+            .inc_stale_on_error(&self.store.label);
+
+        Some(Arc::new(response.to_stale(REVALIDATION_FAILED_WARNING)))
+    }
+
+    /// Whether the upstream's response with `status` and `headers` is kept.
+    pub fn is_storable(&self, status: u16, headers: &[(String, String)]) -> bool {
+        !self.is_head && STORED_STATUSES.contains(&status) && super::is_shareable(headers)
+    }
+
+    /// The biggest body worth collecting for [`Self::store`].
+    pub fn get_max_body_size(&self) -> usize {
+        self.store.settings.max_entry_size
+    }
+
+    /// Keeps the upstream's response, once its body has been read in full.
+    pub fn store(&self, status: u16, headers: Vec<(String, String)>, body: Vec<u8>) {
+        let response =
+            CachedResponse::new(status, headers, body, SystemTime::now(), Duration::ZERO);
+        self.store.store(&self.key, &self.headers, response);
+    }
+
+    /// Keeps a response the location's cache got confirmed by the upstream.
+    pub fn store_revalidated(&self, response: &CachedResponse) {
+        if self.is_storable(response.status, &response.headers) {
+            self.store.store(&self.key, &self.headers, response.clone());
+        }
+    }
+}
+
+impl Drop for StaleRequest {
+    fn drop(&mut self) {
+        if self.refreshing {
+            self.store.refreshing.lock().remove(&self.full_key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(src: &[(&str, &str)]) -> Vec<(String, String)> {
+        src.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn store(if_error: Option<u64>, while_revalidate: Option<u64>) -> Arc<StaleStore> {
+        Arc::new(StaleStore::new(
+            "test".to_string(),
+            StaleStoreSettings {
+                max_size: 1024,
+                max_entry_size: 256,
+                if_error: if_error.map(Duration::from_secs),
+                while_revalidate: while_revalidate.map(Duration::from_secs),
+            },
+        ))
+    }
+
+    fn begin(store: &Arc<StaleStore>, request_headers: &[(&str, &str)]) -> StaleRequest {
+        store
+            .begin(
+                "GET",
+                "Example.com:443",
+                "/a?b=1",
+                false,
+                headers(request_headers),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn the_last_good_response_stands_in_for_a_failure() {
+        let store = store(Some(60), None);
+
+        let request = begin(&store, &[]);
+        assert!(request.find_on_error().is_none());
+        assert!(request.is_storable(200, &headers(&[])));
+        request.store(
+            200,
+            headers(&[("Content-Type", "text/plain")]),
+            b"ok".to_vec(),
+        );
+
+        let request = begin(&store, &[]);
+        assert!(request.serves_on_status(503));
+        assert!(!request.serves_on_status(404));
+
+        let response = request.find_on_error().unwrap();
+        assert_eq!(response.body, b"ok".to_vec());
+        assert_eq!(
+            response.get_header("warning"),
+            Some(REVALIDATION_FAILED_WARNING)
+        );
+
+        // Without `while_revalidate` nothing is served before the upstream is
+        // asked.
+        assert!(begin(&store, &[]).find_to_revalidate().is_none());
+    }
+
+    #[test]
+    fn one_request_at_a_time_refreshes_a_response() {
+        let store = store(None, Some(60));
+        begin(&store, &[]).store(200, headers(&[]), b"ok".to_vec());
+
+        let mut first = begin(&store, &[]);
+        assert!(matches!(
+            first.find_to_revalidate(),
+            Some(StaleRevalidation::Refresh(_))
+        ));
+
+        let mut second = begin(&store, &[]);
+        assert!(matches!(
+            second.find_to_revalidate(),
+            Some(StaleRevalidation::Serve(_))
+        ));
+
+        // The client asks for a confirmed response.
+        let mut no_cache = begin(&store, &[("Cache-Control", "no-cache")]);
+        assert!(no_cache.find_to_revalidate().is_none());
+
+        drop(first);
+        assert!(matches!(
+            begin(&store, &[]).find_to_revalidate(),
+            Some(StaleRevalidation::Refresh(_))
+        ));
+
+        assert!(!begin(&store, &[]).serves_on_status(503));
+    }
+
+    #[test]
+    fn personal_and_oversized_responses_are_not_kept() {
+        let store = store(Some(60), None);
+
+        let request = begin(&store, &[]);
+        assert!(!request.is_storable(500, &headers(&[])));
+        assert!(!request.is_storable(200, &headers(&[("Set-Cookie", "a=b")])));
+        assert!(!request.is_storable(200, &headers(&[("Cache-Control", "private")])));
+
+        request.store(200, headers(&[]), vec![0; 300]);
+        assert!(begin(&store, &[]).find_on_error().is_none());
+
+        assert!(store
+            .begin(
+                "GET",
+                "example.com",
+                "/a",
+                false,
+                headers(&[("Authorization", "x")])
+            )
+            .is_none());
+        assert!(store
+            .begin("GET", "example.com", "/a", true, headers(&[]))
+            .is_none());
+        assert!(store
+            .begin("POST", "example.com", "/a", false, headers(&[]))
+            .is_none());
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use crate::{
+    app::{ConcurrencyLimiterSettings, ConnectionLimits},
+    settings::{EndpointSettings, LocationSettings},
+};
+
+/// `max_connections` and `max_connections_per_ip` of an endpoint. `owner` is
+/// what errors name — `Endpoint 'example.com:443'`, say.
+pub fn compile_connection_limits(
+    settings: &EndpointSettings,
+    owner: &str,
+) -> Result<ConnectionLimits, String> {
+    if settings.max_connections == Some(0) {
+        return Err(format!("{}: max_connections must be above 0", owner));
+    }
+
+    if settings.max_connections_per_ip == Some(0) {
+        return Err(format!("{}: max_connections_per_ip must be above 0", owner));
+    }
+
+    Ok(ConnectionLimits {
+        max_connections: settings.max_connections,
+        max_connections_per_ip: settings.max_connections_per_ip,
+    })
+}
+
+/// `max_concurrent_requests` of a location, with its queue.
+pub fn compile_concurrency_limit(
+    settings: &LocationSettings,
+    owner: &str,
+) -> Result<Option<ConcurrencyLimiterSettings>, String> {
+    let Some(max_requests) = settings.max_concurrent_requests else {
+        if settings.max_queued_requests.is_some() || settings.queue_timeout.is_some() {
+            return Err(format!(
+                "{}: max_queued_requests and queue_timeout need max_concurrent_requests",
+                owner
+            ));
+        }
+
+        return Ok(None);
+    };
+
+    if max_requests == 0 {
+        return Err(format!(
+            "{}: max_concurrent_requests must be above 0",
+            owner
+        ));
+    }
+
+    Ok(Some(ConcurrencyLimiterSettings {
+        max_requests,
+        max_queued: settings.max_queued_requests.unwrap_or(0),
+        queue_timeout: match settings.queue_timeout {
+            Some(queue_timeout) => Duration::from_millis(queue_timeout),
+            None => crate::consts::DEFAULT_CONCURRENCY_QUEUE_TIMEOUT,
+        },
+    }))
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use crate::{
+    configurations::{CorsConfig, CorsOrigin},
+    settings::CorsSettings,
+};
+
+/// `cors` of an endpoint or a location. `owner` is what errors name —
+/// `Location /api`, say.
+pub fn compile_cors(
+    settings: Option<&CorsSettings>,
+    owner: &str,
+) -> Result<Option<Arc<CorsConfig>>, String> {
+    let Some(settings) = settings else {
+        return Ok(None);
+    };
+
+    if settings.allowed_origins.is_empty() {
+        return Err(format!("{}: cors.allowed_origins is empty", owner));
+    }
+
+    let mut origins = Vec::with_capacity(settings.allowed_origins.len());
+    for origin in settings.allowed_origins.iter() {
+        origins.push(CorsOrigin::parse(origin).map_err(|err| format!("{}: {}", owner, err))?);
+    }
+
+    let allowed_methods = match settings.allowed_methods.as_ref() {
+        Some(methods) => {
+            let mut result = Vec::with_capacity(methods.len());
+            for method in methods {
+                let method = method.trim();
+                if method.is_empty() || !method.bytes().all(|itm| itm.is_ascii_alphabetic()) {
+                    return Err(format!(
+                        "{}: cors.allowed_methods has an invalid method '{}'",
+                        owner, method
+                    ));
+                }
+                result.push(method.to_uppercase());
+            }
+            result
+        }
+        None => crate::consts::DEFAULT_CORS_METHODS
+            .iter()
+            .map(|itm| itm.to_string())
+            .collect(),
+    };
+
+    Ok(Some(Arc::new(CorsConfig::new(
+        origins,
+        allowed_methods,
+        settings.allowed_headers.clone(),
+        settings.expose_headers.clone(),
+        settings.allow_credentials.unwrap_or(false),
+        settings.max_age,
+    ))))
+}
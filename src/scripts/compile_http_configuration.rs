@@ -3,6 +3,8 @@ use std::sync::Arc;
 use crate::{
     configurations::{
         EndpointHttpHostString, HttpEndpointInfo, HttpEndpointInfoParams, ListenHttpEndpointType,
+        LocationMatchMode, LocationMatcher, ProxyPassLocationConfig, ProxyPassToConfig,
+        RedirectConfig,
     },
     settings::HostSettings,
     settings_compiled::SettingsCompiled,
@@ -55,6 +57,64 @@ pub async fn compile_http_configuration(
         ));
     }
 
+    // Unlike timeouts, rate limits do not override one another: the global one
+    // counts a client's requests to every endpoint, the endpoint's to this one.
+    let mut rate_limits = Vec::new();
+
+    if let Some(rate_limit) =
+        super::compile_rate_limit(settings_model.get_global_rate_limit(), "global_settings")?
+    {
+        rate_limits.push(
+            crate::app::APP_CTX
+                .rate_limiters
+                .apply(crate::consts::GLOBAL_RATE_LIMIT_LABEL, rate_limit),
+        );
+    }
+
+    if let Some(rate_limit) = super::compile_rate_limit(
+        host_settings.endpoint.rate_limit.as_ref(),
+        &format!("Endpoint '{}'", host_endpoint.as_str()),
+    )? {
+        if matches!(http_type, ListenHttpEndpointType::Mcp) {
+            return Err(format!(
+                "Endpoint '{}' is 'type: mcp', which is a raw TCP bridge with no HTTP layer, so \
+                 'rate_limit' can not be applied to it. Set it on a location of 'type: mcp' \
+                 under a 'type: https' endpoint instead",
+                host_endpoint.as_str()
+            ));
+        }
+
+        rate_limits.push(
+            crate::app::APP_CTX
+                .rate_limiters
+                .apply(host_endpoint.as_str(), rate_limit),
+        );
+    }
+
+    let connection_limits = super::compile_connection_limits(
+        &host_settings.endpoint,
+        &format!("Endpoint '{}'", host_endpoint.as_str()),
+    )?;
+
+    let request_limits = super::compile_request_limits(
+        &host_settings.endpoint.request_limits,
+        &format!("Endpoint '{}'", host_endpoint.as_str()),
+    )?;
+
+    let cors = super::compile_cors(
+        host_settings.endpoint.cors.as_ref(),
+        &format!("Endpoint '{}'", host_endpoint.as_str()),
+    )?;
+
+    if cors.is_some() && matches!(http_type, ListenHttpEndpointType::Mcp) {
+        return Err(format!(
+            "Endpoint '{}' is 'type: mcp', which is a raw TCP bridge with no HTTP layer, so \
+             'cors' can not be applied to it. Set it on a location of 'type: mcp' under a \
+             'type: https' endpoint instead",
+            host_endpoint.as_str()
+        ));
+    }
+
     // Timeout cascade: HardCode < Global < Endpoint < Location.
     // Build the global→endpoint layer once, then layer each location on top.
     let global_timeouts = settings_model.get_global_timeouts();
@@ -110,6 +170,45 @@ pub async fn compile_http_configuration(
         }
     }
 
+    // `https_redirect` is a catch-all `/` redirect location: the endpoint's own
+    // locations (an ACME challenge path, say) still win over it.
+    if host_settings.endpoint.https_redirect.unwrap_or(false) {
+        if !matches!(
+            http_type,
+            ListenHttpEndpointType::Http1 | ListenHttpEndpointType::Http2
+        ) {
+            return Err(format!(
+                "Endpoint '{}': https_redirect is only for 'type: http' / 'type: http2' endpoints",
+                listen_host
+            ));
+        }
+
+        if locations.iter().any(|itm| {
+            matches!(
+                itm.matcher.get_mode(),
+                LocationMatchMode::Prefix | LocationMatchMode::PriorityPrefix
+            ) && (itm.path.is_empty() || itm.path == "/")
+        }) {
+            return Err(format!(
+                "Endpoint '{}': https_redirect takes the '/' location, which is already configured",
+                listen_host
+            ));
+        }
+
+        let redirect = ProxyPassToConfig::Redirect(RedirectConfig::new_https_redirect().into());
+        locations.push(Arc::new(ProxyPassLocationConfig::new(
+            "/".to_string(),
+            LocationMatcher::new_prefix("/"),
+            None,
+            None,
+            redirect,
+            None,
+            false,
+            None,
+            &listen_host,
+        )));
+    }
+
     let mcp_settings = crate::configurations::McpEndpointSettings::new(
         mcp_resolved.read_timeout,
         mcp_resolved.write_timeout,
@@ -145,6 +244,10 @@ pub async fn compile_http_configuration(
         hsts: host_settings.endpoint.hsts.unwrap_or(false),
         mcp_settings,
         timeouts: http_timeouts,
+        rate_limits,
+        connection_limits,
+        request_limits,
+        cors,
     });
 
     Ok(http_endpoint_info)
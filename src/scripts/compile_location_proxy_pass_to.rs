@@ -1,6 +1,15 @@
+use std::{sync::Arc, time::Duration};
+
 use my_ssh::ssh_settings::OverSshConnectionSettings;
 
-use crate::{configurations::*, settings::*, settings_compiled::SettingsCompiled};
+use crate::{
+    body_filter::{ResponseCompression, SubFilter, SubFilterRule},
+    configurations::*,
+    http_content_source::FileLookup,
+    response_cache::{LocationCacheSettings, StaleStoreSettings},
+    settings::*,
+    settings_compiled::SettingsCompiled,
+};
 
 pub async fn compile_location_proxy_pass_to(
     settings_model: &SettingsCompiled,
@@ -15,6 +24,16 @@ pub async fn compile_location_proxy_pass_to(
         .unwrap_or("/")
         .to_string();
 
+    let matcher = location_settings.get_location_matcher(path.as_str())?;
+
+    // A split is a weighted list of upstreams with names and matches on top:
+    // the rest of the compilation sees its branches as `proxy_pass_to`.
+    let split = compile_split(location_settings, path.as_str())?;
+    let location_settings = match split.as_ref() {
+        Some((settings, _)) => settings,
+        None => location_settings,
+    };
+
     let location_type = match location_settings.get_location_type()? {
         Some(location_type) => location_type,
         None => LocationType::detect_from_location_settings(location_settings)?,
@@ -61,34 +80,55 @@ pub async fn compile_location_proxy_pass_to(
         ),
 
         LocationType::Files => {
-            if location_settings.proxy_pass_to.is_none() {
+            let Some(proxy_pass_to) = location_settings
+                .proxy_pass_to
+                .as_ref()
+                .and_then(|itm| itm.get_first())
+            else {
                 return Err("proxy_pass_to is required for files location type".to_string());
-            }
+            };
 
-            let proxy_pass_to = location_settings.proxy_pass_to.clone().unwrap();
             let files_path =
-                MyReverseProxyRemoteEndpoint::try_parse(proxy_pass_to.as_str(), settings_model)
-                    .await?;
+                MyReverseProxyRemoteEndpoint::try_parse(proxy_pass_to, settings_model).await?;
 
             let model = ProxyPassFilesPathModel {
                 files_path,
-                default_file: location_settings.default_file.clone(),
+                lookup: compile_file_lookup(location_settings, &path)?.into(),
             };
 
             ProxyPassToConfig::FilesPath(model)
         }
         LocationType::StaticContent => {
-            let body = location_settings.body.clone().unwrap_or_default();
+            let body = match location_settings.body_file.as_ref() {
+                Some(body_file) => {
+                    if location_settings.body.is_some() {
+                        return Err(format!(
+                            "Location {}: body and body_file can not be used together",
+                            path
+                        ));
+                    }
+
+                    load_body_file(body_file, settings_model)
+                        .await
+                        .map_err(|err| format!("Location {}: body_file: {}", path, err))?
+                }
+                None => {
+                    let body = location_settings.body.clone().unwrap_or_default();
+                    get_static_content_body(body).await?
+                }
+            };
 
-            let body = get_static_content_body(body).await?;
             let model: StaticContentConfig = StaticContentConfig {
                 status_code: location_settings.status_code.unwrap_or(200),
                 content_type: location_settings.content_type.clone(),
-                body,
+                body: StaticBodyTemplate::new(body, location_settings.content_type.as_deref()),
             };
 
             ProxyPassToConfig::Static(model.into())
         }
+        LocationType::Redirect => ProxyPassToConfig::Redirect(
+            compile_redirect(location_settings, &path, &matcher)?.into(),
+        ),
         LocationType::Drop => ProxyPassToConfig::Drop,
         LocationType::DynamicProxy => ProxyPassToConfig::DynamicProxy(
             DynamicProxyConfig {
@@ -100,8 +140,70 @@ pub async fn compile_location_proxy_pass_to(
         ),
     };
 
-    let result = ProxyPassLocationConfig::new(
+    if !matches!(proxy_pass_to, ProxyPassToConfig::FilesPath(_))
+        && (location_settings.autoindex.is_some()
+            || location_settings.try_files.is_some()
+            || location_settings.precompressed.is_some())
+    {
+        return Err(format!(
+            "Location {}: autoindex, try_files and precompressed are not supported for {} locations",
+            path,
+            proxy_pass_to.get_type_as_str()
+        ));
+    }
+
+    if !matches!(proxy_pass_to, ProxyPassToConfig::Static(_))
+        && location_settings.body_file.is_some()
+    {
+        return Err(format!(
+            "Location {}: body_file is only for static locations",
+            path
+        ));
+    }
+
+    let path_rewrite =
+        compile_path_rewrite(location_settings, path.as_str(), &matcher, &proxy_pass_to)?;
+
+    let response_rewrite = compile_response_rewrite(
+        location_settings,
+        path.as_str(),
+        &proxy_pass_to,
+        path_rewrite.is_some(),
+    )?;
+
+    let upstreams = compile_upstreams(
+        location_settings,
+        settings_model,
+        resolved,
+        path.as_str(),
+        &proxy_pass_to,
+    )
+    .await?;
+
+    let sticky = compile_sticky(location_settings, path.as_str(), upstreams.is_some())?;
+
+    let backups = compile_backups(
+        location_settings,
+        settings_model,
+        resolved,
+        path.as_str(),
+        &proxy_pass_to,
+        upstreams.as_ref().map(|(_, itm)| itm.as_slice()),
+    )
+    .await?;
+
+    let mirror = compile_mirror(
+        location_settings,
+        settings_model,
+        resolved,
+        path.as_str(),
+        &proxy_pass_to,
+    )
+    .await?;
+
+    let mut result = ProxyPassLocationConfig::new(
         path,
+        matcher,
         location_settings.modify_http_headers.clone(),
         location_settings.whitelisted_ip.clone(),
         proxy_pass_to,
@@ -111,9 +213,1156 @@ pub async fn compile_location_proxy_pass_to(
         listen_host,
     );
 
+    result.path_rewrite = path_rewrite;
+    result.response_rewrite = response_rewrite.map(Arc::new);
+
+    if let Some((policy, upstreams)) = upstreams {
+        result.set_upstreams(policy, upstreams, listen_host);
+    }
+
+    if let Some(sticky) = sticky {
+        result.set_sticky(sticky);
+    }
+
+    if let Some((_, branches)) = split {
+        result.set_split(branches);
+    }
+
+    if let Some((backups, on_status)) = backups {
+        result.set_backups(backups, on_status, listen_host);
+    }
+
+    if let Some((mirror, percent, max_body_size)) = mirror {
+        result.set_mirror(mirror, percent, max_body_size, listen_host);
+    }
+
+    result.retry = compile_retry(
+        location_settings,
+        &result.path,
+        &result.proxy_pass_to,
+        &result.id_string,
+    )?
+    .map(Arc::new);
+
+    if let Some(cache) = compile_cache(
+        location_settings,
+        &result.path,
+        &result.proxy_pass_to,
+        &result.id_string,
+    )? {
+        result.set_cache(cache)?;
+    }
+
+    if let Some(stale) = compile_stale(location_settings, &result.path, &result.proxy_pass_to)? {
+        result.set_stale(stale);
+    }
+
+    if let Some(rate_limit) = super::compile_rate_limit(
+        location_settings.rate_limit.as_ref(),
+        &format!("Location {}", result.path),
+    )? {
+        result.set_rate_limit(rate_limit);
+    }
+
+    if let Some(concurrency_limit) =
+        super::compile_concurrency_limit(location_settings, &format!("Location {}", result.path))?
+    {
+        // Only what goes on to an upstream is counted.
+        if matches!(
+            result.proxy_pass_to,
+            ProxyPassToConfig::FilesPath(_)
+                | ProxyPassToConfig::Static(_)
+                | ProxyPassToConfig::Redirect(_)
+                | ProxyPassToConfig::Drop
+        ) {
+            return Err(format!(
+                "Location {}: max_concurrent_requests is not supported for {} locations",
+                result.path,
+                result.proxy_pass_to.get_type_as_str()
+            ));
+        }
+
+        result.set_concurrency_limit(concurrency_limit);
+    }
+
+    result.request_limits = super::compile_request_limits(
+        &location_settings.request_limits,
+        &format!("Location {}", result.path),
+    )?;
+
+    result.cors = super::compile_cors(
+        location_settings.cors.as_ref(),
+        &format!("Location {}", result.path),
+    )?;
+
+    result.sub_filter =
+        compile_sub_filter(location_settings, &result.path, &result.proxy_pass_to)?.map(Arc::new);
+
+    result.compression = compile_compression(location_settings, &result.path)?.map(Arc::new);
+
     Ok(result)
 }
 
+/// Compiles a `proxy_pass_to` list into the location's upstreams. Every entry
+/// is an upstream of the location's own type — a list mixes direct, ssh,
+/// gateway and unix socket targets, not http1 and http2. `proxy_pass_to` is the
+/// already compiled first entry. `None` for an ordinary single target.
+async fn compile_upstreams(
+    location_settings: &LocationSettings,
+    settings_model: &SettingsCompiled,
+    resolved: &ResolvedTimeouts,
+    path: &str,
+    proxy_pass_to: &ProxyPassToConfig,
+) -> Result<Option<(LoadBalancePolicy, Vec<(ProxyPassToConfig, u32)>)>, String> {
+    let upstreams = match location_settings.proxy_pass_to.as_ref() {
+        Some(settings) if settings.is_list() => settings.get_upstreams(),
+        _ => {
+            if location_settings.balance.is_some() {
+                return Err(format!(
+                    "Location {}: balance needs a list of upstreams in proxy_pass_to",
+                    path
+                ));
+            }
+            return Ok(None);
+        }
+    };
+
+    let policy = LoadBalancePolicy::parse(location_settings.balance.as_deref())?;
+
+    if upstreams.len() < 2 {
+        return Ok(None);
+    }
+
+    let mut result: Vec<(ProxyPassToConfig, u32)> = Vec::with_capacity(upstreams.len());
+    result.push((proxy_pass_to.clone(), upstreams[0].1));
+
+    for (url, weight) in upstreams.iter().skip(1) {
+        let model = compile_upstream_model(url, settings_model, resolved).await?;
+        let Some(upstream) = proxy_pass_to.with_model(model) else {
+            return Err(format!(
+                "Location {}: a list of upstreams is not supported for {} locations",
+                path,
+                proxy_pass_to.get_type_as_str()
+            ));
+        };
+        result.push((upstream, *weight));
+    }
+
+    for (index, (upstream, _)) in result.iter().enumerate() {
+        let target = upstream.to_string();
+        if result[..index]
+            .iter()
+            .any(|(itm, _)| itm.to_string() == target)
+        {
+            return Err(format!(
+                "Location {}: upstream {} is listed twice. Use weight instead",
+                path, target
+            ));
+        }
+    }
+
+    // The request path is settled before the upstream is picked, so every
+    // upstream has to agree on what it is: an MCP location forwards onto the
+    // upstream's own path, append_to_upstream_path appends to it.
+    let path_matters =
+        proxy_pass_to.is_mcp() || location_settings.append_to_upstream_path.unwrap_or(false);
+
+    if path_matters {
+        let first_path = get_upstream_path(&result[0].0);
+        if result
+            .iter()
+            .any(|(itm, _)| get_upstream_path(itm) != first_path)
+        {
+            return Err(format!(
+                "Location {}: all upstreams must have the same path",
+                path
+            ));
+        }
+    }
+
+    Ok(Some((policy, result)))
+}
+
+/// Compiles `sticky`. Pinning only means something for a location balancing
+/// between several upstreams.
+fn compile_sticky(
+    location_settings: &LocationSettings,
+    path: &str,
+    has_upstreams: bool,
+) -> Result<Option<StickyMode>, String> {
+    let Some(sticky) = location_settings.sticky.as_ref() else {
+        return Ok(None);
+    };
+
+    if !has_upstreams {
+        return Err(format!(
+            "Location {}: sticky needs a list of upstreams in proxy_pass_to",
+            path
+        ));
+    }
+
+    let mode = match (sticky.cookie.as_deref(), sticky.header.as_deref()) {
+        (Some(name), None) => StickyMode::Cookie {
+            name: name.trim().to_string(),
+        },
+        (None, Some(name)) => StickyMode::Header {
+            name: name.trim().to_ascii_lowercase(),
+        },
+        _ => {
+            return Err(format!(
+                "Location {}: sticky needs exactly one of cookie or header",
+                path
+            ));
+        }
+    };
+
+    let name = match &mode {
+        StickyMode::Cookie { name } | StickyMode::Header { name } => name,
+    };
+
+    if name.is_empty() || !name.bytes().all(is_token_char) {
+        return Err(format!(
+            "Location {}: '{}' is not a valid sticky cookie or header name",
+            path, name
+        ));
+    }
+
+    Ok(Some(mode))
+}
+
+/// Compiles `split` into its branches, along with a copy of the location's
+/// settings whose `proxy_pass_to` lists the branches' targets in order.
+fn compile_split(
+    location_settings: &LocationSettings,
+    path: &str,
+) -> Result<Option<(LocationSettings, Vec<SplitBranch>)>, String> {
+    let Some(split_settings) = location_settings.split.as_ref() else {
+        return Ok(None);
+    };
+
+    if location_settings.proxy_pass_to.is_some()
+        || location_settings.balance.is_some()
+        || location_settings.sticky.is_some()
+    {
+        return Err(format!(
+            "Location {}: split replaces proxy_pass_to, balance and sticky",
+            path
+        ));
+    }
+
+    if split_settings.len() < 2 {
+        return Err(format!(
+            "Location {}: split needs at least two branches",
+            path
+        ));
+    }
+
+    let mut branches: Vec<SplitBranch> = Vec::with_capacity(split_settings.len());
+
+    for branch in split_settings {
+        let name = branch.name.trim();
+        if name.is_empty() {
+            return Err(format!("Location {}: a split branch has no name", path));
+        }
+
+        if branches.iter().any(|itm| itm.name == name) {
+            return Err(format!(
+                "Location {}: split branch {} is listed twice",
+                path, name
+            ));
+        }
+
+        let mut matches = Vec::new();
+
+        if let Some(cookie) = branch.cookie.as_deref() {
+            let (name, value) = match cookie.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (cookie, None),
+            };
+            matches.push(compile_split_match(
+                SplitMatchSource::Cookie,
+                name,
+                value,
+                path,
+            )?);
+        }
+
+        if let Some(header) = branch.header.as_deref() {
+            let (name, value) = match header.split_once(':') {
+                Some((name, value)) => (name, Some(value)),
+                None => (header, None),
+            };
+            matches.push(compile_split_match(
+                SplitMatchSource::Header,
+                name,
+                value,
+                path,
+            )?);
+        }
+
+        branches.push(SplitBranch::new(
+            name.to_string(),
+            branch.weight.unwrap_or(1),
+            matches,
+        ));
+    }
+
+    if branches.iter().all(|itm| itm.get_weight() == 0) {
+        return Err(format!(
+            "Location {}: at least one split branch needs a weight above 0",
+            path
+        ));
+    }
+
+    let mut settings = location_settings.clone();
+    settings.split = None;
+    settings.proxy_pass_to = Some(ProxyPassToSettings::Upstreams(
+        split_settings
+            .iter()
+            .map(|itm| UpstreamSettings::Weighted {
+                url: itm.proxy_pass_to.clone(),
+                weight: Some(1),
+            })
+            .collect(),
+    ));
+
+    Ok(Some((settings, branches)))
+}
+
+fn compile_split_match(
+    source: SplitMatchSource,
+    name: &str,
+    value: Option<&str>,
+    path: &str,
+) -> Result<SplitMatch, String> {
+    let name = match source {
+        SplitMatchSource::Cookie => name.trim().to_string(),
+        SplitMatchSource::Header => name.trim().to_ascii_lowercase(),
+    };
+
+    if name.is_empty() || !name.bytes().all(is_token_char) {
+        return Err(format!(
+            "Location {}: '{}' is not a valid split cookie or header name",
+            path, name
+        ));
+    }
+
+    Ok(SplitMatch {
+        source,
+        name,
+        value: value.map(|itm| itm.trim().to_string()),
+    })
+}
+
+/// `tchar` of RFC 9110 — what a header or a cookie name is made of.
+fn is_token_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+}
+
+/// Compiles `backup_proxy_pass_to` / `backup_on_status`. A backup is an
+/// upstream of the location's own type, like an entry of a `proxy_pass_to`
+/// list; `upstreams` are the compiled entries of that list, if it is one.
+async fn compile_backups(
+    location_settings: &LocationSettings,
+    settings_model: &SettingsCompiled,
+    resolved: &ResolvedTimeouts,
+    path: &str,
+    proxy_pass_to: &ProxyPassToConfig,
+    upstreams: Option<&[(ProxyPassToConfig, u32)]>,
+) -> Result<Option<(Vec<ProxyPassToConfig>, Vec<u16>)>, String> {
+    let Some(backup_settings) = location_settings.backup_proxy_pass_to.as_ref() else {
+        if location_settings.backup_on_status.is_some() {
+            return Err(format!(
+                "Location {}: backup_on_status needs backup_proxy_pass_to",
+                path
+            ));
+        }
+        return Ok(None);
+    };
+
+    let on_status = location_settings
+        .backup_on_status
+        .clone()
+        .unwrap_or_default();
+    if let Some(status) = on_status.iter().find(|itm| !(400..600).contains(*itm)) {
+        return Err(format!(
+            "Location {}: backup_on_status {} is not an error status (400-599)",
+            path, status
+        ));
+    }
+
+    let primary: Vec<&ProxyPassToConfig> = match upstreams {
+        Some(upstreams) => upstreams.iter().map(|(itm, _)| itm).collect(),
+        None => vec![proxy_pass_to],
+    };
+
+    let mut result: Vec<ProxyPassToConfig> = Vec::new();
+
+    for (url, _) in backup_settings.get_upstreams() {
+        let model = compile_upstream_model(url, settings_model, resolved).await?;
+        let Some(backup) = proxy_pass_to.with_model(model) else {
+            return Err(format!(
+                "Location {}: backup_proxy_pass_to is not supported for {} locations",
+                path,
+                proxy_pass_to.get_type_as_str()
+            ));
+        };
+
+        let target = backup.to_string();
+        if primary.iter().any(|itm| itm.to_string() == target)
+            || result.iter().any(|itm| itm.to_string() == target)
+        {
+            return Err(format!(
+                "Location {}: backup {} is already an upstream of the location",
+                path, target
+            ));
+        }
+
+        result.push(backup);
+    }
+
+    // Failing over re-sends the request as it was built for the upstream, so
+    // a backup has to agree on the path just like the upstreams of a list.
+    let path_matters =
+        proxy_pass_to.is_mcp() || location_settings.append_to_upstream_path.unwrap_or(false);
+
+    if path_matters {
+        let upstream_path = get_upstream_path(proxy_pass_to);
+        if result
+            .iter()
+            .any(|itm| get_upstream_path(itm) != upstream_path)
+        {
+            return Err(format!(
+                "Location {}: backups must have the same path as proxy_pass_to",
+                path
+            ));
+        }
+    }
+
+    Ok(Some((result, on_status)))
+}
+
+/// Compiles `mirror_to` into the mirror target, the sampling percentage and
+/// the body size cap. The mirror is an upstream of the location's own type and
+/// gets the request exactly as it is built for the location's upstreams.
+async fn compile_mirror(
+    location_settings: &LocationSettings,
+    settings_model: &SettingsCompiled,
+    resolved: &ResolvedTimeouts,
+    path: &str,
+    proxy_pass_to: &ProxyPassToConfig,
+) -> Result<Option<(ProxyPassToConfig, u32, usize)>, String> {
+    let Some(mirror_settings) = location_settings.mirror_to.as_ref() else {
+        return Ok(None);
+    };
+
+    // An MCP exchange lives in a session the upstream hands out — a copy of
+    // its requests would only be rejected by the mirror.
+    let mirror = match proxy_pass_to.is_mcp() {
+        true => None,
+        false => {
+            let model =
+                compile_upstream_model(mirror_settings.url.as_str(), settings_model, resolved)
+                    .await?;
+            proxy_pass_to.with_model(model)
+        }
+    };
+
+    let Some(mirror) = mirror else {
+        return Err(format!(
+            "Location {}: mirror_to is not supported for {} locations",
+            path,
+            proxy_pass_to.get_type_as_str()
+        ));
+    };
+
+    let percent = mirror_settings.percent.unwrap_or(100);
+    if !(1..=100).contains(&percent) {
+        return Err(format!(
+            "Location {}: mirror_to percent {} is not in 1-100",
+            path, percent
+        ));
+    }
+
+    let max_body_size = match mirror_settings.max_body_size.as_deref() {
+        Some(value) => parse_buffer_size(value)
+            .map_err(|err| format!("Location {}: mirror_to max_body_size. {}", path, err))?,
+        None => crate::consts::DEFAULT_MIRROR_MAX_BODY_SIZE,
+    };
+
+    if location_settings.append_to_upstream_path.unwrap_or(false)
+        && get_upstream_path(&mirror) != get_upstream_path(proxy_pass_to)
+    {
+        return Err(format!(
+            "Location {}: mirror_to must have the same path as proxy_pass_to",
+            path
+        ));
+    }
+
+    Ok(Some((mirror, percent, max_body_size)))
+}
+
+/// Compiles `retry`. `label` is what the retry metrics of the location are
+/// labelled with.
+fn compile_retry(
+    location_settings: &LocationSettings,
+    path: &str,
+    proxy_pass_to: &ProxyPassToConfig,
+    label: &str,
+) -> Result<Option<LocationRetry>, String> {
+    let Some(retry_settings) = location_settings.retry.as_ref() else {
+        return Ok(None);
+    };
+
+    if matches!(
+        proxy_pass_to,
+        ProxyPassToConfig::FilesPath(_)
+            | ProxyPassToConfig::Static(_)
+            | ProxyPassToConfig::Redirect(_)
+            | ProxyPassToConfig::Drop
+    ) {
+        return Err(format!(
+            "Location {}: retry is not supported for {} locations",
+            path,
+            proxy_pass_to.get_type_as_str()
+        ));
+    }
+
+    let attempts = retry_settings.attempts.unwrap_or(2);
+    if !(1..=10).contains(&attempts) {
+        return Err(format!(
+            "Location {}: retry attempts {} is not in 1-10",
+            path, attempts
+        ));
+    }
+
+    let mut retry_on = Vec::new();
+
+    let on = match retry_settings.on.as_ref() {
+        Some(on) => on.iter().map(|itm| itm.trim()).collect(),
+        None => vec!["connect"],
+    };
+    for itm in on {
+        match itm {
+            "connect" => retry_on.push(RetryFailure::Connect),
+            "timeout" => retry_on.push(RetryFailure::Timeout),
+            _ => {
+                return Err(format!(
+                    "Location {}: retry on '{}' is not one of connect, timeout",
+                    path, itm
+                ));
+            }
+        }
+    }
+
+    let on_status = match retry_settings.on_status.as_ref() {
+        Some(on_status) => on_status.clone(),
+        None => vec![502, 503, 504],
+    };
+    for status in on_status {
+        if !(400..600).contains(&status) {
+            return Err(format!(
+                "Location {}: retry on_status {} is not an error status (400-599)",
+                path, status
+            ));
+        }
+        retry_on.push(RetryFailure::Status(status));
+    }
+
+    let mut methods = Vec::new();
+    for method in retry_settings.methods.iter().flatten() {
+        let method = method.trim().to_ascii_uppercase();
+        if method.is_empty() || !method.bytes().all(is_token_char) {
+            return Err(format!(
+                "Location {}: '{}' is not a valid retry method",
+                path, method
+            ));
+        }
+        methods.push(method);
+    }
+
+    let backoff = Duration::from_millis(retry_settings.backoff.unwrap_or(25));
+    let max_backoff = Duration::from_millis(retry_settings.max_backoff.unwrap_or(1000));
+    if backoff > max_backoff {
+        return Err(format!(
+            "Location {}: retry backoff is longer than max_backoff",
+            path
+        ));
+    }
+
+    let budget_percent = retry_settings.budget_percent.unwrap_or(20);
+    if !(1..=100).contains(&budget_percent) {
+        return Err(format!(
+            "Location {}: retry budget_percent {} is not in 1-100",
+            path, budget_percent
+        ));
+    }
+
+    Ok(Some(LocationRetry::new(
+        label.to_string(),
+        attempts,
+        retry_on,
+        methods,
+        backoff,
+        max_backoff,
+        budget_percent,
+    )))
+}
+
+/// Compiles `cache`. The disk tier of every location gets a folder of its own
+/// under `disk_path`, named after the location's `id_string`.
+fn compile_cache(
+    location_settings: &LocationSettings,
+    path: &str,
+    proxy_pass_to: &ProxyPassToConfig,
+    id_string: &str,
+) -> Result<Option<LocationCacheSettings>, String> {
+    let Some(cache_settings) = location_settings.cache.as_ref() else {
+        return Ok(None);
+    };
+
+    if !keeps_responses(proxy_pass_to) {
+        return Err(format!(
+            "Location {}: cache is not supported for {} locations",
+            path,
+            proxy_pass_to.get_type_as_str()
+        ));
+    }
+
+    let get_size = |value: Option<&String>, name: &str, default: usize| match value {
+        Some(value) => parse_buffer_size(value)
+            .map_err(|err| format!("Location {}: cache {}. {}", path, name, err)),
+        None => Ok(default),
+    };
+
+    let max_size = get_size(
+        cache_settings.max_size.as_ref(),
+        "max_size",
+        crate::consts::DEFAULT_CACHE_MAX_SIZE,
+    )?;
+    let max_entry_size = get_size(
+        cache_settings.max_entry_size.as_ref(),
+        "max_entry_size",
+        crate::consts::DEFAULT_CACHE_MAX_ENTRY_SIZE,
+    )?;
+    let disk_max_size = get_size(
+        cache_settings.disk_max_size.as_ref(),
+        "disk_max_size",
+        crate::consts::DEFAULT_CACHE_DISK_MAX_SIZE,
+    )?;
+
+    if max_size == 0 || max_entry_size == 0 {
+        return Err(format!(
+            "Location {}: cache max_size and max_entry_size must be above 0",
+            path
+        ));
+    }
+
+    let max_ttl = match cache_settings.max_ttl {
+        Some(0) => return Err(format!("Location {}: cache max_ttl must be above 0", path)),
+        Some(seconds) => Duration::from_secs(seconds),
+        None => crate::consts::DEFAULT_CACHE_MAX_TTL,
+    };
+
+    let disk_path = cache_settings.disk_path.as_ref().map(|disk_path| {
+        let disk_path = LocalFilePath::new(disk_path.to_string());
+        std::path::PathBuf::from(disk_path.get_value().as_str())
+            .join(get_cache_folder_name(id_string))
+    });
+
+    Ok(Some(LocationCacheSettings {
+        max_size,
+        max_entry_size,
+        max_ttl,
+        disk_path,
+        disk_max_size,
+    }))
+}
+
+/// Compiles `stale`.
+fn compile_stale(
+    location_settings: &LocationSettings,
+    path: &str,
+    proxy_pass_to: &ProxyPassToConfig,
+) -> Result<Option<StaleStoreSettings>, String> {
+    let Some(stale_settings) = location_settings.stale.as_ref() else {
+        return Ok(None);
+    };
+
+    if !keeps_responses(proxy_pass_to) {
+        return Err(format!(
+            "Location {}: stale is not supported for {} locations",
+            path,
+            proxy_pass_to.get_type_as_str()
+        ));
+    }
+
+    let get_seconds = |value: Option<u64>, name: &str| match value {
+        Some(0) => Err(format!("Location {}: stale {} must be above 0", path, name)),
+        Some(seconds) => Ok(Some(Duration::from_secs(seconds))),
+        None => Ok(None),
+    };
+
+    let if_error = get_seconds(stale_settings.if_error, "if_error")?;
+    let while_revalidate = get_seconds(stale_settings.while_revalidate, "while_revalidate")?;
+
+    if if_error.is_none() && while_revalidate.is_none() {
+        return Err(format!(
+            "Location {}: stale needs if_error, while_revalidate or both",
+            path
+        ));
+    }
+
+    let get_size = |value: Option<&String>, name: &str, default: usize| match value {
+        Some(value) => parse_buffer_size(value)
+            .map_err(|err| format!("Location {}: stale {}. {}", path, name, err)),
+        None => Ok(default),
+    };
+
+    let max_size = get_size(
+        stale_settings.max_size.as_ref(),
+        "max_size",
+        crate::consts::DEFAULT_STALE_MAX_SIZE,
+    )?;
+    let max_entry_size = get_size(
+        stale_settings.max_entry_size.as_ref(),
+        "max_entry_size",
+        crate::consts::DEFAULT_CACHE_MAX_ENTRY_SIZE,
+    )?;
+
+    if max_size == 0 || max_entry_size == 0 {
+        return Err(format!(
+            "Location {}: stale max_size and max_entry_size must be above 0",
+            path
+        ));
+    }
+
+    Ok(Some(StaleStoreSettings {
+        max_size,
+        max_entry_size,
+        if_error,
+        while_revalidate,
+    }))
+}
+
+/// Whether the responses of the location can be kept for other clients. An
+/// MCP exchange belongs to its session, and a dynamic_proxy request is
+/// answered by whichever upstream it names — neither is the same response for
+/// the next client.
+fn keeps_responses(proxy_pass_to: &ProxyPassToConfig) -> bool {
+    matches!(
+        proxy_pass_to,
+        ProxyPassToConfig::Http1(_)
+            | ProxyPassToConfig::Http2(_)
+            | ProxyPassToConfig::UnixHttp1(_)
+            | ProxyPassToConfig::UnixHttp2(_)
+    )
+}
+
+/// `id_string` is no file name — it has `|`, `/` and `:` in it — so its hash is.
+fn get_cache_folder_name(id_string: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(id_string.as_bytes())
+        .iter()
+        .take(8)
+        .map(|itm| format!("{:02x}", itm))
+        .collect()
+}
+
+/// Compiles a `redirect` location: `redirect_to` is required and `status_code`
+/// is one of the redirects that carry a `Location` (302 unless set).
+fn compile_redirect(
+    location_settings: &LocationSettings,
+    path: &str,
+    matcher: &LocationMatcher,
+) -> Result<RedirectConfig, String> {
+    let Some(redirect_to) = location_settings.redirect_to.as_ref() else {
+        return Err(format!(
+            "Location {}: redirect_to is required for redirect location type",
+            path
+        ));
+    };
+
+    if location_settings.proxy_pass_to.is_some()
+        || location_settings.body.is_some()
+        || location_settings.body_file.is_some()
+    {
+        return Err(format!(
+            "Location {}: a redirect location has no proxy_pass_to, body or body_file",
+            path
+        ));
+    }
+
+    let status_code = location_settings.status_code.unwrap_or(302);
+    if !matches!(status_code, 301 | 302 | 307 | 308) {
+        return Err(format!(
+            "Location {}: redirect status_code {} is not one of 301, 302, 307, 308",
+            path, status_code
+        ));
+    }
+
+    let redirect_to = RedirectTemplate::new(redirect_to, matcher.get_regex())
+        .map_err(|err| format!("Location {}: {}", path, err))?;
+
+    Ok(RedirectConfig {
+        status_code,
+        redirect_to,
+    })
+}
+
+fn get_upstream_path(proxy_pass_to: &ProxyPassToConfig) -> &str {
+    match proxy_pass_to {
+        ProxyPassToConfig::Http1(model)
+        | ProxyPassToConfig::Http2(model)
+        | ProxyPassToConfig::McpHttp1(model)
+        | ProxyPassToConfig::McpHttp2(model)
+        | ProxyPassToConfig::UnixHttp1(model)
+        | ProxyPassToConfig::UnixHttp2(model) => model.remote_host.get_path_and_query(),
+        _ => "",
+    }
+}
+
+/// Compiles `strip_prefix` / `rewrite` / `append_to_upstream_path`. They only
+/// make sense where the client's path is forwarded to an upstream: an MCP
+/// location already replaces the path with its own, and static / files / drop
+/// locations forward nothing.
+fn compile_path_rewrite(
+    location_settings: &LocationSettings,
+    path: &str,
+    matcher: &LocationMatcher,
+    proxy_pass_to: &ProxyPassToConfig,
+) -> Result<Option<LocationPathRewrite>, String> {
+    let strip_prefix = location_settings.strip_prefix.unwrap_or(false);
+    let append_to_upstream_path = location_settings.append_to_upstream_path.unwrap_or(false);
+
+    if !strip_prefix && !append_to_upstream_path && location_settings.rewrite.is_none() {
+        return Ok(None);
+    }
+
+    let upstream = match proxy_pass_to {
+        ProxyPassToConfig::Http1(model)
+        | ProxyPassToConfig::Http2(model)
+        | ProxyPassToConfig::UnixHttp1(model)
+        | ProxyPassToConfig::UnixHttp2(model) => Some(model),
+        ProxyPassToConfig::DynamicProxy(_) => None,
+        _ => {
+            return Err(format!(
+                "Location {}: strip_prefix, rewrite and append_to_upstream_path are not supported for {} locations",
+                path,
+                proxy_pass_to.get_type_as_str()
+            ));
+        }
+    };
+
+    if strip_prefix && matcher.get_mode() == LocationMatchMode::Regex {
+        return Err(format!(
+            "Location {}: strip_prefix needs a prefix or exact location. Use rewrite for a regex one",
+            path
+        ));
+    }
+
+    let upstream_path = if append_to_upstream_path {
+        let Some(upstream) = upstream else {
+            return Err(format!(
+                "Location {}: append_to_upstream_path needs a fixed upstream in proxy_pass_to",
+                path
+            ));
+        };
+
+        let path_and_query = upstream.remote_host.get_path_and_query();
+        match path_and_query.find('?') {
+            Some(index) => Some(&path_and_query[..index]),
+            None => Some(path_and_query),
+        }
+    } else {
+        None
+    };
+
+    LocationPathRewrite::new(
+        if strip_prefix { Some(path) } else { None },
+        matcher.is_case_sensitive(),
+        location_settings
+            .rewrite
+            .as_ref()
+            .map(|itm| (itm.from.as_str(), itm.to.as_str())),
+        upstream_path,
+    )
+}
+
+/// Compiles `proxy_redirect`, `proxy_cookie_domain` and `proxy_cookie_path`.
+/// Each one left unset is `default` on a location with a path rewrite and
+/// `off` on any other.
+fn compile_response_rewrite(
+    location_settings: &LocationSettings,
+    path: &str,
+    proxy_pass_to: &ProxyPassToConfig,
+    has_path_rewrite: bool,
+) -> Result<Option<LocationResponseRewrite>, String> {
+    let is_set = location_settings.proxy_redirect.is_some()
+        || location_settings.proxy_cookie_domain.is_some()
+        || location_settings.proxy_cookie_path.is_some();
+
+    if !is_set && !has_path_rewrite {
+        return Ok(None);
+    }
+
+    if is_set
+        && !keeps_responses(proxy_pass_to)
+        && !matches!(proxy_pass_to, ProxyPassToConfig::DynamicProxy(_))
+    {
+        return Err(format!(
+            "Location {}: proxy_redirect, proxy_cookie_domain and proxy_cookie_path are not supported for {} locations",
+            path,
+            proxy_pass_to.get_type_as_str()
+        ));
+    }
+
+    let (redirects, cookie_domains, cookie_paths) =
+        get_default_response_rewrite(location_settings, path);
+
+    let select = |name: &str,
+                  settings: Option<&ResponseRewriteSettings>,
+                  defaults: Vec<ResponseRewriteRule>|
+     -> Result<Vec<ResponseRewriteRule>, String> {
+        match settings {
+            None if has_path_rewrite => Ok(defaults),
+            None => Ok(vec![]),
+            Some(ResponseRewriteSettings::Mode(mode)) => match mode.trim() {
+                "default" => Ok(defaults),
+                "off" => Ok(vec![]),
+                other => Err(format!(
+                    "Location {}: {} '{}' is unknown. Use default, off or a list of from / to rules",
+                    path, name, other
+                )),
+            },
+            Some(ResponseRewriteSettings::Rules(rules)) => {
+                let mut result = Vec::with_capacity(rules.len());
+                for rule in rules {
+                    if rule.from.trim().is_empty() {
+                        return Err(format!("Location {}: {} has a rule with no from", path, name));
+                    }
+                    result.push(ResponseRewriteRule::new(rule.from.trim(), rule.to.trim()));
+                }
+                Ok(result)
+            }
+        }
+    };
+
+    Ok(LocationResponseRewrite::new(
+        select(
+            "proxy_redirect",
+            location_settings.proxy_redirect.as_ref(),
+            redirects,
+        )?,
+        select(
+            "proxy_cookie_domain",
+            location_settings.proxy_cookie_domain.as_ref(),
+            cookie_domains,
+        )?,
+        select(
+            "proxy_cookie_path",
+            location_settings.proxy_cookie_path.as_ref(),
+            cookie_paths,
+        )?,
+    ))
+}
+
+/// What `default` means for `proxy_redirect`, `proxy_cookie_domain` and
+/// `proxy_cookie_path`, for every http upstream in `proxy_pass_to` and
+/// `backup_proxy_pass_to`: the url the upstream is reached by — with its path
+/// when the location appends onto it — maps onto the location's path when the
+/// location strips it, else onto `/`; a cookie for the upstream's host is left
+/// to the public one.
+fn get_default_response_rewrite(
+    location_settings: &LocationSettings,
+    path: &str,
+) -> (
+    Vec<ResponseRewriteRule>,
+    Vec<ResponseRewriteRule>,
+    Vec<ResponseRewriteRule>,
+) {
+    fn with_trailing_slash(path: &str) -> String {
+        format!("{}/", path.trim_end_matches('/'))
+    }
+
+    let public_path = match location_settings.strip_prefix.unwrap_or(false) {
+        true => with_trailing_slash(path),
+        false => "/".to_string(),
+    };
+
+    let upstreams = location_settings
+        .proxy_pass_to
+        .iter()
+        .chain(location_settings.backup_proxy_pass_to.iter())
+        .flat_map(|itm| itm.get_upstreams())
+        .map(|(src, _)| src.rsplit("->").next().unwrap_or(src).trim());
+
+    let mut redirects = Vec::new();
+    let mut cookie_domains = Vec::new();
+    let mut cookie_paths = Vec::new();
+
+    for url in upstreams {
+        let Some((scheme, rest)) = url.split_once("://") else {
+            continue;
+        };
+
+        if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+            continue;
+        }
+
+        let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+        let authority = &rest[..authority_end];
+        let upstream_path = match location_settings.append_to_upstream_path.unwrap_or(false) {
+            true => with_trailing_slash(rest[authority_end..].split('?').next().unwrap_or("")),
+            false => "/".to_string(),
+        };
+
+        let redirect = ResponseRewriteRule::new(
+            format!("{}://{}{}", scheme, authority, upstream_path),
+            public_path.as_str(),
+        );
+        if !redirects.contains(&redirect) {
+            redirects.push(redirect);
+        }
+
+        let host = match authority.rsplit_once(':') {
+            Some((host, port)) if port.bytes().all(|itm| itm.is_ascii_digit()) => host,
+            _ => authority,
+        };
+        let cookie_domain = ResponseRewriteRule::new(host, "");
+        if !cookie_domains.contains(&cookie_domain) {
+            cookie_domains.push(cookie_domain);
+        }
+
+        if upstream_path != public_path {
+            let cookie_path = ResponseRewriteRule::new(upstream_path, public_path.as_str());
+            if !cookie_paths.contains(&cookie_path) {
+                cookie_paths.push(cookie_path);
+            }
+        }
+    }
+
+    (redirects, cookie_domains, cookie_paths)
+}
+
+/// Compiles `sub_filter` with `sub_filter_types`. Only a location proxying
+/// http has response bodies of its upstream to replace in: an MCP one is left
+/// to speak its protocol as it is.
+fn compile_sub_filter(
+    location_settings: &LocationSettings,
+    path: &str,
+    proxy_pass_to: &ProxyPassToConfig,
+) -> Result<Option<SubFilter>, String> {
+    let Some(sub_filter) = location_settings.sub_filter.as_ref() else {
+        if location_settings.sub_filter_types.is_some() {
+            return Err(format!(
+                "Location {}: sub_filter_types needs sub_filter",
+                path
+            ));
+        }
+
+        return Ok(None);
+    };
+
+    if !keeps_responses(proxy_pass_to)
+        && !matches!(proxy_pass_to, ProxyPassToConfig::DynamicProxy(_))
+    {
+        return Err(format!(
+            "Location {}: sub_filter is not supported for {} locations",
+            path,
+            proxy_pass_to.get_type_as_str()
+        ));
+    }
+
+    let mut rules = Vec::with_capacity(sub_filter.len());
+    for itm in sub_filter {
+        if itm.from.is_empty() {
+            return Err(format!(
+                "Location {}: sub_filter has a rule with no from",
+                path
+            ));
+        }
+
+        rules.push(
+            SubFilterRule::parse(&itm.from, &itm.to)
+                .map_err(|err| format!("Location {}: {}", path, err))?,
+        );
+    }
+
+    if rules.is_empty() {
+        return Ok(None);
+    }
+
+    let types = match location_settings.sub_filter_types.as_ref() {
+        Some(types) => types.clone(),
+        None => crate::consts::DEFAULT_SUB_FILTER_TYPES
+            .iter()
+            .map(|itm| itm.to_string())
+            .collect(),
+    };
+
+    Ok(Some(SubFilter::new(rules, types)))
+}
+
+/// Compiles `default_file`, `autoindex`, `try_files` and `precompressed` of a
+/// files location.
+fn compile_file_lookup(
+    location_settings: &LocationSettings,
+    path: &str,
+) -> Result<FileLookup, String> {
+    let try_files = match location_settings.try_files.as_ref() {
+        Some(items) => Some(
+            FileLookup::parse_try_files(items)
+                .map_err(|err| format!("Location {}: {}", path, err))?,
+        ),
+        None => None,
+    };
+
+    Ok(FileLookup {
+        default_file: location_settings.default_file.clone(),
+        autoindex: location_settings.get_autoindex(),
+        try_files,
+        precompressed: location_settings.get_precompressed(),
+        probes: Default::default(),
+    })
+}
+
+/// Compiles `compress` with `compress_types` and `compress_min_size`.
+fn compile_compression(
+    location_settings: &LocationSettings,
+    path: &str,
+) -> Result<Option<ResponseCompression>, String> {
+    if !location_settings.get_compress() {
+        if location_settings.compress_types.is_some()
+            || location_settings.compress_min_size.is_some()
+        {
+            return Err(format!(
+                "Location {}: compress_types and compress_min_size need compress: true",
+                path
+            ));
+        }
+
+        return Ok(None);
+    }
+
+    let types = match location_settings.compress_types.as_ref() {
+        Some(types) => types.clone(),
+        None => crate::consts::DEFAULT_COMPRESS_TYPES
+            .iter()
+            .map(|itm| itm.to_string())
+            .collect(),
+    };
+
+    let min_size = match location_settings.compress_min_size.as_deref() {
+        Some(value) => parse_buffer_size(value)
+            .map_err(|err| format!("Location {}: compress_min_size. {}", path, err))?,
+        None => crate::consts::DEFAULT_COMPRESS_MIN_SIZE,
+    };
+
+    Ok(Some(ResponseCompression::new(types, min_size)))
+}
+
 /// Parses `proxy_pass_to` into the upstream model shared by every network
 /// location type. `location_type_name` only names the type in the error.
 async fn compile_model(
@@ -122,25 +1371,59 @@ async fn compile_model(
     resolved: &ResolvedTimeouts,
     location_type_name: &str,
 ) -> Result<ProxyPassToModel, String> {
-    let Some(proxy_pass_to) = location_settings.proxy_pass_to.as_ref() else {
+    let Some(proxy_pass_to) = location_settings
+        .proxy_pass_to
+        .as_ref()
+        .and_then(|itm| itm.get_first())
+    else {
         return Err(format!(
             "proxy_pass_to is required for {} location type",
             location_type_name
         ));
     };
 
+    compile_upstream_model(proxy_pass_to, settings_model, resolved).await
+}
+
+async fn compile_upstream_model(
+    proxy_pass_to: &str,
+    settings_model: &SettingsCompiled,
+    resolved: &ResolvedTimeouts,
+) -> Result<ProxyPassToModel, String> {
     Ok(ProxyPassToModel {
-        remote_host: MyReverseProxyRemoteEndpoint::try_parse(
-            proxy_pass_to.as_str(),
-            settings_model,
-        )
-        .await?,
+        remote_host: MyReverseProxyRemoteEndpoint::try_parse(proxy_pass_to, settings_model).await?,
         request_timeout: resolved.request_timeout,
         connect_timeout: resolved.connect_timeout,
         pool_tuning: PoolTuning::from_resolved(resolved),
     })
 }
 
+/// A static location's `body_file`: a local file, or one over ssh or a
+/// gateway.
+async fn load_body_file(
+    body_file: &str,
+    settings_model: &SettingsCompiled,
+) -> Result<Vec<u8>, String> {
+    if body_file.starts_with(GATEWAY_PREFIX) {
+        let MyReverseProxyRemoteEndpoint::Gateway { id, remote_host } =
+            MyReverseProxyRemoteEndpoint::try_parse_gateway_source(body_file)?
+        else {
+            return Err(format!("Invalid gateway source: {}", body_file));
+        };
+
+        return super::load_file_over_gateway(id.as_str(), remote_host.as_str()).await;
+    }
+
+    let Some(data_source) = OverSshConnectionSettings::try_parse(body_file) else {
+        return Err(format!("Invalid file source: {}", body_file));
+    };
+
+    let data_source =
+        super::ssh::enrich_with_private_key_or_password(data_source, settings_model).await?;
+
+    super::load_file(&data_source, crate::consts::DEFAULT_HTTP_CONNECT_TIMEOUT).await
+}
+
 async fn get_static_content_body(body: String) -> Result<Vec<u8>, String> {
     if body.is_empty() {
         return Ok(Vec::new());
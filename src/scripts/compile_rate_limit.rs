@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use crate::{
+    app::{RateLimitKey, RateLimiterSettings},
+    settings::RateLimitSettings,
+};
+
+/// Compiles a `rate_limit:` block. `owner` is what errors name — `Location
+/// /api`, say.
+pub fn compile_rate_limit(
+    settings: Option<&RateLimitSettings>,
+    owner: &str,
+) -> Result<Option<RateLimiterSettings>, String> {
+    let Some(settings) = settings else {
+        return Ok(None);
+    };
+
+    let (requests, per) = parse_rate(&settings.rate).ok_or_else(|| {
+        format!(
+            "{}: rate_limit rate '{}' is invalid. Expected requests per s, m or h — e.g. 10/s",
+            owner, settings.rate
+        )
+    })?;
+
+    let burst = settings.burst.unwrap_or(requests);
+    if burst == 0 {
+        return Err(format!("{}: rate_limit burst must be above 0", owner));
+    }
+
+    let key = match settings.key.as_deref().map(|itm| itm.trim()) {
+        None | Some("ip") => RateLimitKey::Ip,
+        Some("identity") => RateLimitKey::Identity,
+        Some(key) => match key.split_once(':') {
+            Some(("header", name)) if !name.trim().is_empty() => {
+                RateLimitKey::Header(name.trim().to_ascii_lowercase())
+            }
+            Some(("query", name)) if !name.trim().is_empty() => {
+                RateLimitKey::Query(name.trim().to_string())
+            }
+            _ => {
+                return Err(format!(
+                    "{}: rate_limit key '{}' is invalid. Expected ip, identity, header:{{name}} or query:{{name}}",
+                    owner, key
+                ));
+            }
+        },
+    };
+
+    let max_clients = settings
+        .max_clients
+        .unwrap_or(crate::consts::DEFAULT_RATE_LIMIT_MAX_CLIENTS);
+    if max_clients == 0 {
+        return Err(format!("{}: rate_limit max_clients must be above 0", owner));
+    }
+
+    Ok(Some(RateLimiterSettings {
+        requests,
+        per,
+        burst,
+        key,
+        block_offenders: settings.block_offenders.unwrap_or(false),
+        max_clients,
+    }))
+}
+
+/// `10/s`, `600/m` or `1000/h`.
+fn parse_rate(src: &str) -> Option<(u64, Duration)> {
+    let (requests, per) = src.trim().split_once('/')?;
+    let requests: u64 = requests.trim().parse().ok()?;
+    if requests == 0 {
+        return None;
+    }
+
+    let per = match per.trim() {
+        "s" => Duration::from_secs(1),
+        "m" => Duration::from_secs(60),
+        "h" => Duration::from_secs(3600),
+        _ => return None,
+    };
+
+    Some((requests, per))
+}
@@ -0,0 +1,41 @@
+use crate::{
+    configurations::RequestLimits,
+    settings::{parse_buffer_size, RequestLimitsSettings},
+};
+
+/// `max_header_size`, `max_headers`, `max_uri_length` and `max_body_size` of an
+/// endpoint or a location. `owner` is what errors name — `Location /upload`,
+/// say.
+pub fn compile_request_limits(
+    settings: &RequestLimitsSettings,
+    owner: &str,
+) -> Result<RequestLimits, String> {
+    let parse_size = |name: &str, value: Option<&String>| -> Result<Option<usize>, String> {
+        let Some(value) = value else {
+            return Ok(None);
+        };
+
+        match parse_buffer_size(value) {
+            Ok(0) => Err(format!("{}: {} must be above 0", owner, name)),
+            Ok(size) => Ok(Some(size)),
+            Err(err) => Err(format!("{}: {}. {}", owner, name, err)),
+        }
+    };
+
+    let result = RequestLimits {
+        max_header_size: parse_size("max_header_size", settings.max_header_size.as_ref())?,
+        max_headers: settings.max_headers,
+        max_uri_length: settings.max_uri_length,
+        max_body_size: parse_size("max_body_size", settings.max_body_size.as_ref())?,
+    };
+
+    if result.max_headers == Some(0) {
+        return Err(format!("{}: max_headers must be above 0", owner));
+    }
+
+    if result.max_uri_length == Some(0) {
+        return Err(format!("{}: max_uri_length must be above 0", owner));
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use crate::tcp_gateway::{FileStreamEvent, GetFileStatus};
+
+/// Reads a whole file on the other side of a gateway. A gateway that is not
+/// connected yet — the configuration is applied while it connects at start —
+/// is waited for up to [`crate::consts::GATEWAY_FILE_STREAM_TIMEOUT`].
+pub async fn load_file_over_gateway(gateway_id: &str, path: &str) -> Result<Vec<u8>, String> {
+    let wait_for_gateway = async {
+        loop {
+            if let Some(gateway) = crate::app::APP_CTX.get_gateway_by_id(gateway_id) {
+                if gateway.is_gateway_connected() && gateway.has_handshake() {
+                    return gateway;
+                }
+            }
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    };
+
+    let gateway =
+        tokio::time::timeout(crate::consts::GATEWAY_FILE_STREAM_TIMEOUT, wait_for_gateway)
+            .await
+            .map_err(|_| format!("Gateway '{}' is not connected", gateway_id))?;
+
+    let mut stream = gateway
+        .start_file_stream(path, 0, u64::MAX)
+        .map_err(|err| {
+            format!(
+                "Can not read '{}' over gateway '{}': {:?}",
+                path, gateway_id, err
+            )
+        })?;
+
+    match stream.recv().await {
+        Ok(FileStreamEvent::Head {
+            status: GetFileStatus::Ok,
+            ..
+        }) => {}
+        Ok(_) => {
+            return Err(format!(
+                "File '{}' is not found over gateway '{}'",
+                path, gateway_id
+            ))
+        }
+        Err(err) => {
+            return Err(format!(
+                "Can not read '{}' over gateway '{}': {:?}",
+                path, gateway_id, err
+            ))
+        }
+    }
+
+    stream.read_to_end().await.map_err(|err| {
+        format!(
+            "Can not read '{}' over gateway '{}': {:?}",
+            path, gateway_id, err
+        )
+    })
+}
@@ -2,6 +2,14 @@
 //pub use apply_variables::*;
 mod compile_location_proxy_pass_to;
 pub use compile_location_proxy_pass_to::*;
+mod compile_rate_limit;
+pub use compile_rate_limit::*;
+mod compile_concurrency_limits;
+pub use compile_concurrency_limits::*;
+mod compile_request_limits;
+pub use compile_request_limits::*;
+mod compile_cors;
+pub use compile_cors::*;
 mod find_location_id_by_id_string;
 pub use find_location_id_by_id_string::*;
 mod get_endpoint_users_list;
@@ -27,6 +35,8 @@ mod get_endpoint_template;
 pub use get_endpoint_template::*;
 mod load_file;
 pub use load_file::*;
+mod load_file_over_gateway;
+pub use load_file_over_gateway::*;
 mod get_google_auth_credentials;
 pub use get_google_auth_credentials::*;
 mod get_oauth_credentials;
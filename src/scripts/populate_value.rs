@@ -22,13 +22,23 @@ pub fn populate_value<'s>(
         match token {
             ContentToken::Text(text) => result.push_str(text),
             ContentToken::Placeholder(placeholder) => match placeholder {
-                "ENDPOINT_IP" => {
+                "ENDPOINT_IP" | "CLIENT_IP" => {
                     if let Some(ip) = http_connection_info.connection_ip.get_ip_addr() {
                         let ip = format!("{}", ip);
                         result.push_str(ip.as_str());
                     }
                 }
 
+                "COUNTRY" => {
+                    if let Some(country) = http_connection_info
+                        .connection_ip
+                        .get_ip_addr()
+                        .and_then(crate::ip_db::lookup_country)
+                    {
+                        result.push_str(std::str::from_utf8(&country).unwrap_or_default());
+                    }
+                }
+
                 "CLIENT_CERT_CN" => {
                     if let Some(value) = identity {
                         result.push_str(value.as_str());
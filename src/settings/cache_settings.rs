@@ -0,0 +1,20 @@
+use serde::*;
+
+/// `cache:` block of a location — GET responses the upstream marks cacheable
+/// (`Cache-Control: max-age`, `Expires`, or a validator to revalidate by) are
+/// kept and served without the upstream while fresh.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheSettings {
+    /// Responses kept in memory, e.g. `64Mb`. Default 64Mb.
+    pub max_size: Option<String>,
+    /// A bigger response is not stored. Default 1Mb.
+    pub max_entry_size: Option<String>,
+    /// Seconds a response is fresh at most, whatever its headers say.
+    /// Default 3600.
+    pub max_ttl: Option<u64>,
+    /// Folder the responses pushed out of memory are moved to. Emptied on
+    /// start; no disk tier without it.
+    pub disk_path: Option<String>,
+    /// Responses kept on disk. Default 1024Mb.
+    pub disk_max_size: Option<String>,
+}
@@ -0,0 +1,23 @@
+use serde::*;
+
+/// `cors:` block — of an endpoint or a location; a location's replaces its
+/// endpoint's. The proxy answers a preflight `OPTIONS` itself, without the
+/// upstream, and puts the CORS headers on every other response in place of the
+/// upstream's.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CorsSettings {
+    /// `https://app.example.com`, `https://*.example.com`, `*` for any origin,
+    /// or a regex after `~` (`~*` for a case-insensitive one).
+    pub allowed_origins: Vec<String>,
+    /// Default `GET, HEAD, POST, PUT, PATCH, DELETE`.
+    pub allowed_methods: Option<Vec<String>>,
+    /// Request headers a preflight is allowed. Default — the ones it asks for.
+    pub allowed_headers: Option<Vec<String>>,
+    /// Response headers the page's script may read besides the simple ones.
+    pub expose_headers: Option<Vec<String>>,
+    /// Lets the browser send cookies and credentials. The origin is then
+    /// echoed even when `*` allowed it.
+    pub allow_credentials: Option<bool>,
+    /// Seconds a browser keeps a preflight's answer.
+    pub max_age: Option<u64>,
+}
@@ -32,7 +32,24 @@ pub struct EndpointSettings {
     pub keep_alive: Option<bool>,
     pub track_metrics_by_all_domains: Option<bool>,
     pub hsts: Option<bool>,
+    /// On an `http` endpoint: answer every request no location claims with a
+    /// 301 to the same host and path over https.
+    pub https_redirect: Option<bool>,
     pub mcp_buffer_size: Option<String>,
+    /// Limits how many requests each client makes to the endpoint, whatever
+    /// the location.
+    pub rate_limit: Option<RateLimitSettings>,
+    /// Connections open on the listen port at once. Counted at accept, before
+    /// the endpoint is known, so endpoints sharing a port share the lowest
+    /// limit any of them sets.
+    pub max_connections: Option<usize>,
+    /// Connections one client IP holds open on the listen port at once.
+    pub max_connections_per_ip: Option<usize>,
+    /// Answers CORS preflights and adds the CORS headers to responses, for
+    /// every location without a `cors` of its own.
+    pub cors: Option<CorsSettings>,
+    #[serde(flatten)]
+    pub request_limits: RequestLimitsSettings,
     #[serde(flatten)]
     pub timeouts: TimeoutsSettings,
 }
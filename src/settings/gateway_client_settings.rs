@@ -17,6 +17,8 @@ pub struct GatewayClientSettings {
     /// Connect timeout for the gateway-client link, in milliseconds.
     pub connect_timeout: Option<u64>,
     pub sync_ssl_certificates: Option<Vec<String>>,
+    /// Directories the server may read files from; none without it.
+    pub file_roots: Option<Vec<String>>,
 }
 
 impl GatewayClientSettings {
@@ -43,6 +45,10 @@ impl GatewayClientSettings {
         self.sync_ssl_certificates.clone().unwrap_or_default()
     }
 
+    pub fn get_file_roots(&self) -> &[String] {
+        self.file_roots.as_deref().unwrap_or_default()
+    }
+
     pub fn load_signing_key(
         &self,
         client_id: &str,
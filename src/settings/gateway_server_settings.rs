@@ -10,6 +10,8 @@ pub struct GatewayServerSettings {
     pub authorized_keys: Vec<String>,
     pub allowed_ip: Option<Vec<String>>,
     pub debug: Option<bool>,
+    /// Directories the clients may read files from; none without it.
+    pub file_roots: Option<Vec<String>>,
 }
 
 impl GatewayServerSettings {
@@ -50,6 +52,10 @@ impl GatewayServerSettings {
         Ok(keys)
     }
 
+    pub fn get_file_roots(&self) -> &[String] {
+        self.file_roots.as_deref().unwrap_or_default()
+    }
+
     pub fn get_allowed_ip_list(&self) -> Option<HashSet<String>> {
         let items = self.allowed_ip.as_ref()?;
 
@@ -1,6 +1,6 @@
 use serde::*;
 
-use super::{ConnectionsSettings, ModifyHttpHeadersSettings, TimeoutsSettings};
+use super::{ConnectionsSettings, ModifyHttpHeadersSettings, RateLimitSettings, TimeoutsSettings};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GlobalSettings {
@@ -19,6 +19,9 @@ pub struct GlobalSettings {
     /// pool, in milliseconds. Defaults to 10000 (10s). Global-only — a single
     /// timer drives every pool, so it is not part of the cascade.
     pub pool_supervisor_interval: Option<u64>,
+    /// Limits how many requests each client makes to the http endpoints, all
+    /// of them together. Checked before the endpoint's and the location's own.
+    pub rate_limit: Option<RateLimitSettings>,
     /// Lowest level of the timeout cascade — overridden by the endpoint, then
     /// the location.
     #[serde(flatten)]
@@ -5,5 +5,6 @@ use super::*;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct HostSettings {
     pub endpoint: EndpointSettings,
+    #[serde(default)]
     pub locations: Vec<LocationSettings>,
 }
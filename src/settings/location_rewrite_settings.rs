@@ -0,0 +1,9 @@
+use serde::*;
+
+/// `rewrite:` block of a location — a regex replacement over the request path.
+/// `to` may refer to capture groups of `from` as `${1}`, `${name}`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LocationRewriteSettings {
+    pub from: String,
+    pub to: String,
+}
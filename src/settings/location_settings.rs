@@ -1,5 +1,7 @@
 use serde::*;
 
+use crate::configurations::{LocationMatchMode, LocationMatcher};
+
 use super::*;
 
 pub enum LocationType {
@@ -9,6 +11,7 @@ pub enum LocationType {
     Https2,
     Files,
     StaticContent,
+    Redirect,
     UnixSocketHttp,
     UnixSocketHttp2,
     Mcp,
@@ -21,7 +24,11 @@ impl LocationType {
     pub fn detect_from_location_settings(
         location_settings: &LocationSettings,
     ) -> Result<Self, String> {
-        match location_settings.proxy_pass_to.as_ref() {
+        match location_settings
+            .proxy_pass_to
+            .as_ref()
+            .and_then(|itm| itm.get_first())
+        {
             Some(src) => {
                 if src.eq_ignore_ascii_case("static") {
                     return Ok(Self::StaticContent);
@@ -63,6 +70,7 @@ impl LocationType {
 
                 Ok(Self::Files)
             }
+            None if location_settings.redirect_to.is_some() => Ok(Self::Redirect),
             None => Ok(Self::StaticContent),
         }
     }
@@ -71,20 +79,104 @@ impl LocationType {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LocationSettings {
     pub path: Option<String>,
-    pub proxy_pass_to: Option<String>,
+    /// How `path` is matched: `prefix` (default), `exact` / `=`, `regex` / `~`,
+    /// or `~*` for a case-insensitive regex.
+    #[serde(rename = "match")]
+    pub match_mode: Option<String>,
+    pub case_sensitive: Option<bool>,
+    pub proxy_pass_to: Option<ProxyPassToSettings>,
+    /// How a location with a list in `proxy_pass_to` picks an upstream:
+    /// `round_robin` (default), `weighted`, `least_outstanding` or
+    /// `consistent_hash`.
+    pub balance: Option<String>,
+    /// Pins a client to one of the upstreams by cookie or request header.
+    pub sticky: Option<StickySettings>,
+    /// Splits the traffic between upstreams by weight and by cookie / header,
+    /// in place of `proxy_pass_to`.
+    pub split: Option<Vec<SplitBranchSettings>>,
+    /// Where a request goes when the upstream cannot take it — one target or
+    /// an ordered list, tried in turn.
+    pub backup_proxy_pass_to: Option<ProxyPassToSettings>,
+    /// Upstream response statuses that also send the request on to the backup.
+    pub backup_on_status: Option<Vec<u16>>,
+    /// Copies requests to a shadow upstream, discarding its responses.
+    pub mirror_to: Option<MirrorSettings>,
+    /// Sends a failed idempotent request to the upstream again.
+    pub retry: Option<RetrySettings>,
+    /// Keeps cacheable GET responses and serves them without the upstream.
+    pub cache: Option<CacheSettings>,
+    /// Serves the last good response when the upstream fails, or while it is
+    /// refreshed.
+    pub stale: Option<StaleSettings>,
+    /// Limits how many requests each client makes to the location.
+    pub rate_limit: Option<RateLimitSettings>,
+    /// Requests on their way to the upstream at once; the ones past it wait in
+    /// the queue or are answered with 503.
+    pub max_concurrent_requests: Option<usize>,
+    /// Requests that wait for a slot at once. Default 0 — no queue.
+    pub max_queued_requests: Option<usize>,
+    /// Milliseconds a queued request waits for a slot. Default 5000.
+    pub queue_timeout: Option<u64>,
     #[serde(rename = "type")]
     pub location_type: Option<String>,
     pub domain_name: Option<String>,
     pub modify_http_headers: Option<ModifyHttpHeadersSettings>,
     pub default_file: Option<String>,
+    /// Answers a files location's directory that has no `default_file` with
+    /// its listing — HTML, or JSON for a client that accepts it.
+    pub autoindex: Option<bool>,
+    /// Paths of a files location tried in turn — `$uri` is the request path —
+    /// with an optional `=404` status last.
+    pub try_files: Option<Vec<String>>,
+    /// Serves a files location's `.zst`, `.br` or `.gz` sibling of a file to a
+    /// client that accepts its encoding.
+    pub precompressed: Option<bool>,
     pub status_code: Option<u16>,
+    /// Where a `redirect` location sends the client; `${HOST}`,
+    /// `${PATH_AND_QUERY}` and the location regex's `${1}`... are filled in.
+    pub redirect_to: Option<String>,
     pub content_type: Option<String>,
     pub body: Option<String>,
+    /// The body of a `static` location read from a file — a local path,
+    /// `ssh:...->path` or `gateway:...->path` — when the configuration is
+    /// applied.
+    pub body_file: Option<String>,
     pub whitelisted_ip: Option<String>,
+    /// Encodes the upstream's responses in what the client accepts — zstd, br
+    /// or gzip — on an http/1.1 endpoint.
     pub compress: Option<bool>,
+    /// Media types `compress` encodes. Default: text, json, javascript, xml and
+    /// svg.
+    pub compress_types: Option<Vec<String>>,
+    /// Responses with a smaller `Content-Length` are not encoded; bytes, or
+    /// with a `Kb` / `Mb` suffix. Default 1Kb.
+    pub compress_min_size: Option<String>,
     pub trace_payload: Option<bool>,
     pub auth_header: Option<String>,
     pub allowed_hosts: Option<Vec<String>>,
+    /// Cut the location's `path` off the request path before forwarding.
+    pub strip_prefix: Option<bool>,
+    pub rewrite: Option<LocationRewriteSettings>,
+    /// Forward onto the path of `proxy_pass_to` instead of replacing it.
+    pub append_to_upstream_path: Option<bool>,
+    /// Rewrites the upstream's own url in `Location` and `Refresh` back onto
+    /// the location. `default` with a path rewrite set, `off` otherwise.
+    pub proxy_redirect: Option<ResponseRewriteSettings>,
+    /// Rewrites `Domain` of the upstream's cookies. By default, with a path
+    /// rewrite set, a cookie for the upstream's host is left to the public one.
+    pub proxy_cookie_domain: Option<ResponseRewriteSettings>,
+    /// Rewrites `Path` of the upstream's cookies. By default, with a path
+    /// rewrite set, the upstream's path is turned into the location's.
+    pub proxy_cookie_path: Option<ResponseRewriteSettings>,
+    /// Replacements made in the bodies of the upstream's responses.
+    pub sub_filter: Option<Vec<SubFilterSettings>>,
+    /// Media types `sub_filter` replaces in. Default `text/html`.
+    pub sub_filter_types: Option<Vec<String>>,
+    /// Answers CORS preflights and adds the CORS headers to responses; replaces
+    /// the endpoint's `cors`.
+    pub cors: Option<CorsSettings>,
+    #[serde(flatten)]
+    pub request_limits: RequestLimitsSettings,
     #[serde(flatten)]
     pub timeouts: TimeoutsSettings,
 }
@@ -102,6 +194,7 @@ impl LocationSettings {
                 "https1" => return Ok(LocationType::Https1.into()),
                 "https2" => return Ok(LocationType::Https2.into()),
                 STATIC => return Ok(LocationType::StaticContent.into()),
+                REDIRECT => return Ok(LocationType::Redirect.into()),
                 MCP => return Ok(LocationType::Mcp.into()),
                 MCP_H2 => return Ok(LocationType::McpH2.into()),
                 "drop" => return Ok(LocationType::Drop.into()),
@@ -116,4 +209,27 @@ impl LocationSettings {
     pub fn get_compress(&self) -> bool {
         self.compress.unwrap_or(false)
     }
+
+    pub fn get_autoindex(&self) -> bool {
+        self.autoindex.unwrap_or(false)
+    }
+
+    pub fn get_precompressed(&self) -> bool {
+        self.precompressed.unwrap_or(false)
+    }
+
+    pub fn get_location_matcher(&self, path: &str) -> Result<LocationMatcher, String> {
+        let match_mode = self.match_mode.as_deref();
+        let mode = LocationMatchMode::parse(match_mode)?;
+
+        // `~` and `~*` carry their own case sensitivity, the way nginx reads
+        // them; everything else is case-insensitive unless asked otherwise.
+        let case_sensitive = match match_mode.map(|itm| itm.trim()) {
+            Some("~") => true,
+            Some("~*") => false,
+            _ => self.case_sensitive.unwrap_or(false),
+        };
+
+        LocationMatcher::new(mode, path, case_sensitive)
+    }
 }
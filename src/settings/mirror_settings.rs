@@ -0,0 +1,13 @@
+use serde::*;
+
+/// `mirror_to:` block of a location — a shadow upstream every sampled request
+/// is copied to. Its response is read and thrown away.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MirrorSettings {
+    pub url: String,
+    /// Share of requests copied, 1-100. Default 100.
+    pub percent: Option<u32>,
+    /// Largest request body still copied, e.g. `64Kb`. A request with a bigger
+    /// one is not mirrored. Default 64Kb.
+    pub max_body_size: Option<String>,
+}
@@ -19,6 +19,32 @@ mod end_point_settings;
 pub use end_point_settings::*;
 mod location_settings;
 pub use location_settings::*;
+mod location_rewrite_settings;
+pub use location_rewrite_settings::*;
+mod response_rewrite_settings;
+pub use response_rewrite_settings::*;
+mod sub_filter_settings;
+pub use sub_filter_settings::*;
+mod sticky_settings;
+pub use sticky_settings::*;
+mod mirror_settings;
+pub use mirror_settings::*;
+mod split_settings;
+pub use split_settings::*;
+mod retry_settings;
+pub use retry_settings::*;
+mod cache_settings;
+pub use cache_settings::*;
+mod stale_settings;
+pub use stale_settings::*;
+mod rate_limit_settings;
+pub use rate_limit_settings::*;
+mod request_limits_settings;
+pub use request_limits_settings::*;
+mod cors_settings;
+pub use cors_settings::*;
+mod proxy_pass_to_settings;
+pub use proxy_pass_to_settings::*;
 
 mod client_certificate_ca_settings;
 pub use client_certificate_ca_settings::*;
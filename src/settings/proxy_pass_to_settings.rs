@@ -0,0 +1,72 @@
+use serde::*;
+
+/// `proxy_pass_to` of a location: a single target, or a list of upstreams the
+/// location balances between (see `balance`).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ProxyPassToSettings {
+    Single(String),
+    Upstreams(Vec<UpstreamSettings>),
+}
+
+/// One entry of a `proxy_pass_to` list — the bare target, or the target with
+/// its weight.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum UpstreamSettings {
+    Url(String),
+    Weighted { url: String, weight: Option<u32> },
+}
+
+impl UpstreamSettings {
+    pub fn get_url(&self) -> &str {
+        match self {
+            Self::Url(url) => url,
+            Self::Weighted { url, .. } => url,
+        }
+    }
+
+    pub fn get_weight(&self) -> u32 {
+        match self {
+            Self::Url(_) => 1,
+            Self::Weighted { weight, .. } => weight.unwrap_or(1),
+        }
+    }
+}
+
+impl ProxyPassToSettings {
+    /// The single target, or the first upstream of a list — what decides the
+    /// location type when `type` is not set.
+    pub fn get_first(&self) -> Option<&str> {
+        match self {
+            Self::Single(src) => Some(src.as_str()),
+            Self::Upstreams(upstreams) => upstreams.first().map(|itm| itm.get_url()),
+        }
+    }
+
+    /// `(target, weight)` of every upstream, in declaration order.
+    pub fn get_upstreams(&self) -> Vec<(&str, u32)> {
+        match self {
+            Self::Single(src) => vec![(src.as_str(), 1)],
+            Self::Upstreams(upstreams) => upstreams
+                .iter()
+                .map(|itm| (itm.get_url(), itm.get_weight()))
+                .collect(),
+        }
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, Self::Upstreams(_))
+    }
+
+    pub fn as_string(&self) -> String {
+        match self {
+            Self::Single(src) => src.to_string(),
+            Self::Upstreams(upstreams) => upstreams
+                .iter()
+                .map(|itm| itm.get_url())
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
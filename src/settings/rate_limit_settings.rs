@@ -0,0 +1,24 @@
+use serde::*;
+
+/// `rate_limit:` block — of `global_settings`, an endpoint or a location. Every
+/// client gets a token bucket of its own; a request with none left in it is
+/// answered `429 Too Many Requests`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RateLimitSettings {
+    /// Requests a client may make on average, e.g. `10/s`, `600/m` or `1000/h`.
+    pub rate: String,
+    /// Requests a client may make at once after being idle. Default is the
+    /// number of the rate.
+    pub burst: Option<u64>,
+    /// What a client is: `ip` (default), `identity` (the client certificate's
+    /// CN or the Google user), `header:{name}` or `query:{name}` — an API key,
+    /// say. A request without an identity, the header or the query parameter is
+    /// counted against its IP.
+    pub key: Option<String>,
+    /// Every request turned away counts against the client's IP in the IP
+    /// block-list, so a client that keeps going over the limit gets blocked.
+    pub block_offenders: Option<bool>,
+    /// Clients tracked at once. Default 100000 — past it, new clients share
+    /// one bucket until the idle ones are dropped.
+    pub max_clients: Option<usize>,
+}
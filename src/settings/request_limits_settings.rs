@@ -0,0 +1,18 @@
+use serde::*;
+
+/// Limits on the size of a request, of an endpoint or a location. A location
+/// takes what it leaves unset from its endpoint. The sizes take the same
+/// values as `buffer_size`: bytes, or a number of `Kb` / `Mb`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RequestLimitsSettings {
+    /// The request line and headers together; over it the request is answered
+    /// with 431. The head is read before the location is known, so a location
+    /// can lower the endpoint's limit but not raise it.
+    pub max_header_size: Option<String>,
+    /// Headers a request has; over it the request is answered with 431.
+    pub max_headers: Option<usize>,
+    /// Bytes of the path and query; over it the request is answered with 414.
+    pub max_uri_length: Option<usize>,
+    /// The request body; over it the request is answered with 413.
+    pub max_body_size: Option<String>,
+}
@@ -0,0 +1,18 @@
+use serde::*;
+
+/// `proxy_redirect`, `proxy_cookie_domain` or `proxy_cookie_path` of a
+/// location: `default`, `off`, or a list of `from` → `to` rules.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ResponseRewriteSettings {
+    Mode(String),
+    Rules(Vec<ResponseRewriteRuleSettings>),
+}
+
+/// One rule: `from` at the start of the value (the whole value for
+/// `proxy_cookie_domain`) is replaced by `to`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponseRewriteRuleSettings {
+    pub from: String,
+    pub to: String,
+}
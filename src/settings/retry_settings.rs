@@ -0,0 +1,25 @@
+use serde::*;
+
+/// `retry:` block of a location — when a failed request is sent to its
+/// upstream again. Only GET / HEAD / OPTIONS requests and the `methods` listed
+/// are retried.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RetrySettings {
+    /// Attempts in total, the first one included. Default 2.
+    pub attempts: Option<u32>,
+    /// Failures retried: `connect` (the upstream could not be reached or
+    /// dropped the connection before answering) and `timeout` (no response
+    /// within read_timeout). Default `[connect]`.
+    pub on: Option<Vec<String>>,
+    /// Upstream response statuses retried. Default `[502, 503, 504]`.
+    pub on_status: Option<Vec<u16>>,
+    /// Methods retried besides GET, HEAD and OPTIONS, e.g. `PUT`.
+    pub methods: Option<Vec<String>>,
+    /// Milliseconds waited before the first retry; doubled for every next one.
+    /// Default 25.
+    pub backoff: Option<u64>,
+    /// Milliseconds the wait before a retry is capped at. Default 1000.
+    pub max_backoff: Option<u64>,
+    /// Retries allowed as a percentage of the location's requests. Default 20.
+    pub budget_percent: Option<u32>,
+}
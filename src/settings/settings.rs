@@ -100,24 +100,64 @@ mod tests {
                     keep_alive: None,
                     track_metrics_by_all_domains: None,
                     hsts: None,
+                    https_redirect: None,
                     mcp_buffer_size: None,
+                    rate_limit: None,
+                    max_connections: None,
+                    max_connections_per_ip: None,
+                    cors: None,
+                    request_limits: RequestLimitsSettings::default(),
                     timeouts: TimeoutsSettings::default(),
                 },
                 locations: vec![LocationSettings {
                     path: Some("/".to_owned()),
-                    proxy_pass_to: "https://www.google.com".to_owned().into(),
+                    match_mode: None,
+                    case_sensitive: None,
+                    proxy_pass_to: Some(ProxyPassToSettings::Single(
+                        "https://www.google.com".to_owned(),
+                    )),
+                    balance: None,
+                    sticky: None,
+                    split: None,
+                    backup_proxy_pass_to: None,
+                    backup_on_status: None,
+                    mirror_to: None,
+                    retry: None,
+                    cache: None,
+                    stale: None,
+                    rate_limit: None,
+                    max_concurrent_requests: None,
+                    max_queued_requests: None,
+                    queue_timeout: None,
                     location_type: Some("http".to_owned()),
                     modify_http_headers: None,
                     default_file: None,
+                    autoindex: None,
+                    try_files: None,
+                    precompressed: None,
                     status_code: None,
+                    redirect_to: None,
                     body: None,
+                    body_file: None,
                     content_type: None,
                     whitelisted_ip: None,
                     domain_name: None,
                     compress: None,
+                    compress_types: None,
+                    compress_min_size: None,
                     trace_payload: None,
                     auth_header: None,
                     allowed_hosts: None,
+                    strip_prefix: None,
+                    rewrite: None,
+                    append_to_upstream_path: None,
+                    proxy_redirect: None,
+                    proxy_cookie_domain: None,
+                    proxy_cookie_path: None,
+                    sub_filter: None,
+                    sub_filter_types: None,
+                    cors: None,
+                    request_limits: RequestLimitsSettings::default(),
                     timeouts: TimeoutsSettings::default(),
                 }],
             },
@@ -155,6 +195,7 @@ mod tests {
                 allow_incoming_forward_connections: None,
                 connect_timeout: None,
                 sync_ssl_certificates: None,
+                file_roots: None,
             },
         );
 
@@ -0,0 +1,17 @@
+use serde::*;
+
+/// One branch of a location's `split:` — an upstream and its share of the
+/// requests.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SplitBranchSettings {
+    /// Names the branch in the metrics, e.g. `v1`.
+    pub name: String,
+    pub proxy_pass_to: String,
+    /// Share of the requests relative to the other branches. Default 1; 0
+    /// takes only the requests `cookie` / `header` send to the branch.
+    pub weight: Option<u32>,
+    /// Requests carrying this cookie go to the branch: `name` or `name=value`.
+    pub cookie: Option<String>,
+    /// Requests carrying this header go to the branch: `name` or `name: value`.
+    pub header: Option<String>,
+}
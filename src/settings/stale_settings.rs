@@ -0,0 +1,19 @@
+use serde::*;
+
+/// `stale:` block of a location — the last good GET response of the upstream
+/// to every request is kept and served when the upstream fails, or at once
+/// while the upstream is asked for a new one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StaleSettings {
+    /// Seconds after it was received a kept response is served when the
+    /// upstream can not be reached, fails the request or answers 500, 502,
+    /// 503 or 504.
+    pub if_error: Option<u64>,
+    /// Seconds after it was received a kept response is served at once, with
+    /// the request refreshing it from the upstream in the background.
+    pub while_revalidate: Option<u64>,
+    /// Responses kept in memory, e.g. `16Mb`. Default 16Mb.
+    pub max_size: Option<String>,
+    /// A bigger response is not kept. Default 1Mb.
+    pub max_entry_size: Option<String>,
+}
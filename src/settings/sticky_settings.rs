@@ -0,0 +1,11 @@
+use serde::*;
+
+/// `sticky:` block of a location with several upstreams — pins a client to one
+/// of them. Exactly one of `cookie` / `header`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StickySettings {
+    /// Name of the affinity cookie the proxy issues.
+    pub cookie: Option<String>,
+    /// Request header whose value pins the client, e.g. `Mcp-Session-Id`.
+    pub header: Option<String>,
+}
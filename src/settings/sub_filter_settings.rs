@@ -0,0 +1,10 @@
+use serde::*;
+
+/// One entry of a location's `sub_filter:` — a replacement in the response
+/// body. `from` is a literal, or a regex as `~...` / `~*...`; `to` of a regex
+/// may refer to its capture groups as `${1}`, `${name}`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubFilterSettings {
+    pub from: String,
+    pub to: String,
+}
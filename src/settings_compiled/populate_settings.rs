@@ -125,8 +125,17 @@ impl SettingsCompiled {
                         .endpoint
                         .track_metrics_by_all_domains,
                     hsts: host_settings.endpoint.hsts,
+                    https_redirect: host_settings.endpoint.https_redirect,
                     mcp_buffer_size: variables
                         .apply_variables_opt(host_settings.endpoint.mcp_buffer_size)?,
+                    rate_limit: populate_rate_limit(host_settings.endpoint.rate_limit, variables)?,
+                    max_connections: host_settings.endpoint.max_connections,
+                    max_connections_per_ip: host_settings.endpoint.max_connections_per_ip,
+                    cors: populate_cors(host_settings.endpoint.cors, variables)?,
+                    request_limits: populate_request_limits(
+                        host_settings.endpoint.request_limits,
+                        variables,
+                    )?,
                     timeouts: host_settings.endpoint.timeouts,
                 },
                 locations,
@@ -198,6 +207,7 @@ impl SettingsCompiled {
                 default_h2_livness_url: itm.default_h2_livness_url,
                 ip_blocklist_white_list: itm.ip_blocklist_white_list,
                 pool_supervisor_interval: itm.pool_supervisor_interval,
+                rate_limit: populate_rate_limit(itm.rate_limit, variables)?,
                 timeouts: itm.timeouts,
             })
         }
@@ -329,6 +339,7 @@ impl SettingsCompiled {
                 allowed_ip: itm.allowed_ip.clone(),
                 authorized_keys,
                 debug: itm.debug,
+                file_roots: apply_variables_to_file_roots(itm.file_roots, variables)?,
             });
         }
 
@@ -352,6 +363,7 @@ impl SettingsCompiled {
                         allow_incoming_forward_connections: itm.allow_incoming_forward_connections,
                         connect_timeout: itm.connect_timeout,
                         sync_ssl_certificates: itm.sync_ssl_certificates,
+                        file_roots: apply_variables_to_file_roots(itm.file_roots, variables)?,
                     },
                 );
             }
@@ -361,6 +373,22 @@ impl SettingsCompiled {
     }
 }
 
+fn apply_variables_to_file_roots(
+    file_roots: Option<Vec<String>>,
+    variables: &VariablesCompiled,
+) -> Result<Option<Vec<String>>, String> {
+    let Some(file_roots) = file_roots else {
+        return Ok(None);
+    };
+
+    let mut result = Vec::with_capacity(file_roots.len());
+    for root in file_roots {
+        result.push(variables.apply_variables(root)?);
+    }
+
+    Ok(Some(result))
+}
+
 async fn load_includes(
     settings_model: &SettingsModel,
 ) -> Result<Vec<crate::settings::SettingsModel>, String> {
@@ -416,6 +444,108 @@ fn dynamic_configurations_file_exists(file_name: &str) -> bool {
     std::path::Path::new(path.as_str()).exists()
 }
 
+fn populate_proxy_pass_to(
+    proxy_pass_to: ProxyPassToSettings,
+    variables: &VariablesCompiled,
+) -> Result<ProxyPassToSettings, String> {
+    match proxy_pass_to {
+        ProxyPassToSettings::Single(src) => {
+            Ok(ProxyPassToSettings::Single(variables.apply_variables(src)?))
+        }
+        ProxyPassToSettings::Upstreams(upstreams) => {
+            let mut result = Vec::with_capacity(upstreams.len());
+            for upstream in upstreams {
+                result.push(match upstream {
+                    UpstreamSettings::Url(url) => {
+                        UpstreamSettings::Url(variables.apply_variables(url)?)
+                    }
+                    UpstreamSettings::Weighted { url, weight } => UpstreamSettings::Weighted {
+                        url: variables.apply_variables(url)?,
+                        weight,
+                    },
+                });
+            }
+            Ok(ProxyPassToSettings::Upstreams(result))
+        }
+    }
+}
+
+fn populate_rate_limit(
+    rate_limit: Option<RateLimitSettings>,
+    variables: &VariablesCompiled,
+) -> Result<Option<RateLimitSettings>, String> {
+    let Some(rate_limit) = rate_limit else {
+        return Ok(None);
+    };
+
+    Ok(Some(RateLimitSettings {
+        rate: variables.apply_variables(rate_limit.rate)?,
+        burst: rate_limit.burst,
+        key: variables.apply_variables_opt(rate_limit.key)?,
+        block_offenders: rate_limit.block_offenders,
+        max_clients: rate_limit.max_clients,
+    }))
+}
+
+fn populate_request_limits(
+    request_limits: RequestLimitsSettings,
+    variables: &VariablesCompiled,
+) -> Result<RequestLimitsSettings, String> {
+    Ok(RequestLimitsSettings {
+        max_header_size: variables.apply_variables_opt(request_limits.max_header_size)?,
+        max_headers: request_limits.max_headers,
+        max_uri_length: request_limits.max_uri_length,
+        max_body_size: variables.apply_variables_opt(request_limits.max_body_size)?,
+    })
+}
+
+fn populate_response_rewrite(
+    response_rewrite: Option<ResponseRewriteSettings>,
+    variables: &VariablesCompiled,
+) -> Result<Option<ResponseRewriteSettings>, String> {
+    let result = match response_rewrite {
+        Some(ResponseRewriteSettings::Mode(mode)) => {
+            ResponseRewriteSettings::Mode(variables.apply_variables(mode)?)
+        }
+        Some(ResponseRewriteSettings::Rules(rules)) => {
+            let mut result = Vec::with_capacity(rules.len());
+            for rule in rules {
+                result.push(ResponseRewriteRuleSettings {
+                    from: variables.apply_variables(rule.from)?,
+                    to: variables.apply_variables(rule.to)?,
+                });
+            }
+            ResponseRewriteSettings::Rules(result)
+        }
+        None => return Ok(None),
+    };
+
+    Ok(Some(result))
+}
+
+fn populate_cors(
+    cors: Option<CorsSettings>,
+    variables: &VariablesCompiled,
+) -> Result<Option<CorsSettings>, String> {
+    let Some(cors) = cors else {
+        return Ok(None);
+    };
+
+    let mut allowed_origins = Vec::with_capacity(cors.allowed_origins.len());
+    for origin in cors.allowed_origins {
+        allowed_origins.push(variables.apply_variables(origin)?);
+    }
+
+    Ok(Some(CorsSettings {
+        allowed_origins,
+        allowed_methods: cors.allowed_methods,
+        allowed_headers: cors.allowed_headers,
+        expose_headers: cors.expose_headers,
+        allow_credentials: cors.allow_credentials,
+        max_age: cors.max_age,
+    }))
+}
+
 fn compile_locations(
     locations: Vec<LocationSettings>,
     variables: &VariablesCompiled,
@@ -425,7 +555,73 @@ fn compile_locations(
     for location in locations {
         result.push(LocationSettings {
             path: variables.apply_variables_opt(location.path)?,
-            proxy_pass_to: variables.apply_variables_opt(location.proxy_pass_to)?,
+            match_mode: location.match_mode,
+            case_sensitive: location.case_sensitive,
+            proxy_pass_to: match location.proxy_pass_to {
+                Some(proxy_pass_to) => Some(populate_proxy_pass_to(proxy_pass_to, variables)?),
+                None => None,
+            },
+            balance: location.balance,
+            sticky: match location.sticky {
+                Some(sticky) => Some(StickySettings {
+                    cookie: variables.apply_variables_opt(sticky.cookie)?,
+                    header: variables.apply_variables_opt(sticky.header)?,
+                }),
+                None => None,
+            },
+            split: match location.split {
+                Some(split) => {
+                    let mut result = Vec::with_capacity(split.len());
+                    for branch in split {
+                        result.push(SplitBranchSettings {
+                            name: branch.name,
+                            proxy_pass_to: variables.apply_variables(branch.proxy_pass_to)?,
+                            weight: branch.weight,
+                            cookie: variables.apply_variables_opt(branch.cookie)?,
+                            header: variables.apply_variables_opt(branch.header)?,
+                        });
+                    }
+                    Some(result)
+                }
+                None => None,
+            },
+            backup_proxy_pass_to: match location.backup_proxy_pass_to {
+                Some(backup) => Some(populate_proxy_pass_to(backup, variables)?),
+                None => None,
+            },
+            backup_on_status: location.backup_on_status,
+            mirror_to: match location.mirror_to {
+                Some(mirror) => Some(MirrorSettings {
+                    url: variables.apply_variables(mirror.url)?,
+                    percent: mirror.percent,
+                    max_body_size: variables.apply_variables_opt(mirror.max_body_size)?,
+                }),
+                None => None,
+            },
+            retry: location.retry,
+            cache: match location.cache {
+                Some(cache) => Some(CacheSettings {
+                    max_size: variables.apply_variables_opt(cache.max_size)?,
+                    max_entry_size: variables.apply_variables_opt(cache.max_entry_size)?,
+                    max_ttl: cache.max_ttl,
+                    disk_path: variables.apply_variables_opt(cache.disk_path)?,
+                    disk_max_size: variables.apply_variables_opt(cache.disk_max_size)?,
+                }),
+                None => None,
+            },
+            stale: match location.stale {
+                Some(stale) => Some(StaleSettings {
+                    if_error: stale.if_error,
+                    while_revalidate: stale.while_revalidate,
+                    max_size: variables.apply_variables_opt(stale.max_size)?,
+                    max_entry_size: variables.apply_variables_opt(stale.max_entry_size)?,
+                }),
+                None => None,
+            },
+            rate_limit: populate_rate_limit(location.rate_limit, variables)?,
+            max_concurrent_requests: location.max_concurrent_requests,
+            max_queued_requests: location.max_queued_requests,
+            queue_timeout: location.queue_timeout,
             location_type: variables.apply_variables_opt(location.location_type)?,
             domain_name: variables.apply_variables_opt(location.domain_name)?,
             modify_http_headers: super::populate_modify_http_headers_settings(
@@ -433,17 +629,115 @@ fn compile_locations(
                 variables,
             )?,
             default_file: variables.apply_variables_opt(location.default_file)?,
+            autoindex: location.autoindex,
+            try_files: location.try_files,
+            precompressed: location.precompressed,
             status_code: location.status_code,
+            redirect_to: variables.apply_variables_to_replacement_opt(location.redirect_to)?,
             content_type: variables.apply_variables_opt(location.content_type)?,
             body: variables.apply_variables_opt(location.body)?,
+            body_file: variables.apply_variables_opt(location.body_file)?,
             whitelisted_ip: variables.apply_variables_opt(location.whitelisted_ip)?,
             compress: location.compress,
+            compress_types: location.compress_types,
+            compress_min_size: variables.apply_variables_opt(location.compress_min_size)?,
             trace_payload: location.trace_payload,
             auth_header: variables.apply_variables_opt(location.auth_header)?,
             allowed_hosts: location.allowed_hosts,
+            strip_prefix: location.strip_prefix,
+            rewrite: match location.rewrite {
+                Some(rewrite) => Some(LocationRewriteSettings {
+                    from: variables.apply_variables(rewrite.from)?,
+                    to: variables.apply_variables_to_replacement(rewrite.to)?,
+                }),
+                None => None,
+            },
+            append_to_upstream_path: location.append_to_upstream_path,
+            proxy_redirect: populate_response_rewrite(location.proxy_redirect, variables)?,
+            proxy_cookie_domain: populate_response_rewrite(
+                location.proxy_cookie_domain,
+                variables,
+            )?,
+            proxy_cookie_path: populate_response_rewrite(location.proxy_cookie_path, variables)?,
+            sub_filter: match location.sub_filter {
+                Some(sub_filter) => {
+                    let mut result = Vec::with_capacity(sub_filter.len());
+                    for itm in sub_filter {
+                        result.push(SubFilterSettings {
+                            from: variables.apply_variables(itm.from)?,
+                            to: variables.apply_variables_to_replacement(itm.to)?,
+                        });
+                    }
+                    Some(result)
+                }
+                None => None,
+            },
+            sub_filter_types: location.sub_filter_types,
+            cors: populate_cors(location.cors, variables)?,
+            request_limits: populate_request_limits(location.request_limits, variables)?,
             timeouts: location.timeouts,
         });
     }
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile_location(yaml: &str) -> Result<LocationSettings, String> {
+        let location: LocationSettings = serde_yaml::from_str(yaml).unwrap();
+        let mut result = compile_locations(vec![location], &VariablesCompiled::default())?;
+        Ok(result.remove(0))
+    }
+
+    #[test]
+    fn capture_groups_of_replacements_are_left_for_the_request() {
+        let location = compile_location(
+            r#"
+path: /api
+proxy_pass_to: http://localhost:5000
+rewrite:
+  from: ^/legacy/(?P<version>v\d+)/(\w+)$
+  to: /${version}/${2}
+sub_filter:
+- from: ~src="/(?P<file>\w+)\.js"
+  to: src="/static/${file}.${1}.js"
+"#,
+        )
+        .unwrap();
+
+        let rewrite = location.rewrite.unwrap();
+        assert_eq!(rewrite.to, "/${version}/${2}");
+
+        let path_rewrite = crate::configurations::LocationPathRewrite::new(
+            None,
+            false,
+            Some((rewrite.from.as_str(), rewrite.to.as_str())),
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            path_rewrite.apply("/legacy/v1/orders?a=1"),
+            "/v1/orders?a=1"
+        );
+
+        let sub_filter = location.sub_filter.unwrap();
+        assert_eq!(sub_filter[0].to, "src=\"/static/${file}.${1}.js\"");
+        crate::body_filter::SubFilterRule::parse(&sub_filter[0].from, &sub_filter[0].to).unwrap();
+    }
+
+    #[test]
+    fn an_unknown_variable_elsewhere_is_still_an_error() {
+        let result = compile_location(
+            r#"
+path: /api
+proxy_pass_to: http://${not_a_variable}:5000
+"#,
+        );
+
+        assert!(result.is_err());
+    }
+}
@@ -113,6 +113,11 @@ impl SettingsCompiled {
         false
     }
 
+    /// `rate_limit` of `global_settings`, covering every http endpoint.
+    pub fn get_global_rate_limit(&self) -> Option<&crate::settings::RateLimitSettings> {
+        self.global_settings.as_ref()?.rate_limit.as_ref()
+    }
+
     /// Global IP block-list allow-list (fail2ban exemptions), if configured.
     pub fn get_ip_blocklist_white_list(&self) -> Option<Vec<String>> {
         self.global_settings
@@ -32,6 +32,27 @@ impl VariablesCompiled {
     }
 
     pub fn apply_variables(&self, value: String) -> Result<String, String> {
+        self.apply(value, false)
+    }
+
+    /// For a regex replacement — a `rewrite` or `sub_filter` `to`, a
+    /// `redirect_to`. Its `${1}` and `${name}` are capture groups filled in per
+    /// request, so a placeholder that is not a variable is left as it is.
+    pub fn apply_variables_to_replacement(&self, value: String) -> Result<String, String> {
+        self.apply(value, true)
+    }
+
+    pub fn apply_variables_to_replacement_opt(
+        &self,
+        value: Option<String>,
+    ) -> Result<Option<String>, String> {
+        match value {
+            Some(value) => Ok(Some(self.apply_variables_to_replacement(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn apply(&self, value: String, keep_unknown: bool) -> Result<String, String> {
         if !value.contains("${") {
             return Ok(value.into());
         }
@@ -47,7 +68,13 @@ impl VariablesCompiled {
                     result.push_str(text);
                 }
                 ContentToken::Placeholder(placeholder) => {
-                    match name_characteristics(placeholder)? {
+                    let name_type = match name_characteristics(placeholder) {
+                        Ok(name_type) => name_type,
+                        Err(_) if keep_unknown => VariableNameType::Reserved,
+                        Err(err) => return Err(err),
+                    };
+
+                    match name_type {
                         VariableNameType::Reserved => {
                             result.push_str("${");
                             result.push_str(placeholder);
@@ -64,6 +91,13 @@ impl VariablesCompiled {
                                 result.push_str(value.as_str());
                                 continue;
                             }
+
+                            if keep_unknown {
+                                result.push_str("${");
+                                result.push_str(placeholder);
+                                result.push('}');
+                                continue;
+                            }
                         }
                     }
 
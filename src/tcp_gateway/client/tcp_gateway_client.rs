@@ -25,6 +25,7 @@ impl TcpGatewayClient {
         connect_timeout: Duration,
         debug: bool,
         sync_ssl_certificates: Vec<String>,
+        file_roots: GatewayFileRoots,
     ) -> Self {
         let inner = Arc::new(TcpGatewayInner::new_client(
             id,
@@ -33,6 +34,7 @@ impl TcpGatewayClient {
             allow_incoming_forward_connections,
             sync_ssl_certificates,
             compress_outbound,
+            file_roots,
         ));
         let result = Self {
             inner: inner.clone(),
@@ -169,6 +171,8 @@ async fn connection_loop(inner: Arc<TcpGatewayInner>, connect_timeout: Duration,
             ),
         );
 
+        gateway_connection.probe_file_streams();
+
         let sync_ids: Vec<&str> = inner
             .sync_ssl_certificates
             .iter()
@@ -16,14 +16,32 @@ const GET_FILE_RESPONSE_PACKET_ID: u8 = 10;
 const SYNC_SSL_CERTIFICATES_PACKET_ID: u8 = 11;
 const SYNC_SSL_CERTIFICATES_REQUEST_PACKET_ID: u8 = 12;
 const SYNC_SSL_CERTIFICATE_NOT_FOUND_PACKET_ID: u8 = 13;
+const FILE_STREAM_REQUEST_PACKET_ID: u8 = 14;
+const FILE_STREAM_HEAD_PACKET_ID: u8 = 15;
+const FILE_STREAM_CHUNK_PACKET_ID: u8 = 16;
+const FILE_STREAM_END_PACKET_ID: u8 = 17;
+const FILE_STREAM_ERROR_PACKET_ID: u8 = 18;
+const FILE_STREAM_CANCEL_PACKET_ID: u8 = 19;
 pub const COMPRESSED_BATCH_PACKET_ID: u8 = 20;
 
 pub const COMPRESSION_ALGO_ZSTD: u8 = 0;
 
-#[derive(Debug)]
+/// The path of the `GetFileRequest` each side sends right after the handshake.
+/// A peer that streams files answers it `Ok`; one that predates file streams
+/// tries to read it, and no file has a NUL in its path, so it answers `Error`.
+pub const FILE_STREAM_PROBE_PATH: &str = "\0file_stream";
+/// The request id of that probe — one no file request is given.
+pub const FILE_STREAM_PROBE_REQUEST_ID: u32 = 0;
+
+/// What the path of a file stream is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GetFileStatus {
     Ok,
+    /// Not found, or not readable.
     Error,
+    /// The path is a directory; the content is its entries, one
+    /// `<d|f>\t<size>\t<modified>\t<name>` line each.
+    Directory,
 }
 
 impl GetFileStatus {
@@ -31,12 +49,14 @@ impl GetFileStatus {
         match self {
             Self::Ok => 0,
             Self::Error => 1,
+            Self::Directory => 2,
         }
     }
 
     pub fn from_u8(value: u8) -> Self {
         match value {
             0 => Self::Ok,
+            2 => Self::Directory,
             _ => Self::Error,
         }
     }
@@ -69,6 +89,9 @@ pub enum TcpGatewayContract<'s> {
     UpdatePingTime {
         duration: Duration,
     },
+    /// Asks for the whole content of `path`, answered in one
+    /// `GetFileResponse`: how a peer that predates file streams asks, and how
+    /// it is asked.
     GetFileRequest {
         path: &'s str,
         request_id: u32,
@@ -78,6 +101,39 @@ pub enum TcpGatewayContract<'s> {
         status: GetFileStatus,
         content: SliceOrVec<'s, u8>,
     },
+    /// Asks for `length` bytes of the content of `path` from `offset` on.
+    /// The answer is a `FileStreamHead`, then the bytes in `FileStreamChunk`s,
+    /// then `FileStreamEnd` — or `FileStreamError` when the content can not
+    /// be read on.
+    FileStreamRequest {
+        request_id: u32,
+        offset: u64,
+        length: u64,
+        path: &'s str,
+    },
+    /// The size of the whole content and — seconds since the unix epoch — when
+    /// it was modified.
+    FileStreamHead {
+        request_id: u32,
+        status: GetFileStatus,
+        size: u64,
+        modified: Option<u64>,
+    },
+    FileStreamChunk {
+        request_id: u32,
+        payload: SliceOrVec<'s, u8>,
+    },
+    FileStreamEnd {
+        request_id: u32,
+    },
+    FileStreamError {
+        request_id: u32,
+        error: &'s str,
+    },
+    /// The requester needs no more of the stream.
+    FileStreamCancel {
+        request_id: u32,
+    },
     SyncSslCertificates {
         cert_id: &'s str,
         cert_pem: SliceOrVec<'s, u8>,
@@ -170,29 +226,81 @@ impl<'s> TcpGatewayContract<'s> {
             }
 
             GET_FILE_REQUEST_PACKET_ID => {
-                let request_id =
-                    u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]);
-
+                let request_id = read_u32(payload, 0)?;
                 let path = convert_to_string(&payload[4..], "GET_FILE_REQUEST")?;
 
                 return Ok(Self::GetFileRequest { path, request_id });
             }
 
             GET_FILE_RESPONSE_PACKET_ID => {
-                let request_id =
-                    u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                let request_id = read_u32(payload, 0)?;
+                let Some(status) = payload.get(4) else {
+                    return Err("GET_FILE_RESPONSE: truncated status".to_string());
+                };
+
+                return Ok(Self::GetFileResponse {
+                    request_id,
+                    status: GetFileStatus::from_u8(*status),
+                    content: (&payload[5..]).into(),
+                });
+            }
 
-                let status = GetFileStatus::from_u8(payload[4]);
+            FILE_STREAM_REQUEST_PACKET_ID => {
+                let request_id = read_u32(payload, 0)?;
+                let offset = read_u64(payload, 4)?;
+                let length = read_u64(payload, 12)?;
+                let path = convert_to_string(&payload[20..], "FILE_STREAM_REQUEST")?;
+
+                return Ok(Self::FileStreamRequest {
+                    request_id,
+                    offset,
+                    length,
+                    path,
+                });
+            }
 
-                let content: SliceOrVec<'_, u8> = (&payload[5..]).into();
+            FILE_STREAM_HEAD_PACKET_ID => {
+                let request_id = read_u32(payload, 0)?;
+                let Some(status) = payload.get(4) else {
+                    return Err("FILE_STREAM_HEAD: truncated status".to_string());
+                };
+                let status = GetFileStatus::from_u8(*status);
+                let size = read_u64(payload, 5)?;
+                let modified = read_u64(payload, 13)?;
 
-                return Ok(Self::GetFileResponse {
+                return Ok(Self::FileStreamHead {
                     request_id,
                     status,
-                    content,
+                    size,
+                    modified: if modified == 0 { None } else { Some(modified) },
                 });
             }
 
+            FILE_STREAM_CHUNK_PACKET_ID => {
+                let request_id = read_u32(payload, 0)?;
+
+                return Ok(Self::FileStreamChunk {
+                    request_id,
+                    payload: (&payload[4..]).into(),
+                });
+            }
+
+            FILE_STREAM_END_PACKET_ID => {
+                let request_id = read_u32(payload, 0)?;
+                return Ok(Self::FileStreamEnd { request_id });
+            }
+
+            FILE_STREAM_ERROR_PACKET_ID => {
+                let request_id = read_u32(payload, 0)?;
+                let error = convert_to_string(&payload[4..], "FILE_STREAM_ERROR")?;
+                return Ok(Self::FileStreamError { request_id, error });
+            }
+
+            FILE_STREAM_CANCEL_PACKET_ID => {
+                let request_id = read_u32(payload, 0)?;
+                return Ok(Self::FileStreamCancel { request_id });
+            }
+
             SYNC_SSL_CERTIFICATE_NOT_FOUND_PACKET_ID => {
                 let mut offset = 0usize;
                 let id_len = read_u32(payload, offset)? as usize;
@@ -344,7 +452,7 @@ impl<'s> TcpGatewayContract<'s> {
             }
             Self::GetFileRequest { path, request_id } => {
                 body.push(GET_FILE_REQUEST_PACKET_ID);
-                body.extend_from_slice(request_id.to_le_bytes().as_slice());
+                push_u32(&mut body, *request_id);
                 body.extend_from_slice(path.as_bytes());
             }
             Self::GetFileResponse {
@@ -353,10 +461,55 @@ impl<'s> TcpGatewayContract<'s> {
                 content,
             } => {
                 body.push(GET_FILE_RESPONSE_PACKET_ID);
-                body.extend_from_slice(request_id.to_le_bytes().as_slice());
+                push_u32(&mut body, *request_id);
                 body.push(status.as_u8());
                 body.extend_from_slice(content.as_slice());
             }
+            Self::FileStreamRequest {
+                request_id,
+                offset,
+                length,
+                path,
+            } => {
+                body.push(FILE_STREAM_REQUEST_PACKET_ID);
+                push_u32(&mut body, *request_id);
+                body.extend_from_slice(offset.to_le_bytes().as_slice());
+                body.extend_from_slice(length.to_le_bytes().as_slice());
+                body.extend_from_slice(path.as_bytes());
+            }
+            Self::FileStreamHead {
+                request_id,
+                status,
+                size,
+                modified,
+            } => {
+                body.push(FILE_STREAM_HEAD_PACKET_ID);
+                push_u32(&mut body, *request_id);
+                body.push(status.as_u8());
+                body.extend_from_slice(size.to_le_bytes().as_slice());
+                body.extend_from_slice(modified.unwrap_or_default().to_le_bytes().as_slice());
+            }
+            Self::FileStreamChunk {
+                request_id,
+                payload,
+            } => {
+                body.push(FILE_STREAM_CHUNK_PACKET_ID);
+                push_u32(&mut body, *request_id);
+                body.extend_from_slice(payload.as_slice());
+            }
+            Self::FileStreamEnd { request_id } => {
+                body.push(FILE_STREAM_END_PACKET_ID);
+                push_u32(&mut body, *request_id);
+            }
+            Self::FileStreamError { request_id, error } => {
+                body.push(FILE_STREAM_ERROR_PACKET_ID);
+                push_u32(&mut body, *request_id);
+                body.extend_from_slice(error.as_bytes());
+            }
+            Self::FileStreamCancel { request_id } => {
+                body.push(FILE_STREAM_CANCEL_PACKET_ID);
+                push_u32(&mut body, *request_id);
+            }
             Self::SyncSslCertificates {
                 cert_id,
                 cert_pem,
@@ -435,6 +588,14 @@ fn read_u32(payload: &[u8], offset: usize) -> Result<u32, String> {
     ]))
 }
 
+fn read_u64(payload: &[u8], offset: usize) -> Result<u64, String> {
+    let Some(bytes) = payload.get(offset..offset + 8) else {
+        return Err("truncated u64".to_string());
+    };
+
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
 fn convert_to_string<'s>(payload: &'s [u8], packet_type: &str) -> Result<&'s str, String> {
     if payload.is_empty() {
         return Ok("");
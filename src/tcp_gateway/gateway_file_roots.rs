@@ -0,0 +1,108 @@
+use std::path::{Component, Path, PathBuf};
+
+/// A root directory files may be served from to the peer.
+struct GatewayFileRoot {
+    /// As it is configured, `~` expanded.
+    configured: PathBuf,
+    /// With every symlink resolved: what a requested path must be under.
+    canonical: PathBuf,
+}
+
+pub enum GatewayFileAccess {
+    /// The path, canonicalised: the one to open.
+    Allowed(PathBuf),
+    /// Under a root, but there is nothing there.
+    NotFound,
+    Denied(&'static str),
+}
+
+/// The directories a gateway serves files from to its peer. Without any, it
+/// serves none.
+pub struct GatewayFileRoots {
+    roots: Vec<GatewayFileRoot>,
+}
+
+impl GatewayFileRoots {
+    pub fn new(file_roots: &[String]) -> Result<Self, String> {
+        let mut roots = Vec::with_capacity(file_roots.len());
+
+        for root in file_roots {
+            let configured =
+                PathBuf::from(rust_extensions::file_utils::format_path(root).to_string());
+
+            let canonical = std::fs::canonicalize(&configured).map_err(|err| {
+                format!(
+                    "Gateway file_roots: cannot resolve '{}': {}",
+                    configured.display(),
+                    err
+                )
+            })?;
+
+            if !canonical.is_dir() {
+                return Err(format!(
+                    "Gateway file_roots: '{}' is not a directory",
+                    configured.display()
+                ));
+            }
+
+            roots.push(GatewayFileRoot {
+                configured,
+                canonical,
+            });
+        }
+
+        Ok(Self { roots })
+    }
+
+    /// No roots are configured: every file the peer asks for is denied.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Whether the peer may read `path`. A path with `..`, or one a symlink
+    /// takes out of every root, is denied.
+    pub async fn check(&self, path: &str) -> GatewayFileAccess {
+        if self.roots.is_empty() {
+            return GatewayFileAccess::Denied("no file_roots are configured");
+        }
+
+        let path = Path::new(path);
+
+        if !path.is_absolute() {
+            return GatewayFileAccess::Denied("path is not absolute");
+        }
+
+        if path
+            .components()
+            .any(|itm| matches!(itm, Component::ParentDir))
+        {
+            return GatewayFileAccess::Denied("path has '..'");
+        }
+
+        let canonical = match tokio::fs::canonicalize(path).await {
+            Ok(canonical) => canonical,
+            Err(_) => {
+                // Nothing to escape through: a missing file under a root is
+                // just missing — a precompressed sibling looked for, say.
+                let under_root = self.roots.iter().any(|root| {
+                    path.starts_with(&root.configured) || path.starts_with(&root.canonical)
+                });
+
+                return match under_root {
+                    true => GatewayFileAccess::NotFound,
+                    false => GatewayFileAccess::Denied("path is outside of file_roots"),
+                };
+            }
+        };
+
+        if self
+            .roots
+            .iter()
+            .any(|root| canonical.starts_with(&root.canonical))
+        {
+            return GatewayFileAccess::Allowed(canonical);
+        }
+
+        GatewayFileAccess::Denied("path resolves outside of file_roots")
+    }
+}
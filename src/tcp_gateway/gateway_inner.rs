@@ -6,7 +6,7 @@ use std::{
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use parking_lot::Mutex;
 
-use super::TcpGatewayConnection;
+use super::{GatewayFileRoots, TcpGatewayConnection};
 
 pub struct TcpGatewayInner {
     pub gateway_id: Arc<String>,
@@ -18,6 +18,8 @@ pub struct TcpGatewayInner {
     pub authorized_keys: Option<Arc<Vec<VerifyingKey>>>,
     pub signing_key: Option<Arc<SigningKey>>,
     pub compress_outbound: bool,
+    /// What of this side's files the peer may read.
+    pub file_roots: Arc<GatewayFileRoots>,
 }
 
 impl TcpGatewayInner {
@@ -26,6 +28,7 @@ impl TcpGatewayInner {
         addr: String,
         authorized_keys: Vec<VerifyingKey>,
         compress_outbound: bool,
+        file_roots: GatewayFileRoots,
     ) -> Self {
         Self {
             gateway_id: Arc::new(gateway_id),
@@ -37,6 +40,7 @@ impl TcpGatewayInner {
             authorized_keys: Some(Arc::new(authorized_keys)),
             signing_key: None,
             compress_outbound,
+            file_roots: Arc::new(file_roots),
         }
     }
 
@@ -47,6 +51,7 @@ impl TcpGatewayInner {
         allow_incoming_forward_connections: bool,
         sync_ssl_certificates: Vec<String>,
         compress_outbound: bool,
+        file_roots: GatewayFileRoots,
     ) -> Self {
         Self {
             gateway_id: Arc::new(gateway_id),
@@ -58,6 +63,7 @@ impl TcpGatewayInner {
             authorized_keys: None,
             signing_key: Some(Arc::new(signing_key)),
             compress_outbound,
+            file_roots: Arc::new(file_roots),
         }
     }
 
@@ -10,6 +10,8 @@ pub mod server;
 pub use gateway_read_loop::*;
 mod gateway_inner;
 pub use gateway_inner::*;
+mod gateway_file_roots;
+pub use gateway_file_roots::*;
 pub mod forwarded_connection;
 pub mod scripts;
 mod tcp_gateway_connection;
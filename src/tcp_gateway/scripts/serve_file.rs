@@ -1,39 +1,255 @@
-use std::sync::Arc;
+use std::{
+    io::SeekFrom,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::UNIX_EPOCH,
+};
 
 use rust_extensions::{file_utils::FilePath, SliceOrVec};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
-use crate::tcp_gateway::{GetFileStatus, TcpGatewayConnection};
+use crate::tcp_gateway::{
+    GatewayFileAccess, GatewayFileRoots, GetFileStatus, TcpGatewayConnection, TcpGatewayContract,
+    FILE_STREAM_PROBE_PATH,
+};
 
-pub async fn serve_file(
+/// Streams `length` bytes of `path` from `offset` on — of a file, or of the
+/// entries of a directory — until the peer cancels it. A path outside of
+/// `file_roots` is answered as one that can not be read.
+pub fn serve_file(
     request_id: u32,
     path: String,
+    offset: u64,
+    length: u64,
     gateway_connection: Arc<TcpGatewayConnection>,
+    file_roots: Arc<GatewayFileRoots>,
 ) {
     crate::app::spawn_named("tcp_gateway_serve_file", async move {
-        let path = FilePath::from_str(path.as_str());
+        let cancelled = gateway_connection.file_requests.start_serving(request_id);
 
-        let payload = match tokio::fs::read(path.as_str()).await {
-            Ok(content) => {
-                let response = crate::tcp_gateway::TcpGatewayContract::GetFileResponse {
-                    request_id,
-                    status: GetFileStatus::Ok,
-                    content: SliceOrVec::AsVec(content),
-                };
+        let stream = FileStream {
+            request_id,
+            gateway_connection: &gateway_connection,
+            cancelled: &cancelled,
+        };
 
-                response
+        let path = FilePath::from_str(path.as_str());
+        match file_roots.check(path.as_str()).await {
+            GatewayFileAccess::Allowed(path) => {
+                stream
+                    .send(path.to_string_lossy().as_ref(), offset, length)
+                    .await;
+            }
+            GatewayFileAccess::NotFound => {
+                stream.send_head(GetFileStatus::Error, 0, None);
             }
+            GatewayFileAccess::Denied(reason) => {
+                report_denied(&gateway_connection, path.as_str(), reason);
+                stream.send_head(GetFileStatus::Error, 0, None);
+            }
+        }
+
+        gateway_connection.file_requests.stop_serving(request_id);
+    });
+}
 
-            Err(_) => {
-                let response = crate::tcp_gateway::TcpGatewayContract::GetFileResponse {
-                    request_id,
-                    status: GetFileStatus::Error,
-                    content: SliceOrVec::AsSlice(&[]),
-                };
+/// Answers a peer that predates file streams with the whole content of `path`
+/// in one frame, under the same `file_roots` as a stream — and answers the
+/// file stream probe of a peer that does not.
+pub fn serve_whole_file(
+    request_id: u32,
+    path: String,
+    gateway_connection: Arc<TcpGatewayConnection>,
+    file_roots: Arc<GatewayFileRoots>,
+) {
+    if path == FILE_STREAM_PROBE_PATH {
+        gateway_connection.send_payload(&TcpGatewayContract::GetFileResponse {
+            request_id,
+            status: GetFileStatus::Ok,
+            content: SliceOrVec::AsSlice(&[]),
+        });
+        return;
+    }
+
+    crate::app::spawn_named("tcp_gateway_serve_whole_file", async move {
+        let path = FilePath::from_str(path.as_str());
 
-                response
+        let (status, content) = match file_roots.check(path.as_str()).await {
+            GatewayFileAccess::Allowed(path) => read_whole(path.to_string_lossy().as_ref()).await,
+            GatewayFileAccess::NotFound => (GetFileStatus::Error, Vec::new()),
+            GatewayFileAccess::Denied(reason) => {
+                report_denied(&gateway_connection, path.as_str(), reason);
+                (GetFileStatus::Error, Vec::new())
             }
         };
 
-        gateway_connection.send_payload(&payload);
+        gateway_connection.send_payload(&TcpGatewayContract::GetFileResponse {
+            request_id,
+            status,
+            content: SliceOrVec::AsVec(content),
+        });
     });
 }
+
+async fn read_whole(path: &str) -> (GetFileStatus, Vec<u8>) {
+    let is_dir = tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|itm| itm.is_dir());
+
+    if is_dir {
+        return match crate::http_content_source::read_local_dir(path).await {
+            Ok(entries) => (
+                GetFileStatus::Directory,
+                crate::http_content_source::serialize_dir_entries(&entries),
+            ),
+            Err(_) => (GetFileStatus::Error, Vec::new()),
+        };
+    }
+
+    match tokio::fs::read(path).await {
+        Ok(content) => (GetFileStatus::Ok, content),
+        Err(_) => (GetFileStatus::Error, Vec::new()),
+    }
+}
+
+fn report_denied(gateway_connection: &TcpGatewayConnection, path: &str, reason: &str) {
+    let gateway_id = gateway_connection.get_gateway_id();
+    println!(
+        "Gateway:[{}] denied file request '{}': {}",
+        gateway_id.as_str(),
+        path,
+        reason
+    );
+    crate::app::APP_CTX
+        .prometheus
+        .inc_gateway_file_requests_denied(gateway_id.as_str());
+}
+
+struct FileStream<'s> {
+    request_id: u32,
+    gateway_connection: &'s TcpGatewayConnection,
+    cancelled: &'s AtomicBool,
+}
+
+impl<'s> FileStream<'s> {
+    async fn send(&self, path: &str, offset: u64, length: u64) {
+        let Ok(metadata) = tokio::fs::metadata(path).await else {
+            self.send_head(GetFileStatus::Error, 0, None);
+            return;
+        };
+
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|itm| itm.duration_since(UNIX_EPOCH).ok())
+            .map(|itm| itm.as_secs());
+
+        if metadata.is_dir() {
+            let entries = match crate::http_content_source::read_local_dir(path).await {
+                Ok(entries) => crate::http_content_source::serialize_dir_entries(&entries),
+                Err(_) => {
+                    self.send_head(GetFileStatus::Error, 0, None);
+                    return;
+                }
+            };
+
+            if !self.send_head(GetFileStatus::Directory, entries.len() as u64, modified) {
+                return;
+            }
+
+            let start = (offset.min(entries.len() as u64)) as usize;
+            let end = start + (length.min((entries.len() - start) as u64)) as usize;
+
+            for chunk in entries[start..end].chunks(crate::consts::GATEWAY_FILE_CHUNK_SIZE) {
+                if !self.send_chunk(chunk) {
+                    return;
+                }
+            }
+
+            self.send_end();
+            return;
+        }
+
+        if !metadata.is_file() {
+            self.send_head(GetFileStatus::Error, 0, None);
+            return;
+        }
+
+        let Ok(mut file) = tokio::fs::File::open(path).await else {
+            self.send_head(GetFileStatus::Error, 0, None);
+            return;
+        };
+
+        if !self.send_head(GetFileStatus::Ok, metadata.len(), modified) {
+            return;
+        }
+
+        let mut remaining = length.min(metadata.len().saturating_sub(offset));
+
+        if remaining > 0 {
+            if let Err(err) = file.seek(SeekFrom::Start(offset)).await {
+                self.send_error(&err);
+                return;
+            }
+        }
+
+        while remaining > 0 {
+            let size = remaining.min(crate::consts::GATEWAY_FILE_CHUNK_SIZE as u64);
+            let mut chunk = vec![0u8; size as usize];
+
+            if let Err(err) = file.read_exact(&mut chunk).await {
+                self.send_error(&err);
+                return;
+            }
+
+            if !self.send_chunk(&chunk) {
+                return;
+            }
+
+            remaining -= size;
+        }
+
+        self.send_end();
+    }
+
+    fn send_head(&self, status: GetFileStatus, size: u64, modified: Option<u64>) -> bool {
+        self.gateway_connection
+            .send_payload(&TcpGatewayContract::FileStreamHead {
+                request_id: self.request_id,
+                status,
+                size,
+                modified,
+            })
+    }
+
+    /// `false` once the stream is cancelled or the gateway is gone.
+    fn send_chunk(&self, chunk: &[u8]) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        self.gateway_connection
+            .send_payload(&TcpGatewayContract::FileStreamChunk {
+                request_id: self.request_id,
+                payload: chunk.into(),
+            })
+    }
+
+    fn send_end(&self) {
+        self.gateway_connection
+            .send_payload(&TcpGatewayContract::FileStreamEnd {
+                request_id: self.request_id,
+            });
+    }
+
+    fn send_error(&self, err: &std::io::Error) {
+        let error = format!("{}", err);
+        self.gateway_connection
+            .send_payload(&TcpGatewayContract::FileStreamError {
+                request_id: self.request_id,
+                error: error.as_str(),
+            });
+    }
+}
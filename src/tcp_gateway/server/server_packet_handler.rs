@@ -11,7 +11,7 @@ impl TcpGatewayPacketHandler for TcpGatewayServerPacketHandler {
     async fn handle_packet<'d>(
         &self,
         contract: TcpGatewayContract<'d>,
-        _tcp_gateway: &Arc<TcpGatewayInner>,
+        tcp_gateway: &Arc<TcpGatewayInner>,
         gateway_connection: &Arc<TcpGatewayConnection>,
     ) -> Result<(), String> {
         match contract {
@@ -82,22 +82,65 @@ impl TcpGatewayPacketHandler for TcpGatewayServerPacketHandler {
             TcpGatewayContract::UpdatePingTime { duration } => {
                 gateway_connection.last_ping_duration.update(duration);
             }
-            TcpGatewayContract::GetFileRequest { path, request_id } => {
+            TcpGatewayContract::FileStreamRequest {
+                request_id,
+                offset,
+                length,
+                path,
+            } => {
                 crate::tcp_gateway::scripts::serve_file(
                     request_id,
                     path.to_string(),
+                    offset,
+                    length,
                     gateway_connection.clone(),
-                )
-                .await
+                    tcp_gateway.file_roots.clone(),
+                );
+            }
+            TcpGatewayContract::GetFileRequest { path, request_id } => {
+                crate::tcp_gateway::scripts::serve_whole_file(
+                    request_id,
+                    path.to_string(),
+                    gateway_connection.clone(),
+                    tcp_gateway.file_roots.clone(),
+                );
             }
             TcpGatewayContract::GetFileResponse {
                 request_id,
                 status,
                 content,
             } => {
-                gateway_connection
-                    .notify_file_response(request_id, status, content)
-                    .await;
+                gateway_connection.notify_file_response(request_id, status, content.into_vec());
+            }
+            TcpGatewayContract::FileStreamCancel { request_id } => {
+                gateway_connection.file_requests.cancel_serving(request_id);
+            }
+            TcpGatewayContract::FileStreamHead {
+                request_id,
+                status,
+                size,
+                modified,
+            } => {
+                let event = FileStreamEvent::Head {
+                    status,
+                    size,
+                    modified,
+                };
+                gateway_connection.notify_file_stream(request_id, event);
+            }
+            TcpGatewayContract::FileStreamChunk {
+                request_id,
+                payload,
+            } => {
+                let event = FileStreamEvent::Chunk(payload.into_vec());
+                gateway_connection.notify_file_stream(request_id, event);
+            }
+            TcpGatewayContract::FileStreamEnd { request_id } => {
+                gateway_connection.notify_file_stream(request_id, FileStreamEvent::End);
+            }
+            TcpGatewayContract::FileStreamError { request_id, error } => {
+                let event = FileStreamEvent::Error(error.to_string());
+                gateway_connection.notify_file_stream(request_id, event);
             }
             TcpGatewayContract::SyncSslCertificatesRequest { cert_ids } => {
                 let requested: Vec<String> = cert_ids.iter().map(|s| s.to_string()).collect();
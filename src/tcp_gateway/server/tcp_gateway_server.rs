@@ -19,6 +19,7 @@ impl TcpGatewayServer {
         listen: String,
         authorized_keys: Vec<VerifyingKey>,
         compress_outbound: bool,
+        file_roots: GatewayFileRoots,
         debug: bool,
     ) -> Self {
         println!("Starting TCP Gateway Server at address: {}", listen);
@@ -27,6 +28,7 @@ impl TcpGatewayServer {
             listen,
             authorized_keys,
             compress_outbound,
+            file_roots,
         ));
         let result = Self {
             inner: inner.clone(),
@@ -172,6 +174,8 @@ async fn handle_inbound(tcp_gateway: Arc<TcpGatewayInner>, mut stream: TcpStream
         Some(gateway_connection.clone()),
     );
 
+    gateway_connection.probe_file_streams();
+
     crate::app::spawn_named(
         "tcp_gateway_server_read_loop",
         crate::tcp_gateway::gateway_read_loop(
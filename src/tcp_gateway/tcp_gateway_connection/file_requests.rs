@@ -1,17 +1,73 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use parking_lot::Mutex;
-use rust_extensions::{TaskCompletion, TaskCompletionAwaiter};
+use tokio::sync::mpsc;
 
+use crate::tcp_gateway::{GetFileStatus, TcpGatewayConnection, TcpGatewayContract};
+
+#[derive(Debug)]
 pub enum FileRequestError {
-    FileNotFound,
     GatewayDisconnected,
+    /// The gateway sent nothing of the stream for
+    /// [`crate::consts::GATEWAY_FILE_STREAM_TIMEOUT`].
+    Timeout,
+    /// The gateway could not read the content on.
+    Error(String),
+}
+
+impl From<FileRequestError> for std::io::Error {
+    fn from(value: FileRequestError) -> Self {
+        match value {
+            FileRequestError::GatewayDisconnected => std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "Gateway disconnected",
+            ),
+            FileRequestError::Timeout => std::io::Error::from(std::io::ErrorKind::TimedOut),
+            FileRequestError::Error(err) => std::io::Error::other(err),
+        }
+    }
+}
+
+/// What comes of a file stream, in the order it comes. A head with the
+/// `Error` status is all there is of it.
+#[derive(Debug)]
+pub enum FileStreamEvent {
+    Head {
+        status: GetFileStatus,
+        size: u64,
+        modified: Option<u64>,
+    },
+    Chunk(Vec<u8>),
+    End,
+    Error(String),
+}
+
+impl FileStreamEvent {
+    fn is_last(&self) -> bool {
+        match self {
+            Self::Head { status, .. } => *status == GetFileStatus::Error,
+            Self::Chunk(_) => false,
+            Self::End | Self::Error(_) => true,
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct FileRequestsInner {
-    requests: HashMap<u32, TaskCompletion<Vec<u8>, FileRequestError>>,
+    /// The streams this side asked the peer for.
+    requests: HashMap<u32, mpsc::UnboundedSender<FileStreamEvent>>,
+    /// Of those, the ones asked of a peer that predates file streams: the part
+    /// of the whole content it answers with that each one wants.
+    whole_file: HashMap<u32, (u64, u64)>,
     next_request_id: u32,
+    /// The streams the peer asked this side for — set once it cancels one.
+    serving: HashMap<u32, Arc<AtomicBool>>,
 }
 
 impl FileRequestsInner {
@@ -32,36 +88,179 @@ impl FileRequests {
         }
     }
 
-    pub fn start_request(&self) -> (TaskCompletionAwaiter<Vec<u8>, FileRequestError>, u32) {
-        let mut task_completion = TaskCompletion::new();
-        task_completion.set_drop_error(FileRequestError::GatewayDisconnected);
-        let awaiter = task_completion.get_awaiter();
+    pub fn start_request(&self) -> (mpsc::UnboundedReceiver<FileStreamEvent>, u32) {
+        let (sender, receiver) = mpsc::unbounded_channel();
         let mut write_access = self.inner.lock();
         let request_id = write_access.get_next_request_id();
-        write_access.requests.insert(request_id, task_completion);
+        write_access.requests.insert(request_id, sender);
 
-        (awaiter, request_id)
+        (receiver, request_id)
     }
 
-    pub fn set_content(&self, request_id: u32, content: Vec<u8>) {
-        let item = {
-            let mut write_access = self.inner.lock();
-            write_access.requests.remove(&request_id)
+    /// A request answered with the whole content in one frame, of which only
+    /// `length` bytes from `offset` on are wanted.
+    pub fn expect_whole_file(&self, request_id: u32, offset: u64, length: u64) {
+        self.inner
+            .lock()
+            .whole_file
+            .insert(request_id, (offset, length));
+    }
+
+    /// Replays a whole content answer as the stream that was asked for.
+    pub fn notify_whole_file(&self, request_id: u32, status: GetFileStatus, content: Vec<u8>) {
+        let Some((offset, length)) = self.inner.lock().whole_file.remove(&request_id) else {
+            return;
+        };
+
+        let size = match status {
+            GetFileStatus::Error => 0,
+            _ => content.len() as u64,
         };
 
-        if let Some(mut item) = item {
-            let _ = item.try_set_ok(content);
+        let head = FileStreamEvent::Head {
+            status,
+            size,
+            modified: None,
+        };
+        self.notify(request_id, head);
+
+        if status == GetFileStatus::Error {
+            return;
+        }
+
+        let start = offset.min(size);
+        let end = start + length.min(size - start);
+        if end > start {
+            let chunk = content[start as usize..end as usize].to_vec();
+            self.notify(request_id, FileStreamEvent::Chunk(chunk));
         }
+
+        self.notify(request_id, FileStreamEvent::End);
     }
 
-    pub fn set_error(&self, request_id: u32, error: FileRequestError) {
-        let item = {
-            let mut write_access = self.inner.lock();
-            write_access.requests.remove(&request_id)
+    pub fn notify(&self, request_id: u32, event: FileStreamEvent) {
+        let mut write_access = self.inner.lock();
+
+        let sender = match event.is_last() {
+            true => write_access.requests.remove(&request_id),
+            false => write_access.requests.get(&request_id).cloned(),
         };
 
-        if let Some(mut item) = item {
-            let _ = item.try_set_error(error);
+        if let Some(sender) = sender {
+            if sender.send(event).is_err() {
+                write_access.requests.remove(&request_id);
+            }
+        }
+    }
+
+    pub fn remove_request(&self, request_id: u32) {
+        let mut write_access = self.inner.lock();
+        write_access.requests.remove(&request_id);
+        write_access.whole_file.remove(&request_id);
+    }
+
+    /// Every stream asked for ends with [`FileRequestError::GatewayDisconnected`].
+    pub fn disconnect(&self) {
+        let mut write_access = self.inner.lock();
+        write_access.requests.clear();
+        write_access.whole_file.clear();
+    }
+
+    /// The flag a stream being served stops at.
+    pub fn start_serving(&self, request_id: u32) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.inner
+            .lock()
+            .serving
+            .insert(request_id, cancelled.clone());
+        cancelled
+    }
+
+    pub fn cancel_serving(&self, request_id: u32) {
+        if let Some(cancelled) = self.inner.lock().serving.remove(&request_id) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stop_serving(&self, request_id: u32) {
+        self.inner.lock().serving.remove(&request_id);
+    }
+}
+
+/// A file stream asked of the peer. Dropped before its end, it is cancelled.
+pub struct FileStreamReceiver {
+    request_id: u32,
+    connection: Arc<TcpGatewayConnection>,
+    receiver: mpsc::UnboundedReceiver<FileStreamEvent>,
+    finished: bool,
+}
+
+impl FileStreamReceiver {
+    pub fn new(
+        request_id: u32,
+        connection: Arc<TcpGatewayConnection>,
+        receiver: mpsc::UnboundedReceiver<FileStreamEvent>,
+    ) -> Self {
+        Self {
+            request_id,
+            connection,
+            receiver,
+            finished: false,
+        }
+    }
+
+    pub async fn recv(&mut self) -> Result<FileStreamEvent, FileRequestError> {
+        let event = tokio::time::timeout(
+            crate::consts::GATEWAY_FILE_STREAM_TIMEOUT,
+            self.receiver.recv(),
+        )
+        .await;
+
+        match event {
+            Ok(Some(event)) => {
+                self.finished = event.is_last();
+                Ok(event)
+            }
+            Ok(None) => {
+                self.finished = true;
+                Err(FileRequestError::GatewayDisconnected)
+            }
+            Err(_) => Err(FileRequestError::Timeout),
+        }
+    }
+
+    /// The rest of the content, past its head: what a directory's entries
+    /// come in.
+    pub async fn read_to_end(&mut self) -> Result<Vec<u8>, FileRequestError> {
+        let mut result = Vec::new();
+
+        loop {
+            match self.recv().await? {
+                FileStreamEvent::Head { .. } => {}
+                FileStreamEvent::Chunk(chunk) => result.extend_from_slice(&chunk),
+                FileStreamEvent::End => return Ok(result),
+                FileStreamEvent::Error(err) => return Err(FileRequestError::Error(err)),
+            }
+        }
+    }
+}
+
+impl Drop for FileStreamReceiver {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        self.connection
+            .file_requests
+            .remove_request(self.request_id);
+
+        // A peer that predates file streams would fail on the cancel.
+        if self.connection.peer_streams_files() {
+            self.connection
+                .send_payload(&TcpGatewayContract::FileStreamCancel {
+                    request_id: self.request_id,
+                });
         }
     }
 }
@@ -0,0 +1,164 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use super::{FileStreamEvent, FileStreamReceiver, TcpGatewayConnection};
+use crate::tcp_gateway::GetFileStatus;
+
+/// A window of the file asked for, and what of it is still to come.
+struct FileStreamWindow {
+    receiver: FileStreamReceiver,
+    remaining: u64,
+}
+
+/// Where a [`FileStreamReader`] is in the range it reads, and how much of the
+/// range is asked for.
+#[derive(Default)]
+struct FileStreamRange {
+    /// Where the next byte read is.
+    position: u64,
+    /// Where the windows asked for so far end.
+    requested_to: u64,
+    /// Where the range being read ends — exclusive.
+    end: u64,
+}
+
+impl FileStreamRange {
+    /// Reads on from `start` up to `end`, exclusive. `true` when that is not
+    /// the range read so far: what was asked for of it is of no use then.
+    fn start_read(&mut self, start: u64, end: u64) -> bool {
+        if start == self.position && end == self.end {
+            return false;
+        }
+
+        self.position = start;
+        self.requested_to = start;
+        self.end = end;
+        true
+    }
+
+    /// Where the next window to ask for starts, and its length — `None` once
+    /// the whole range is asked for.
+    fn next_window(&self) -> Option<(u64, u64)> {
+        if self.requested_to >= self.end {
+            return None;
+        }
+
+        let length = (self.end - self.requested_to).min(crate::consts::GATEWAY_FILE_WINDOW_SIZE);
+        Some((self.requested_to, length))
+    }
+}
+
+/// Reads a file on the other side of a gateway the way a local file is read:
+/// by ranges. A range is asked for
+/// [`crate::consts::GATEWAY_FILE_WINDOW_SIZE`] at a time, a few windows ahead;
+/// a read of another range drops what was asked for and starts over.
+pub struct FileStreamReader {
+    gateway_connection: Arc<TcpGatewayConnection>,
+    path: String,
+    range: FileStreamRange,
+    windows: VecDeque<FileStreamWindow>,
+    buffer: Vec<u8>,
+}
+
+impl FileStreamReader {
+    pub fn new(gateway_connection: Arc<TcpGatewayConnection>, path: String) -> Self {
+        Self {
+            gateway_connection,
+            path,
+            range: FileStreamRange::default(),
+            windows: VecDeque::new(),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// `size` bytes from `start` on, of a range that ends at `end` — included.
+    pub async fn read(&mut self, start: u64, size: u64, end: u64) -> std::io::Result<Vec<u8>> {
+        if self.range.start_read(start, end + 1) {
+            self.windows.clear();
+            self.buffer.clear();
+        }
+
+        while (self.buffer.len() as u64) < size {
+            while self.windows.len() < crate::consts::GATEWAY_FILE_WINDOWS_IN_FLIGHT {
+                let Some((offset, length)) = self.range.next_window() else {
+                    break;
+                };
+
+                let receiver = self
+                    .gateway_connection
+                    .start_file_stream(&self.path, offset, length)?;
+
+                self.windows.push_back(FileStreamWindow {
+                    receiver,
+                    remaining: length,
+                });
+                self.range.requested_to += length;
+            }
+
+            let Some(window) = self.windows.front_mut() else {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            };
+
+            match window.receiver.recv().await? {
+                FileStreamEvent::Head { status, .. } => {
+                    if status != GetFileStatus::Ok {
+                        return Err(std::io::ErrorKind::NotFound.into());
+                    }
+                }
+                FileStreamEvent::Chunk(chunk) => {
+                    if chunk.len() as u64 > window.remaining {
+                        return Err(std::io::Error::other("Gateway sent past the window"));
+                    }
+
+                    window.remaining -= chunk.len() as u64;
+                    self.buffer.extend_from_slice(&chunk);
+                }
+                FileStreamEvent::End => {
+                    // A file cut short while it is sent fails here.
+                    if window.remaining > 0 {
+                        return Err(std::io::ErrorKind::UnexpectedEof.into());
+                    }
+
+                    self.windows.pop_front();
+                }
+                FileStreamEvent::Error(err) => return Err(std::io::Error::other(err)),
+            }
+        }
+
+        self.range.position += size;
+        Ok(self.buffer.drain(..size as usize).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: u64 = crate::consts::GATEWAY_FILE_WINDOW_SIZE;
+
+    #[test]
+    fn a_range_read_on_keeps_its_windows() {
+        let mut range = FileStreamRange::default();
+        assert!(range.start_read(0, 3 * WINDOW));
+        assert_eq!(range.next_window(), Some((0, WINDOW)));
+        range.requested_to += WINDOW;
+
+        range.position += 1024;
+        assert!(!range.start_read(1024, 3 * WINDOW));
+        assert_eq!(range.next_window(), Some((WINDOW, WINDOW)));
+    }
+
+    /// `bytes=0-99,100-199`: the second range starts where the first one was
+    /// read to, but it is not the same range — it is asked for anew.
+    #[test]
+    fn an_adjacent_range_is_asked_for_anew() {
+        let mut range = FileStreamRange::default();
+        assert!(range.start_read(0, 100));
+        assert_eq!(range.next_window(), Some((0, 100)));
+        range.requested_to += 100;
+        range.position += 100;
+        assert_eq!(range.next_window(), None);
+
+        assert!(range.start_read(100, 200));
+        assert_eq!(range.next_window(), Some((100, 100)));
+    }
+}
@@ -2,5 +2,7 @@ mod tcp_gateway_connection;
 pub use tcp_gateway_connection::*;
 mod file_requests;
 pub use file_requests::*;
+mod file_stream_reader;
+pub use file_stream_reader::*;
 mod gateway_connection_metrics;
 pub use gateway_connection_metrics::*;
@@ -1,6 +1,9 @@
 use std::{
     collections::HashMap,
-    sync::{atomic::AtomicI64, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicI64},
+        Arc,
+    },
     time::Duration,
 };
 
@@ -11,7 +14,7 @@ use parking_lot::Mutex;
 use rust_extensions::{
     date_time::{AtomicDateTimeAsMicroseconds, DateTimeAsMicroseconds},
     remote_endpoint::RemoteEndpointOwned,
-    AtomicDuration, AtomicStopWatch, SliceOrVec,
+    AtomicDuration, AtomicStopWatch,
 };
 
 use crate::{metrics::PerSecondAccumulator, network_stream::MyOwnedWriteHalf};
@@ -29,6 +32,10 @@ pub struct TcpGatewayConnection {
     pub ping_stop_watch: AtomicStopWatch,
     pub last_ping_duration: AtomicDuration,
     pub file_requests: FileRequests,
+    /// The peer answered the file stream probe: it is asked for files in
+    /// streams. Until then — and for good with a peer that predates them —
+    /// a file is asked for whole.
+    peer_streams_files: AtomicBool,
     pub metrics: Mutex<GatewayConnectionMetrics>,
     pub in_per_second: PerSecondAccumulator,
     pub out_per_second: PerSecondAccumulator,
@@ -55,6 +62,7 @@ impl TcpGatewayConnection {
             ping_stop_watch: AtomicStopWatch::new(),
             last_ping_duration: AtomicDuration::from_micros(0),
             file_requests: FileRequests::new(),
+            peer_streams_files: AtomicBool::new(false),
             allow_incoming_forward_connection,
             metrics: Mutex::default(),
             in_per_second: PerSecondAccumulator::new(),
@@ -266,41 +274,78 @@ impl TcpGatewayConnection {
         }
     }
 
-    pub async fn request_file(&self, path: &str) -> Result<Vec<u8>, FileRequestError> {
+    /// Finds out whether the peer streams files; sent once the handshake is
+    /// done.
+    pub fn probe_file_streams(&self) {
+        self.send_payload(&TcpGatewayContract::GetFileRequest {
+            path: FILE_STREAM_PROBE_PATH,
+            request_id: FILE_STREAM_PROBE_REQUEST_ID,
+        });
+    }
+
+    pub fn peer_streams_files(&self) -> bool {
+        self.peer_streams_files
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Asks the peer for `length` bytes of `path` from `offset` on. A peer
+    /// that predates file streams sends the whole content, and the part of it
+    /// asked for is what is received.
+    pub fn start_file_stream(
+        self: &Arc<Self>,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<FileStreamReceiver, FileRequestError> {
         if !self.has_handshake() {
             return Err(FileRequestError::GatewayDisconnected);
         }
 
-        let (task_completion, request_id) = self.file_requests.start_request();
+        let (receiver, request_id) = self.file_requests.start_request();
 
-        {
-            let request = TcpGatewayContract::GetFileRequest { path, request_id };
-            self.send_payload(&request);
+        let request = if self.peer_streams_files() {
+            TcpGatewayContract::FileStreamRequest {
+                request_id,
+                offset,
+                length,
+                path,
+            }
+        } else {
+            self.file_requests
+                .expect_whole_file(request_id, offset, length);
+            TcpGatewayContract::GetFileRequest { path, request_id }
+        };
+
+        if !self.send_payload(&request) {
+            self.file_requests.remove_request(request_id);
+            return Err(FileRequestError::GatewayDisconnected);
         }
 
-        task_completion.get_result().await
+        Ok(FileStreamReceiver::new(request_id, self.clone(), receiver))
     }
 
-    pub async fn notify_file_response(
-        &self,
-        request_id: u32,
-        status: GetFileStatus,
-        content: SliceOrVec<'_, u8>,
-    ) {
-        match status {
-            GetFileStatus::Ok => {
-                self.file_requests
-                    .set_content(request_id, content.into_vec());
-            }
-            GetFileStatus::Error => {
-                self.file_requests
-                    .set_error(request_id, FileRequestError::FileNotFound);
+    pub fn notify_file_stream(&self, request_id: u32, event: FileStreamEvent) {
+        self.file_requests.notify(request_id, event);
+    }
+
+    /// A whole content answer: to the probe, or to a request asked of a peer
+    /// that predates file streams.
+    pub fn notify_file_response(&self, request_id: u32, status: GetFileStatus, content: Vec<u8>) {
+        if request_id == FILE_STREAM_PROBE_REQUEST_ID {
+            if status == GetFileStatus::Ok {
+                self.peer_streams_files
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
             }
+            return;
         }
+
+        self.file_requests
+            .notify_whole_file(request_id, status, content);
     }
 
     pub async fn disconnect_gateway(&self) {
         self.inner.disconnect().await;
+        self.file_requests.disconnect();
     }
 
     pub fn is_gateway_connected(&self) -> bool {
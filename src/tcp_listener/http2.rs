@@ -4,6 +4,7 @@ use hyper::service::service_fn;
 use hyper_util::rt::{TokioExecutor, TokioIo};
 
 use crate::{
+    app::ConnectionPermit,
     configurations::HttpListenPortConfiguration,
     network_stream::*,
     types::{AcceptedServerConnection, ListenHost},
@@ -15,6 +16,7 @@ pub async fn handle_connection(
     accepted_connection: AcceptedServerConnection,
     listen_host: ListenHost,
     configuration: Arc<HttpListenPortConfiguration>,
+    connection_permit: Option<ConnectionPermit>,
 ) {
     let listening_addr_str =
         listen_host.to_pretty_string(configuration.listen_endpoint_type.is_https());
@@ -41,6 +43,8 @@ pub async fn handle_connection(
 
     let connection_addr = accepted_connection.get_addr();
     crate::app::spawn_named("http2_server_connection", async move {
+        let _connection_permit = connection_permit;
+
         let io = match accepted_connection {
             AcceptedServerConnection::Tcp { network_stream, .. } => {
                 let io = TokioIo::new(network_stream);
@@ -49,6 +49,8 @@ pub async fn handle_requests(
         );
     }
 
+    let client_ip = connection_ip.get_ip_addr();
+
     match proxy_pass
         .send_payload(req, connection_ip, proxy_pass.endpoint_info.debug)
         .await
@@ -118,6 +120,13 @@ pub async fn handle_requests(
                     ),
                 );
             }
+            // A request over a size limit counts against its source, like
+            // malformed framing on the h1 path.
+            if let (ProxyPassError::RequestLimitExceeded(_), Some(client_ip)) = (&err, client_ip) {
+                crate::app::APP_CTX
+                    .ip_blocklist
+                    .register_failure(client_ip, crate::app::FailureSeverity::Soft);
+            }
             if matches!(err, ProxyPassError::DropConnection) {
                 let body: BoxBody<Bytes, String> = Full::new(Bytes::new())
                     .map_err(|never| match never {})
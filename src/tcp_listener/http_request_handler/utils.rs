@@ -84,6 +84,50 @@ pub fn generate_tech_page(
                 )
                 .unwrap();
         }
+        ProxyPassError::TooManyRequests(retry_after) => {
+            return hyper::Response::builder()
+                .status(hyper::StatusCode::TOO_MANY_REQUESTS)
+                .header(hyper::header::RETRY_AFTER, retry_after)
+                .body(
+                    Full::from(crate::error_templates::generate_layout(
+                        429,
+                        "Too many requests",
+                        second_line_error,
+                    ))
+                    .map_err(|e| crate::to_hyper_error(e))
+                    .boxed(),
+                )
+                .unwrap();
+        }
+        ProxyPassError::LocationIsBusy => {
+            return hyper::Response::builder()
+                .status(hyper::StatusCode::SERVICE_UNAVAILABLE)
+                .body(
+                    Full::from(crate::error_templates::generate_layout(
+                        503,
+                        "Too many requests in flight",
+                        second_line_error,
+                    ))
+                    .map_err(|e| crate::to_hyper_error(e))
+                    .boxed(),
+                )
+                .unwrap();
+        }
+        ProxyPassError::RequestLimitExceeded(exceeded) => {
+            let status_code = exceeded.get_status_code();
+            return hyper::Response::builder()
+                .status(status_code)
+                .body(
+                    Full::from(crate::error_templates::generate_layout(
+                        status_code,
+                        exceeded.get_status_text(),
+                        second_line_error,
+                    ))
+                    .map_err(|e| crate::to_hyper_error(e))
+                    .boxed(),
+                )
+                .unwrap();
+        }
         ProxyPassError::UpstreamUnavailable => {
             return hyper::Response::builder()
                 .status(hyper::StatusCode::SERVICE_UNAVAILABLE)
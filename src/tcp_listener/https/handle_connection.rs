@@ -3,6 +3,7 @@ use std::{net::SocketAddr, sync::Arc};
 use hyper_util::rt::TokioIo;
 use tokio::io::AsyncWriteExt;
 
+use crate::app::ConnectionPermit;
 use crate::configurations::*;
 use crate::tcp_listener::http_request_handler::https::HttpsRequestsHandler;
 use crate::types::ConnectionIp;
@@ -15,10 +16,12 @@ pub fn handle_connection(
     listening_addr: SocketAddr,
     configuration: Arc<HttpListenPortConfiguration>,
     connection_id: u64,
+    connection_permit: ConnectionPermit,
 ) {
     let connection_ip = connection_ip.into();
 
     crate::app::spawn_named("https_connection", async move {
+        let _connection_permit = connection_permit;
         let endpoint_port = listening_addr.port();
 
         let result = super::utils::lazy_accept_tcp_stream(
@@ -1,6 +1,6 @@
 use std::{net::SocketAddr, sync::Arc};
 
-use crate::configurations::ListenConfiguration;
+use crate::{app::ConnectionLimitExceeded, configurations::ListenConfiguration};
 
 use super::ListenServerHandler;
 
@@ -119,6 +119,39 @@ async fn handle_accepted_connection(
 
     let endpoint_type = endpoint_type.unwrap();
 
+    // Counted at accept, before SNI or the Host header picks the endpoint: a
+    // connection over the port's limits is closed at once.
+    let connection_permit = match crate::app::APP_CTX.connection_counters.try_acquire(
+        listen_port,
+        socket_addr.ip(),
+        &endpoint_type.get_connection_limits(),
+    ) {
+        Ok(connection_permit) => connection_permit,
+        Err(err) => {
+            let reason = match err {
+                ConnectionLimitExceeded::MaxConnections(max) => {
+                    format!("port is at max_connections {}", max)
+                }
+                ConnectionLimitExceeded::MaxConnectionsPerIp(max) => {
+                    format!("IP is at max_connections_per_ip {}", max)
+                }
+            };
+
+            crate::app::APP_CTX.proxy_logs.write_port(
+                listen_port.to_string().as_str(),
+                Some(socket_addr.ip().to_string()),
+                format!("Dropped connection: {}", reason),
+            );
+            crate::app::APP_CTX
+                .prometheus
+                .inc_connections_rejected(listen_port.to_string().as_str());
+
+            use tokio::io::AsyncWriteExt;
+            let _ = accepted_connection.shutdown().await;
+            return;
+        }
+    };
+
     match endpoint_type {
         ListenConfiguration::Http(configuration) => match configuration.listen_endpoint_type {
             crate::configurations::ListenHttpEndpointType::Http1 => {
@@ -126,6 +159,7 @@ async fn handle_accepted_connection(
                     accepted_connection,
                     socket_addr,
                     configuration,
+                    connection_permit,
                 );
                 //super::http::handle_connection(accepted_connection, listening_addr, configuration)
                 //    .await;
@@ -135,6 +169,7 @@ async fn handle_accepted_connection(
                     (accepted_connection, socket_addr).into(),
                     listening_host.into(),
                     configuration,
+                    Some(connection_permit),
                 )
                 .await;
             }
@@ -145,6 +180,7 @@ async fn handle_accepted_connection(
                     listening_host,
                     configuration,
                     connection_id,
+                    connection_permit,
                 );
             }
             crate::configurations::ListenHttpEndpointType::Https2 => {
@@ -154,6 +190,7 @@ async fn handle_accepted_connection(
                     listening_host,
                     configuration,
                     connection_id,
+                    connection_permit,
                 );
             }
             crate::configurations::ListenHttpEndpointType::Mcp => {
@@ -163,6 +200,7 @@ async fn handle_accepted_connection(
                     listening_host,
                     configuration,
                     connection_id,
+                    connection_permit,
                 );
             }
         },
@@ -174,6 +212,7 @@ async fn handle_accepted_connection(
                     configuration.clone(),
                     id,
                     remote_host.clone(),
+                    Some(connection_permit),
                 )
                 .await;
             }
@@ -186,6 +225,7 @@ async fn handle_accepted_connection(
                     configuration.clone(),
                     ssh_credentials,
                     remote_host.clone(),
+                    Some(connection_permit),
                 )
                 .await;
             }
@@ -194,6 +234,7 @@ async fn handle_accepted_connection(
                     (accepted_connection, socket_addr).into(),
                     configuration.clone(),
                     remote_host.clone(),
+                    Some(connection_permit),
                 )
                 .await;
             }
@@ -206,6 +247,7 @@ async fn handle_accepted_connection(
                 listening_host,
                 configuration,
                 connection_id,
+                connection_permit,
             );
         }
     }
@@ -123,6 +123,7 @@ async fn handle_accepted_connection(
                     accepted_connection.into(),
                     listen_host.clone().into(),
                     configuration,
+                    None,
                 )
                 .await;
             }
@@ -153,6 +154,7 @@ async fn handle_accepted_connection(
                     configuration.clone(),
                     id,
                     remote_host.clone(),
+                    None,
                 )
                 .await;
             }
@@ -167,6 +169,7 @@ async fn handle_accepted_connection(
                     accepted_connection.into(),
                     configuration.clone(),
                     remote_host.clone(),
+                    None,
                 )
                 .await;
             }
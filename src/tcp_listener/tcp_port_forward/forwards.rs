@@ -1,6 +1,11 @@
+use std::{future::Future, sync::Arc};
+
 use crate::{
-    app::SshSessionHandler, network_stream::*, tcp_utils::LoopBuffer,
-    types::AcceptedServerConnection, types::HttpTimeouts,
+    app::{ConnectionPermit, SshSessionHandler},
+    network_stream::*,
+    tcp_utils::LoopBuffer,
+    types::AcceptedServerConnection,
+    types::HttpTimeouts,
 };
 
 pub async fn handle_port_forward<TRemoteNetworkStream: NetworkStream + Send + Sync + 'static>(
@@ -8,33 +13,43 @@ pub async fn handle_port_forward<TRemoteNetworkStream: NetworkStream + Send + Sy
     remote_stream: TRemoteNetworkStream,
     ssh_session_handler: Option<SshSessionHandler>,
     timeouts: HttpTimeouts,
+    connection_permit: Option<ConnectionPermit>,
 ) {
+    // The connection counts against the listen port's limits until both
+    // directions are done.
+    let connection_permit = Arc::new(connection_permit);
     let (remote_reader, remote_writer) = remote_stream.split();
     match server_stream {
         AcceptedServerConnection::Tcp { network_stream, .. } => {
             let (server_reader, server_writer) = tokio::io::split(network_stream);
             crate::app::spawn_named(
                 "tcp_forward_copy_server_to_remote_tcp",
-                crate::tcp_utils::copy_streams(
-                    server_reader,
-                    remote_writer,
-                    LoopBuffer::new(),
-                    ssh_session_handler,
-                    None,
-                    None,
-                    timeouts,
+                holding_permit(
+                    connection_permit.clone(),
+                    crate::tcp_utils::copy_streams(
+                        server_reader,
+                        remote_writer,
+                        LoopBuffer::new(),
+                        ssh_session_handler,
+                        None,
+                        None,
+                        timeouts,
+                    ),
                 ),
             );
             crate::app::spawn_named(
                 "tcp_forward_copy_remote_to_server_tcp",
-                crate::tcp_utils::copy_streams(
-                    remote_reader,
-                    server_writer,
-                    LoopBuffer::new(),
-                    None,
-                    None,
-                    None,
-                    timeouts,
+                holding_permit(
+                    connection_permit.clone(),
+                    crate::tcp_utils::copy_streams(
+                        remote_reader,
+                        server_writer,
+                        LoopBuffer::new(),
+                        None,
+                        None,
+                        None,
+                        timeouts,
+                    ),
                 ),
             );
         }
@@ -42,28 +57,42 @@ pub async fn handle_port_forward<TRemoteNetworkStream: NetworkStream + Send + Sy
             let (server_reader, server_writer) = tokio::io::split(unix_stream);
             crate::app::spawn_named(
                 "tcp_forward_copy_server_to_remote_unix",
-                crate::tcp_utils::copy_streams(
-                    server_reader,
-                    remote_writer,
-                    LoopBuffer::new(),
-                    ssh_session_handler,
-                    None,
-                    None,
-                    timeouts,
+                holding_permit(
+                    connection_permit.clone(),
+                    crate::tcp_utils::copy_streams(
+                        server_reader,
+                        remote_writer,
+                        LoopBuffer::new(),
+                        ssh_session_handler,
+                        None,
+                        None,
+                        timeouts,
+                    ),
                 ),
             );
             crate::app::spawn_named(
                 "tcp_forward_copy_remote_to_server_unix",
-                crate::tcp_utils::copy_streams(
-                    remote_reader,
-                    server_writer,
-                    LoopBuffer::new(),
-                    None,
-                    None,
-                    None,
-                    timeouts,
+                holding_permit(
+                    connection_permit.clone(),
+                    crate::tcp_utils::copy_streams(
+                        remote_reader,
+                        server_writer,
+                        LoopBuffer::new(),
+                        None,
+                        None,
+                        None,
+                        timeouts,
+                    ),
                 ),
             );
         }
     }
 }
+
+/// Runs a copy loop, keeping the connection counted while it runs.
+async fn holding_permit(
+    _connection_permit: Arc<Option<ConnectionPermit>>,
+    future: impl Future<Output = ()>,
+) {
+    future.await
+}
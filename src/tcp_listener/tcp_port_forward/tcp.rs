@@ -3,13 +3,14 @@ use std::sync::Arc;
 use rust_extensions::remote_endpoint::RemoteEndpointOwned;
 use tokio::net::TcpStream;
 
-use crate::{configurations::*, types::AcceptedServerConnection};
+use crate::{app::ConnectionPermit, configurations::*, types::AcceptedServerConnection};
 
 pub async fn handle_connection(
     mut accepted_server_connection: AcceptedServerConnection,
 
     configuration: Arc<TcpEndpointHostConfig>,
     remote_host: Arc<RemoteEndpointOwned>,
+    connection_permit: Option<ConnectionPermit>,
 ) {
     let socket_addr = accepted_server_connection.get_addr();
 
@@ -58,6 +59,7 @@ pub async fn handle_connection(
             remote_tcp_connection_result,
             None,
             configuration.timeouts,
+            connection_permit,
         ),
     );
 }
@@ -2,13 +2,16 @@ use std::{sync::Arc, time::Duration};
 
 use rust_extensions::remote_endpoint::RemoteEndpointOwned;
 
-use crate::{configurations::TcpEndpointHostConfig, types::AcceptedServerConnection};
+use crate::{
+    app::ConnectionPermit, configurations::TcpEndpointHostConfig, types::AcceptedServerConnection,
+};
 
 pub async fn handle_connection(
     mut accepted_server_connection: AcceptedServerConnection,
     configuration: Arc<TcpEndpointHostConfig>,
     gateway_id: &Arc<String>,
     remote_endpoint: Arc<RemoteEndpointOwned>,
+    connection_permit: Option<ConnectionPermit>,
 ) {
     let connection_ip = accepted_server_connection.get_addr();
     if configuration.debug {
@@ -76,6 +79,7 @@ pub async fn handle_connection(
             proxy_connection,
             None,
             configuration.timeouts,
+            connection_permit,
         ),
     );
 }
@@ -3,7 +3,11 @@ use std::sync::Arc;
 use my_ssh::SshCredentials;
 use rust_extensions::remote_endpoint::RemoteEndpointOwned;
 
-use crate::{app::APP_CTX, configurations::*, types::AcceptedServerConnection};
+use crate::{
+    app::{ConnectionPermit, APP_CTX},
+    configurations::*,
+    types::AcceptedServerConnection,
+};
 
 pub async fn handle_connection(
     mut accepted_server_connection: AcceptedServerConnection,
@@ -11,6 +15,7 @@ pub async fn handle_connection(
     configuration: Arc<TcpEndpointHostConfig>,
     ssh_credentials: &Arc<SshCredentials>,
     remote_endpoint: Arc<RemoteEndpointOwned>,
+    connection_permit: Option<ConnectionPermit>,
 ) {
     let ssh_session_handler = APP_CTX.ssh_sessions_pool.get(ssh_credentials);
 
@@ -63,6 +68,7 @@ pub async fn handle_connection(
             ssh_channel,
             None,
             configuration.timeouts,
+            connection_permit,
         ),
     );
 }
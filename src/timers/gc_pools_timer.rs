@@ -11,8 +11,9 @@ use crate::{
 
 /// Periodic GC for the per-location upstream pools. Removes pools whose
 /// location is no longer referenced by any location in the current
-/// configuration. Pools are created lazily on first request — this timer is
-/// the only mechanism that removes them.
+/// configuration, together with their outlier-detection state. Pools are
+/// created lazily on first request — this timer is the only mechanism that
+/// removes them.
 pub struct GcPoolsTimer;
 
 #[async_trait::async_trait]
@@ -29,6 +30,7 @@ impl MyTimerTick for GcPoolsTimer {
         APP_CTX.h2_tcp_pools.drain_unused(&desired.h2_tcp);
         APP_CTX.h2_tls_pools.drain_unused(&desired.h2_tls);
         APP_CTX.h2_uds_pools.drain_unused(&desired.h2_uds);
+        APP_CTX.upstream_outliers.drain_unused(&desired.outliers);
 
         RepeatTimerIteration::WithInterval
     }
@@ -42,6 +44,9 @@ struct DesiredKeys {
     h2_tcp: AHashSet<i64>,
     h2_tls: AHashSet<i64>,
     h2_uds: AHashSet<i64>,
+    /// Every pool id, whatever the upstream type — outlier detection also
+    /// tracks upstreams (gateway, ssh) that have no pool in the registries.
+    outliers: AHashSet<i64>,
 }
 
 fn collect_desired_keys(cfg: &crate::configurations::AppConfigurationInner) -> DesiredKeys {
@@ -78,13 +83,19 @@ enum PoolFamily {
 }
 
 fn absorb_location(location: &ProxyPassLocationConfig, out: &mut DesiredKeys) {
-    let location_id = location.id;
+    // A location balancing between several upstreams has a pool per upstream.
+    for (location_id, proxy_pass_to) in location.get_pool_ids() {
+        out.outliers.insert(location_id);
+        absorb_upstream(location_id, proxy_pass_to, out);
+    }
+}
 
+fn absorb_upstream(location_id: i64, proxy_pass_to: &ProxyPassToConfig, out: &mut DesiredKeys) {
     // Unix socket variants short-circuit: their `remote_host` is a filesystem
     // path with no URL scheme, so `get_scheme()` would return None below and
     // we'd skip the location — leaving the pool orphaned and drained on the
     // next tick.
-    let (family, model) = match &location.proxy_pass_to {
+    let (family, model) = match proxy_pass_to {
         ProxyPassToConfig::UnixHttp1(_) => {
             out.h1_uds.insert(location_id);
             return;
@@ -16,6 +16,8 @@ mod endpoint_rps_timer;
 pub use endpoint_rps_timer::*;
 mod ip_blocklist_gc_timer;
 pub use ip_blocklist_gc_timer::*;
+mod rate_limit_gc_timer;
+pub use rate_limit_gc_timer::*;
 mod traffic_timer;
 pub use traffic_timer::*;
 mod resolve_domains_ip_timer;
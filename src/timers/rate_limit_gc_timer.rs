@@ -0,0 +1,18 @@
+use rust_extensions::{MyTimerTick, RepeatTimerIteration};
+
+use crate::app::APP_CTX;
+
+pub struct RateLimitGcTimer;
+
+#[async_trait::async_trait]
+impl MyTimerTick for RateLimitGcTimer {
+    async fn tick(&self) -> RepeatTimerIteration {
+        for (limiter, clients) in APP_CTX.rate_limiters.gc() {
+            APP_CTX
+                .prometheus
+                .set_rate_limit_clients(limiter.get_label(), clients as i64);
+        }
+
+        RepeatTimerIteration::WithInterval
+    }
+}